@@ -0,0 +1,24 @@
+use std::process::Command;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // protox compiles the schema in pure Rust, so no protoc install is needed
+    println!("cargo:rerun-if-changed=proto/orbex.proto");
+    // sqlx::migrate! embeds these, so new files must trigger a rebuild
+    println!("cargo:rerun-if-changed=db/migrations");
+    let descriptors = protox::compile(["proto/orbex.proto"], ["proto"])?;
+    tonic_build::configure().compile_fds(descriptors)?;
+
+    // Commit reported by /api/info; source tarballs without .git report "unknown"
+    let git_commit = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=ORBEX_GIT_COMMIT={}", git_commit);
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+
+    Ok(())
+}
@@ -0,0 +1,2 @@
+--- Aggressor side of the trade ("Buy" when the buyer took liquidity), NULL when unknown
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS side TEXT;
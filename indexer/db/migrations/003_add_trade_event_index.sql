@@ -0,0 +1,9 @@
+--- Position of the TradeExecuted event within its block. Together with block_number and
+--- trade_id this is the trade's natural key, so distinct trades that share an id inside
+--- one block are kept apart while a re-indexed event is still recognised as a duplicate.
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS event_index INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE trades DROP CONSTRAINT IF EXISTS trades_pkey;
+ALTER TABLE trades ADD PRIMARY KEY (block_number, event_index, trade_id, created_at);
+
+CREATE INDEX IF NOT EXISTS idx_trades_block_event ON trades(block_number, event_index);
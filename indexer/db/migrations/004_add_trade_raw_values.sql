@@ -0,0 +1,3 @@
+--- Unscaled on-chain integers (u128) kept alongside the scaled decimals for exact reconciliation
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS raw_price NUMERIC(39, 0);
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS raw_quantity NUMERIC(39, 0);
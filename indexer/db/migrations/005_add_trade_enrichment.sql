@@ -0,0 +1,2 @@
+--- Fields computed by the configured TradeEnricher (e.g. {"size_bucket": "large"})
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS enrichment JSONB;
@@ -0,0 +1,2 @@
+--- Block-range candle and trade queries filter on (symbol, block_number)
+CREATE INDEX IF NOT EXISTS idx_trades_symbol_block ON trades(symbol, block_number);
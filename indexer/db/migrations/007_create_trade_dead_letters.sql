@@ -0,0 +1,21 @@
+--- Trades that failed validation (e.g. zero price or quantity) and were kept out of aggregation
+CREATE TABLE IF NOT EXISTS trade_dead_letters (
+    id BIGSERIAL PRIMARY KEY,
+
+    trade_id BIGINT NOT NULL,
+    block_number BIGINT NOT NULL,
+    event_index INTEGER NOT NULL,
+    symbol TEXT NOT NULL,
+
+    -- Why the trade was rejected and the policy that applied ("reject" or "flag")
+    reason TEXT NOT NULL,
+    policy TEXT NOT NULL,
+
+    -- Values exactly as emitted on chain, before decimal scaling
+    raw_price NUMERIC(39, 0) NOT NULL,
+    raw_quantity NUMERIC(39, 0) NOT NULL,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_trade_dead_letters_block ON trade_dead_letters(block_number);
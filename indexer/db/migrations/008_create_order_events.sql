@@ -0,0 +1,13 @@
+--- Order lifecycle events (placements and cancellations) for order flow statistics
+CREATE TABLE IF NOT EXISTS order_events (
+    order_id BIGINT NOT NULL,
+    event_type TEXT NOT NULL,  -- "placed" or "cancelled"
+    symbol TEXT NOT NULL,
+
+    block_number BIGINT NOT NULL,
+    event_index INTEGER NOT NULL,
+
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_order_events_symbol_time ON order_events(symbol, created_at DESC);
@@ -0,0 +1,11 @@
+--- Periodic top-of-book samples, the book-based counterpart to trade-based candles
+CREATE TABLE IF NOT EXISTS book_samples (
+    symbol TEXT NOT NULL,
+    sampled_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    best_bid NUMERIC(20, 6) NOT NULL,
+    best_ask NUMERIC(20, 6) NOT NULL,
+    mid NUMERIC(21, 7) NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_book_samples_symbol_time ON book_samples(symbol, sampled_at DESC);
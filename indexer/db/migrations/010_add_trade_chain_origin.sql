@@ -0,0 +1,4 @@
+--- Where the TradeExecuted event came from on chain, so a trade can be checked against
+--- the block explorer. NULL for trades indexed before capture or with it disabled.
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS block_hash TEXT;
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS extrinsic_index INTEGER;
@@ -0,0 +1,18 @@
+--- Per-market trade sequence: 1, 2, 3, ... in indexing order, so clients can detect
+--- missed trades after a reconnect. New trades continue from the market's highest
+--- sequence; existing rows are numbered in chain order.
+ALTER TABLE trades ADD COLUMN IF NOT EXISTS seq BIGINT;
+
+UPDATE trades SET seq = numbered.seq
+FROM (
+    SELECT block_number, event_index, trade_id, created_at,
+        ROW_NUMBER() OVER (PARTITION BY symbol ORDER BY block_number, event_index, trade_id) AS seq
+    FROM trades
+) numbered
+WHERE trades.seq IS NULL
+    AND trades.block_number = numbered.block_number
+    AND trades.event_index = numbered.event_index
+    AND trades.trade_id = numbered.trade_id
+    AND trades.created_at = numbered.created_at;
+
+CREATE INDEX IF NOT EXISTS idx_trades_symbol_seq ON trades(symbol, seq DESC);
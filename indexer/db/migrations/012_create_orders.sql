@@ -0,0 +1,29 @@
+--- Current state of every order the indexer has seen, reloaded into the book on restart
+CREATE TABLE IF NOT EXISTS orders (
+    order_id BIGINT PRIMARY KEY,
+    symbol TEXT NOT NULL,
+    side TEXT NOT NULL,
+
+    price NUMERIC(20, 6) NOT NULL,
+    quantity NUMERIC(20, 6) NOT NULL,
+    filled_quantity NUMERIC(20, 6) NOT NULL,
+    status TEXT NOT NULL,  -- "Open", "PartiallyFilled", "Filled", "Cancelled" or "Expired"
+    raw_price NUMERIC(39, 0) NOT NULL,
+    raw_quantity NUMERIC(39, 0) NOT NULL,
+
+    -- Where the order was placed, keeping time priority within a level on reload
+    placed_block BIGINT NOT NULL,
+    placed_event INTEGER NOT NULL,
+
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_orders_resting ON orders(symbol, placed_block, placed_event)
+    WHERE status IN ('Open', 'PartiallyFilled');
+
+--- Last finalized block whose events were fully applied, so a restart resumes after it
+CREATE TABLE IF NOT EXISTS indexer_progress (
+    id TEXT PRIMARY KEY,
+    last_block BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,4 @@
+--- Fill events in the order lifecycle, with the order's cumulative fill after each transition
+ALTER TABLE order_events ADD COLUMN IF NOT EXISTS filled_quantity NUMERIC(20, 6);
+
+CREATE INDEX IF NOT EXISTS idx_order_events_order ON order_events(order_id, block_number, event_index);
@@ -8,6 +8,8 @@ SELECT time_bucket('1 minute', created_at) AS bucket,
     candlestick_agg(created_at, price, quantity) AS candlestick,
     COUNT(*) AS trade_count
 FROM trades
+-- Trades flagged for a zero price or quantity are stored but never aggregated
+WHERE price > 0 AND quantity > 0
 GROUP BY 1, 2 WITH NO DATA;
 
 CREATE VIEW one_minute_candles AS
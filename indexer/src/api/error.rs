@@ -0,0 +1,112 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::{json, Value};
+
+/// A failed REST request, rendered as `{"error": {"code", "message"}}` with a matching
+/// status. The UDF endpoints keep TradingView's `{"s": "error", "errmsg"}` instead,
+/// since the charting library reads that shape.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiError {
+    /// 404: the order, market or data the request asked for doesn't exist
+    NotFound(String),
+    /// 400: a query or path parameter is invalid
+    BadRequest(String),
+    /// 409: the request was already applied and can't be answered again
+    Conflict(String),
+    /// 500: the request was fine but serving it failed
+    Internal(String),
+}
+
+impl ApiError {
+    /// The live book only covers the default market
+    pub fn untracked_market(symbol: &str) -> Self {
+        Self::NotFound(format!("No orderbook tracked for market: {}", symbol))
+    }
+
+    /// A failed query, logged with the handler it happened in
+    pub fn database(handler: &str, error: sqlx::Error) -> Self {
+        eprintln!("❌ Database error in {}: {}", handler, error);
+        Self::Internal(format!("Database error: {}", error))
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Machine-readable code clients can match on
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "NOT_FOUND",
+            Self::BadRequest(_) => "BAD_REQUEST",
+            Self::Conflict(_) => "CONFLICT",
+            Self::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::NotFound(message)
+            | Self::BadRequest(message)
+            | Self::Conflict(message)
+            | Self::Internal(message) => message,
+        }
+    }
+
+    /// The JSON envelope, also used for per-item errors inside a successful response
+    pub fn body(&self) -> Value {
+        json!({
+            "error": {
+                "code": self.code(),
+                "message": self.message(),
+            }
+        })
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status(), Json(self.body())).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_error_envelope_and_status() {
+        let response = ApiError::NotFound("Order not found: 7".to_string()).into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({ "error": { "code": "NOT_FOUND", "message": "Order not found: 7" } })
+        );
+
+        let bad = ApiError::BadRequest("from must be before to".to_string());
+        assert_eq!(
+            (bad.status(), bad.code()),
+            (StatusCode::BAD_REQUEST, "BAD_REQUEST")
+        );
+        let conflict = ApiError::Conflict("Already applied".to_string());
+        assert_eq!(
+            (conflict.status(), conflict.code()),
+            (StatusCode::CONFLICT, "CONFLICT")
+        );
+        let internal = ApiError::Internal("Database error".to_string());
+        assert_eq!(
+            (internal.status(), internal.code()),
+            (StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR")
+        );
+    }
+}
@@ -0,0 +1,314 @@
+// `tonic::Status` is the error type the generated service traits require
+#![allow(clippy::result_large_err)]
+
+use crate::api::state::SharedIndexPricer;
+use crate::config::IndexerConfig;
+use crate::indexer::candle_aggregator::{CandleReader, CandleUpdate};
+use crate::indexer::orderbook_reducer::{self, OrderbookSnapshot, OrderbookState};
+use crate::telemetry;
+use futures::Stream;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex};
+use tokio_stream::wrappers::errors::BroadcastStreamRecvError;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::StreamExt;
+use tonic::{Request, Response, Status};
+use tracing::{info, warn};
+
+pub mod proto {
+    tonic::include_proto!("orbex.v1");
+}
+
+use proto::market_data_server::{MarketData, MarketDataServer};
+
+const DEFAULT_DEPTH: usize = 20;
+const MAX_DEPTH: usize = 100;
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+/// gRPC market data service sharing the HTTP API's state and broadcast channels
+#[derive(Clone)]
+pub struct MarketDataService {
+    orderbook: Arc<Mutex<OrderbookState>>,
+    ob_broadcast: broadcast::Sender<OrderbookSnapshot>,
+    candle_broadcast: broadcast::Sender<CandleUpdate>,
+    candle_reader: CandleReader,
+    index_pricer: SharedIndexPricer,
+    config: Arc<IndexerConfig>,
+}
+
+impl MarketDataService {
+    pub fn new(
+        orderbook: Arc<Mutex<OrderbookState>>,
+        ob_broadcast: broadcast::Sender<OrderbookSnapshot>,
+        candle_broadcast: broadcast::Sender<CandleUpdate>,
+        candle_reader: CandleReader,
+        index_pricer: SharedIndexPricer,
+        config: Arc<IndexerConfig>,
+    ) -> Self {
+        Self {
+            orderbook,
+            ob_broadcast,
+            candle_broadcast,
+            candle_reader,
+            index_pricer,
+            config,
+        }
+    }
+
+    /// Resolve the requested symbol, empty meaning the default market
+    ///
+    /// Only the default market has a tracked book for now.
+    fn resolve_symbol(&self, symbol: &str) -> Result<String, Status> {
+        let symbol = self.config.symbol_or_default(Some(symbol));
+        if symbol == self.config.default_symbol {
+            Ok(symbol.to_string())
+        } else {
+            Err(Status::not_found(format!("Unknown symbol: {}", symbol)))
+        }
+    }
+}
+
+fn clamp_depth(depth: u32) -> usize {
+    match depth as usize {
+        0 => DEFAULT_DEPTH,
+        depth => depth.min(MAX_DEPTH),
+    }
+}
+
+fn to_levels(levels: Vec<orderbook_reducer::PriceLevel>) -> Vec<proto::PriceLevel> {
+    levels
+        .into_iter()
+        .map(|level| proto::PriceLevel {
+            price: level.price.to_string(),
+            quantity: level.total_quantity.to_string(),
+            order_count: level.order_count as u32,
+        })
+        .collect()
+}
+
+fn to_orderbook(symbol: &str, snapshot: OrderbookSnapshot, depth: usize) -> proto::Orderbook {
+    let snapshot = snapshot.truncated(depth);
+    proto::Orderbook {
+        symbol: symbol.to_string(),
+        bids: to_levels(snapshot.bids),
+        asks: to_levels(snapshot.asks),
+    }
+}
+
+fn to_candle(update: CandleUpdate) -> proto::Candle {
+    proto::Candle {
+        symbol: update.s,
+        interval: update.i,
+        open_time: update.t,
+        close_time: update.end_time,
+        open: update.o,
+        high: update.h,
+        low: update.l,
+        close: update.c,
+        volume: update.v,
+        trade_count: update.n,
+    }
+}
+
+#[tonic::async_trait]
+impl MarketData for MarketDataService {
+    async fn get_orderbook(
+        &self,
+        request: Request<proto::OrderbookRequest>,
+    ) -> Result<Response<proto::Orderbook>, Status> {
+        let request = request.into_inner();
+        let symbol = self.resolve_symbol(&request.symbol)?;
+        let snapshot = self.orderbook.lock().await.get_snapshot();
+
+        Ok(Response::new(to_orderbook(
+            &symbol,
+            snapshot,
+            clamp_depth(request.depth),
+        )))
+    }
+
+    async fn get_ticker(
+        &self,
+        request: Request<proto::TickerRequest>,
+    ) -> Result<Response<proto::Ticker>, Status> {
+        let symbol = self.resolve_symbol(&request.into_inner().symbol)?;
+        let (spread, index_price, reference_price) = {
+            let book = self.orderbook.lock().await;
+            (
+                book.get_snapshot().spread,
+                self.index_pricer.index_price(&book),
+                self.config.reference_price.price(&book),
+            )
+        };
+        let last_price = self
+            .candle_reader
+            .current(&symbol, "1m")
+            .map(|candle| candle.close.to_string());
+
+        Ok(Response::new(proto::Ticker {
+            symbol,
+            best_bid: spread.as_ref().map(|s| s.best_bid.to_string()),
+            best_ask: spread.as_ref().map(|s| s.best_ask.to_string()),
+            spread: spread.as_ref().map(|s| s.spread.to_string()),
+            last_price,
+            index_price: index_price.map(|price| price.to_string()),
+            reference_price: reference_price.map(|price| price.to_string()),
+        }))
+    }
+
+    type StreamOrderbookStream = ResponseStream<proto::Orderbook>;
+
+    async fn stream_orderbook(
+        &self,
+        request: Request<proto::OrderbookRequest>,
+    ) -> Result<Response<Self::StreamOrderbookStream>, Status> {
+        let request = request.into_inner();
+        let symbol = self.resolve_symbol(&request.symbol)?;
+        let depth = clamp_depth(request.depth);
+
+        // Subscribe before taking the initial snapshot so no update falls in between
+        let updates = BroadcastStream::new(self.ob_broadcast.subscribe());
+        let initial = self.orderbook.lock().await.get_snapshot();
+
+        let initial = tokio_stream::once(Ok(to_orderbook(&symbol, initial, depth)));
+        // Lagged receivers skip ahead; the next snapshot is complete anyway
+        let updates = updates.filter_map(move |update| {
+            skip_lagged("orderbook", update)
+                .map(|snapshot| Ok(to_orderbook(&symbol, snapshot, depth)))
+        });
+
+        Ok(Response::new(Box::pin(initial.chain(updates))))
+    }
+
+    type StreamCandlesStream = ResponseStream<proto::Candle>;
+
+    async fn stream_candles(
+        &self,
+        request: Request<proto::CandleRequest>,
+    ) -> Result<Response<Self::StreamCandlesStream>, Status> {
+        let request = request.into_inner();
+        let symbol = self.resolve_symbol(&request.symbol)?;
+        let interval = request.interval;
+
+        let updates =
+            BroadcastStream::new(self.candle_broadcast.subscribe()).filter_map(move |update| {
+                skip_lagged("ohlcv", update)
+                    .filter(|c| c.s == symbol && (interval.is_empty() || c.i == interval))
+                    .map(|c| Ok(to_candle(c)))
+            });
+
+        Ok(Response::new(Box::pin(updates)))
+    }
+}
+
+/// The received update, or None after logging how many updates a lagging stream missed
+fn skip_lagged<T>(channel: &'static str, update: Result<T, BroadcastStreamRecvError>) -> Option<T> {
+    match update {
+        Ok(update) => Some(update),
+        Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+            warn!(
+                "gRPC {}: stream lagged, skipped {} updates",
+                channel, skipped
+            );
+            telemetry::record_broadcast_lag(channel, skipped);
+            None
+        }
+    }
+}
+
+/// Serve the gRPC API on its own port until the server fails
+pub async fn run_grpc_server(
+    service: MarketDataService,
+    port: u16,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    info!("🛰️  gRPC API: http://0.0.0.0:{}", port);
+
+    tonic::transport::Server::builder()
+        .add_service(MarketDataServer::new(service))
+        .serve(addr)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::proto::market_data_client::MarketDataClient;
+    use super::*;
+    use crate::api::test_support::order;
+    use crate::indexer::candle_aggregator::CandleAggregator;
+    use rust_decimal::Decimal;
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    #[tokio::test]
+    async fn test_grpc_orderbook_and_ticker_in_process() {
+        let (ob_tx, _) = broadcast::channel(16);
+        let (candle_tx, _) = broadcast::channel(16);
+        let mut candles = CandleAggregator::new(candle_tx.clone());
+        candles
+            .process_trade("ETH/USDT", Decimal::from(100), Decimal::ONE, 60_000)
+            .unwrap();
+
+        let mut book = OrderbookState::new();
+        book.add_order(order(1, "Buy", 99, 2));
+        book.add_order(order(2, "Sell", 101, 2));
+
+        let service = MarketDataService::new(
+            Arc::new(Mutex::new(book)),
+            ob_tx,
+            candle_tx,
+            candles.reader(),
+            Arc::new(crate::indexer::index_price::MidIndex),
+            Arc::new(IndexerConfig::default()),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(MarketDataServer::new(service))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+
+        let mut client = MarketDataClient::connect(format!("http://{}", addr))
+            .await
+            .unwrap();
+
+        let book = client
+            .get_orderbook(proto::OrderbookRequest {
+                symbol: "ETH/USDT".to_string(),
+                depth: 0,
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(book.bids.len(), 1);
+        assert_eq!(book.bids[0].price, "99");
+        assert_eq!(book.asks[0].quantity, "2");
+
+        let ticker = client
+            .get_ticker(proto::TickerRequest {
+                symbol: String::new(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(ticker.symbol, "ETH/USDT");
+        assert_eq!(ticker.best_bid.as_deref(), Some("99"));
+        assert_eq!(ticker.spread.as_deref(), Some("2"));
+        assert_eq!(ticker.last_price.as_deref(), Some("100"));
+        assert_eq!(ticker.index_price.as_deref(), Some("100"));
+
+        let unknown = client
+            .get_ticker(proto::TickerRequest {
+                symbol: "BTC/USD".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(unknown.code(), tonic::Code::NotFound);
+    }
+}
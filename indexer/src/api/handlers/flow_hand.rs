@@ -0,0 +1,205 @@
+use crate::api::error::ApiError;
+use crate::api::handlers::tape_hand::parse_window;
+use crate::config::IndexerConfig;
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+
+/// Default look-back window when `window` is omitted (5 minutes)
+const DEFAULT_WINDOW_SECS: i64 = 300;
+/// Largest window we aggregate over (7 days)
+const MAX_WINDOW_SECS: i64 = 604_800;
+
+#[derive(Debug, Deserialize)]
+pub struct FlowQuery {
+    /// Trading pair symbol (e.g., "ETH/USDT"), defaults to the configured default market
+    pub symbol: Option<String>,
+    /// Look-back window, either plain seconds ("300") or with a unit ("30s", "5m", "1h", "1d")
+    pub window: Option<String>,
+}
+
+/// Order flow rates over a window
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct FlowStats {
+    pub symbol: String,
+    pub window_secs: i64,
+    pub placements: i64,
+    pub cancellations: i64,
+    pub trades: i64,
+    pub placements_per_sec: Decimal,
+    pub cancellations_per_sec: Decimal,
+    /// Cancellations per trade, zero when nothing traded
+    pub cancel_to_trade_ratio: Decimal,
+}
+
+impl FlowStats {
+    /// Derive the rates from raw event counts over `window_secs`
+    pub fn from_counts(
+        symbol: &str,
+        window_secs: i64,
+        placements: i64,
+        cancellations: i64,
+        trades: i64,
+    ) -> Self {
+        let rate = |count: i64, per: i64| {
+            if per <= 0 {
+                Decimal::ZERO
+            } else {
+                (Decimal::from(count) / Decimal::from(per))
+                    .round_dp(6)
+                    .normalize()
+            }
+        };
+
+        Self {
+            symbol: symbol.to_string(),
+            window_secs,
+            placements,
+            cancellations,
+            trades,
+            placements_per_sec: rate(placements, window_secs),
+            cancellations_per_sec: rate(cancellations, window_secs),
+            cancel_to_trade_ratio: rate(cancellations, trades),
+        }
+    }
+}
+
+/// Count placements, cancellations and trades for `symbol` over the last `window_secs`
+pub async fn fetch_flow_stats(
+    pool: &PgPool,
+    symbol: &str,
+    window_secs: i64,
+) -> Result<FlowStats, sqlx::Error> {
+    let (placements, cancellations, trades) = sqlx::query_as::<_, (i64, i64, i64)>(
+        "SELECT
+            (SELECT COUNT(*) FROM order_events
+                WHERE symbol = $1 AND event_type = 'placed'
+                    AND created_at >= NOW() - make_interval(secs => $2))::bigint,
+            (SELECT COUNT(*) FROM order_events
+                WHERE symbol = $1 AND event_type = 'cancelled'
+                    AND created_at >= NOW() - make_interval(secs => $2))::bigint,
+            (SELECT COUNT(*) FROM trades
+                WHERE symbol = $1
+                    AND created_at >= NOW() - make_interval(secs => $2)
+                    AND price > 0 AND quantity > 0)::bigint",
+    )
+    .bind(symbol)
+    .bind(window_secs as f64)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(FlowStats::from_counts(
+        symbol,
+        window_secs,
+        placements,
+        cancellations,
+        trades,
+    ))
+}
+
+/// Get order placement/cancellation rates and the cancel-to-trade ratio
+///
+/// Query parameters:
+/// - `symbol`: Trading pair (e.g., "ETH/USDT", default: the configured default market)
+/// - `window`: Look-back window ("30s", "5m", "1h", "1d" or seconds, default 5m, max 7d)
+pub async fn get_flow_stats(
+    Query(params): Query<FlowQuery>,
+    State(pool): State<PgPool>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> Result<Json<FlowStats>, ApiError> {
+    let window_secs = match params.window.as_deref() {
+        None => DEFAULT_WINDOW_SECS,
+        Some(window) => match parse_window(window) {
+            Some(secs) if secs <= MAX_WINDOW_SECS => secs,
+            _ => return Err(ApiError::BadRequest(format!("Invalid window: {}", window))),
+        },
+    };
+    let symbol = config.symbol_or_default(params.symbol.as_deref());
+
+    fetch_flow_stats(&pool, symbol, window_secs)
+        .await
+        .map(Json)
+        .map_err(|e| ApiError::database("get_flow_stats", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::{insert_trade, TestTrade};
+    use crate::indexer::order_events::{record_order_event, OrderEventKind};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_flow_rates_from_counts() {
+        let stats = FlowStats::from_counts("ETH/USDT", 60, 30, 12, 4);
+        assert_eq!(stats.placements_per_sec, Decimal::from_str("0.5").unwrap());
+        assert_eq!(
+            stats.cancellations_per_sec,
+            Decimal::from_str("0.2").unwrap()
+        );
+        assert_eq!(stats.cancel_to_trade_ratio, Decimal::from(3));
+
+        let empty = FlowStats::from_counts("ETH/USDT", 60, 0, 0, 0);
+        assert_eq!(empty.placements_per_sec, Decimal::ZERO);
+        assert_eq!(empty.cancel_to_trade_ratio, Decimal::ZERO);
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_flow_stats_from_event_stream(pool: PgPool) {
+        // Ten placements, four cancellations and two trades within the window
+        for order_id in 0..10 {
+            record_order_event(
+                &pool,
+                "ETH/USDT",
+                order_id,
+                OrderEventKind::Placed,
+                None,
+                1,
+                0,
+            )
+            .await
+            .unwrap();
+        }
+        for order_id in 0..4 {
+            record_order_event(
+                &pool,
+                "ETH/USDT",
+                order_id,
+                OrderEventKind::Cancelled,
+                None,
+                2,
+                0,
+            )
+            .await
+            .unwrap();
+        }
+        for trade_id in 0..2_i64 {
+            let trade = TestTrade {
+                trade_id,
+                ..TestTrade::default()
+            };
+            insert_trade(&pool, trade).await;
+        }
+        // Another market's events don't count
+        record_order_event(&pool, "DOT/USDT", 99, OrderEventKind::Placed, None, 1, 0)
+            .await
+            .unwrap();
+
+        let stats = fetch_flow_stats(&pool, "ETH/USDT", 10).await.unwrap();
+        assert_eq!(stats.placements, 10);
+        assert_eq!(stats.cancellations, 4);
+        assert_eq!(stats.trades, 2);
+        assert_eq!(stats.placements_per_sec, Decimal::ONE);
+        assert_eq!(
+            stats.cancellations_per_sec,
+            Decimal::from_str("0.4").unwrap()
+        );
+        assert_eq!(stats.cancel_to_trade_ratio, Decimal::from(2));
+    }
+}
@@ -0,0 +1,53 @@
+use crate::api::error::ApiError;
+use crate::api::state::{SharedIndexPricer, SharedOrderbook};
+use crate::config::IndexerConfig;
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Debug, Deserialize)]
+pub struct IndexQuery {
+    /// Trading pair symbol (e.g., "ETH/USDT"), defaults to the configured default market
+    pub symbol: Option<String>,
+}
+
+/// Index (mark) price of a market
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct IndexPrice {
+    pub symbol: String,
+    /// None when the book lacks the liquidity the method needs
+    pub index_price: Option<Decimal>,
+    /// Computation used, e.g. "mid"
+    pub method: &'static str,
+    /// Computation time in milliseconds
+    pub time: i64,
+}
+
+/// Get the index price, distinct from the last trade price
+///
+/// Query parameters:
+/// - `symbol`: Trading pair (e.g., "ETH/USDT", default: the configured default market)
+pub async fn get_index(
+    Query(params): Query<IndexQuery>,
+    State(orderbook): State<SharedOrderbook>,
+    State(pricer): State<SharedIndexPricer>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> Result<Json<IndexPrice>, ApiError> {
+    let symbol = config.symbol_or_default(params.symbol.as_deref());
+    // Only the default market has a tracked book
+    if symbol != config.default_symbol {
+        return Err(ApiError::NotFound(format!("Unknown symbol: {}", symbol)));
+    }
+
+    let index_price = pricer.index_price(&*orderbook.lock().await);
+    Ok(Json(IndexPrice {
+        symbol: symbol.to_string(),
+        index_price,
+        method: pricer.name(),
+        time: chrono::Utc::now().timestamp_millis(),
+    }))
+}
@@ -0,0 +1,126 @@
+use crate::api::state::SharedOrderbook;
+use crate::config::IndexerConfig;
+use crate::indexer::orderbook_reducer::OrderbookState;
+use axum::{
+    extract::State,
+    response::{IntoResponse, Json},
+};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Liquidity status of one configured market
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct MarketActivity {
+    pub symbol: String,
+    /// True when the book has both a best bid and a best ask
+    pub active: bool,
+    pub has_bids: bool,
+    pub has_asks: bool,
+    pub open_orders: usize,
+    /// Last trade time in milliseconds, None if the market never traded
+    pub last_trade_time: Option<i64>,
+}
+
+impl MarketActivity {
+    /// Describe a market from its book; markets without a tracked book are inactive
+    pub fn from_book(
+        symbol: &str,
+        book: Option<&OrderbookState>,
+        last_trade_time: Option<i64>,
+    ) -> Self {
+        let (has_bids, has_asks, open_orders) = book
+            .map(|ob| {
+                (
+                    !ob.bids.is_empty(),
+                    !ob.asks.is_empty(),
+                    ob.open_order_count(),
+                )
+            })
+            .unwrap_or((false, false, 0));
+
+        Self {
+            symbol: symbol.to_string(),
+            active: book.is_some_and(|ob| ob.get_spread().is_some()),
+            has_bids,
+            has_asks,
+            open_orders,
+            last_trade_time,
+        }
+    }
+}
+
+/// Last trade time (ms) per symbol
+async fn fetch_last_trade_times(pool: &PgPool) -> Result<HashMap<String, i64>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (String, i64)>(
+        "SELECT symbol, (EXTRACT(EPOCH FROM MAX(created_at)) * 1000)::bigint
+        FROM trades
+        WHERE price > 0 AND quantity > 0
+        GROUP BY symbol",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().collect())
+}
+
+/// List every configured market with liquidity flags
+///
+/// Markets with no resting orders are listed as inactive rather than omitted.
+pub async fn get_active_markets(
+    State(orderbook): State<SharedOrderbook>,
+    State(pool): State<PgPool>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> impl IntoResponse {
+    let last_trades = fetch_last_trade_times(&pool).await.unwrap_or_else(|e| {
+        eprintln!("❌ Database error in get_active_markets: {}", e);
+        HashMap::new()
+    });
+
+    let ob = orderbook.lock().await;
+    // Only the default market has a tracked book until multi-market support lands
+    let default_symbol = config.default_symbol.as_str();
+
+    let markets: Vec<MarketActivity> = config
+        .markets
+        .iter()
+        .map(|market| {
+            let book = (market.symbol == default_symbol).then_some(&*ob);
+            MarketActivity::from_book(
+                &market.symbol,
+                book,
+                last_trades.get(&market.symbol).copied(),
+            )
+        })
+        .collect();
+
+    Json(markets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::order;
+
+    #[test]
+    fn test_liquid_and_empty_markets() {
+        let mut liquid = OrderbookState::new();
+        liquid.add_order(order(1, "Buy", 99, 1));
+        liquid.add_order(order(2, "Sell", 101, 1));
+        let empty = OrderbookState::new();
+
+        let eth = MarketActivity::from_book("ETH/USDT", Some(&liquid), Some(1_000));
+        assert!(eth.active);
+        assert_eq!(eth.open_orders, 2);
+        assert_eq!(eth.last_trade_time, Some(1_000));
+
+        let btc = MarketActivity::from_book("BTC/USD", Some(&empty), None);
+        assert!(!btc.active);
+        assert!(!btc.has_bids && !btc.has_asks);
+        assert_eq!(btc.open_orders, 0);
+
+        let untracked = MarketActivity::from_book("DOT/USDT", None, None);
+        assert!(!untracked.active);
+    }
+}
@@ -1,4 +1,11 @@
+pub mod flow_hand;
+pub mod index_hand;
+pub mod info_hand;
+pub mod markets_hand;
 pub mod ohlcv_hand;
 pub mod orderbook_hand;
+pub mod profile_hand;
+pub mod tape_hand;
+pub mod ticker_hand;
 pub mod trades_hand;
 pub mod udf;
@@ -1,37 +1,246 @@
-use crate::indexer::candle_aggregator::CandleUpdate;
+use crate::api::error::ApiError;
+use crate::indexer::candle_aggregator::{CandleReader, CandleUpdate};
 use axum::{
     extract::{Query, State},
     response::{IntoResponse, Json},
 };
+use rust_decimal::Decimal;
 use serde::Deserialize;
-use serde_json::json;
 use sqlx::PgPool;
 use std::sync::Arc;
-use tokio::sync::Mutex;
 
-use crate::indexer::orderbook_reducer::OrderbookState;
-
-pub type AppState = (Arc<Mutex<OrderbookState>>, PgPool);
+use crate::config::IndexerConfig;
 
 #[derive(Debug, Deserialize)]
 pub struct CandleQuery {
-    /// Trading pair symbol (e.g., "ETH/USDT")
-    pub symbol: String,
+    /// Trading pair symbol (default: the configured `DEFAULT_SYMBOL`)
+    pub symbol: Option<String>,
     /// Start time in seconds (Unix timestamp)
-    pub start_time: i64,
+    pub start_time: Option<i64>,
     /// End time in seconds (Unix timestamp)
-    pub end_time: i64,
+    pub end_time: Option<i64>,
+    /// First block to include, instead of a time range
+    pub from_block: Option<i64>,
+    /// Last block to include (inclusive)
+    pub to_block: Option<i64>,
     /// Interval/timeframe (e.g., "1m", "5m", "15m", "1h", etc.)
     pub interval: String,
+    /// Add an RFC3339 `time_iso` field to every candle
+    pub with_iso: Option<bool>,
+}
+
+/// Which rows a candle request covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandleRange {
+    /// Seconds, `start..end`
+    Time { start: i64, end: i64 },
+    /// Block numbers, both ends inclusive
+    Blocks { from: i64, to: i64 },
+}
+
+impl CandleQuery {
+    /// Either a complete time range or a complete block range, never both
+    pub fn range(&self) -> Result<CandleRange, String> {
+        match (
+            self.start_time,
+            self.end_time,
+            self.from_block,
+            self.to_block,
+        ) {
+            (Some(start), Some(end), None, None) => Ok(CandleRange::Time { start, end }),
+            (None, None, Some(from), Some(to)) if from <= to => {
+                Ok(CandleRange::Blocks { from, to })
+            }
+            (None, None, Some(_), Some(_)) => {
+                Err("from_block must not exceed to_block".to_string())
+            }
+            (None, None, None, None) => {
+                Err("Specify start_time/end_time or from_block/to_block".to_string())
+            }
+            (start, end, None, None) if start.is_some() || end.is_some() => {
+                Err("Both start_time and end_time are required".to_string())
+            }
+            (None, None, _, _) => Err("Both from_block and to_block are required".to_string()),
+            _ => Err("Use either a time range or a block range, not both".to_string()),
+        }
+    }
+}
+
+/// TimescaleDB view holding candles for an interval
+pub fn candle_view(interval: &str) -> Option<&'static str> {
+    match interval {
+        "1m" => Some("one_minute_candles"),
+        "5m" => Some("five_minutes_candles"),
+        "15m" => Some("fifteen_minutes_candles"),
+        "30m" => Some("thirty_minutes_candles"),
+        "1h" => Some("one_hour_candles"),
+        "4h" => Some("four_hours_candles"),
+        "1d" => Some("one_day_candles"),
+        "1w" => Some("one_week_candles"),
+        "1M" => Some("one_month_candles"),
+        _ => None,
+    }
+}
+
+/// `time_bucket` width for an interval, matching the continuous aggregates
+pub fn bucket_width(interval: &str) -> Option<&'static str> {
+    match interval {
+        "1m" => Some("1 minute"),
+        "5m" => Some("5 minutes"),
+        "15m" => Some("15 minutes"),
+        "30m" => Some("30 minutes"),
+        "1h" => Some("1 hour"),
+        "4h" => Some("4 hours"),
+        "1d" => Some("1 day"),
+        "1w" => Some("1 week"),
+        "1M" => Some("1 month"),
+        _ => None,
+    }
+}
+
+/// Load candles for `symbol` over a time or block range.
+///
+/// Buckets are stored as UTC `TIMESTAMPTZ` (`time_bucket` over `trades.created_at`) and
+/// served as unix time, so SQL tooling and the API agree whatever the session time zone.
+/// Time ranges read the continuous aggregates. Block ranges can't, since the aggregates
+/// don't keep block numbers, so they aggregate the matching trades directly with the
+/// same `candlestick_agg` the views use.
+pub async fn fetch_candles(
+    pool: &PgPool,
+    symbol: &str,
+    interval: &str,
+    range: CandleRange,
+    limit: i64,
+) -> Result<Vec<CandleUpdate>, sqlx::Error> {
+    let (Some(view_name), Some(width)) = (candle_view(interval), bucket_width(interval)) else {
+        return Ok(Vec::new());
+    };
+
+    // Note: bucket is timestamp, open/high/low/close/volume/vwap are NUMERIC (vwap is NULL
+    // without volume), trade_count is BIGINT
+    let (query, lower, upper) = match range {
+        CandleRange::Time { start, end } => (
+            format!(
+                "SELECT
+                    EXTRACT(EPOCH FROM bucket)::bigint as bucket_time,
+                    open::numeric as open,
+                    high::numeric as high,
+                    low::numeric as low,
+                    close::numeric as close,
+                    volume::numeric as volume,
+                    vwap::numeric as vwap,
+                    trade_count::bigint as trade_count
+                FROM {}
+                WHERE symbol = $1
+                    AND bucket >= to_timestamp($2)
+                    AND bucket < to_timestamp($3)
+                ORDER BY bucket ASC
+                LIMIT $4",
+                view_name
+            ),
+            start,
+            end,
+        ),
+        CandleRange::Blocks { from, to } => (
+            format!(
+                "SELECT
+                    EXTRACT(EPOCH FROM bucket)::bigint as bucket_time,
+                    open(candlestick)::numeric as open,
+                    high(candlestick)::numeric as high,
+                    low(candlestick)::numeric as low,
+                    close(candlestick)::numeric as close,
+                    volume(candlestick)::numeric as volume,
+                    vwap(candlestick)::numeric as vwap,
+                    trade_count
+                FROM (
+                    SELECT time_bucket('{}', created_at) AS bucket,
+                        candlestick_agg(created_at, price, quantity) AS candlestick,
+                        COUNT(*)::bigint AS trade_count
+                    FROM trades
+                    WHERE symbol = $1
+                        AND block_number BETWEEN $2 AND $3
+                        AND price > 0 AND quantity > 0
+                    GROUP BY 1
+                ) candles
+                ORDER BY bucket ASC
+                LIMIT $4",
+                width
+            ),
+            from,
+            to,
+        ),
+    };
+
+    type CandleRow = (
+        i64,
+        Decimal,
+        Decimal,
+        Decimal,
+        Decimal,
+        Decimal,
+        Option<Decimal>,
+        i64,
+    );
+    let rows = sqlx::query_as::<_, CandleRow>(&query)
+        .bind(symbol)
+        .bind(lower)
+        .bind(upper)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+    // Calculate interval duration in milliseconds
+    let interval_ms = match interval {
+        "1m" => 60_000,
+        "5m" => 300_000,
+        "15m" => 900_000,
+        "30m" => 1_800_000,
+        "1h" => 3_600_000,
+        "4h" => 14_400_000,
+        "1d" => 86_400_000,
+        "1w" => 604_800_000,
+        "1M" => 2_592_000_000, // ~30 days
+        _ => 60_000,
+    };
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(bucket_time, open, high, low, close, volume, vwap, trade_count)| {
+                let start_time_ms = bucket_time * 1000;
+                let end_time_ms = start_time_ms + interval_ms;
+
+                CandleUpdate {
+                    end_time: end_time_ms,
+                    t: start_time_ms,
+                    o: open.normalize().to_string(),
+                    h: high.normalize().to_string(),
+                    l: low.normalize().to_string(),
+                    c: close.normalize().to_string(),
+                    v: volume.normalize().to_string(),
+                    vwap: vwap.unwrap_or(close).normalize().to_string(),
+                    i: interval.to_string(),
+                    s: symbol.to_string(),
+                    n: trade_count as u64,
+                    time_iso: None,
+                }
+            },
+        )
+        .collect())
 }
 
 /// Get historical OHLCV candles in Hyperliquid format
 ///
 /// Query parameters:
-/// - `symbol`: Trading pair (e.g., "ETH/USDT")
+/// - `symbol`: Trading pair (default: `DEFAULT_SYMBOL`)
 /// - `start_time`: Start timestamp in SECONDS (Unix epoch)
 /// - `end_time`: End timestamp in SECONDS (Unix epoch)
+/// - `from_block` / `to_block`: Inclusive block range, instead of `start_time`/`end_time`
 /// - `interval`: Time interval ("1m", "5m", "15m", "30m", "1h", "4h", "1d", "1w", "1M")
+/// - `with_iso`: Add `time_iso` (RFC3339 UTC bucket start) to each candle (default: false)
+///
+/// Requests spanning more than the interval's `MAX_HISTORY_RANGES` window are clamped to
+/// the most recent allowed window, or rejected when `CLAMP_HISTORY_RANGE=false`.
 ///
 /// Returns array of candles in Hyperliquid format:
 /// ```json
@@ -52,102 +261,174 @@ pub struct CandleQuery {
 /// ```
 pub async fn get_candles(
     Query(params): Query<CandleQuery>,
-    State((_orderbook, pool)): State<AppState>,
-) -> impl IntoResponse {
-    // Map interval to TimescaleDB view names
-    let view_name = match params.interval.as_str() {
-        "1m" => "one_minute_candles",
-        "5m" => "five_minutes_candles",
-        "15m" => "fifteen_minutes_candles",
-        "30m" => "thirty_minutes_candles",
-        "1h" => "one_hour_candles",
-        "4h" => "four_hours_candles",
-        "1d" => "one_day_candles",
-        "1w" => "one_week_candles",
-        "1M" => "one_month_candles",
-        _ => {
-            return Json(json!({
-                "error": format!("Unsupported interval: {}", params.interval)
-            }));
-        }
-    };
+    State(pool): State<PgPool>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> Result<Json<Vec<CandleUpdate>>, ApiError> {
+    if candle_view(&params.interval).is_none() {
+        return Err(ApiError::BadRequest(format!(
+            "Unsupported interval: {}",
+            params.interval
+        )));
+    }
 
     // Limit candles to prevent abuse
     const MAX_CANDLES: i64 = 5000;
 
-    // Query TimescaleDB for candles
-    // Note: bucket is timestamp, open/high/low/close/volume are NUMERIC, trade_count is BIGINT
-    let query = format!(
-        "SELECT
-            EXTRACT(EPOCH FROM bucket)::bigint as bucket_time,
-            open::float8 as open,
-            high::float8 as high,
-            low::float8 as low,
-            close::float8 as close,
-            volume::float8 as volume,
-            trade_count::bigint as trade_count
-        FROM {}
-        WHERE symbol = $1
-            AND bucket >= to_timestamp($2)
-            AND bucket < to_timestamp($3)
-        ORDER BY bucket ASC
-        LIMIT $4",
-        view_name
-    );
+    let range = match params.range().map_err(ApiError::BadRequest)? {
+        CandleRange::Time { start, end } => {
+            // Cap how far back a single request may reach for this interval
+            let start = config
+                .limit_history_range(&params.interval, start, end)
+                .map_err(ApiError::BadRequest)?;
+            CandleRange::Time { start, end }
+        }
+        blocks => blocks,
+    };
 
-    match sqlx::query_as::<_, (i64, f64, f64, f64, f64, f64, i64)>(&query)
-        .bind(&params.symbol)
-        .bind(params.start_time)
-        .bind(params.end_time)
-        .bind(MAX_CANDLES)
-        .fetch_all(&pool)
+    let symbol = config.symbol_or_default(params.symbol.as_deref());
+    let candles = fetch_candles(&pool, symbol, &params.interval, range, MAX_CANDLES)
         .await
-    {
-        Ok(rows) => {
-            let candles: Vec<CandleUpdate> = rows
-                .into_iter()
-                .map(
-                    |(bucket_time, open, high, low, close, volume, trade_count)| {
-                        // Calculate interval duration in milliseconds
-                        let interval_ms = match params.interval.as_str() {
-                            "1m" => 60_000,
-                            "5m" => 300_000,
-                            "15m" => 900_000,
-                            "30m" => 1_800_000,
-                            "1h" => 3_600_000,
-                            "4h" => 14_400_000,
-                            "1d" => 86_400_000,
-                            "1w" => 604_800_000,
-                            "1M" => 2_592_000_000, // ~30 days
-                            _ => 60_000,
-                        };
-
-                        let start_time_ms = bucket_time * 1000;
-                        let end_time_ms = start_time_ms + interval_ms;
-
-                        CandleUpdate {
-                            end_time: end_time_ms,
-                            t: start_time_ms,
-                            o: open.to_string(),
-                            h: high.to_string(),
-                            l: low.to_string(),
-                            c: close.to_string(),
-                            v: volume.to_string(),
-                            i: params.interval.clone(),
-                            s: params.symbol.clone(),
-                            n: trade_count as u64,
-                        }
-                    },
-                )
-                .collect();
-
-            Json(json!(candles))
+        .map_err(|e| ApiError::database("get_candles", e))?;
+    if params.with_iso.unwrap_or(false) {
+        return Ok(Json(
+            candles.into_iter().map(CandleUpdate::with_iso).collect(),
+        ));
+    }
+    Ok(Json(candles))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OpenCandleQuery {
+    /// Only candles for this symbol
+    pub symbol: Option<String>,
+    /// Only candles for this interval (e.g., "1m")
+    pub interval: Option<String>,
+}
+
+/// Get the candles currently being built by the aggregator
+///
+/// Served from the aggregator's published snapshot, so it never waits on the indexer.
+/// Query parameters `symbol` and `interval` are optional filters.
+pub async fn get_open_candles(
+    Query(params): Query<OpenCandleQuery>,
+    State(reader): State<CandleReader>,
+) -> impl IntoResponse {
+    let snapshot = reader.snapshot();
+    let mut candles: Vec<CandleUpdate> = snapshot
+        .values()
+        .filter(|c| params.symbol.as_ref().is_none_or(|s| *s == c.symbol))
+        .filter(|c| params.interval.as_ref().is_none_or(|i| *i == c.timeframe))
+        .map(|c| CandleUpdate::from_candle(c, false))
+        .collect();
+    candles.sort_by(|a, b| a.s.cmp(&b.s).then(a.t.cmp(&b.t)).then(a.i.cmp(&b.i)));
+
+    Json(candles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::{insert_trade, TestTrade};
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    fn query(times: (Option<i64>, Option<i64>), blocks: (Option<i64>, Option<i64>)) -> CandleQuery {
+        CandleQuery {
+            symbol: Some("ETH/USDT".to_string()),
+            start_time: times.0,
+            end_time: times.1,
+            from_block: blocks.0,
+            to_block: blocks.1,
+            interval: "1m".to_string(),
+            with_iso: None,
         }
-        Err(e) => {
-            eprintln!("❌ Database error in get_candles: {}", e);
-            Json(json!({
-                "error": format!("Database error: {}", e)
-            }))
+    }
+
+    #[test]
+    fn test_candle_range_selection() {
+        assert_eq!(
+            query((Some(0), Some(60)), (None, None)).range(),
+            Ok(CandleRange::Time { start: 0, end: 60 })
+        );
+        assert_eq!(
+            query((None, None), (Some(5), Some(9))).range(),
+            Ok(CandleRange::Blocks { from: 5, to: 9 })
+        );
+        assert!(query((Some(0), Some(60)), (Some(5), Some(9)))
+            .range()
+            .is_err());
+        assert!(query((Some(0), None), (None, None)).range().is_err());
+        assert!(query((None, None), (Some(9), None)).range().is_err());
+        assert!(query((None, None), (Some(9), Some(5))).range().is_err());
+        assert!(query((None, None), (None, None)).range().is_err());
+    }
+
+    fn trade(id: i64, block: i64, price: &str, qty: &str) -> TestTrade {
+        TestTrade {
+            trade_id: id,
+            block_number: block,
+            price: Decimal::from_str(price).unwrap(),
+            quantity: Decimal::from_str(qty).unwrap(),
+            time_ms: Some(1_700_000_000_000),
+            ..TestTrade::default()
         }
     }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_candles_filtered_by_block_range(pool: PgPool) {
+        insert_trade(&pool, trade(1, 10, "2000", "1")).await;
+        insert_trade(&pool, trade(2, 11, "2100", "2")).await;
+        insert_trade(&pool, trade(3, 12, "1900", "4")).await;
+
+        let range = CandleRange::Blocks { from: 10, to: 11 };
+        let candles = fetch_candles(&pool, "ETH/USDT", "1m", range, 100)
+            .await
+            .unwrap();
+
+        assert_eq!(candles.len(), 1);
+        assert_eq!(candles[0].n, 2);
+        assert_eq!(candles[0].h, "2100");
+        assert_eq!(candles[0].l, "2000");
+        assert_eq!(candles[0].v, "3");
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_served_time_matches_stored_bucket(pool: PgPool) {
+        // 2023-11-14T22:13:20Z, inside the 22:13 minute
+        insert_trade(&pool, trade(1, 10, "2000", "1")).await;
+
+        let stored: chrono::DateTime<chrono::Utc> =
+            sqlx::query_scalar("SELECT time_bucket('1 minute', created_at) FROM trades")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        let range = CandleRange::Blocks { from: 10, to: 10 };
+        let candles = fetch_candles(&pool, "ETH/USDT", "1m", range, 100)
+            .await
+            .unwrap();
+
+        assert_eq!(stored.to_rfc3339(), "2023-11-14T22:13:00+00:00");
+        assert_eq!(candles[0].t, stored.timestamp_millis());
+        assert_eq!(candles[0].t / 1000, 1_699_999_980);
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_omitted_symbol_defaults(pool: PgPool) {
+        insert_trade(&pool, trade(1, 10, "100", "2")).await;
+        let config = Arc::new(IndexerConfig::default());
+        assert_eq!(config.default_symbol, "ETH/USDT");
+
+        let query = CandleQuery {
+            symbol: None,
+            ..query((None, None), (Some(10), Some(10)))
+        };
+        let Json(candles) = get_candles(Query(query), State(pool), State(config))
+            .await
+            .unwrap();
+        assert_eq!(candles.len(), 1);
+        assert_eq!((candles[0].s.as_str(), candles[0].n), ("ETH/USDT", 1));
+    }
 }
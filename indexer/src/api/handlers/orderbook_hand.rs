@@ -1,59 +1,623 @@
-use crate::indexer::orderbook_reducer::OrderbookState;
+use crate::api::error::ApiError;
+use crate::api::state::{AppState, SharedOrderbook};
+use crate::config::IndexerConfig;
+use crate::indexer::order_events::fetch_order_history;
+use crate::indexer::orderbook_reducer::{OrderInfo, OrderbookSnapshot, OrderbookState};
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::{IntoResponse, Json},
+    extract::{Path, Query, State},
+    response::Json,
     routing::get,
     Router,
 };
-use serde_json::json;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
 use sqlx::PgPool;
 use std::sync::Arc;
-use tokio::sync::Mutex;
 
 // Type alias for our shared state
-pub type AppState = (Arc<Mutex<OrderbookState>>, PgPool);
+/// Most markets accepted by a single `/api/orderbooks` call
+const MAX_SYMBOLS: usize = 20;
+/// Default and maximum levels per side returned by `/api/orderbooks`
+const DEFAULT_DEPTH: usize = 20;
+const MAX_DEPTH: usize = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct MultiOrderbookQuery {
+    /// Comma-separated symbols (e.g., "ETH/USDT,BTC/USD")
+    pub symbols: String,
+    /// Levels per side (default 20, max 100)
+    pub depth: Option<usize>,
+}
+
+/// Default and maximum orders per side returned by `/api/orderbook/orders`
+const DEFAULT_ORDER_LIMIT: usize = 100;
+const MAX_ORDER_LIMIT: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct OrdersQuery {
+    /// Trading pair symbol, defaults to the configured default market
+    pub symbol: Option<String>,
+    /// "Buy" or "Sell", both sides when omitted
+    pub side: Option<String>,
+    /// Orders per side (default 100, max 1000)
+    pub limit: Option<usize>,
+}
+
+/// Default levels per side returned by `/api/orderbook`
+const DEFAULT_SNAPSHOT_LEVELS: usize = 50;
+
+/// Default levels per side `/api/orderbook/metrics` looks at
+const DEFAULT_METRIC_LEVELS: usize = 5;
+
+#[derive(Debug, Deserialize)]
+pub struct BookMetricsQuery {
+    /// Trading pair symbol, defaults to the configured default market
+    pub symbol: Option<String>,
+    /// Levels per side for imbalance and weighted mid (default 5, max 100)
+    pub levels: Option<usize>,
+}
+
+/// Top-of-book signals; each is null when the book is too empty to define it
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BookMetrics {
+    pub symbol: String,
+    pub levels: usize,
+    /// `(bid_size - ask_size) / (bid_size + ask_size)` over `levels` levels
+    pub imbalance: Option<Decimal>,
+    /// Touch prices weighted by the opposite touch sizes
+    pub microprice: Option<Decimal>,
+    /// Microprice over `levels` levels, from each side's volume-weighted price
+    pub weighted_mid: Option<Decimal>,
+    pub mid: Option<Decimal>,
+}
+
+impl BookMetrics {
+    pub fn from_book(symbol: &str, ob: &OrderbookState, levels: usize) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            levels,
+            imbalance: ob.imbalance(levels).map(|d| d.normalize()),
+            microprice: ob.microprice().map(|d| d.normalize()),
+            weighted_mid: ob.weighted_mid(levels).map(|d| d.normalize()),
+            mid: ob.mid_price().map(|d| d.normalize()),
+        }
+    }
+}
+
+/// One resting order, unaggregated
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RestingOrder {
+    pub order_id: u64,
+    pub side: String,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub filled_quantity: Decimal,
+    pub remaining_quantity: Decimal,
+}
+
+impl From<&OrderInfo> for RestingOrder {
+    fn from(order: &OrderInfo) -> Self {
+        Self {
+            order_id: order.order_id,
+            side: order.side.clone(),
+            price: order.price,
+            quantity: order.quantity,
+            filled_quantity: order.filled_quantity,
+            remaining_quantity: order.quantity - order.filled_quantity,
+        }
+    }
+}
+
+/// Up to `limit` resting orders per side in price-time priority
+pub fn resting_orders(ob: &OrderbookState, side: &str, limit: usize) -> Vec<RestingOrder> {
+    ob.orders_by_priority(side)
+        .into_iter()
+        .take(limit)
+        .map(RestingOrder::from)
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BookRangeQuery {
+    /// Trading pair symbol, defaults to the configured default market
+    pub symbol: Option<String>,
+    /// Window start (unix seconds)
+    pub from: i64,
+    /// Window end (unix seconds)
+    pub to: i64,
+}
+
+/// Book-based price extremes over a window, from the top-of-book samples
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BookRange {
+    pub symbol: String,
+    pub from: i64,
+    pub to: i64,
+    pub samples: i64,
+    pub mid_low: Decimal,
+    pub mid_high: Decimal,
+    pub best_bid_low: Decimal,
+    pub best_bid_high: Decimal,
+    pub best_ask_low: Decimal,
+    pub best_ask_high: Decimal,
+}
+
+/// Min/max of the sampled mid, best bid and best ask in `[from, to)`, None without samples
+pub async fn fetch_book_range(
+    pool: &PgPool,
+    symbol: &str,
+    from: i64,
+    to: i64,
+) -> Result<Option<BookRange>, sqlx::Error> {
+    let row = sqlx::query_as::<
+        _,
+        (
+            i64,
+            Option<Decimal>,
+            Option<Decimal>,
+            Option<Decimal>,
+            Option<Decimal>,
+            Option<Decimal>,
+            Option<Decimal>,
+        ),
+    >(
+        "SELECT COUNT(*)::bigint, MIN(mid), MAX(mid), MIN(best_bid), MAX(best_bid),
+            MIN(best_ask), MAX(best_ask)
+        FROM book_samples
+        WHERE symbol = $1
+            AND sampled_at >= to_timestamp($2)
+            AND sampled_at < to_timestamp($3)",
+    )
+    .bind(symbol)
+    .bind(from)
+    .bind(to)
+    .fetch_one(pool)
+    .await?;
+
+    let (samples, mid_low, mid_high, bid_low, bid_high, ask_low, ask_high) = row;
+    if samples == 0 {
+        return Ok(None);
+    }
+    Ok(Some(BookRange {
+        symbol: symbol.to_string(),
+        from,
+        to,
+        samples,
+        mid_low: mid_low.unwrap_or_default().normalize(),
+        mid_high: mid_high.unwrap_or_default().normalize(),
+        best_bid_low: bid_low.unwrap_or_default(),
+        best_bid_high: bid_high.unwrap_or_default(),
+        best_ask_low: ask_low.unwrap_or_default(),
+        best_ask_high: ask_high.unwrap_or_default(),
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RawQuery {
+    /// Include unscaled on-chain integers as `raw_price`/`raw_quantity` (default: false)
+    pub raw: Option<bool>,
+}
+
+/// Serialize a snapshot, adding each level's raw on-chain price and remaining quantity
+pub fn snapshot_with_raw(ob: &OrderbookState, snapshot: &OrderbookSnapshot) -> Value {
+    let mut value = json!(snapshot);
+    for (key, side, levels) in [
+        ("bids", "Buy", &snapshot.bids),
+        ("asks", "Sell", &snapshot.asks),
+    ] {
+        let Some(entries) = value[key].as_array_mut() else {
+            continue;
+        };
+        // The aggregated tail level has no single on-chain level to report
+        for (entry, level) in entries.iter_mut().zip(levels).filter(|(_, l)| !l.rest) {
+            if let Some((raw_price, raw_quantity)) = ob.raw_level(side, &level.price) {
+                entry["raw_price"] = json!(raw_price.to_string());
+                entry["raw_quantity"] = json!(raw_quantity.to_string());
+            }
+        }
+    }
+    value
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OrderbookQuery {
+    /// Trading pair symbol, defaults to the configured default market
+    pub symbol: Option<String>,
+    /// Include unscaled on-chain integers as `raw_price`/`raw_quantity` (default: false)
+    pub raw: Option<bool>,
+    /// Merge levels into buckets of this price step, a multiple of the tick size
+    pub agg: Option<Decimal>,
+    /// Include each level's price times quantity as `notional` (default: false)
+    pub with_notional: Option<bool>,
+    /// Price levels per side (default 50)
+    pub levels: Option<usize>,
+}
+
+pub async fn get_orderbook(
+    Query(params): Query<OrderbookQuery>,
+    State(orderbook): State<SharedOrderbook>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> Result<Json<Value>, ApiError> {
+    let symbol = config.symbol_or_default(params.symbol.as_deref());
+    if symbol != config.default_symbol {
+        return Err(ApiError::untracked_market(symbol));
+    }
+    let raw = params.raw.unwrap_or(false);
+    let agg = params
+        .agg
+        .map(|agg| config.aggregation_step(agg))
+        .transpose()
+        .map_err(ApiError::BadRequest)?;
+    if raw && agg.is_some() {
+        // Aggregated levels have no single on-chain level to report
+        return Err(ApiError::BadRequest(
+            "raw and agg cannot be combined".to_string(),
+        ));
+    }
+
+    let levels = params.levels.unwrap_or(DEFAULT_SNAPSHOT_LEVELS).max(1);
 
-pub async fn get_orderbook(State((orderbook, _pool)): State<AppState>) -> impl IntoResponse {
     let ob = orderbook.lock().await;
-    let snapshot = ob.get_snapshot();
+    let mut snapshot = match agg {
+        // Buckets can span many levels, so aggregate the whole book before cutting
+        Some(step) => ob.get_snapshot().aggregated(step).truncated(levels),
+        None => ob.get_snapshot_depth(levels),
+    };
+    // Computed after aggregation so merged levels report their own notional
+    if params.with_notional.unwrap_or(false) {
+        snapshot = snapshot.with_notional();
+    }
 
-    Json(snapshot)
+    if raw {
+        return Ok(Json(snapshot_with_raw(&ob, &snapshot)));
+    }
+    Ok(Json(json!(snapshot)))
 }
 
 pub async fn get_order(
-    State((orderbook, _pool)): State<AppState>,
+    Query(params): Query<RawQuery>,
+    State(orderbook): State<SharedOrderbook>,
+    Path(order_id): Path<u64>,
+) -> Result<Json<Value>, ApiError> {
+    let ob = orderbook.lock().await;
+
+    let order = ob
+        .orders
+        .get(&order_id)
+        .ok_or_else(|| ApiError::NotFound(format!("Order not found: {}", order_id)))?;
+    let mut body = json!({
+        "order_id": order.order_id,
+        "side": order.side,
+        "price": order.price,
+        "quantity": order.quantity,
+        "filled_quantity": order.filled_quantity,
+        "remaining_quantity": order.quantity - order.filled_quantity,
+        "status": order.status,
+    });
+    if params.raw.unwrap_or(false) {
+        body["raw_price"] = json!(order.raw_price.to_string());
+        body["raw_quantity"] = json!(order.raw_quantity.to_string());
+    }
+    Ok(Json(body))
+}
+
+/// Build the symbol -> snapshot map for `/api/orderbooks`.
+///
+/// Only the default (first configured) market has a tracked book today, so any other
+/// configured market reports an error entry instead of failing the whole request.
+pub fn collect_orderbooks(
+    ob: &OrderbookState,
+    config: &IndexerConfig,
+    symbols: &[&str],
+    depth: usize,
+) -> Map<String, Value> {
+    symbols
+        .iter()
+        .map(|symbol| {
+            let entry = if config.market(symbol).is_none() {
+                ApiError::NotFound(format!("Unknown market: {}", symbol)).body()
+            } else if *symbol == config.default_symbol {
+                json!(ob.get_snapshot().truncated(depth))
+            } else {
+                ApiError::untracked_market(symbol).body()
+            };
+            (symbol.to_string(), entry)
+        })
+        .collect()
+}
+
+/// Get truncated snapshots for several markets in one call
+///
+/// Query parameters:
+/// - `symbols`: Comma-separated trading pairs (max 20)
+/// - `depth`: Levels per side (default 20, max 100)
+pub async fn get_orderbooks(
+    Query(params): Query<MultiOrderbookQuery>,
+    State(orderbook): State<SharedOrderbook>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> Result<Json<Map<String, Value>>, ApiError> {
+    let symbols: Vec<&str> = params
+        .symbols
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    if symbols.is_empty() || symbols.len() > MAX_SYMBOLS {
+        return Err(ApiError::BadRequest(format!(
+            "Expected between 1 and {} symbols",
+            MAX_SYMBOLS
+        )));
+    }
+
+    let depth = params.depth.unwrap_or(DEFAULT_DEPTH).min(MAX_DEPTH);
+    let ob = orderbook.lock().await;
+
+    Ok(Json(collect_orderbooks(&ob, &config, &symbols, depth)))
+}
+
+/// Get the recorded lifecycle of an order: placement, fills and cancellation with the
+/// block and time of each, in chain order
+pub async fn get_order_history(
+    State(pool): State<PgPool>,
     Path(order_id): Path<u64>,
-) -> impl IntoResponse {
+) -> Result<Json<Value>, ApiError> {
+    let events = fetch_order_history(&pool, order_id)
+        .await
+        .map_err(|e| ApiError::database("get_order_history", e))?;
+    if events.is_empty() {
+        return Err(ApiError::NotFound(format!(
+            "No history for order: {}",
+            order_id
+        )));
+    }
+    Ok(Json(json!({ "order_id": order_id, "events": events })))
+}
+
+/// Get individual resting orders in price-time priority, for matching simulations
+///
+/// Query parameters:
+/// - `symbol`: Trading pair (default: the configured default market)
+/// - `side`: "Buy" or "Sell" (default: both)
+/// - `limit`: Orders per side (default 100, max 1000)
+pub async fn get_orders(
+    Query(params): Query<OrdersQuery>,
+    State(orderbook): State<SharedOrderbook>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> Result<Json<Value>, ApiError> {
+    let symbol = config.symbol_or_default(params.symbol.as_deref());
+    if symbol != config.default_symbol {
+        return Err(ApiError::untracked_market(symbol));
+    }
+    let sides: &[&str] = match params.side.as_deref() {
+        None => &["Buy", "Sell"],
+        Some("Buy") => &["Buy"],
+        Some("Sell") => &["Sell"],
+        Some(other) => return Err(ApiError::BadRequest(format!("Invalid side: {}", other))),
+    };
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_ORDER_LIMIT)
+        .min(MAX_ORDER_LIMIT);
+
     let ob = orderbook.lock().await;
+    let mut body = json!({ "symbol": symbol });
+    for side in sides {
+        let key = if *side == "Buy" { "bids" } else { "asks" };
+        body[key] = json!(resting_orders(&ob, side, limit));
+    }
+    Ok(Json(body))
+}
+
+/// Get order-flow imbalance, microprice and weighted mid of the live book
+///
+/// Query parameters:
+/// - `symbol`: Trading pair (default: the configured default market)
+/// - `levels`: Levels per side for imbalance and weighted mid (default 5, max 100)
+pub async fn get_book_metrics(
+    Query(params): Query<BookMetricsQuery>,
+    State(orderbook): State<SharedOrderbook>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> Result<Json<BookMetrics>, ApiError> {
+    let symbol = config.symbol_or_default(params.symbol.as_deref());
+    if symbol != config.default_symbol {
+        return Err(ApiError::untracked_market(symbol));
+    }
+    let levels = params
+        .levels
+        .unwrap_or(DEFAULT_METRIC_LEVELS)
+        .clamp(1, MAX_DEPTH);
+
+    let ob = orderbook.lock().await;
+    Ok(Json(BookMetrics::from_book(symbol, &ob, levels)))
+}
 
-    match ob.orders.get(&order_id) {
-        Some(order) => (
-            StatusCode::OK,
-            Json(json!({
-                "order_id": order.order_id,
-                "side": order.side,
-                "price": order.price,
-                "quantity": order.quantity,
-                "filled_quantity": order.filled_quantity,
-                "remaining_quantity": order.quantity - order.filled_quantity,
-                "status": order.status,
-            })),
-        )
-            .into_response(),
-        None => (
-            StatusCode::NOT_FOUND,
-            Json(json!({
-                "error": "irder Not found",
-                "order_id": order_id,
-            })),
-        )
-            .into_response(),
+/// Get the lowest and highest mid price (and best bid/ask) sampled over a window
+///
+/// Query parameters:
+/// - `symbol`: Trading pair (default: the configured default market)
+/// - `from` / `to`: Window in unix seconds
+pub async fn get_book_range(
+    Query(params): Query<BookRangeQuery>,
+    State(pool): State<PgPool>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> Result<Json<BookRange>, ApiError> {
+    if params.from >= params.to {
+        return Err(ApiError::BadRequest("from must be before to".to_string()));
     }
+    let symbol = config.symbol_or_default(params.symbol.as_deref());
+
+    fetch_book_range(&pool, symbol, params.from, params.to)
+        .await
+        .map_err(|e| ApiError::database("get_book_range", e))?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound("No book samples in the requested window".to_string()))
 }
 
 pub async fn orderbook_routes() -> Router<AppState> {
     Router::new()
         .route("/api/orderbook", get(get_orderbook))
+        .route("/orders", get(get_orders))
+        .route("/range", get(get_book_range))
+        .route("/metrics", get(get_book_metrics))
         .route("/api/order/{id}", get(get_order))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::order;
+    use crate::config::MarketConfig;
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use rust_decimal::Decimal;
+    use tokio::sync::Mutex;
+
+    #[test]
+    fn test_collect_orderbooks_reports_per_symbol_errors() {
+        let mut config = IndexerConfig::default();
+        config.markets.push(MarketConfig::new(
+            "BTC/USD",
+            config.default_timeframes.clone(),
+        ));
+
+        let mut ob = OrderbookState::new();
+        for (id, price) in [(1, 100), (2, 99), (3, 98)] {
+            ob.add_order(order(id, "Buy", price, 1));
+        }
+
+        let books = collect_orderbooks(&ob, &config, &["ETH/USDT", "BTC/USD", "DOGE/USD"], 2);
+
+        let eth = &books["ETH/USDT"];
+        assert_eq!(eth["bids"].as_array().unwrap().len(), 2);
+        assert_eq!(eth["summary"]["total_bid_levels"], 3);
+        assert_eq!(books["BTC/USD"]["error"]["code"], "NOT_FOUND");
+        assert!(books["DOGE/USD"]["error"]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Unknown market"));
+    }
+
+    #[tokio::test]
+    async fn test_orderbook_selects_market_by_symbol() {
+        let mut config = IndexerConfig::default();
+        config.markets.push(MarketConfig::new(
+            "BTC/USD",
+            config.default_timeframes.clone(),
+        ));
+        let config = Arc::new(config);
+        let orderbook = Arc::new(Mutex::new(OrderbookState::new()));
+
+        let status = |symbol: Option<&str>| {
+            let query = OrderbookQuery {
+                symbol: symbol.map(str::to_string),
+                raw: None,
+                agg: None,
+                with_notional: None,
+                levels: None,
+            };
+            let (orderbook, config) = (orderbook.clone(), config.clone());
+            async move {
+                get_orderbook(Query(query), State(orderbook), State(config))
+                    .await
+                    .into_response()
+                    .status()
+            }
+        };
+        // Single-market clients keep working without a symbol
+        assert_eq!(status(None).await, StatusCode::OK);
+        assert_eq!(status(Some("ETH/USDT")).await, StatusCode::OK);
+        assert_eq!(status(Some("BTC/USD")).await, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_missing_order_is_a_not_found_error() {
+        let orderbook = Arc::new(Mutex::new(OrderbookState::new()));
+        let query = RawQuery { raw: None };
+        let error = get_order(Query(query), State(orderbook), Path(42))
+            .await
+            .unwrap_err();
+        assert_eq!(error, ApiError::NotFound("Order not found: 42".to_string()));
+    }
+
+    #[test]
+    fn test_resting_orders_in_price_time_priority() {
+        let mut ob = OrderbookState::new();
+        // (order id, side, price) in placement order
+        for (id, side, price) in [
+            (1, "Buy", 99),
+            (2, "Buy", 100),
+            (3, "Sell", 102),
+            (4, "Buy", 99),
+            (5, "Sell", 101),
+            (6, "Buy", 100),
+            (7, "Sell", 101),
+        ] {
+            ob.add_order(OrderInfo {
+                filled_quantity: Decimal::ONE,
+                ..order(id, side, price, 3)
+            });
+        }
+
+        let ids = |orders: Vec<RestingOrder>| orders.iter().map(|o| o.order_id).collect::<Vec<_>>();
+        assert_eq!(ids(resting_orders(&ob, "Buy", 10)), vec![2, 6, 1, 4]);
+        assert_eq!(ids(resting_orders(&ob, "Sell", 10)), vec![5, 7, 3]);
+        assert_eq!(ids(resting_orders(&ob, "Buy", 3)), vec![2, 6, 1]);
+        assert_eq!(
+            resting_orders(&ob, "Sell", 1)[0].remaining_quantity,
+            Decimal::TWO
+        );
+    }
+
+    #[test]
+    fn test_book_metrics_are_null_on_an_empty_book() {
+        let metrics = serde_json::to_value(BookMetrics::from_book(
+            "ETH/USDT",
+            &OrderbookState::new(),
+            DEFAULT_METRIC_LEVELS,
+        ))
+        .unwrap();
+        for key in ["imbalance", "microprice", "weighted_mid", "mid"] {
+            assert!(metrics[key].is_null(), "{} should be null", key);
+        }
+        assert_eq!(metrics["levels"], 5);
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_book_range_over_mid_series(pool: PgPool) {
+        use crate::indexer::book_sampler::{insert_sample, BookSample};
+        use std::str::FromStr;
+
+        // (best bid, best ask): mids 100, 103, 98.5, 101
+        for (bid, ask) in [("99", "101"), ("102", "104"), ("98", "99"), ("100", "102")] {
+            let (best_bid, best_ask) = (
+                Decimal::from_str(bid).unwrap(),
+                Decimal::from_str(ask).unwrap(),
+            );
+            let sample = BookSample {
+                best_bid,
+                best_ask,
+                mid: (best_bid + best_ask) / Decimal::TWO,
+            };
+            insert_sample(&pool, "ETH/USDT", &sample).await.unwrap();
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let range = fetch_book_range(&pool, "ETH/USDT", now - 60, now + 60)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(range.samples, 4);
+        assert_eq!(range.mid_low, Decimal::from_str("98.5").unwrap());
+        assert_eq!(range.mid_high, Decimal::from(103));
+        assert_eq!(range.best_bid_high, Decimal::from(102));
+        assert_eq!(range.best_ask_low, Decimal::from(99));
+
+        let empty = fetch_book_range(&pool, "ETH/USDT", now - 7200, now - 3600)
+            .await
+            .unwrap();
+        assert_eq!(empty, None);
+    }
+}
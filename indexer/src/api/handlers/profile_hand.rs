@@ -0,0 +1,226 @@
+use crate::api::error::ApiError;
+use crate::config::IndexerConfig;
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+
+/// Most price bins one profile may be split into
+const MAX_BINS: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct VolumeProfileQuery {
+    /// Trading pair symbol, defaults to the configured default market
+    pub symbol: Option<String>,
+    /// Window start (unix seconds)
+    pub from: i64,
+    /// Window end, exclusive (unix seconds)
+    pub to: i64,
+    /// Price bins spanning the window's range (default: `VOLUME_PROFILE_BINS`, max 500)
+    pub bins: Option<usize>,
+}
+
+/// Volume traded within one price range, `low` inclusive and `high` exclusive except
+/// for the top bin
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ProfileBin {
+    pub low: Decimal,
+    pub high: Decimal,
+    pub volume: Decimal,
+}
+
+/// Histogram of volume at price over a window
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct VolumeProfile {
+    pub symbol: String,
+    pub from: i64,
+    pub to: i64,
+    pub total_volume: Decimal,
+    /// Midpoint of the highest-volume bin, the lowest such bin on a tie
+    pub point_of_control: Decimal,
+    pub bins: Vec<ProfileBin>,
+}
+
+impl VolumeProfile {
+    /// Bucket `(price, volume)` levels into `bins` equal-width ranges between the lowest
+    /// and highest price, None when nothing traded. A window with a single price gets a
+    /// single bin.
+    pub fn from_levels(
+        symbol: &str,
+        from: i64,
+        to: i64,
+        levels: &[(Decimal, Decimal)],
+        bins: usize,
+    ) -> Option<Self> {
+        let low = levels.iter().map(|(price, _)| *price).min()?;
+        let high = levels.iter().map(|(price, _)| *price).max()?;
+        let bins = if low == high { 1 } else { bins.max(1) };
+        let width = (high - low) / Decimal::from(bins);
+
+        let mut histogram: Vec<ProfileBin> = (0..bins)
+            .map(|i| ProfileBin {
+                low: (low + width * Decimal::from(i)).normalize(),
+                high: if i + 1 == bins {
+                    high
+                } else {
+                    (low + width * Decimal::from(i + 1)).normalize()
+                },
+                volume: Decimal::ZERO,
+            })
+            .collect();
+        for (price, volume) in levels {
+            // Rounding in `width` can put the top price one bin past the end
+            let index = if width.is_zero() {
+                0
+            } else {
+                ((*price - low) / width)
+                    .floor()
+                    .try_into()
+                    .unwrap_or(usize::MAX)
+                    .min(bins - 1)
+            };
+            histogram[index].volume += *volume;
+        }
+
+        let peak = histogram
+            .iter()
+            .reduce(|peak, bin| if bin.volume > peak.volume { bin } else { peak })?;
+        Some(Self {
+            symbol: symbol.to_string(),
+            from,
+            to,
+            total_volume: histogram.iter().map(|bin| bin.volume).sum(),
+            point_of_control: ((peak.low + peak.high) / Decimal::TWO).normalize(),
+            bins: histogram,
+        })
+    }
+}
+
+/// Volume traded at each price of `symbol` in `[from, to)`, lowest price first
+pub async fn fetch_volume_at_price(
+    pool: &PgPool,
+    symbol: &str,
+    from: i64,
+    to: i64,
+) -> Result<Vec<(Decimal, Decimal)>, sqlx::Error> {
+    sqlx::query_as(
+        "SELECT price, SUM(quantity) AS volume
+        FROM trades
+        WHERE symbol = $1 AND created_at >= to_timestamp($2) AND created_at < to_timestamp($3)
+            AND price > 0 AND quantity > 0
+        GROUP BY price
+        ORDER BY price",
+    )
+    .bind(symbol)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+}
+
+/// Get traded volume bucketed by price over a window
+///
+/// Query parameters:
+/// - `symbol`: Trading pair (default: the configured default market)
+/// - `from`, `to`: Window in unix seconds, `to` exclusive
+/// - `bins`: Price bins spanning the window's price range (default: `VOLUME_PROFILE_BINS`,
+///   max 500)
+pub async fn get_volume_profile(
+    Query(params): Query<VolumeProfileQuery>,
+    State(pool): State<PgPool>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> Result<Json<VolumeProfile>, ApiError> {
+    if params.from >= params.to {
+        return Err(ApiError::BadRequest("from must be before to".to_string()));
+    }
+    let bins = params.bins.unwrap_or(config.volume_profile_bins);
+    if bins == 0 || bins > MAX_BINS {
+        return Err(ApiError::BadRequest(format!(
+            "bins must be between 1 and {}",
+            MAX_BINS
+        )));
+    }
+    let symbol = config.symbol_or_default(params.symbol.as_deref());
+
+    let levels = fetch_volume_at_price(&pool, symbol, params.from, params.to)
+        .await
+        .map_err(|e| ApiError::database("get_volume_profile", e))?;
+    VolumeProfile::from_levels(symbol, params.from, params.to, &levels, bins)
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound("No trades in window".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::{insert_trade, TestTrade};
+
+    fn levels(levels: &[(i64, i64)]) -> Vec<(Decimal, Decimal)> {
+        levels
+            .iter()
+            .map(|(price, volume)| (Decimal::from(*price), Decimal::from(*volume)))
+            .collect()
+    }
+
+    #[test]
+    fn test_profile_of_synthetic_trades() {
+        // Prices 100..=110 in four bins of 2.5
+        let trades = levels(&[(100, 1), (101, 2), (103, 5), (104, 1), (108, 3), (110, 4)]);
+        let profile = VolumeProfile::from_levels("ETH/USDT", 0, 60, &trades, 4).unwrap();
+
+        let bins: Vec<(String, String, Decimal)> = profile
+            .bins
+            .iter()
+            .map(|bin| (bin.low.to_string(), bin.high.to_string(), bin.volume))
+            .collect();
+        assert_eq!(
+            bins,
+            vec![
+                ("100".to_string(), "102.5".to_string(), Decimal::from(3)),
+                ("102.5".to_string(), "105".to_string(), Decimal::from(6)),
+                ("105".to_string(), "107.5".to_string(), Decimal::ZERO),
+                ("107.5".to_string(), "110".to_string(), Decimal::from(7)),
+            ]
+        );
+        assert_eq!(profile.total_volume, Decimal::from(16));
+        assert_eq!(profile.point_of_control.to_string(), "108.75");
+    }
+
+    #[test]
+    fn test_single_price_and_empty_windows() {
+        let profile =
+            VolumeProfile::from_levels("ETH/USDT", 0, 60, &levels(&[(100, 2)]), 24).unwrap();
+        assert_eq!(profile.bins.len(), 1);
+        assert_eq!(profile.point_of_control, Decimal::from(100));
+
+        assert!(VolumeProfile::from_levels("ETH/USDT", 0, 60, &[], 24).is_none());
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_volume_at_price_from_trades(pool: PgPool) {
+        for (trade_id, price, quantity) in [(1_i64, 100, 1), (2, 100, 2), (3, 105, 4)] {
+            let trade = TestTrade {
+                trade_id,
+                price: Decimal::from(price),
+                quantity: Decimal::from(quantity),
+                ..TestTrade::default()
+            };
+            insert_trade(&pool, trade).await;
+        }
+
+        let now = chrono::Utc::now().timestamp();
+        let at_price = fetch_volume_at_price(&pool, "ETH/USDT", now - 60, now + 60)
+            .await
+            .unwrap();
+        assert_eq!(at_price, levels(&[(100, 3), (105, 4)]));
+        assert!(fetch_volume_at_price(&pool, "DOT/USDT", now - 60, now + 60)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,234 @@
+use crate::api::error::ApiError;
+use crate::config::IndexerConfig;
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+
+/// Default look-back window when `window` is omitted (5 minutes)
+const DEFAULT_WINDOW_SECS: i64 = 300;
+/// Largest window we aggregate over (7 days)
+const MAX_WINDOW_SECS: i64 = 604_800;
+
+#[derive(Debug, Deserialize)]
+pub struct TapeQuery {
+    /// Trading pair symbol (default: the configured `DEFAULT_SYMBOL`)
+    pub symbol: Option<String>,
+    /// Look-back window, either plain seconds ("300") or with a unit ("30s", "5m", "1h", "1d")
+    pub window: Option<String>,
+}
+
+/// Time-and-sales summary over a window of recent trades
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TapeSummary {
+    pub symbol: String,
+    pub window_secs: i64,
+    pub trade_count: i64,
+    pub buy_count: i64,
+    pub sell_count: i64,
+    pub buy_volume: Decimal,
+    pub sell_volume: Decimal,
+    pub avg_trade_size: Decimal,
+    pub largest_trade: Decimal,
+    pub price_low: Decimal,
+    pub price_high: Decimal,
+}
+
+/// Parse a window like "300", "30s", "5m", "1h" or "1d" into seconds
+pub fn parse_window(window: &str) -> Option<i64> {
+    let window = window.trim();
+    let (digits, multiplier) = match window.chars().last()? {
+        's' => (&window[..window.len() - 1], 1),
+        'm' => (&window[..window.len() - 1], 60),
+        'h' => (&window[..window.len() - 1], 3_600),
+        'd' => (&window[..window.len() - 1], 86_400),
+        _ => (window, 1),
+    };
+
+    let value = digits.parse::<i64>().ok()?;
+    if value <= 0 {
+        return None;
+    }
+    value.checked_mul(multiplier)
+}
+
+/// Aggregate the tape for `symbol` over the last `window_secs` seconds.
+///
+/// Trades are split by their inferred aggressor `side`. Rows indexed before side inference
+/// (or with it disabled) fall back to order age: the taker is the newer of the two orders,
+/// so `buy_order_id > sell_order_id` means a buyer lifted a resting ask.
+/// Every aggregate is coalesced to zero so an empty window is not an error.
+pub async fn fetch_tape_summary(
+    pool: &PgPool,
+    symbol: &str,
+    window_secs: i64,
+) -> Result<TapeSummary, sqlx::Error> {
+    let row = sqlx::query_as::<
+        _,
+        (
+            i64,
+            i64,
+            i64,
+            Decimal,
+            Decimal,
+            Decimal,
+            Decimal,
+            Decimal,
+            Decimal,
+        ),
+    >(
+        "SELECT
+            COUNT(*)::bigint,
+            COUNT(*) FILTER (WHERE aggressor = 'Buy')::bigint,
+            COUNT(*) FILTER (WHERE aggressor = 'Sell')::bigint,
+            COALESCE(SUM(quantity) FILTER (WHERE aggressor = 'Buy'), 0),
+            COALESCE(SUM(quantity) FILTER (WHERE aggressor = 'Sell'), 0),
+            COALESCE(ROUND(AVG(quantity), 6), 0),
+            COALESCE(MAX(quantity), 0),
+            COALESCE(MIN(price), 0),
+            COALESCE(MAX(price), 0)
+        FROM (
+            SELECT price, quantity,
+                COALESCE(side, CASE WHEN buy_order_id > sell_order_id THEN 'Buy' ELSE 'Sell' END) AS aggressor
+            FROM trades
+            WHERE symbol = $1
+                AND created_at >= NOW() - make_interval(secs => $2)
+                AND price > 0 AND quantity > 0
+        ) recent",
+    )
+    .bind(symbol)
+    .bind(window_secs as f64)
+    .fetch_one(pool)
+    .await?;
+
+    let (
+        trade_count,
+        buy_count,
+        sell_count,
+        buy_volume,
+        sell_volume,
+        avg_trade_size,
+        largest_trade,
+        price_low,
+        price_high,
+    ) = row;
+
+    Ok(TapeSummary {
+        symbol: symbol.to_string(),
+        window_secs,
+        trade_count,
+        buy_count,
+        sell_count,
+        buy_volume,
+        sell_volume,
+        avg_trade_size: avg_trade_size.normalize(),
+        largest_trade,
+        price_low,
+        price_high,
+    })
+}
+
+/// Get a time-and-sales summary of recent trades
+///
+/// Query parameters:
+/// - `symbol`: Trading pair (default: `DEFAULT_SYMBOL`)
+/// - `window`: Look-back window ("30s", "5m", "1h", "1d" or seconds, default 5m, max 7d)
+pub async fn get_tape(
+    Query(params): Query<TapeQuery>,
+    State(pool): State<PgPool>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> Result<Json<TapeSummary>, ApiError> {
+    let window_secs = match params.window.as_deref() {
+        None => DEFAULT_WINDOW_SECS,
+        Some(window) => match parse_window(window) {
+            Some(secs) if secs <= MAX_WINDOW_SECS => secs,
+            _ => return Err(ApiError::BadRequest(format!("Invalid window: {}", window))),
+        },
+    };
+
+    let symbol = config.symbol_or_default(params.symbol.as_deref());
+    fetch_tape_summary(&pool, symbol, window_secs)
+        .await
+        .map(Json)
+        .map_err(|e| ApiError::database("get_tape", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::{insert_trade, TestTrade};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_parse_window() {
+        assert_eq!(parse_window("300"), Some(300));
+        assert_eq!(parse_window("30s"), Some(30));
+        assert_eq!(parse_window("5m"), Some(300));
+        assert_eq!(parse_window("1h"), Some(3_600));
+        assert_eq!(parse_window("1d"), Some(86_400));
+        assert_eq!(parse_window("0m"), None);
+        assert_eq!(parse_window("abc"), None);
+        assert_eq!(parse_window(""), None);
+    }
+
+    fn trade(id: i64, buy: i64, sell: i64, price: &str, qty: &str) -> TestTrade {
+        TestTrade {
+            trade_id: id,
+            buy_order_id: buy,
+            sell_order_id: sell,
+            price: Decimal::from_str(price).unwrap(),
+            quantity: Decimal::from_str(qty).unwrap(),
+            ..TestTrade::default()
+        }
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_tape_summary_aggregates(pool: PgPool) {
+        // Two buyer-initiated trades and one seller-initiated trade
+        insert_trade(&pool, trade(1, 10, 1, "2000", "1.5")).await;
+        insert_trade(&pool, trade(2, 11, 2, "2010", "0.5")).await;
+        insert_trade(&pool, trade(3, 3, 12, "1990", "4")).await;
+
+        let summary = fetch_tape_summary(&pool, "ETH/USDT", 300).await.unwrap();
+        assert_eq!(summary.trade_count, 3);
+        assert_eq!(summary.buy_count, 2);
+        assert_eq!(summary.sell_count, 1);
+        assert_eq!(summary.buy_volume, Decimal::from(2));
+        assert_eq!(summary.sell_volume, Decimal::from(4));
+        assert_eq!(summary.avg_trade_size, Decimal::from(2));
+        assert_eq!(summary.largest_trade, Decimal::from(4));
+        assert_eq!(summary.price_low, Decimal::from(1990));
+        assert_eq!(summary.price_high, Decimal::from(2010));
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_tape_summary_empty_window(pool: PgPool) {
+        let summary = fetch_tape_summary(&pool, "ETH/USDT", 300).await.unwrap();
+        assert_eq!(summary.trade_count, 0);
+        assert_eq!(summary.buy_volume, Decimal::ZERO);
+        assert_eq!(summary.price_high, Decimal::ZERO);
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_omitted_symbol_defaults(pool: PgPool) {
+        insert_trade(&pool, trade(1, 10, 1, "100", "2")).await;
+        let config = Arc::new(IndexerConfig::default());
+        assert_eq!(config.default_symbol, "ETH/USDT");
+
+        let query = TapeQuery {
+            symbol: None,
+            window: None,
+        };
+        let Json(tape) = get_tape(Query(query), State(pool), State(config))
+            .await
+            .unwrap();
+        assert_eq!((tape.symbol.as_str(), tape.trade_count), ("ETH/USDT", 1));
+    }
+}
@@ -0,0 +1,210 @@
+use crate::api::error::ApiError;
+use crate::config::IndexerConfig;
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Rolling window the ticker covers
+const WINDOW_MS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct TickerQuery {
+    /// Trading pair symbol, defaults to the configured default market
+    pub symbol: Option<String>,
+}
+
+/// Price and volume statistics over the last 24 hours
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Ticker {
+    pub symbol: String,
+    pub last: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub volume: Decimal,
+    /// Change from `open_24h` to `last` in percent
+    pub change_pct: Decimal,
+    /// Price of the first trade in the window
+    pub open_24h: Decimal,
+}
+
+#[derive(sqlx::FromRow)]
+struct TickerRow {
+    open: Option<Decimal>,
+    last: Option<Decimal>,
+    high: Option<Decimal>,
+    low: Option<Decimal>,
+    volume: Option<Decimal>,
+}
+
+/// Ticker of `symbol` over the 24 hours up to `now_ms`, None when nothing traded
+pub async fn fetch_ticker(
+    pool: &PgPool,
+    symbol: &str,
+    now_ms: i64,
+) -> Result<Option<Ticker>, sqlx::Error> {
+    let row: TickerRow = sqlx::query_as(
+        "SELECT
+            (array_agg(price ORDER BY created_at, trade_id))[1] AS open,
+            (array_agg(price ORDER BY created_at DESC, trade_id DESC))[1] AS last,
+            MAX(price) AS high,
+            MIN(price) AS low,
+            SUM(quantity) AS volume
+        FROM trades
+        WHERE symbol = $1
+            AND created_at > to_timestamp($2::bigint / 1000.0)
+            AND created_at <= to_timestamp($3::bigint / 1000.0)
+            AND price > 0 AND quantity > 0",
+    )
+    .bind(symbol)
+    .bind(now_ms - WINDOW_MS)
+    .bind(now_ms)
+    .fetch_one(pool)
+    .await?;
+
+    let (Some(open), Some(last), Some(high), Some(low), Some(volume)) =
+        (row.open, row.last, row.high, row.low, row.volume)
+    else {
+        return Ok(None);
+    };
+    Ok(Some(Ticker {
+        symbol: symbol.to_string(),
+        last,
+        high,
+        low,
+        volume,
+        change_pct: change_pct(open, last),
+        open_24h: open,
+    }))
+}
+
+/// Percent change from `open` to `last`, to two decimals
+fn change_pct(open: Decimal, last: Decimal) -> Decimal {
+    if open.is_zero() {
+        return Decimal::ZERO;
+    }
+    ((last - open) / open * Decimal::ONE_HUNDRED)
+        .round_dp(2)
+        .normalize()
+}
+
+/// Recently computed tickers per symbol, so frequent polling doesn't rescan a day of trades
+pub struct TickerCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Instant, Option<Ticker>)>>,
+}
+
+impl TickerCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Cached ticker of `symbol` if younger than the TTL, else a fresh one from `pool`
+    pub async fn get(&self, pool: &PgPool, symbol: &str) -> Result<Option<Ticker>, sqlx::Error> {
+        if let Some((computed_at, ticker)) = self.entries.lock().await.get(symbol) {
+            if computed_at.elapsed() < self.ttl {
+                return Ok(ticker.clone());
+            }
+        }
+        // Computed without holding the lock; concurrent misses just query twice
+        let ticker = fetch_ticker(pool, symbol, chrono::Utc::now().timestamp_millis()).await?;
+        self.entries
+            .lock()
+            .await
+            .insert(symbol.to_string(), (Instant::now(), ticker.clone()));
+        Ok(ticker)
+    }
+}
+
+/// Get last price, high, low, volume and change over the last 24 hours
+///
+/// Query parameters:
+/// - `symbol`: Trading pair (default: the configured default market)
+///
+/// Results are cached for `TICKER_CACHE_MS`.
+pub async fn get_ticker(
+    Query(params): Query<TickerQuery>,
+    State(pool): State<PgPool>,
+    State(cache): State<Arc<TickerCache>>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> Result<Json<Ticker>, ApiError> {
+    let symbol = config.symbol_or_default(params.symbol.as_deref());
+
+    cache
+        .get(&pool, symbol)
+        .await
+        .map_err(|e| ApiError::database("get_ticker", e))?
+        .map(Json)
+        .ok_or_else(|| ApiError::NotFound("No trades in the last 24h".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::{insert_trade, TestTrade};
+
+    #[test]
+    fn test_change_pct() {
+        assert_eq!(
+            change_pct(Decimal::from(2000), Decimal::from(2100)),
+            Decimal::from(5)
+        );
+        assert_eq!(
+            change_pct(Decimal::from(3), Decimal::from(2)).to_string(),
+            "-33.33"
+        );
+        assert_eq!(change_pct(Decimal::ZERO, Decimal::ONE), Decimal::ZERO);
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_ticker_only_covers_last_24h(pool: PgPool) {
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let hour_ms = 60 * 60 * 1000;
+        // (trade_id, hours ago, price, quantity); the first two fall outside the window
+        let trades = [
+            (1_i64, 30, 1500, 9),
+            (2, 25, 3000, 9),
+            (3, 23, 2000, 1),
+            (4, 12, 2300, 2),
+            (5, 6, 1900, 3),
+            (6, 1, 2100, 4),
+        ];
+        for (trade_id, hours_ago, price, quantity) in trades {
+            let trade = TestTrade {
+                trade_id,
+                price: Decimal::from(price),
+                quantity: Decimal::from(quantity),
+                time_ms: Some(now_ms - hours_ago * hour_ms),
+                ..TestTrade::default()
+            };
+            insert_trade(&pool, trade).await;
+        }
+
+        let ticker = fetch_ticker(&pool, "ETH/USDT", now_ms)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(ticker.open_24h, Decimal::from(2000));
+        assert_eq!(ticker.last, Decimal::from(2100));
+        assert_eq!(ticker.high, Decimal::from(2300));
+        assert_eq!(ticker.low, Decimal::from(1900));
+        assert_eq!(ticker.volume, Decimal::from(10));
+        assert_eq!(ticker.change_pct, Decimal::from(5));
+
+        assert!(fetch_ticker(&pool, "DOT/USDT", now_ms)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
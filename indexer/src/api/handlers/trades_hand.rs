@@ -1 +1,320 @@
-// HERE I WILL LET JO HANDLE THE TRADE API ENDPOINT
+use crate::api::error::ApiError;
+use crate::config::IndexerConfig;
+use axum::{
+    extract::{Query, State},
+    response::Json,
+};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+
+/// Default and maximum trades returned by `/api/trades`
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct TradesQuery {
+    /// Trading pair symbol, defaults to the configured default market
+    pub symbol: Option<String>,
+    /// Window start (unix seconds)
+    pub from: Option<i64>,
+    /// Window end, exclusive (unix seconds)
+    pub to: Option<i64>,
+    /// Trades per page (default 100, max 1000)
+    pub limit: Option<i64>,
+    /// Trades to skip, for paging further into the past
+    pub offset: Option<i64>,
+    /// Aggressor side, "buy" or "sell"
+    pub side: Option<String>,
+    /// Smallest quantity to include
+    pub min_size: Option<Decimal>,
+    /// Largest quantity to include
+    pub max_size: Option<Decimal>,
+}
+
+impl TradesQuery {
+    /// Page size, capped at `MAX_LIMIT`
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+
+    /// Validated filters for `symbol`
+    pub fn filter(&self, symbol: &str) -> Result<TradeFilter, String> {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from >= to {
+                return Err("from must be before to".to_string());
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_size, self.max_size) {
+            if min > max {
+                return Err("min_size must not exceed max_size".to_string());
+            }
+        }
+        let side = match self.side.as_deref().map(str::to_ascii_lowercase).as_deref() {
+            None => None,
+            Some("buy") => Some("Buy"),
+            Some("sell") => Some("Sell"),
+            Some(other) => return Err(format!("Invalid side: {}", other)),
+        };
+        Ok(TradeFilter {
+            symbol: symbol.to_string(),
+            from: self.from,
+            to: self.to,
+            side,
+            min_size: self.min_size,
+            max_size: self.max_size,
+        })
+    }
+}
+
+/// Which trades a `/api/trades` request selects
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TradeFilter {
+    pub symbol: String,
+    /// Window start (unix seconds)
+    pub from: Option<i64>,
+    /// Window end, exclusive (unix seconds)
+    pub to: Option<i64>,
+    /// "Buy" or "Sell"
+    pub side: Option<&'static str>,
+    pub min_size: Option<Decimal>,
+    pub max_size: Option<Decimal>,
+}
+
+/// One executed trade
+#[derive(Debug, Clone, Serialize, PartialEq, sqlx::FromRow)]
+pub struct Trade {
+    pub trade_id: i64,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    /// Aggressor side, falling back to order age for rows indexed before side inference
+    pub side: String,
+    pub block_number: i64,
+    /// Trade time in milliseconds
+    pub timestamp: i64,
+    /// Per-market sequence, contiguous so clients can spot missed trades
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<i64>,
+}
+
+/// Trades matching `filter`, newest first.
+///
+/// Sizes compare as NUMERIC against the scaled `quantity` column, so bounds like
+/// `0.000001` are exact. Offset paging is applied after filtering.
+pub async fn fetch_trades(
+    pool: &PgPool,
+    filter: &TradeFilter,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Trade>, sqlx::Error> {
+    sqlx::query_as::<_, Trade>(
+        "SELECT * FROM (
+            SELECT trade_id, price, quantity,
+                COALESCE(side, CASE WHEN buy_order_id > sell_order_id THEN 'Buy' ELSE 'Sell' END) AS side,
+                block_number,
+                (EXTRACT(EPOCH FROM created_at) * 1000)::bigint AS timestamp,
+                seq,
+                created_at
+            FROM trades
+            WHERE symbol = $1
+                AND ($2::bigint IS NULL OR created_at >= to_timestamp($2))
+                AND ($3::bigint IS NULL OR created_at < to_timestamp($3))
+                AND ($4::numeric IS NULL OR quantity >= $4)
+                AND ($5::numeric IS NULL OR quantity <= $5)
+        ) filtered
+        WHERE $6::text IS NULL OR side = $6
+        ORDER BY created_at DESC, trade_id DESC
+        LIMIT $7 OFFSET $8",
+    )
+    .bind(&filter.symbol)
+    .bind(filter.from)
+    .bind(filter.to)
+    .bind(filter.min_size)
+    .bind(filter.max_size)
+    .bind(filter.side)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+}
+
+/// Get executed trades, newest first
+///
+/// Query parameters:
+/// - `symbol`: Trading pair (default: the configured default market)
+/// - `from`, `to`: Optional window in unix seconds, `to` exclusive
+/// - `limit`: Trades per page (default 100, max 1000)
+/// - `offset`: Trades to skip (default 0)
+/// - `side`: "buy" or "sell" (default: both)
+/// - `min_size`, `max_size`: Optional quantity range, both ends inclusive
+pub async fn get_trades(
+    Query(params): Query<TradesQuery>,
+    State(pool): State<PgPool>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> Result<Json<Vec<Trade>>, ApiError> {
+    let symbol = config.symbol_or_default(params.symbol.as_deref());
+    let filter = params.filter(symbol).map_err(ApiError::BadRequest)?;
+    let offset = params.offset.unwrap_or(0);
+    if offset < 0 {
+        return Err(ApiError::BadRequest(
+            "offset must not be negative".to_string(),
+        ));
+    }
+
+    let mut trades = fetch_trades(&pool, &filter, params.limit(), offset)
+        .await
+        .map_err(|e| ApiError::database("get_trades", e))?;
+    if !config.trade_sequence {
+        trades.iter_mut().for_each(|trade| trade.seq = None);
+    }
+    Ok(Json(trades))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::{insert_trade, TestTrade};
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+
+    fn query(limit: Option<i64>) -> TradesQuery {
+        TradesQuery {
+            symbol: None,
+            from: None,
+            to: None,
+            limit,
+            offset: None,
+            side: None,
+            min_size: None,
+            max_size: None,
+        }
+    }
+
+    #[test]
+    fn test_filter_validation() {
+        let filter = |side: Option<&str>, min: Option<i64>, max: Option<i64>| {
+            TradesQuery {
+                side: side.map(str::to_string),
+                min_size: min.map(Decimal::from),
+                max_size: max.map(Decimal::from),
+                ..query(None)
+            }
+            .filter("ETH/USDT")
+        };
+        assert_eq!(filter(Some("SELL"), None, None).unwrap().side, Some("Sell"));
+        assert_eq!(filter(Some("buy"), None, None).unwrap().side, Some("Buy"));
+        assert!(filter(Some("short"), None, None).is_err());
+        assert!(filter(None, Some(5), Some(5)).is_ok());
+        assert!(filter(None, Some(6), Some(5)).is_err());
+    }
+
+    #[test]
+    fn test_limit_is_capped() {
+        assert_eq!(query(None).limit(), DEFAULT_LIMIT);
+        assert_eq!(query(Some(10)).limit(), 10);
+        assert_eq!(query(Some(50_000)).limit(), MAX_LIMIT);
+        assert_eq!(query(Some(0)).limit(), 1);
+    }
+
+    fn trade(id: i64, seconds_ago: i64, side: &'static str, quantity: Decimal) -> TestTrade {
+        TestTrade {
+            trade_id: id,
+            block_number: id,
+            buy_order_id: 2,
+            sell_order_id: 1,
+            price: Decimal::from(2000),
+            quantity,
+            side: Some(side),
+            time_ms: Some(chrono::Utc::now().timestamp_millis() - seconds_ago * 1000),
+            ..TestTrade::default()
+        }
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_trades_are_newest_first_and_paged(pool: PgPool) {
+        for (id, seconds_ago) in [(1, 30), (2, 20), (3, 10)] {
+            insert_trade(&pool, trade(id, seconds_ago, "Buy", Decimal::ONE)).await;
+        }
+
+        let ids = |trades: Vec<Trade>| trades.iter().map(|t| t.trade_id).collect::<Vec<_>>();
+        let filter = TradeFilter {
+            symbol: "ETH/USDT".to_string(),
+            ..Default::default()
+        };
+        let all = fetch_trades(&pool, &filter, 10, 0).await.unwrap();
+        assert_eq!(all[0].side, "Buy");
+        assert_eq!(ids(all), vec![3, 2, 1]);
+
+        let page = fetch_trades(&pool, &filter, 1, 1).await.unwrap();
+        assert_eq!(ids(page), vec![2]);
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_side_and_size_filters(pool: PgPool) {
+        // (id, side, quantity), newest last
+        let seeded = [
+            (1, "Buy", "0.5"),
+            (2, "Sell", "0.5"),
+            (3, "Buy", "10"),
+            (4, "Sell", "10"),
+            (5, "Sell", "25.000001"),
+        ];
+        for (id, side, quantity) in seeded {
+            let quantity = quantity.parse().unwrap();
+            insert_trade(&pool, trade(id, 60 - id, side, quantity)).await;
+        }
+
+        let ids = |side: Option<&str>, min: Option<&str>, max: Option<&str>| {
+            let query = TradesQuery {
+                side: side.map(str::to_string),
+                min_size: min.map(|min| min.parse().unwrap()),
+                max_size: max.map(|max| max.parse().unwrap()),
+                ..query(None)
+            };
+            let filter = query.filter("ETH/USDT").unwrap();
+            let pool = pool.clone();
+            async move {
+                let trades = fetch_trades(&pool, &filter, 10, 0).await.unwrap();
+                trades.iter().map(|t| t.trade_id).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(ids(None, None, None).await, vec![5, 4, 3, 2, 1]);
+        assert_eq!(ids(Some("sell"), None, None).await, vec![5, 4, 2]);
+        assert_eq!(ids(Some("buy"), None, None).await, vec![3, 1]);
+        assert_eq!(ids(None, Some("10"), None).await, vec![5, 4, 3]);
+        assert_eq!(ids(None, None, Some("10")).await, vec![4, 3, 2, 1]);
+        assert_eq!(ids(None, Some("10"), Some("25")).await, vec![4, 3]);
+        // Large sells only, down to the last scaled digit
+        assert_eq!(ids(Some("sell"), Some("25.000001"), None).await, vec![5]);
+        assert_eq!(ids(Some("buy"), Some("10"), Some("10")).await, vec![3]);
+        assert!(ids(Some("buy"), Some("11"), None).await.is_empty());
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_handler_caps_limit(pool: PgPool) {
+        for id in 1..=(MAX_LIMIT + 5) {
+            insert_trade(&pool, trade(id, 1, "Buy", Decimal::ONE)).await;
+        }
+
+        let response = get_trades(
+            Query(TradesQuery {
+                limit: Some(MAX_LIMIT * 10),
+                ..query(None)
+            }),
+            State(pool),
+            State(Arc::new(IndexerConfig::default())),
+        )
+        .await
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let trades: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(trades.len(), MAX_LIMIT as usize);
+    }
+}
@@ -1,32 +1,173 @@
+use crate::api::handlers::ohlcv_hand::{bucket_width, candle_view};
+use crate::api::state::{AppState, SharedOrderbook};
+use crate::config::{env_key, IndexerConfig, MarketConfig};
 use crate::indexer::orderbook_reducer::OrderbookState;
+use crate::indexer::scaling::AmountScale;
 use axum::{
     extract::{Query, State},
     response::{IntoResponse, Json},
     routing::get,
     Router,
 };
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
 use serde::Deserialize;
 use serde_json::{json, Value};
 use sqlx::PgPool;
+use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::Mutex;
-
-pub type AppState = (Arc<Mutex<OrderbookState>>, PgPool);
+use tracing::warn;
 
 const EXCHANGE: &str = "Polkadex";
 const TIMEZONE: &str = "UTC";
-const SYMBOL: &str = "ETH/USDT"; // Your symbol
-const SUPPORTED_RESOLUTIONS: &[&str] = &["1", "5", "15", "30", "60", "240", "1D", "1W", "1M"];
+/// Most trade marks returned for one chart range
+const MAX_MARKS: i64 = 1000;
+
+/// How trade marks look on the chart: color by aggressor side, label by size tier
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkScheme {
+    pub buy_color: String,
+    pub sell_color: String,
+    /// Trades whose aggressor side is unknown
+    pub unknown_color: String,
+    /// `(minimum quantity, label)` in ascending order; smaller trades get no mark
+    pub tiers: Vec<(Decimal, String)>,
+}
+
+impl Default for MarkScheme {
+    fn default() -> Self {
+        Self {
+            buy_color: "green".to_string(),
+            sell_color: "red".to_string(),
+            unknown_color: "blue".to_string(),
+            tiers: vec![
+                (Decimal::ONE, "S".to_string()),
+                (Decimal::TEN, "M".to_string()),
+                (Decimal::ONE_HUNDRED, "L".to_string()),
+            ],
+        }
+    }
+}
+
+impl MarkScheme {
+    /// Parse `UDF_MARK_COLORS` ("buy=green,sell=red,unknown=blue", any subset) and
+    /// `UDF_MARK_TIERS` ("S=1,M=10,L=100", label=minimum quantity) over the defaults
+    pub fn parse(colors: &str, tiers: &str) -> Self {
+        let mut scheme = Self::default();
+        for entry in colors.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match entry.split_once('=').map(|(k, v)| (k.trim(), v.trim())) {
+                Some(("buy", color)) => scheme.buy_color = color.to_string(),
+                Some(("sell", color)) => scheme.sell_color = color.to_string(),
+                Some(("unknown", color)) => scheme.unknown_color = color.to_string(),
+                _ => warn!("Ignoring invalid mark color: {}", entry),
+            }
+        }
+
+        let parsed: Vec<(Decimal, String)> = tiers
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .filter_map(|entry| {
+                let tier = entry.split_once('=').and_then(|(label, min)| {
+                    Some((min.trim().parse().ok()?, label.trim().to_string()))
+                });
+                if tier.is_none() {
+                    warn!("Ignoring invalid mark tier: {}", entry);
+                }
+                tier
+            })
+            .collect();
+        if !parsed.is_empty() {
+            scheme.tiers = parsed;
+            scheme.tiers.sort_by_key(|tier| tier.0);
+        }
+        scheme
+    }
+
+    /// Smallest quantity that gets a mark
+    pub fn min_quantity(&self) -> Decimal {
+        self.tiers.first().map(|(min, _)| *min).unwrap_or_default()
+    }
+
+    /// Color, label and tier index for a trade, None below the smallest tier
+    pub fn style(&self, side: Option<&str>, quantity: Decimal) -> Option<(&str, &str, usize)> {
+        let tier = self.tiers.iter().rposition(|(min, _)| quantity >= *min)?;
+        let color = match side {
+            Some("Buy") => &self.buy_color,
+            Some("Sell") => &self.sell_color,
+            _ => &self.unknown_color,
+        };
+        Some((color, &self.tiers[tier].1, tier))
+    }
+}
+
+/// How UDF bars carry prices and volumes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberFormat {
+    /// JSON numbers, as the TradingView UDF protocol expects; beyond about 15 significant
+    /// digits values are rounded
+    #[default]
+    Number,
+    /// Decimal strings, exact, for datafeeds that parse them
+    String,
+}
+
+impl NumberFormat {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format.trim().to_ascii_lowercase().as_str() {
+            "number" => Some(Self::Number),
+            "string" => Some(Self::String),
+            _ => None,
+        }
+    }
+
+    pub fn value(&self, value: Decimal) -> Value {
+        match self {
+            Self::Number => json!(value.to_f64()),
+            Self::String => json!(value.normalize().to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResolveQuery {
+    pub symbol: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SearchQuery {
+    /// Text to match against market symbols and descriptions; the default market when omitted
+    pub query: Option<String>,
+    /// Most results to return (default 30, max 100)
+    pub limit: Option<usize>,
+}
 
 #[derive(Debug, Deserialize)]
 pub struct QuoteQuery {
-    pub _symbol: String,
+    /// Trading pair (default: the configured default market)
+    #[serde(alias = "symbols")]
+    pub symbol: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct DepthQuery {
-    pub _symbol: String,
+    /// Trading pair (default: the configured default market)
+    pub symbol: Option<String>,
+    /// Rows per side (default 20, max 500)
     pub levels: Option<usize>,
+    /// Include per-level order counts (default: true, unless disabled by config)
+    pub counts: Option<bool>,
+    /// Include running totals from the best price outward, for depth charts (default: false)
+    pub cumulative: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MarksQuery {
+    pub symbol: String,
+    pub from: i64,
+    pub to: i64,
+    #[serde(rename = "resolution")]
+    pub _resolution: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -35,50 +176,164 @@ pub struct HistoryQuery {
     pub from: i64,
     pub to: i64,
     pub resolution: String,
+    /// Bars to return ending at `to`; when set, `from` is ignored
+    pub countback: Option<i64>,
+}
+
+/// Map an aggregator timeframe ("1m", "1h", ...) to its TradingView resolution
+pub fn timeframe_to_resolution(timeframe: &str) -> Option<&'static str> {
+    match timeframe {
+        "1m" => Some("1"),
+        "5m" => Some("5"),
+        "15m" => Some("15"),
+        "30m" => Some("30"),
+        "1h" => Some("60"),
+        "4h" => Some("240"),
+        "1d" => Some("1D"),
+        "1w" => Some("1W"),
+        "1M" => Some("1M"),
+        _ => None,
+    }
+}
+
+/// Map a TradingView resolution to the candle interval used by `/api/candles`
+pub fn resolution_to_interval(resolution: &str) -> Option<&'static str> {
+    match resolution {
+        "1" => Some("1m"),
+        "5" => Some("5m"),
+        "15" => Some("15m"),
+        "30" => Some("30m"),
+        "60" => Some("1h"),
+        "240" => Some("4h"),
+        "1D" | "D" => Some("1d"),
+        "1W" | "W" => Some("1w"),
+        "1M" | "M" => Some("1M"),
+        _ => None,
+    }
+}
+
+/// Where `udf_bars` reads a resolution from
+#[derive(Debug, Clone, PartialEq)]
+pub enum BarSource {
+    /// A persisted candle view (continuous aggregate)
+    Candles(&'static str),
+    /// Raw trades bucketed on the fly, with a `time_bucket` width like "3 minutes"
+    Trades(String),
 }
 
-pub async fn udf_config() -> impl IntoResponse {
+/// `time_bucket` width for any minute ("3"), day ("2D") or week ("2W") resolution
+fn resolution_bucket_width(resolution: &str) -> Option<String> {
+    let (count, unit) = match resolution.strip_suffix('D') {
+        Some(days) => (days, "days"),
+        None => match resolution.strip_suffix('W') {
+            Some(weeks) => (weeks, "weeks"),
+            None => (resolution, "minutes"),
+        },
+    };
+    let count = if count.is_empty() {
+        1
+    } else {
+        count.parse::<u32>().ok()?
+    };
+    (count > 0).then(|| format!("{} {}", count, unit))
+}
+
+/// Pick the bar source for a resolution.
+///
+/// `mode` is the `UDF_BAR_SOURCE` setting: "auto" reads persisted candles whenever the
+/// resolution matches one and aggregates raw trades otherwise, "candles" and "trades"
+/// force one path. Raw trades only cover the retention window, if one is configured.
+pub fn bar_source(resolution: &str, mode: &str) -> Option<BarSource> {
+    let view = resolution_to_interval(resolution).and_then(candle_view);
+    match (mode, view) {
+        ("trades", _) => {
+            let width = match resolution_to_interval(resolution).and_then(bucket_width) {
+                Some(width) => width.to_string(),
+                None => resolution_bucket_width(resolution)?,
+            };
+            Some(BarSource::Trades(width))
+        }
+        (_, Some(view)) => Some(BarSource::Candles(view)),
+        ("candles", None) => None,
+        (_, None) => resolution_bucket_width(resolution).map(BarSource::Trades),
+    }
+}
+
+/// Resolutions we can actually serve for a set of aggregated timeframes.
+///
+/// Daily bars roll up from any intraday timeframe that evenly divides a day,
+/// so "1D" is advertised whenever one is configured.
+pub fn supported_resolutions(timeframes: &[String]) -> Vec<&'static str> {
+    let mut resolutions: Vec<&'static str> = timeframes
+        .iter()
+        .filter_map(|tf| timeframe_to_resolution(tf))
+        .collect();
+
+    let daily_derivable = timeframes.iter().any(|tf| {
+        crate::indexer::candle_aggregator::timeframe_ms(tf).is_some_and(|ms| 86_400_000 % ms == 0)
+    });
+    if daily_derivable && !resolutions.contains(&"1D") {
+        resolutions.push("1D");
+    }
+
+    resolutions
+}
+
+pub async fn udf_config(State(config): State<Arc<IndexerConfig>>) -> impl IntoResponse {
     Json(json!({
-        "supported_resolutions":SUPPORTED_RESOLUTIONS,
+        "supported_resolutions": supported_resolutions(&config.default_timeframes),
         "supports_group_request": true,
-        "supports_marks": false,
-        "supports_search": false,
+        "supports_marks": true,
+        "supports_search": true,
         "supports_timescale_marks": false,
     }))
 }
 
+/// Market of a book request, as a UDF error unless it is known and has a tracked book
+fn book_market<'a>(
+    config: &'a IndexerConfig,
+    symbol: Option<&str>,
+) -> Result<&'a MarketConfig, Json<Value>> {
+    let requested = config.symbol_or_default(symbol);
+    let Some(market) = config.find_market(requested) else {
+        return Err(Json(json!({ "s": "error", "errmsg": "unknown_symbol" })));
+    };
+    // Only the default market has a tracked book
+    if market.symbol != config.default_symbol {
+        return Err(Json(json!({
+            "s": "error",
+            "errmsg": format!("No orderbook tracked for market: {}", market.symbol)
+        })));
+    }
+    Ok(market)
+}
+
 pub async fn udf_quotes(
-    Query(_params): Query<QuoteQuery>,
-    State((orderbook, _pool)): State<AppState>,
+    Query(params): Query<QuoteQuery>,
+    State(orderbook): State<SharedOrderbook>,
+    State(config): State<Arc<IndexerConfig>>,
 ) -> impl IntoResponse {
+    let market = match book_market(&config, params.symbol.as_deref()) {
+        Ok(market) => market,
+        Err(error) => return error,
+    };
+
     let ob = orderbook.lock().await;
-    match ob.get_spread() {
-        Some((best_bid, best_ask)) => {
-            // Get order counts at best levels
-            let bid_orders = ob
-                .bids
-                .get(&best_bid)
-                .map(|orders| orders.len())
-                .unwrap_or(0);
-
-            let ask_orders = ob
-                .asks
-                .get(&best_ask)
-                .map(|orders| orders.len())
-                .unwrap_or(0);
-
-            let spread = best_ask - best_bid;
-            let mid_price = (best_bid + best_ask) / rust_decimal::Decimal::from(2);
+    match best_quote(&ob, market.amount_scale()) {
+        Some(quote) => {
+            let reference_price = config.reference_price.price(&ob);
 
             Json(json!({
                 "s": "ok",
-                "Symbol": SYMBOL,
-                "bid": best_bid,
-                "ask": best_ask,
-                "spread": spread,
-                "mid_price": mid_price,
-                "bid_orders": bid_orders,
-                "ask_orders": ask_orders,
+                "Symbol": market.symbol,
+                "bid": quote.bid,
+                "ask": quote.ask,
+                "spread": quote.spread,
+                "mid_price": quote.mid_price,
+                "reference_price": reference_price,
+                "reference_method": config.reference_price.as_str(),
+                "bid_orders": quote.bid_orders,
+                "ask_orders": quote.ask_orders,
                 "timestamp": chrono::Utc::now().timestamp_millis(),
             }))
         }
@@ -89,16 +344,71 @@ pub async fn udf_quotes(
     }
 }
 
+/// Search results when the request does not say, the charting library's own default
+const DEFAULT_SEARCH_LIMIT: usize = 30;
+/// Most results a single search may ask for
+const MAX_SEARCH_LIMIT: usize = 100;
+
 // udf search
-pub async fn udf_search() -> impl IntoResponse {
-    Json(vec![json!({
-        "symbol": SYMBOL,
-        "full_name": "Ethereum / USDT",
-        "description": "Ethereum",
-        "exchange": EXCHANGE,
-        "type": "crypto",
-        "ticker": "ETHUSDT"
-    })])
+/// Markets whose symbol contains `query`, ignoring case and separators unless
+/// `UDF_FUZZY_SYMBOLS` is off, or whose description contains it ignoring case.
+/// Symbols starting with `query` come first, otherwise markets keep their configured order.
+pub fn search_markets<'a>(config: &'a IndexerConfig, query: &str) -> Vec<&'a MarketConfig> {
+    let fuzzy_query = env_key(query);
+    let lower_query = query.to_lowercase();
+    let symbol_prefix = |market: &MarketConfig| {
+        if config.udf_fuzzy_symbols {
+            env_key(&market.symbol).starts_with(&fuzzy_query)
+        } else {
+            market.symbol.starts_with(query)
+        }
+    };
+
+    let mut found: Vec<&MarketConfig> = config
+        .markets
+        .iter()
+        .filter(|market| {
+            let symbol_match = if config.udf_fuzzy_symbols {
+                env_key(&market.symbol).contains(&fuzzy_query)
+            } else {
+                market.symbol.contains(query)
+            };
+            symbol_match || market.description().to_lowercase().contains(&lower_query)
+        })
+        .collect();
+    // Stable sort, so ties keep the configured order
+    found.sort_by_key(|market| !symbol_prefix(market));
+    found
+}
+
+pub async fn udf_search(
+    Query(params): Query<SearchQuery>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> impl IntoResponse {
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+    let markets = match params.query.as_deref().filter(|q| !q.is_empty()) {
+        Some(query) => search_markets(&config, query),
+        None => config.market(&config.default_symbol).into_iter().collect(),
+    };
+    Json(
+        markets
+            .into_iter()
+            .take(limit)
+            .map(|market| {
+                json!({
+                    "symbol": market.symbol,
+                    "full_name": market.description(),
+                    "description": market.description(),
+                    "exchange": EXCHANGE,
+                    "type": "crypto",
+                    "ticker": format!("{}{}", market.base, market.quote)
+                })
+            })
+            .collect::<Vec<_>>(),
+    )
 }
 
 //time
@@ -108,21 +418,30 @@ pub async fn udf_time() -> impl IntoResponse {
 }
 
 // resolve , we need this due to config configurations
-pub async fn udf_resolve() -> impl IntoResponse {
+pub async fn udf_resolve(
+    Query(params): Query<ResolveQuery>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> impl IntoResponse {
+    let requested = config.symbol_or_default(params.symbol.as_deref());
+    let Some(market) = config.find_market(requested) else {
+        return Json(json!({ "s": "error", "errmsg": "unknown_symbol" }));
+    };
+    let resolutions = supported_resolutions(&market.timeframes);
+
     Json(json!({
         "s": "ok",
-        "symbol": SYMBOL,
-        "description": "Ethereum / USDT",
+        "symbol": market.symbol,
+        "description": market.description(),
         "type": "crypto",
         "exchange": EXCHANGE,
-        "minmove": 1,
-        "pricescale": 100,
+        "minmove": market.minmove,
+        "pricescale": market.pricescale,
         "timezone": TIMEZONE,
-        "session": "24x7",
+        "session": market.session,
         "has_intraday": true,
         "has_daily": true,
         "has_weekly_and_monthly": true,
-        "supported_resolutions": SUPPORTED_RESOLUTIONS,
+        "supported_resolutions": resolutions,
     }))
 }
 
@@ -136,6 +455,8 @@ pub async fn udf_resolve() -> impl IntoResponse {
 /// - `from`: Start timestamp in SECONDS (Unix epoch)
 /// - `to`: End timestamp in SECONDS (Unix epoch)
 /// - `resolution`: Time interval (1, 5, 15, 30, 60, 240, 1D, 1W, 1M)
+/// - `countback`: Optional number of bars to return ending at `to`, however far back
+///   they reach; the chart asks for exactly this many, so `from` is ignored when set
 ///
 /// # Response Format
 /// Success:
@@ -160,94 +481,64 @@ pub async fn udf_resolve() -> impl IntoResponse {
 /// ```
 pub async fn udf_bars(
     Query(params): Query<HistoryQuery>,
-    State((_orderbook, pool)): State<AppState>,
+    State(pool): State<PgPool>,
+    State(config): State<Arc<IndexerConfig>>,
 ) -> impl IntoResponse {
-    // Map TradingView resolution to our TimescaleDB view names
-    let view_name = match params.resolution.as_str() {
-        "1" => "one_minute_candles",
-        "5" => "five_minutes_candles",
-        "15" => "fifteen_minutes_candles",
-        "30" => "thirty_minutes_candles",
-        "60" => "one_hour_candles",
-        "240" => "four_hours_candles",
-        "1D" | "D" => "one_day_candles",
-        "1W" | "W" => "one_week_candles",
-        "1M" | "M" => "one_month_candles",
-        _ => {
-            return Json(json!({
-                "s": "error",
-                "errmsg": format!("Unsupported resolution: {}", params.resolution)
-            }));
-        }
+    let Some(source) = bar_source(&params.resolution, &config.udf_bar_source) else {
+        return Json(json!({
+            "s": "error",
+            "errmsg": format!("Unsupported resolution: {}", params.resolution)
+        }));
     };
 
     // Limit bars to prevent abuse (TradingView typically requests 300-5000 bars)
     const MAX_BARS: i64 = 10000;
 
-    // Query the TimescaleDB view
-    // Note: bucket is a timestamp, open/high/low/close are NUMERIC, volume is NUMERIC
-    // Using parameterized queries to prevent SQL injection (view_name is validated via match)
-    let query = format!(
-        "SELECT
-            EXTRACT(EPOCH FROM bucket)::bigint as time,
-            open::float8 as open,
-            high::float8 as high,
-            low::float8 as low,
-            close::float8 as close,
-            volume::float8 as volume
-        FROM {}
-        WHERE symbol = $1
-            AND bucket >= to_timestamp($2)
-            AND bucket < to_timestamp($3)
-        ORDER BY bucket ASC
-        LIMIT $4",
-        view_name
-    );
-
-    match sqlx::query_as::<_, (i64, f64, f64, f64, f64, f64)>(&query)
-        .bind(&params.symbol)
-        .bind(params.from)
-        .bind(params.to)
-        .bind(MAX_BARS)
-        .fetch_all(&pool)
-        .await
-    {
-        Ok(rows) => {
-            if rows.is_empty() {
-                // No data available for this range
-                Json(json!({
-                    "s": "no_data",
-                    "nextTime": params.from
-                }))
-            } else {
-                // Convert to TradingView UDF format
-                let mut times = Vec::new();
-                let mut opens = Vec::new();
-                let mut highs = Vec::new();
-                let mut lows = Vec::new();
-                let mut closes = Vec::new();
-                let mut volumes = Vec::new();
-
-                for (time, open, high, low, close, volume) in rows {
-                    times.push(time);
-                    opens.push(open);
-                    highs.push(high);
-                    lows.push(low);
-                    closes.push(close);
-                    volumes.push(volume);
+    // Cap how far back a single request may reach for this resolution
+    let interval = resolution_to_interval(&params.resolution).unwrap_or_default();
+    let (rows, from) = match params.countback {
+        Some(countback) => {
+            // Walk back from `to`, but no further than the resolution's history window
+            let since = config
+                .max_history_ranges
+                .get(interval)
+                .map(|max_range| params.to - max_range);
+            let count = countback.clamp(1, MAX_BARS);
+            let rows =
+                fetch_last_bars(&pool, &params.symbol, &source, since, params.to, count).await;
+            (rows, params.to)
+        }
+        None => {
+            let from = match config.limit_history_range(interval, params.from, params.to) {
+                Ok(from) => from,
+                Err(errmsg) => {
+                    return Json(json!({
+                        "s": "error",
+                        "errmsg": errmsg
+                    }));
                 }
+            };
+            let rows = fetch_bars(&pool, &params.symbol, &source, from, params.to, MAX_BARS).await;
+            (rows, from)
+        }
+    };
 
-                Json(json!({
-                    "s": "ok",
-                    "t": times,
-                    "o": opens,
-                    "h": highs,
-                    "l": lows,
-                    "c": closes,
-                    "v": volumes
-                }))
+    match rows {
+        Ok(rows) if rows.is_empty() => {
+            // Point the chart at the last earlier bar, or tell it there is nothing older
+            match next_bar_time(&pool, &params.symbol, &source, from).await {
+                Ok(Some(next_time)) => Json(json!({ "s": "no_data", "nextTime": next_time })),
+                Ok(None) => Json(json!({ "s": "no_data" })),
+                Err(e) => {
+                    eprintln!("❌ Database error in udf_bars: {}", e);
+                    Json(json!({
+                        "s": "error",
+                        "errmsg": format!("Database error: {}", e)
+                    }))
+                }
             }
         }
+        Ok(rows) => Json(bars_response(rows, config.udf_number_format)),
         Err(e) => {
             eprintln!("❌ Database error in udf_bars: {}", e);
             Json(json!({
@@ -258,52 +549,385 @@ pub async fn udf_bars(
     }
 }
 
-//finally the depth, i think this is not part of trading view but keeping it regardlesss
-pub async fn udf_depth(
-    Query(params): Query<DepthQuery>,
-    State((orderbook, _pool)): State<AppState>,
+/// TradingView UDF history response for `rows`
+pub fn bars_response(rows: Vec<BarRow>, format: NumberFormat) -> Value {
+    let mut times = Vec::new();
+    let mut opens = Vec::new();
+    let mut highs = Vec::new();
+    let mut lows = Vec::new();
+    let mut closes = Vec::new();
+    let mut volumes = Vec::new();
+
+    for (time, open, high, low, close, volume) in rows {
+        times.push(time);
+        opens.push(format.value(open));
+        highs.push(format.value(high));
+        lows.push(format.value(low));
+        closes.push(format.value(close));
+        volumes.push(format.value(volume));
+    }
+
+    json!({
+        "s": "ok",
+        "t": times,
+        "o": opens,
+        "h": highs,
+        "l": lows,
+        "c": closes,
+        "v": volumes
+    })
+}
+
+/// One UDF bar: (time in seconds, open, high, low, close, volume)
+pub type BarRow = (i64, Decimal, Decimal, Decimal, Decimal, Decimal);
+
+/// Up to `limit` bars of `symbol` whose bucket starts in `[from, to)` (unix seconds),
+/// in ascending time order
+pub async fn fetch_bars(
+    pool: &PgPool,
+    symbol: &str,
+    source: &BarSource,
+    from: i64,
+    to: i64,
+    limit: i64,
+) -> Result<Vec<BarRow>, sqlx::Error> {
+    // Note: bucket is a timestamp; candlestick values are read as NUMERIC so nothing is
+    // rounded on the way to the response
+    // Using parameterized queries to prevent SQL injection (view names come from a match)
+    let (query, width) = match source {
+        BarSource::Candles(view_name) => (
+            format!(
+                "SELECT
+                    EXTRACT(EPOCH FROM bucket)::bigint as time,
+                    open::numeric as open,
+                    high::numeric as high,
+                    low::numeric as low,
+                    close::numeric as close,
+                    volume::numeric as volume
+                FROM {}
+                WHERE symbol = $1
+                    AND bucket >= to_timestamp($2)
+                    AND bucket < to_timestamp($3)
+                ORDER BY bucket ASC
+                LIMIT $4",
+                view_name
+            ),
+            None,
+        ),
+        // Same candlestick_agg the continuous aggregates use, over raw trades
+        BarSource::Trades(width) => (
+            "SELECT
+                EXTRACT(EPOCH FROM bucket)::bigint as time,
+                open(candlestick)::numeric as open,
+                high(candlestick)::numeric as high,
+                low(candlestick)::numeric as low,
+                close(candlestick)::numeric as close,
+                volume(candlestick)::numeric as volume
+            FROM (
+                SELECT time_bucket($5::interval, created_at) AS bucket,
+                    candlestick_agg(created_at, price, quantity) AS candlestick
+                FROM trades
+                WHERE symbol = $1
+                    AND created_at >= to_timestamp($2)
+                    AND created_at < to_timestamp($3)
+                    AND price > 0 AND quantity > 0
+                GROUP BY 1
+            ) bars
+            ORDER BY bucket ASC
+            LIMIT $4"
+                .to_string(),
+            Some(width),
+        ),
+    };
+
+    let mut bars = sqlx::query_as::<_, BarRow>(&query)
+        .bind(symbol)
+        .bind(from)
+        .bind(to)
+        .bind(limit);
+    if let Some(width) = width {
+        bars = bars.bind(width);
+    }
+    bars.fetch_all(pool).await
+}
+
+/// The last `count` bars of `symbol` starting before `to` (unix seconds), and not before
+/// `since` when given, in ascending time order
+pub async fn fetch_last_bars(
+    pool: &PgPool,
+    symbol: &str,
+    source: &BarSource,
+    since: Option<i64>,
+    to: i64,
+    count: i64,
+) -> Result<Vec<BarRow>, sqlx::Error> {
+    // Same bars as `fetch_bars`, read newest first so the limit keeps the latest ones
+    let (query, width) = match source {
+        BarSource::Candles(view_name) => (
+            format!(
+                "SELECT
+                    EXTRACT(EPOCH FROM bucket)::bigint as time,
+                    open::numeric as open,
+                    high::numeric as high,
+                    low::numeric as low,
+                    close::numeric as close,
+                    volume::numeric as volume
+                FROM {}
+                WHERE symbol = $1
+                    AND ($2::bigint IS NULL OR bucket >= to_timestamp($2))
+                    AND bucket < to_timestamp($3)
+                ORDER BY bucket DESC
+                LIMIT $4",
+                view_name
+            ),
+            None,
+        ),
+        BarSource::Trades(width) => (
+            "SELECT
+                EXTRACT(EPOCH FROM bucket)::bigint as time,
+                open(candlestick)::numeric as open,
+                high(candlestick)::numeric as high,
+                low(candlestick)::numeric as low,
+                close(candlestick)::numeric as close,
+                volume(candlestick)::numeric as volume
+            FROM (
+                SELECT time_bucket($5::interval, created_at) AS bucket,
+                    candlestick_agg(created_at, price, quantity) AS candlestick
+                FROM trades
+                WHERE symbol = $1
+                    AND ($2::bigint IS NULL OR created_at >= to_timestamp($2))
+                    AND created_at < to_timestamp($3)
+                    AND price > 0 AND quantity > 0
+                GROUP BY 1
+            ) bars
+            ORDER BY bucket DESC
+            LIMIT $4"
+                .to_string(),
+            Some(width),
+        ),
+    };
+
+    let mut bars = sqlx::query_as::<_, BarRow>(&query)
+        .bind(symbol)
+        .bind(since)
+        .bind(to)
+        .bind(count);
+    if let Some(width) = width {
+        bars = bars.bind(width);
+    }
+    let mut rows = bars.fetch_all(pool).await?;
+    rows.reverse();
+    Ok(rows)
+}
+
+/// Start of the latest bar before `before` (unix seconds), the UDF `nextTime` for an
+/// empty range. None when nothing older exists, so the chart stops paging back.
+pub async fn next_bar_time(
+    pool: &PgPool,
+    symbol: &str,
+    source: &BarSource,
+    before: i64,
+) -> Result<Option<i64>, sqlx::Error> {
+    let query = match source {
+        BarSource::Candles(view_name) => format!(
+            "SELECT EXTRACT(EPOCH FROM MAX(bucket))::bigint FROM {}
+            WHERE symbol = $1 AND bucket < to_timestamp($2)",
+            view_name
+        ),
+        BarSource::Trades(width) => format!(
+            "SELECT EXTRACT(EPOCH FROM time_bucket('{}'::interval, MAX(created_at)))::bigint
+            FROM trades
+            WHERE symbol = $1 AND created_at < to_timestamp($2)
+                AND price > 0 AND quantity > 0",
+            width
+        ),
+    };
+    sqlx::query_scalar(&query)
+        .bind(symbol)
+        .bind(before)
+        .fetch_one(pool)
+        .await
+}
+
+/// Trade marks for the chart: trades above the smallest size tier, colored by side
+pub async fn udf_marks(
+    Query(params): Query<MarksQuery>,
+    State(pool): State<PgPool>,
+    State(config): State<Arc<IndexerConfig>>,
 ) -> impl IntoResponse {
-    let ob = orderbook.lock().await;
-    let depth = params.levels.unwrap_or(20);
+    let scheme = &config.udf_marks;
+    let symbol = config.symbol_or_default(Some(&params.symbol));
 
-    let ask_levels = ob.get_ask_depth(depth);
-    let bid_levels = ob.get_bid_depth(depth);
+    let rows = sqlx::query_as::<_, (i64, i64, Decimal, Decimal, Option<String>)>(
+        "SELECT trade_id, EXTRACT(EPOCH FROM created_at)::bigint, price, quantity, side
+        FROM trades
+        WHERE symbol = $1
+            AND created_at >= to_timestamp($2)
+            AND created_at < to_timestamp($3)
+            AND price > 0 AND quantity > 0
+            AND quantity >= $4
+        ORDER BY created_at ASC
+        LIMIT $5",
+    )
+    .bind(symbol)
+    .bind(params.from)
+    .bind(params.to)
+    .bind(scheme.min_quantity())
+    .bind(MAX_MARKS)
+    .fetch_all(&pool)
+    .await;
 
-    let bids: Vec<Vec<Value>> = bid_levels
-        .iter()
-        .map(|(price, count)| {
-            // Calculate total quantity at this price level
-            let qty: rust_decimal::Decimal = ob
-                .bids
-                .get(price)
-                .unwrap_or(&vec![])
-                .iter()
-                .filter_map(|id| ob.orders.get(id).map(|o| o.quantity - o.filled_quantity))
-                .sum();
-
-            vec![json!(price), json!(count), json!(qty)]
-        })
-        .collect();
+    match rows {
+        Ok(rows) => {
+            let marks: Vec<Value> = rows
+                .into_iter()
+                .filter_map(|(trade_id, time, price, quantity, side)| {
+                    let (color, label, tier) = scheme.style(side.as_deref(), quantity)?;
+                    Some(json!({
+                        "id": trade_id,
+                        "time": time,
+                        "color": color,
+                        "text": format!(
+                            "{} {} @ {}",
+                            side.as_deref().unwrap_or("Trade"),
+                            quantity.normalize(),
+                            price.normalize()
+                        ),
+                        "label": label,
+                        "labelFontColor": "white",
+                        "minSize": 14 + 6 * tier,
+                    }))
+                })
+                .collect();
+            Json(json!(marks))
+        }
+        Err(e) => {
+            eprintln!("❌ Database error in udf_marks: {}", e);
+            Json(json!({
+                "s": "error",
+                "errmsg": format!("Database error: {}", e)
+            }))
+        }
+    }
+}
 
-    let asks: Vec<Vec<Value>> = ask_levels
-        .iter()
-        .map(|(price, count)| {
-            // Calculate total quantity at this price level
-            let qty: rust_decimal::Decimal = ob
-                .asks
-                .get(price)
-                .unwrap_or(&vec![])
-                .iter()
-                .filter_map(|id| ob.orders.get(id).map(|o| o.quantity - o.filled_quantity))
-                .sum();
-
-            vec![json!(price), json!(count), json!(qty)]
+/// Levels per side when the request does not say
+const DEFAULT_DEPTH_LEVELS: usize = 20;
+/// Most levels per side a single depth request may ask for
+const MAX_DEPTH_LEVELS: usize = 500;
+
+/// Top of book in display units
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookQuote {
+    pub bid: Decimal,
+    pub ask: Decimal,
+    pub spread: Decimal,
+    pub mid_price: Decimal,
+    pub bid_orders: usize,
+    pub ask_orders: usize,
+}
+
+/// Best bid and ask with their spread and mid, None unless both sides have liquidity.
+///
+/// Like `depth_rows`, prices are scaled from the best levels' raw on-chain integers at the
+/// market's quote decimals, so the spread and mid are exact for fractional prices.
+pub fn best_quote(book: &OrderbookState, scale: AmountScale) -> Option<BookQuote> {
+    let (best_bid, best_ask) = book.get_spread()?;
+    let (raw_bid, _) = book.raw_level("Buy", &best_bid)?;
+    let (raw_ask, _) = book.raw_level("Sell", &best_ask)?;
+    let (bid, ask) = (scale.price(raw_bid), scale.price(raw_ask));
+
+    Some(BookQuote {
+        bid,
+        ask,
+        spread: (ask - bid).normalize(),
+        mid_price: ((bid + ask) / Decimal::TWO).normalize(),
+        bid_orders: book.bids.get(&best_bid).map_or(0, |orders| orders.len()),
+        ask_orders: book.asks.get(&best_ask).map_or(0, |orders| orders.len()),
+    })
+}
+
+/// One side of the book as `[price, quantity, order_count]` rows, best price first.
+///
+/// Prices and remaining quantities come from the orders' raw on-chain integers, so they
+/// are exact at the quote and base assets' decimals. `with_counts: false` leaves out the order count
+/// (`[price, quantity]`), which would otherwise hint at individual order sizes.
+/// `cumulative: true` adds the quantity resting at this price or better after the
+/// quantity (`[price, quantity, cumulative, order_count]`), summed from the same raw integers.
+pub fn depth_rows(
+    book: &OrderbookState,
+    side: &str,
+    levels: usize,
+    with_counts: bool,
+    cumulative: bool,
+    scale: AmountScale,
+) -> Vec<Vec<Value>> {
+    let prices = if side == "Buy" {
+        book.get_bid_depth(levels)
+    } else {
+        book.get_ask_depth(levels)
+    };
+    let totals: HashMap<Decimal, u128> = match (cumulative, side) {
+        (false, _) => HashMap::new(),
+        (true, "Buy") => book.cumulative_bid_depth(levels).into_iter().collect(),
+        (true, _) => book.cumulative_ask_depth(levels).into_iter().collect(),
+    };
+    prices
+        .into_iter()
+        .filter_map(|(price, count)| {
+            let (raw_price, raw_quantity) = book.raw_level(side, &price)?;
+            let mut row = vec![
+                json!(scale.price(raw_price)),
+                json!(scale.quantity(raw_quantity)),
+            ];
+            if cumulative {
+                row.push(json!(scale.quantity(*totals.get(&price)?)));
+            }
+            if with_counts {
+                row.push(json!(count));
+            }
+            Some(row)
         })
-        .collect();
+        .collect()
+}
+
+/// Order book depth: `levels` rows per side (default 20, max 500), counts only when
+/// `UDF_DEPTH_COUNTS` allows them and the request does not opt out with `counts=false`,
+/// running totals with `cumulative=true`
+pub async fn udf_depth(
+    Query(params): Query<DepthQuery>,
+    State(orderbook): State<SharedOrderbook>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> impl IntoResponse {
+    let levels = params
+        .levels
+        .unwrap_or(DEFAULT_DEPTH_LEVELS)
+        .clamp(1, MAX_DEPTH_LEVELS);
+    let with_counts = config.udf_depth_counts && params.counts.unwrap_or(true);
+    let cumulative = params.cumulative.unwrap_or(false);
+    let market = match book_market(&config, params.symbol.as_deref()) {
+        Ok(market) => market,
+        Err(error) => return error,
+    };
+
+    let ob = orderbook.lock().await;
+    let scale = market.amount_scale();
+    let bids = depth_rows(&ob, "Buy", levels, with_counts, cumulative, scale);
+    let asks = depth_rows(&ob, "Sell", levels, with_counts, cumulative, scale);
+    drop(ob);
+
+    let mut columns = vec!["price", "quantity"];
+    if cumulative {
+        columns.push("cumulative");
+    }
+    if with_counts {
+        columns.push("order_count");
+    }
 
     Json(json!({
         "s": "ok",
-        "symbol": SYMBOL,
+        "symbol": market.symbol,
+        "columns": columns,
         "bids": bids,
         "asks": asks,
         "timestamp": chrono::Utc::now().timestamp_millis(),
@@ -319,4 +943,469 @@ pub async fn udf_routes() -> Router<AppState> {
         .route("/symbols", get(udf_resolve))
         .route("/time", get(udf_time))
         .route("/history", get(udf_bars))
+        .route("/marks", get(udf_marks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::{insert_trade, resting, TestTrade};
+
+    const EIGHT_DECIMALS: AmountScale = AmountScale {
+        base_decimals: 8,
+        quote_decimals: 8,
+    };
+
+    fn depth_book() -> OrderbookState {
+        let mut book = OrderbookState::new();
+        book.add_order(resting(1, "Buy", 1_999_500_000, 1_250_000));
+        book.add_order(resting(2, "Buy", 1_999_500_000, 750_000));
+        book.add_order(resting(3, "Buy", 1_998_000_000, 3_000_000));
+        book.add_order(resting(4, "Sell", 2_000_500_000, 500_000));
+        book.add_order(resting(5, "Sell", 2_001_000_000, 2_000_000));
+        book
+    }
+
+    #[test]
+    fn test_depth_rows_are_scaled() {
+        let book = depth_book();
+        let bids = depth_rows(&book, "Buy", 20, true, false, AmountScale::default());
+        assert_eq!(bids[0], vec![json!("1999.5"), json!("2"), json!(2)]);
+
+        let anonymous = depth_rows(&book, "Buy", 20, false, false, AmountScale::default());
+        assert_eq!(anonymous[0], vec![json!("1999.5"), json!("2")]);
+    }
+
+    #[test]
+    fn test_depth_sides_read_their_own_map() {
+        let book = depth_book();
+        let bids = depth_rows(&book, "Buy", 20, true, false, AmountScale::default());
+        let asks = depth_rows(&book, "Sell", 20, true, false, AmountScale::default());
+
+        // Bids best (highest) first, asks best (lowest) first, never mixed
+        let prices =
+            |rows: &[Vec<Value>]| -> Vec<Value> { rows.iter().map(|row| row[0].clone()).collect() };
+        assert_eq!(prices(&bids), vec![json!("1999.5"), json!("1998")]);
+        assert_eq!(prices(&asks), vec![json!("2000.5"), json!("2001")]);
+        assert_eq!(asks[0][1], json!("0.5"));
+    }
+
+    #[test]
+    fn test_depth_respects_levels() {
+        let book = depth_book();
+        assert_eq!(
+            depth_rows(&book, "Buy", 1, true, false, AmountScale::default()).len(),
+            1
+        );
+        assert_eq!(
+            depth_rows(&book, "Sell", 1, true, false, AmountScale::default())[0][0],
+            json!("2000.5")
+        );
+        assert_eq!(
+            depth_rows(&book, "Sell", 10, true, false, AmountScale::default()).len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_depth_handler_reports_each_side_from_its_own_orders() {
+        let query = DepthQuery {
+            symbol: Some("ETH/USDT".to_string()),
+            levels: Some(1),
+            counts: None,
+            cumulative: None,
+        };
+        let response = udf_depth(
+            Query(query),
+            State(Arc::new(tokio::sync::Mutex::new(depth_book()))),
+            State(Arc::new(IndexerConfig::default())),
+        )
+        .await
+        .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let depth: Value = serde_json::from_slice(&body).unwrap();
+
+        // One row per side; the best bid level sums its two orders
+        assert_eq!(depth["bids"], json!([["1999.5", "2", 2]]));
+        assert_eq!(depth["asks"], json!([["2000.5", "0.5", 1]]));
+        assert_eq!(depth["symbol"], "ETH/USDT");
+    }
+
+    #[test]
+    fn test_cumulative_depth_matches_per_level_rows() {
+        let book = depth_book();
+        let bids = depth_rows(&book, "Buy", 20, true, true, AmountScale::default());
+        let asks = depth_rows(&book, "Sell", 20, false, true, AmountScale::default());
+
+        // [price, quantity, cumulative, order_count], totals growing away from the touch
+        assert_eq!(
+            bids[0],
+            vec![json!("1999.5"), json!("2"), json!("2"), json!(2)]
+        );
+        assert_eq!(
+            bids[1],
+            vec![json!("1998"), json!("3"), json!("5"), json!(1)]
+        );
+        assert_eq!(asks[0], vec![json!("2000.5"), json!("0.5"), json!("0.5")]);
+        assert_eq!(asks[1], vec![json!("2001"), json!("2"), json!("2.5")]);
+    }
+
+    #[test]
+    fn test_quote_of_fractional_prices_at_market_decimals() {
+        // An 8-decimal market: raw 123_456_789 is 1.23456789, not 123.456789
+        let mut book = OrderbookState::new();
+        book.add_order(resting(1, "Buy", 123_456_789, 250_000_000));
+        book.add_order(resting(2, "Buy", 123_456_789, 50_000_000));
+        book.add_order(resting(3, "Sell", 123_500_000, 100_000_000));
+
+        let quote = best_quote(&book, EIGHT_DECIMALS).unwrap();
+        assert_eq!(quote.bid.to_string(), "1.23456789");
+        assert_eq!(quote.ask.to_string(), "1.235");
+        assert_eq!(quote.spread.to_string(), "0.00043211");
+        assert_eq!(quote.mid_price.to_string(), "1.234783945");
+        assert_eq!((quote.bid_orders, quote.ask_orders), (2, 1));
+
+        let depth = depth_rows(&book, "Buy", 20, true, false, EIGHT_DECIMALS);
+        assert_eq!(depth[0], vec![json!("1.23456789"), json!("3"), json!(2)]);
+
+        // One-sided book has no quote
+        book.cancel_order(3).unwrap();
+        assert!(best_quote(&book, EIGHT_DECIMALS).is_none());
+    }
+
+    #[test]
+    fn test_marks_colored_by_side_and_tier() {
+        let scheme = MarkScheme::parse("buy=#26a69a,sell=#ef5350", "L=50,M=5");
+        assert_eq!(scheme.tiers[0], (Decimal::from(5), "M".to_string()));
+
+        assert_eq!(
+            scheme.style(Some("Buy"), Decimal::from(7)),
+            Some(("#26a69a", "M", 0))
+        );
+        assert_eq!(
+            scheme.style(Some("Sell"), Decimal::from(50)),
+            Some(("#ef5350", "L", 1))
+        );
+        // Unknown side keeps the default color, small trades get no mark
+        assert_eq!(
+            scheme.style(None, Decimal::from(60)),
+            Some(("blue", "L", 1))
+        );
+        assert_eq!(scheme.style(Some("Buy"), Decimal::from(4)), None);
+    }
+
+    #[test]
+    fn test_bar_source_by_resolution() {
+        // Persisted timeframe reads the candle view, others aggregate trades
+        assert_eq!(
+            bar_source("1", "auto"),
+            Some(BarSource::Candles("one_minute_candles"))
+        );
+        assert_eq!(
+            bar_source("3", "auto"),
+            Some(BarSource::Trades("3 minutes".to_string()))
+        );
+        assert_eq!(
+            bar_source("2D", "auto"),
+            Some(BarSource::Trades("2 days".to_string()))
+        );
+
+        // Forced paths
+        assert_eq!(
+            bar_source("1", "trades"),
+            Some(BarSource::Trades("1 minute".to_string()))
+        );
+        assert_eq!(bar_source("3", "candles"), None);
+        assert_eq!(bar_source("abc", "auto"), None);
+        assert_eq!(bar_source("0", "auto"), None);
+    }
+
+    #[test]
+    fn test_supported_resolutions_follow_market_timeframes() {
+        let timeframes = vec!["1m".to_string(), "1h".to_string()];
+        assert_eq!(supported_resolutions(&timeframes), vec!["1", "60", "1D"]);
+
+        let with_daily = vec!["5m".to_string(), "1d".to_string()];
+        assert_eq!(supported_resolutions(&with_daily), vec!["5", "1D"]);
+    }
+
+    #[test]
+    fn test_history_range_by_resolution() {
+        let config = IndexerConfig {
+            clamp_history_range: false,
+            ..IndexerConfig::default()
+        };
+        let to = 1_700_000_000;
+        let year = 365 * 86_400;
+
+        for (resolution, years_allowed) in [("1", false), ("60", true), ("1D", true)] {
+            let interval = resolution_to_interval(resolution).unwrap();
+            let result = config.limit_history_range(interval, to - year, to);
+            assert_eq!(result.is_ok(), years_allowed, "resolution {}", resolution);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_omitted_symbol_resolves_to_configured_default() {
+        let mut config = IndexerConfig::default();
+        config.markets.push(MarketConfig::new(
+            "BTC/USD",
+            config.default_timeframes.clone(),
+        ));
+        config.default_symbol = "BTC/USD".to_string();
+        let config = Arc::new(config);
+
+        let resolved = udf_resolve(Query(ResolveQuery { symbol: None }), State(config.clone()))
+            .await
+            .into_response();
+        let body = axum::body::to_bytes(resolved.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let resolved: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(resolved["symbol"], "BTC/USD");
+
+        let searched = udf_search(Query(SearchQuery::default()), State(config.clone()))
+            .await
+            .into_response();
+        let body = axum::body::to_bytes(searched.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let searched: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(searched[0]["symbol"], "BTC/USD");
+        assert_eq!(searched[0]["ticker"], "BTCUSD");
+
+        assert_eq!(config.symbol_or_default(None), "BTC/USD");
+    }
+
+    async fn resolve(config: &Arc<IndexerConfig>, symbol: &str) -> Value {
+        let query = ResolveQuery {
+            symbol: Some(symbol.to_string()),
+        };
+        let response = udf_resolve(Query(query), State(config.clone()))
+            .await
+            .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_resolve_ignores_case_and_separators() {
+        let mut config = IndexerConfig::default();
+        config.markets.push(MarketConfig::new(
+            "BTC/USD",
+            config.default_timeframes.clone(),
+        ));
+        let config = Arc::new(config);
+
+        for requested in ["BTC/USD", "btc-usd", "Btc_Usd"] {
+            let resolved = resolve(&config, requested).await;
+            assert_eq!(resolved["s"], "ok", "{requested}");
+            assert_eq!(resolved["symbol"], "BTC/USD", "{requested}");
+        }
+        assert_eq!(resolve(&config, "BTC/EUR").await["s"], "error");
+        let found: Vec<&str> = search_markets(&config, "btc")
+            .iter()
+            .map(|market| market.symbol.as_str())
+            .collect();
+        assert_eq!(found, vec!["BTC/USD"]);
+
+        let exact = Arc::new(IndexerConfig {
+            udf_fuzzy_symbols: false,
+            ..(*config).clone()
+        });
+        assert_eq!(resolve(&exact, "BTC/USD").await["symbol"], "BTC/USD");
+        assert_eq!(resolve(&exact, "btc-usd").await["s"], "error");
+    }
+
+    #[tokio::test]
+    async fn test_search_filters_markets_and_respects_limit() {
+        let mut config = IndexerConfig::default();
+        for symbol in ["USDT/DAI", "BTC/USDT", "DOT/USDT"] {
+            config
+                .markets
+                .push(MarketConfig::new(symbol, config.default_timeframes.clone()));
+        }
+        let config = Arc::new(config);
+
+        // Substring on the symbol, with prefix matches ranked first
+        let found: Vec<&str> = search_markets(&config, "usdt")
+            .iter()
+            .map(|market| market.symbol.as_str())
+            .collect();
+        assert_eq!(found, vec!["USDT/DAI", "ETH/USDT", "BTC/USDT", "DOT/USDT"]);
+        // Descriptions match ignoring case
+        assert_eq!(search_markets(&config, "dot / usdt")[0].symbol, "DOT/USDT");
+        assert!(search_markets(&config, "sol").is_empty());
+
+        let query = SearchQuery {
+            query: Some("usdt".to_string()),
+            limit: Some(2),
+        };
+        let response = udf_search(Query(query), State(config.clone()))
+            .await
+            .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let searched: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(searched.as_array().unwrap().len(), 2);
+        assert_eq!(searched[0]["symbol"], "USDT/DAI");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_reads_market_settings() {
+        let mut config = IndexerConfig::default();
+        config.markets[0].pricescale = 10_000;
+        config.markets[0].minmove = 5;
+        config.markets[0].session = "0930-1600".to_string();
+        let config = Arc::new(config);
+
+        let resolved = resolve(&config, "ETH/USDT").await;
+        assert_eq!(resolved["pricescale"], 10_000);
+        assert_eq!(resolved["minmove"], 5);
+        assert_eq!(resolved["session"], "0930-1600");
+        assert_eq!(resolved["description"], "ETH / USDT");
+    }
+
+    /// Start of the 2023-11-14T22:13 minute
+    const MINUTE: i64 = 1_699_999_980;
+
+    #[test]
+    fn test_string_bars_keep_full_precision() {
+        // 20 significant digits, more than an f64 holds
+        let price: Decimal = "123456789012.12345678".parse().unwrap();
+        let volume: Decimal = "0.000000000000000001".parse().unwrap();
+        let rows = vec![(MINUTE, price, price, price, price, volume)];
+
+        let exact = bars_response(rows.clone(), NumberFormat::String);
+        let body = serde_json::to_string(&exact).unwrap();
+        let parsed: Value = serde_json::from_str(&body).unwrap();
+        let close: Decimal = parsed["c"][0].as_str().unwrap().parse().unwrap();
+        assert_eq!(close, price);
+        assert_eq!(parsed["v"][0], "0.000000000000000001");
+        assert_eq!(parsed["t"][0], MINUTE);
+
+        // The protocol default stays numeric
+        let numeric = bars_response(rows, NumberFormat::Number);
+        assert!(numeric["c"][0].is_f64());
+        assert_eq!(NumberFormat::parse("STRING"), Some(NumberFormat::String));
+        assert_eq!(NumberFormat::parse("float"), None);
+    }
+
+    fn trade(id: i64, at: i64, price: i64, quantity: i64) -> TestTrade {
+        TestTrade {
+            trade_id: id,
+            block_number: id,
+            price: Decimal::from(price),
+            quantity: Decimal::from(quantity),
+            time_ms: Some(at * 1000),
+            ..TestTrade::default()
+        }
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_bars_bucket_trades_on_minute_boundaries(pool: PgPool) {
+        // Last second of the first minute and first second of the next stay apart
+        insert_trade(&pool, trade(1, MINUTE, 100, 1)).await;
+        insert_trade(&pool, trade(2, MINUTE + 59, 110, 2)).await;
+        insert_trade(&pool, trade(3, MINUTE + 60, 90, 3)).await;
+        insert_trade(&pool, trade(4, MINUTE + 119, 95, 4)).await;
+        // An empty minute in between produces no bar
+        insert_trade(&pool, trade(5, MINUTE + 180, 120, 5)).await;
+
+        let source = bar_source("1", "trades").unwrap();
+        let bars = fetch_bars(&pool, "ETH/USDT", &source, MINUTE, MINUTE + 240, 100)
+            .await
+            .unwrap();
+
+        let d = Decimal::from;
+        assert_eq!(
+            bars,
+            vec![
+                (MINUTE, d(100), d(110), d(100), d(110), d(3)),
+                (MINUTE + 60, d(90), d(95), d(90), d(95), d(7)),
+                (MINUTE + 180, d(120), d(120), d(120), d(120), d(5)),
+            ]
+        );
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_flagged_trades_stay_out_of_bars(pool: PgPool) {
+        insert_trade(&pool, trade(1, MINUTE, 100, 2)).await;
+        // Stored under the flag policy: a zero price next to a valid trade, and a zero
+        // quantity alone in the following minute
+        insert_trade(&pool, trade(2, MINUTE + 10, 0, 5)).await;
+        insert_trade(&pool, trade(3, MINUTE + 70, 120, 0)).await;
+
+        let source = bar_source("1", "trades").unwrap();
+        let d = Decimal::from;
+        let only_valid = vec![(MINUTE, d(100), d(100), d(100), d(100), d(2))];
+        let bars = fetch_bars(&pool, "ETH/USDT", &source, MINUTE, MINUTE + 120, 100)
+            .await
+            .unwrap();
+        assert_eq!(bars, only_valid);
+        let bars = fetch_last_bars(&pool, "ETH/USDT", &source, None, MINUTE + 120, 10)
+            .await
+            .unwrap();
+        assert_eq!(bars, only_valid);
+
+        let next = next_bar_time(&pool, "ETH/USDT", &source, MINUTE + 120)
+            .await
+            .unwrap();
+        assert_eq!(next, Some(MINUTE));
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_countback_returns_last_bars_before_to(pool: PgPool) {
+        insert_trade(&pool, trade(1, MINUTE, 100, 1)).await;
+        insert_trade(&pool, trade(2, MINUTE + 60, 110, 1)).await;
+        insert_trade(&pool, trade(3, MINUTE + 3_600, 120, 1)).await;
+        // At `to`, so outside the requested bars
+        insert_trade(&pool, trade(4, MINUTE + 7_200, 130, 1)).await;
+
+        // Two bars an hour apart, however short the from/to range would have been
+        let source = bar_source("1", "trades").unwrap();
+        let bars = fetch_last_bars(&pool, "ETH/USDT", &source, None, MINUTE + 7_200, 2)
+            .await
+            .unwrap();
+        let times: Vec<i64> = bars.iter().map(|bar| bar.0).collect();
+        assert_eq!(times, vec![MINUTE + 60, MINUTE + 3_600]);
+
+        // The history window still bounds how far back it reaches
+        let since = Some(MINUTE + 30);
+        let bars = fetch_last_bars(&pool, "ETH/USDT", &source, since, MINUTE + 7_200, 10)
+            .await
+            .unwrap();
+        assert_eq!(bars.len(), 2);
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_empty_range_points_to_previous_bar(pool: PgPool) {
+        insert_trade(&pool, trade(1, MINUTE + 30, 100, 1)).await;
+        let source = bar_source("5", "trades").unwrap();
+
+        let later = (MINUTE + 3_600, MINUTE + 7_200);
+        let bars = fetch_bars(&pool, "ETH/USDT", &source, later.0, later.1, 100)
+            .await
+            .unwrap();
+        assert!(bars.is_empty());
+
+        // 22:13:30 falls in the 22:10 five-minute bar
+        let next = next_bar_time(&pool, "ETH/USDT", &source, later.0)
+            .await
+            .unwrap();
+        assert_eq!(next, Some(MINUTE - 180));
+        let before_any = next_bar_time(&pool, "ETH/USDT", &source, MINUTE)
+            .await
+            .unwrap();
+        assert_eq!(before_any, None);
+    }
 }
@@ -0,0 +1,279 @@
+//! `Idempotency-Key` support for admin mutations
+//!
+//! Not mounted yet: the admin router has no mutating routes. Layer [`idempotent`] onto the
+//! first one that lands.
+
+use crate::api::error::ApiError;
+use axum::{
+    body::{to_bytes, Body, Bytes, HttpBody},
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{debug, error, warn};
+
+/// Request header carrying the client's idempotency key
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+/// Response header set on results replayed from the cache
+pub const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+
+/// Largest response body kept for replay
+const MAX_STORED_BODY: usize = 1 << 20;
+
+/// Result of a mutation, kept to answer retries with the same key
+#[derive(Clone)]
+struct StoredResponse {
+    stored_at: Instant,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl StoredResponse {
+    /// Stands in for a result too large to keep: the mutation was applied, so a retry
+    /// gets a conflict rather than running it again
+    fn too_large() -> Self {
+        let error =
+            ApiError::Conflict("Already applied; the response was too large to replay".to_string());
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        );
+        Self {
+            stored_at: Instant::now(),
+            status: error.status(),
+            headers,
+            body: Bytes::from(error.body().to_string()),
+        }
+    }
+
+    fn replay(&self) -> Response {
+        let mut response = (self.status, self.headers.clone(), self.body.clone()).into_response();
+        response
+            .headers_mut()
+            .insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+        response
+    }
+}
+
+/// One key's result: empty until its first request completes, or when that failed
+type Slot = Arc<Mutex<Option<StoredResponse>>>;
+
+/// Recently seen idempotency keys and the responses they produced.
+///
+/// Keys are scoped to method and path, so one key reused on two endpoints applies both.
+/// Requests with the same key run one at a time: a retry racing the original waits for
+/// it and gets its result instead of applying the mutation again. Different keys don't
+/// wait on each other.
+pub struct IdempotencyCache {
+    retention: Duration,
+    entries: Mutex<HashMap<String, Slot>>,
+}
+
+impl IdempotencyCache {
+    #[allow(dead_code)]
+    pub fn new(retention: Duration) -> Self {
+        Self {
+            retention,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The slot of `key`, dropping expired keys on the way
+    async fn slot(&self, key: &str) -> Slot {
+        let mut entries = self.entries.lock().await;
+        entries.retain(|_, slot| {
+            // Keep keys a request still holds, and results within retention
+            Arc::strong_count(slot) > 1
+                || slot
+                    .try_lock()
+                    .is_ok_and(|stored| stored.as_ref().is_some_and(|stored| self.is_fresh(stored)))
+        });
+        entries.entry(key.to_string()).or_default().clone()
+    }
+
+    fn is_fresh(&self, stored: &StoredResponse) -> bool {
+        stored.stored_at.elapsed() < self.retention
+    }
+}
+
+/// Middleware answering repeated admin POSTs that carry an `Idempotency-Key` with the
+/// original response. Requests without a key, or other methods, pass straight through.
+#[allow(dead_code)]
+pub async fn idempotent(
+    State(cache): State<Arc<IdempotencyCache>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let Some(key) = request
+        .headers()
+        .get(IDEMPOTENCY_KEY)
+        .and_then(|key| key.to_str().ok())
+        .filter(|key| !key.is_empty())
+    else {
+        return next.run(request).await;
+    };
+    let key = format!("{} {} {}", request.method(), request.uri().path(), key);
+
+    let slot = cache.slot(&key).await;
+    let mut stored = slot.lock().await;
+    if let Some(response) = stored.as_ref().filter(|stored| cache.is_fresh(stored)) {
+        debug!("Replaying stored response for {}", key);
+        return response.replay();
+    }
+
+    let (parts, body) = next.run(request).await.into_parts();
+    // Server errors are not final: let a retry attempt the mutation again
+    let keep = !parts.status.is_server_error();
+
+    if body.size_hint().lower() > MAX_STORED_BODY as u64 {
+        warn!("⚠️  Response for {} is too large to store for replay", key);
+        if keep {
+            *stored = Some(StoredResponse::too_large());
+        }
+        return Response::from_parts(parts, body);
+    }
+    let body = match to_bytes(body, MAX_STORED_BODY).await {
+        Ok(body) => body,
+        Err(e) => {
+            error!("❌ Failed to buffer response for {}: {}", key, e);
+            if keep {
+                *stored = Some(StoredResponse::too_large());
+            }
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    if keep {
+        *stored = Some(StoredResponse {
+            stored_at: Instant::now(),
+            status: parts.status,
+            headers: parts.headers.clone(),
+            body: body.clone(),
+        });
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{middleware, routing::post, Router};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tower::ServiceExt;
+
+    fn router(halts: Arc<AtomicUsize>, retention: Duration) -> Router {
+        Router::new()
+            .route(
+                "/admin/halt",
+                post(move |headers: HeaderMap| async move {
+                    if headers.contains_key("x-hang") {
+                        std::future::pending::<()>().await;
+                    }
+                    let applied = halts.fetch_add(1, Ordering::SeqCst) + 1;
+                    if headers.contains_key("x-large") {
+                        return "x".repeat(MAX_STORED_BODY + 1);
+                    }
+                    format!("halted {applied}")
+                }),
+            )
+            .layer(middleware::from_fn_with_state(
+                Arc::new(IdempotencyCache::new(retention)),
+                idempotent,
+            ))
+    }
+
+    async fn halt(router: &Router, key: Option<&str>) -> (bool, String) {
+        halt_with(router, key, None).await
+    }
+
+    /// Halt with an extra test header: `x-hang` never returns, `x-large` can't be stored
+    async fn halt_with(router: &Router, key: Option<&str>, flag: Option<&str>) -> (bool, String) {
+        let mut request = axum::http::Request::post("/admin/halt");
+        if let Some(key) = key {
+            request = request.header(IDEMPOTENCY_KEY, key);
+        }
+        if let Some(flag) = flag {
+            request = request.header(flag, "1");
+        }
+        let response = router
+            .clone()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let replayed = response.headers().contains_key(IDEMPOTENT_REPLAYED);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (replayed, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_repeated_key_applies_mutation_once() {
+        let halts = Arc::new(AtomicUsize::new(0));
+        let router = router(halts.clone(), Duration::from_secs(60));
+
+        assert_eq!(halt(&router, Some("a")).await, (false, "halted 1".into()));
+        // The retry gets the original result without halting again
+        assert_eq!(halt(&router, Some("a")).await, (true, "halted 1".into()));
+        assert_eq!(halts.load(Ordering::SeqCst), 1);
+
+        // A new key, or no key, is a new mutation
+        assert_eq!(halt(&router, Some("b")).await, (false, "halted 2".into()));
+        assert_eq!(halt(&router, None).await, (false, "halted 3".into()));
+        assert_eq!(halt(&router, None).await, (false, "halted 4".into()));
+    }
+
+    #[tokio::test]
+    async fn test_keys_expire_after_retention() {
+        let halts = Arc::new(AtomicUsize::new(0));
+        let router = router(halts.clone(), Duration::from_millis(20));
+
+        halt(&router, Some("a")).await;
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(halt(&router, Some("a")).await, (false, "halted 2".into()));
+    }
+
+    #[tokio::test]
+    async fn test_other_keys_proceed_while_one_is_in_flight() {
+        let halts = Arc::new(AtomicUsize::new(0));
+        let router = router(halts.clone(), Duration::from_secs(60));
+
+        let stuck = tokio::spawn({
+            let router = router.clone();
+            async move { halt_with(&router, Some("a"), Some("x-hang")).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let other = tokio::time::timeout(Duration::from_secs(1), halt(&router, Some("b")))
+            .await
+            .expect("a hanging key must not hold up other keys");
+        assert_eq!(other, (false, "halted 1".into()));
+        // A retry of the stuck key waits for it rather than running alongside
+        let retry = tokio::time::timeout(Duration::from_millis(50), halt(&router, Some("a")));
+        assert!(retry.await.is_err());
+        stuck.abort();
+    }
+
+    #[tokio::test]
+    async fn test_oversized_response_is_not_applied_twice() {
+        let halts = Arc::new(AtomicUsize::new(0));
+        let router = router(halts.clone(), Duration::from_secs(60));
+
+        let (replayed, body) = halt_with(&router, Some("a"), Some("x-large")).await;
+        assert!(!replayed);
+        assert_eq!(body.len(), MAX_STORED_BODY + 1);
+
+        let (replayed, body) = halt_with(&router, Some("a"), Some("x-large")).await;
+        assert!(replayed);
+        assert!(body.contains("CONFLICT"));
+        assert_eq!(halts.load(Ordering::SeqCst), 1);
+    }
+}
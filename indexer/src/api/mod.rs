@@ -1,3 +1,9 @@
+pub mod error;
+pub mod grpc;
 pub mod handlers;
+pub mod idempotency;
 pub mod server;
+pub mod state;
+#[cfg(test)]
+pub mod test_support;
 pub mod websocket;
@@ -1,69 +1,221 @@
+use crate::api::state::AppState;
 use crate::api::{handlers, websocket};
-use crate::indexer::candle_aggregator::CandleUpdate;
-use crate::indexer::orderbook_reducer::{OrderbookSnapshot, OrderbookState};
-use axum::{routing::get, Router};
-use sqlx::PgPool;
+use crate::indexer::event_collector::CollectorProgress;
+use axum::{extract::State, http::StatusCode, routing::get, Router};
+use metrics_exporter_prometheus::PrometheusHandle;
 use std::env;
+use std::future::IntoFuture;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::{broadcast, Mutex};
+use tokio_util::sync::CancellationToken;
 use tower_http::cors::{Any, CorsLayer};
 use tracing::info;
 
+/// Serve the API until `shutdown` is cancelled, then let in-flight requests finish
 pub async fn run_server(
-    orderbook: Arc<Mutex<OrderbookState>>,
-    pool: PgPool,
-    ob_broadcast: broadcast::Sender<OrderbookSnapshot>,
-    candle_broadcast: broadcast::Sender<CandleUpdate>,
+    app_state: AppState,
+    metrics_handle: Option<PrometheusHandle>,
+    shutdown: CancellationToken,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let app_state = (orderbook.clone(), pool);
+    let admin_addr = app_state.config.admin_addr;
+    let public = public_router(app_state.clone()).await;
+    let admin = admin_router(app_state, metrics_handle);
 
-    // Create unified websocket router with its own state
-    let unified_ws_state = (
-        orderbook.clone(),
-        ob_broadcast.clone(),
-        candle_broadcast.clone(),
+    let port = env::var("INDEXER_PORT")
+        .unwrap_or_else(|_| "8081".to_string())
+        .parse::<u16>()?;
+    let listener = tokio::net::TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port))).await?;
+
+    info!("🌐 API Server: http://0.0.0.0:{}", port);
+    info!(
+        "🔥 WebSocket (orderbook + OHLCV): ws://0.0.0.0:{}/ws/market",
+        port
     );
-    let unified_router = Router::new()
-        .route("/ws/market", get(websocket::ws_unified::ws_unified_handler))
-        .with_state(unified_ws_state);
+    info!(
+        "⏪ WebSocket (trade replay): ws://0.0.0.0:{}/ws/replay",
+        port
+    );
+    info!("🧾 WebSocket (L3 orders): ws://0.0.0.0:{}/ws/l3", port);
+    info!("📖 REST API:");
+    info!("   - Orderbook: http://0.0.0.0:{}/api/orderbook", port);
+    info!("   - Candles: http://0.0.0.0:{}/api/candles", port);
+    info!(
+        "   - Open candles: http://0.0.0.0:{}/api/candles/open",
+        port
+    );
+    info!("   - Tape: http://0.0.0.0:{}/api/tape", port);
+    info!("   - Index price: http://0.0.0.0:{}/api/index", port);
+    info!("   - Order flow: http://0.0.0.0:{}/api/flow/stats", port);
+    info!("   - UDF: http://0.0.0.0:{}/udf/", port);
+
+    let Some(admin_addr) = admin_addr else {
+        info!("   - Info: http://0.0.0.0:{}/api/info", port);
+        info!("   - Metrics: http://0.0.0.0:{}/metrics", port);
+        axum::serve(listener, public.merge(admin))
+            .with_graceful_shutdown(shutdown.cancelled_owned())
+            .await?;
+        return Ok(());
+    };
 
-    let app = Router::new()
+    let admin_listener = tokio::net::TcpListener::bind(admin_addr).await?;
+    info!("🔒 Admin Server: http://{}", admin_addr);
+    info!("   - Info: http://{}/api/info", admin_addr);
+    info!("   - Readiness: http://{}/ready", admin_addr);
+    info!("   - Metrics: http://{}/metrics", admin_addr);
+
+    tokio::try_join!(
+        axum::serve(listener, public)
+            .with_graceful_shutdown(shutdown.clone().cancelled_owned())
+            .into_future(),
+        axum::serve(admin_listener, admin)
+            .with_graceful_shutdown(shutdown.cancelled_owned())
+            .into_future(),
+    )?;
+
+    Ok(())
+}
+
+/// Market data routes for external clients
+pub async fn public_router(app_state: AppState) -> Router {
+    Router::new()
         //REST API endpoints
         .nest(
             "/api/orderbook",
             handlers::orderbook_hand::orderbook_routes().await,
         )
+        .route(
+            "/api/orderbooks",
+            get(handlers::orderbook_hand::get_orderbooks),
+        )
+        .route(
+            "/api/markets/active",
+            get(handlers::markets_hand::get_active_markets),
+        )
         .route("/api/candles", get(handlers::ohlcv_hand::get_candles))
+        .route(
+            "/api/candles/open",
+            get(handlers::ohlcv_hand::get_open_candles),
+        )
+        .route("/api/tape", get(handlers::tape_hand::get_tape))
+        .route("/api/trades", get(handlers::trades_hand::get_trades))
+        .route("/api/ticker", get(handlers::ticker_hand::get_ticker))
+        .route(
+            "/api/order/{id}/history",
+            get(handlers::orderbook_hand::get_order_history),
+        )
+        .route("/api/index", get(handlers::index_hand::get_index))
+        .route("/api/flow/stats", get(handlers::flow_hand::get_flow_stats))
+        .route(
+            "/api/volume-profile",
+            get(handlers::profile_hand::get_volume_profile),
+        )
         .nest("/udf", handlers::udf::udf_routes().await)
         //health stuff
         .route("/health", get(|| async { "OK" }))
+        // Unified websocket (orderbook + OHLCV)
+        .route("/ws/market", get(websocket::ws_unified::ws_unified_handler))
+        .route("/ws/replay", get(websocket::replay::ws_replay_handler))
+        .route("/ws/l3", get(websocket::ws_l3::ws_l3_handler))
         .with_state(app_state)
-        // Merge unified websocket router
-        .merge(unified_router)
         .layer(
             CorsLayer::new()
                 .allow_origin(Any)
                 .allow_methods(Any)
                 .allow_headers(Any),
-        );
+        )
+}
 
-    let port = env::var("INDEXER_PORT")
-        .unwrap_or_else(|_| "8081".to_string())
-        .parse::<u16>()?;
-    let listener = tokio::net::TcpListener::bind(SocketAddr::from(([0, 0, 0, 0], port))).await?;
+/// Diagnostics and metrics, meant for an internal network when `ADMIN_ADDR` is set
+pub fn admin_router(app_state: AppState, metrics_handle: Option<PrometheusHandle>) -> Router {
+    Router::new()
+        .route("/api/info", get(handlers::info_hand::get_info))
+        .route("/ready", get(ready))
+        .with_state(app_state)
+        .merge(metrics_router(metrics_handle))
+}
 
-    info!("🌐 API Server: http://0.0.0.0:{}", port);
-    info!(
-        "🔥 WebSocket (orderbook + OHLCV): ws://0.0.0.0:{}/ws/market",
-        port
-    );
-    info!("📖 REST API:");
-    info!("   - Orderbook: http://0.0.0.0:{}/api/orderbook", port);
-    info!("   - Candles: http://0.0.0.0:{}/api/candles", port);
-    info!("   - UDF: http://0.0.0.0:{}/udf/", port);
+/// Readiness probe: 503 until the collector seeded the book and indexed a block
+async fn ready(State(progress): State<Arc<CollectorProgress>>) -> StatusCode {
+    if progress.is_ready() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+}
+
+/// Prometheus text exposition at `/metrics`, absent when metrics are disabled
+fn metrics_router(metrics_handle: Option<PrometheusHandle>) -> Router {
+    match metrics_handle {
+        Some(handle) => Router::new()
+            .route(
+                "/metrics",
+                get(|State(handle): State<PrometheusHandle>| async move { handle.render() }),
+            )
+            .with_state(handle),
+        None => Router::new(),
+    }
+}
 
-    axum::serve(listener, app).await?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::handlers::ticker_hand::TickerCache;
+    use crate::api::state::Channels;
+    use crate::config::IndexerConfig;
+    use crate::indexer::candle_aggregator::CandleAggregator;
+    use crate::indexer::node_failover::NodeEndpoints;
+    use crate::indexer::orderbook_reducer::OrderbookState;
+    use axum::body::Body;
+    use axum::http::Request;
+    use sqlx::PgPool;
+    use std::time::Duration;
+    use tokio::sync::{broadcast, watch, Mutex};
+    use tower::ServiceExt;
 
-    Ok(())
+    fn app_state() -> AppState {
+        let config = Arc::new(IndexerConfig::default());
+        let (ob_tx, _) = broadcast::channel(16);
+        let (candle_tx, _) = broadcast::channel(16);
+        let (signal_tx, _) = broadcast::channel(16);
+        let (trade_tx, _) = broadcast::channel(16);
+        let (l3_tx, _) = broadcast::channel(16);
+        AppState {
+            orderbook: Arc::new(Mutex::new(OrderbookState::new())),
+            pool: PgPool::connect_lazy("postgres://localhost/unused").unwrap(),
+            candles: CandleAggregator::with_config(candle_tx.clone(), &config).reader(),
+            channels: Channels {
+                orderbook: watch::channel(ob_tx).1,
+                candles: watch::channel(candle_tx).1,
+                signals: watch::channel(signal_tx).1,
+                trades: watch::channel(trade_tx).1,
+                l3: watch::channel(l3_tx).1,
+            },
+            disconnect_hook: None,
+            index_pricer: crate::indexer::index_price::from_config(&config),
+            nodes: Arc::new(NodeEndpoints::new(config.node_urls.clone())),
+            progress: Arc::new(CollectorProgress::default()),
+            tickers: Arc::new(TickerCache::new(Duration::from_millis(
+                config.ticker_cache_ms,
+            ))),
+            config,
+        }
+    }
+
+    async fn status(router: &Router, path: &str) -> StatusCode {
+        let request = Request::get(path).body(Body::empty()).unwrap();
+        router.clone().oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_admin_routes_stay_off_the_public_router() {
+        let public = public_router(app_state()).await;
+        let admin = admin_router(app_state(), None);
+
+        for path in ["/api/info", "/ready"] {
+            assert_eq!(status(&public, path).await, StatusCode::NOT_FOUND, "{path}");
+            assert_ne!(status(&admin, path).await, StatusCode::NOT_FOUND, "{path}");
+        }
+        assert_eq!(status(&public, "/health").await, StatusCode::OK);
+        assert_eq!(status(&admin, "/api/index").await, StatusCode::NOT_FOUND);
+    }
 }
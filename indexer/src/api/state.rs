@@ -0,0 +1,111 @@
+use crate::api::handlers::ticker_hand::TickerCache;
+use crate::api::websocket::disconnect::DisconnectHook;
+use crate::config::IndexerConfig;
+use crate::indexer::candle_aggregator::{CandleReader, CandleUpdate};
+use crate::indexer::event_collector::CollectorProgress;
+use crate::indexer::index_price::IndexPricer;
+use crate::indexer::node_failover::NodeEndpoints;
+use crate::indexer::orderbook_reducer::{L3Event, OrderbookSnapshot, OrderbookState};
+use crate::indexer::trade_clusters::MomentumSignal;
+use crate::indexer::trade_mapper::TradeUpdate;
+use axum::extract::FromRef;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tokio::sync::{broadcast, watch, Mutex};
+
+/// In-memory orderbook shared with the event collector
+pub type SharedOrderbook = Arc<Mutex<OrderbookState>>;
+
+/// Hook fired when a watched trader disconnects, None when the feature is off
+pub type SharedDisconnectHook = Option<Arc<dyn DisconnectHook>>;
+
+/// Configured index price computation
+pub type SharedIndexPricer = Arc<dyn IndexPricer>;
+
+/// Live broadcast senders for the push channels, replaced on internal restarts
+#[derive(Clone)]
+pub struct Channels {
+    pub orderbook: watch::Receiver<broadcast::Sender<OrderbookSnapshot>>,
+    pub candles: watch::Receiver<broadcast::Sender<CandleUpdate>>,
+    pub signals: watch::Receiver<broadcast::Sender<MomentumSignal>>,
+    pub trades: watch::Receiver<broadcast::Sender<TradeUpdate>>,
+    pub l3: watch::Receiver<broadcast::Sender<L3Event>>,
+}
+
+/// Everything the HTTP and WebSocket handlers can depend on.
+///
+/// Handlers don't take the whole struct: each extracts only the parts it uses, e.g.
+/// `State(pool): State<PgPool>`, through the `FromRef` impls below.
+#[derive(Clone)]
+pub struct AppState {
+    pub orderbook: SharedOrderbook,
+    pub pool: PgPool,
+    pub candles: CandleReader,
+    pub channels: Channels,
+    pub disconnect_hook: SharedDisconnectHook,
+    pub index_pricer: SharedIndexPricer,
+    pub nodes: Arc<NodeEndpoints>,
+    pub progress: Arc<CollectorProgress>,
+    pub tickers: Arc<TickerCache>,
+    pub config: Arc<IndexerConfig>,
+}
+
+impl FromRef<AppState> for SharedOrderbook {
+    fn from_ref(state: &AppState) -> Self {
+        state.orderbook.clone()
+    }
+}
+
+impl FromRef<AppState> for PgPool {
+    fn from_ref(state: &AppState) -> Self {
+        state.pool.clone()
+    }
+}
+
+impl FromRef<AppState> for CandleReader {
+    fn from_ref(state: &AppState) -> Self {
+        state.candles.clone()
+    }
+}
+
+impl FromRef<AppState> for Channels {
+    fn from_ref(state: &AppState) -> Self {
+        state.channels.clone()
+    }
+}
+
+impl FromRef<AppState> for SharedDisconnectHook {
+    fn from_ref(state: &AppState) -> Self {
+        state.disconnect_hook.clone()
+    }
+}
+
+impl FromRef<AppState> for SharedIndexPricer {
+    fn from_ref(state: &AppState) -> Self {
+        state.index_pricer.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<NodeEndpoints> {
+    fn from_ref(state: &AppState) -> Self {
+        state.nodes.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<CollectorProgress> {
+    fn from_ref(state: &AppState) -> Self {
+        state.progress.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<TickerCache> {
+    fn from_ref(state: &AppState) -> Self {
+        state.tickers.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<IndexerConfig> {
+    fn from_ref(state: &AppState) -> Self {
+        state.config.clone()
+    }
+}
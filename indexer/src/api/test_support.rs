@@ -0,0 +1,84 @@
+//! Fixtures shared by the API test modules
+
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+
+use crate::indexer::orderbook_reducer::OrderInfo;
+use crate::indexer::scaling::scale_amount;
+
+/// An open order with a whole-number price and quantity, on a market with 6 decimals
+pub fn order(order_id: u64, side: &str, price: i64, quantity: i64) -> OrderInfo {
+    resting(
+        order_id,
+        side,
+        price as u128 * 1_000_000,
+        quantity as u128 * 1_000_000,
+    )
+}
+
+/// An open order from its on-chain amounts, on a market with 6 decimals
+pub fn resting(order_id: u64, side: &str, raw_price: u128, raw_quantity: u128) -> OrderInfo {
+    OrderInfo {
+        order_id,
+        side: side.to_string(),
+        price: scale_amount(raw_price, 6),
+        quantity: scale_amount(raw_quantity, 6),
+        filled_quantity: Decimal::ZERO,
+        status: "Open".to_string(),
+        raw_price,
+        raw_quantity,
+    }
+}
+
+/// A `trades` row; fill in what the test cares about and take the rest from `default()`
+#[derive(Debug, Clone)]
+pub struct TestTrade {
+    pub trade_id: i64,
+    pub block_number: i64,
+    pub buy_order_id: i64,
+    pub sell_order_id: i64,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub symbol: &'static str,
+    pub side: Option<&'static str>,
+    /// Trade time in milliseconds, None for the time of the insert
+    pub time_ms: Option<i64>,
+}
+
+impl Default for TestTrade {
+    fn default() -> Self {
+        Self {
+            trade_id: 1,
+            block_number: 1,
+            buy_order_id: 1,
+            sell_order_id: 2,
+            price: Decimal::from(100),
+            quantity: Decimal::ONE,
+            symbol: "ETH/USDT",
+            side: None,
+            time_ms: None,
+        }
+    }
+}
+
+pub async fn insert_trade(pool: &PgPool, trade: TestTrade) {
+    sqlx::query(
+        "INSERT INTO trades
+        (trade_id, block_number, buy_order_id, sell_order_id, buyer, seller, price, quantity, value, symbol, side, created_at)
+        VALUES ($1, $2, $3, $4, '0xb', '0xs', $5, $6, $7, $8, $9,
+            COALESCE(to_timestamp($10::bigint / 1000.0), NOW()))",
+    )
+    .bind(trade.trade_id)
+    .bind(trade.block_number)
+    .bind(trade.buy_order_id)
+    .bind(trade.sell_order_id)
+    .bind(trade.price)
+    .bind(trade.quantity)
+    .bind(trade.price * trade.quantity)
+    .bind(trade.symbol)
+    .bind(trade.side)
+    .bind(trade.time_ms)
+    .execute(pool)
+    .await
+    .unwrap();
+}
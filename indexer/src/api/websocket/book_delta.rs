@@ -0,0 +1,187 @@
+use super::messages::{BookAction, MarketDataMessage, OrderbookDelta, WsPriceLevel};
+/// L2 book streamed as per-level deltas after one full snapshot
+use crate::indexer::orderbook_reducer::{book_checksum, OrderbookSnapshot, PriceLevel};
+
+/// How a client wants L2 book frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BookMode {
+    /// Every frame carries the whole book with cumulative sizes
+    #[default]
+    Snapshot,
+    /// One full snapshot, then only the levels that changed
+    Delta,
+}
+
+impl BookMode {
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode.to_ascii_lowercase().as_str() {
+            "snapshot" => Some(BookMode::Snapshot),
+            "delta" => Some(BookMode::Delta),
+            _ => None,
+        }
+    }
+}
+
+fn ws_levels(levels: Vec<PriceLevel>) -> Vec<WsPriceLevel> {
+    levels
+        .into_iter()
+        .map(|level| WsPriceLevel {
+            px: level.price.to_string(),
+            sz: level.total_quantity.to_string(),
+            n: level.order_count,
+            rest: level.rest,
+        })
+        .collect()
+}
+
+/// How often delta frames carry a book checksum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChecksumSchedule {
+    /// Levels per side covered by the checksum
+    pub levels: usize,
+    /// Every `every`-th frame by `seq`; snapshots always carry one
+    pub every: u64,
+}
+
+/// Last book sent to one subscriber, used to emit only the levels that changed
+pub struct BookDeltas {
+    symbol: String,
+    seq: u64,
+    last: Option<OrderbookSnapshot>,
+    checksums: Option<ChecksumSchedule>,
+}
+
+impl BookDeltas {
+    pub fn new(symbol: String) -> Self {
+        Self {
+            symbol,
+            seq: 0,
+            last: None,
+            checksums: None,
+        }
+    }
+
+    /// Attach book checksums on `schedule`, none when it is None
+    pub fn with_checksums(mut self, schedule: Option<ChecksumSchedule>) -> Self {
+        self.checksums = schedule;
+        self
+    }
+
+    fn checksum(&self, action: BookAction) -> Option<u32> {
+        let schedule = self.checksums?;
+        let book = self.last.as_ref()?;
+        let due = action == BookAction::Snapshot || self.seq.is_multiple_of(schedule.every);
+        due.then(|| book_checksum(&book.bids, &book.asks, schedule.levels))
+    }
+
+    fn message(
+        &mut self,
+        action: BookAction,
+        bids: Vec<PriceLevel>,
+        asks: Vec<PriceLevel>,
+    ) -> MarketDataMessage {
+        self.seq += 1;
+        MarketDataMessage::BookDelta(OrderbookDelta {
+            action,
+            symbol: self.symbol.clone(),
+            time: chrono::Utc::now().timestamp_millis(),
+            seq: self.seq,
+            levels: [ws_levels(bids), ws_levels(asks)],
+            checksum: self.checksum(action),
+        })
+    }
+
+    /// The whole book, replacing whatever the client held
+    pub fn snapshot(&mut self, snapshot: OrderbookSnapshot) -> MarketDataMessage {
+        let (bids, asks) = (snapshot.bids.clone(), snapshot.asks.clone());
+        self.last = Some(snapshot);
+        self.message(BookAction::Snapshot, bids, asks)
+    }
+
+    /// Levels changed since the last message, None when the book is unchanged
+    pub fn delta(&mut self, snapshot: OrderbookSnapshot) -> Option<MarketDataMessage> {
+        let Some(last) = self.last.as_ref() else {
+            return Some(self.snapshot(snapshot));
+        };
+        let (bids, asks) = snapshot.diff(last);
+        self.last = Some(snapshot);
+
+        if bids.is_empty() && asks.is_empty() {
+            return None;
+        }
+        Some(self.message(BookAction::Update, bids, asks))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::order;
+    use crate::indexer::orderbook_reducer::OrderbookState;
+
+    fn delta(message: MarketDataMessage) -> OrderbookDelta {
+        match message {
+            MarketDataMessage::BookDelta(delta) => delta,
+            other => panic!("expected book delta, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_only_changed_levels_follow_the_snapshot() {
+        let mut book = OrderbookState::new();
+        book.add_order(order(1, "Buy", 100, 2));
+        book.add_order(order(2, "Buy", 99, 1));
+        book.add_order(order(3, "Sell", 101, 3));
+
+        let mut deltas = BookDeltas::new("ETH/USDT".to_string());
+        let first = delta(deltas.snapshot(book.get_snapshot()));
+        assert_eq!((first.action, first.seq), (BookAction::Snapshot, 1));
+        // Per-level sizes, not cumulative
+        assert_eq!(first.levels[0][1].sz, "1");
+
+        book.add_order(order(4, "Sell", 101, 1));
+        book.cancel_order(2).unwrap();
+        let update = delta(deltas.delta(book.get_snapshot()).unwrap());
+        assert_eq!((update.action, update.seq), (BookAction::Update, 2));
+        let [bids, asks] = update.levels;
+        assert_eq!((bids[0].px.as_str(), bids[0].sz.as_str()), ("99", "0"));
+        assert_eq!((asks[0].px.as_str(), asks[0].sz.as_str()), ("101", "4"));
+        assert_eq!((bids.len(), asks.len()), (1, 1));
+
+        // Unchanged book: nothing to send, and no sequence number used
+        assert!(deltas.delta(book.get_snapshot()).is_none());
+        book.add_order(order(5, "Buy", 98, 1));
+        assert_eq!(delta(deltas.delta(book.get_snapshot()).unwrap()).seq, 3);
+    }
+
+    #[test]
+    fn test_checksums_follow_schedule() {
+        let mut book = OrderbookState::new();
+        book.add_order(order(1, "Buy", 100, 2));
+        let mut deltas =
+            BookDeltas::new("ETH/USDT".to_string()).with_checksums(Some(ChecksumSchedule {
+                levels: 25,
+                every: 2,
+            }));
+
+        // Same levels as the snapshot's own checksum
+        let first = delta(deltas.snapshot(book.get_snapshot()));
+        assert_eq!(first.checksum, Some(book.get_snapshot().checksum));
+
+        book.add_order(order(2, "Sell", 101, 1));
+        let second = delta(deltas.delta(book.get_snapshot()).unwrap());
+        assert_eq!(second.checksum, Some(book.get_snapshot().checksum));
+        book.add_order(order(3, "Sell", 102, 1));
+        assert_eq!(
+            delta(deltas.delta(book.get_snapshot()).unwrap()).checksum,
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(BookMode::parse("Delta"), Some(BookMode::Delta));
+        assert_eq!(BookMode::parse("snapshot"), Some(BookMode::Snapshot));
+        assert_eq!(BookMode::parse("diff"), None);
+    }
+}
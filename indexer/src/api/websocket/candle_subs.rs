@@ -0,0 +1,313 @@
+use crate::indexer::candle_aggregator::{timeframe_ms, CandleSnapshot, CandleUpdate};
+/// Candle subscriptions a client changes over an open connection
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Client command changing which candles it receives, e.g.
+/// `{"action":"subscribe","symbol":"ETH/USDC","timeframes":["1m","1h"]}`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum CandleCommand {
+    /// Add timeframes of `symbol`, every timeframe when none are listed
+    Subscribe {
+        symbol: String,
+        timeframes: Option<Vec<String>>,
+    },
+    /// Drop timeframes of `symbol`, the whole symbol when none are listed
+    Unsubscribe {
+        symbol: String,
+        timeframes: Option<Vec<String>>,
+    },
+}
+
+impl CandleCommand {
+    /// Parse a client text frame, with a message for the client when it isn't a command
+    pub fn parse(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|e| format!("invalid command: {}", e))
+    }
+
+    /// Market the command changes
+    pub fn symbol(&self) -> &str {
+        match self {
+            CandleCommand::Subscribe { symbol, .. } | CandleCommand::Unsubscribe { symbol, .. } => {
+                symbol
+            }
+        }
+    }
+}
+
+/// Timeframes of one symbol a connection receives
+#[derive(Debug, Clone, PartialEq)]
+enum Timeframes {
+    All,
+    Only(BTreeSet<String>),
+}
+
+/// Symbols and timeframes whose candles go out on one connection
+#[derive(Debug, Clone, Default)]
+pub struct CandleSubscriptions {
+    /// Symbols a client may subscribe to
+    markets: Vec<String>,
+    subscribed: BTreeMap<String, Timeframes>,
+}
+
+impl CandleSubscriptions {
+    pub fn new(markets: Vec<String>) -> Self {
+        Self {
+            markets,
+            subscribed: BTreeMap::new(),
+        }
+    }
+
+    /// Whether any candles are subscribed
+    pub fn is_empty(&self) -> bool {
+        self.subscribed.is_empty()
+    }
+
+    /// Whether `symbol` has any subscribed timeframe
+    pub fn has_symbol(&self, symbol: &str) -> bool {
+        self.subscribed.contains_key(symbol)
+    }
+
+    /// Whether `update` should be sent
+    pub fn wants(&self, update: &CandleUpdate) -> bool {
+        match self.subscribed.get(&update.s) {
+            Some(Timeframes::All) => true,
+            Some(Timeframes::Only(timeframes)) => timeframes.contains(&update.i),
+            None => false,
+        }
+    }
+
+    /// Open candles of `snapshot` this connection subscribes to, by symbol and then
+    /// shortest timeframe first
+    pub fn open_candles(&self, snapshot: &CandleSnapshot) -> Vec<CandleUpdate> {
+        let mut updates: Vec<CandleUpdate> = snapshot
+            .values()
+            .map(|candle| CandleUpdate::from_candle(candle, false))
+            .filter(|update| self.wants(update))
+            .collect();
+        updates.sort_by_key(|update| (update.s.clone(), timeframe_ms(&update.i)));
+        updates
+    }
+
+    /// Apply `command`, returning an acknowledgement or why it was rejected. A rejected
+    /// command leaves the subscriptions unchanged.
+    pub fn apply(&mut self, command: CandleCommand) -> Result<String, String> {
+        let timeframes = match &command {
+            CandleCommand::Subscribe { timeframes, .. }
+            | CandleCommand::Unsubscribe { timeframes, .. } => timeframes,
+        };
+        let symbol = command.symbol();
+        if !self.markets.iter().any(|market| market == symbol) {
+            return Err(format!("unknown symbol: {}", symbol));
+        }
+        if let Some(unknown) = timeframes
+            .iter()
+            .flatten()
+            .find(|timeframe| timeframe_ms(timeframe).is_none())
+        {
+            return Err(format!("unknown timeframe: {}", unknown));
+        }
+
+        match command {
+            CandleCommand::Subscribe { symbol, timeframes } => {
+                let current = self.subscribed.remove(&symbol);
+                let merged = match (current, timeframes) {
+                    (Some(Timeframes::All), _) | (_, None) => Timeframes::All,
+                    (Some(Timeframes::Only(mut current)), Some(added)) => {
+                        current.extend(added);
+                        Timeframes::Only(current)
+                    }
+                    (None, Some(added)) => Timeframes::Only(added.into_iter().collect()),
+                };
+                self.subscribed.insert(symbol.clone(), merged);
+                Ok(format!("subscribed {}", self.describe(&symbol)))
+            }
+            CandleCommand::Unsubscribe { symbol, timeframes } => {
+                let remaining = match (self.subscribed.get(&symbol), timeframes) {
+                    (None, _) => return Ok(format!("not subscribed to {}", symbol)),
+                    (Some(_), None) => None,
+                    (Some(Timeframes::All), Some(_)) => {
+                        return Err(format!(
+                            "{} is subscribed to every timeframe; unsubscribe the symbol or resubscribe with a list",
+                            symbol
+                        ))
+                    }
+                    (Some(Timeframes::Only(current)), Some(removed)) => Some(
+                        current
+                            .iter()
+                            .filter(|timeframe| !removed.contains(timeframe))
+                            .cloned()
+                            .collect::<BTreeSet<_>>(),
+                    )
+                    .filter(|remaining| !remaining.is_empty()),
+                };
+                match remaining {
+                    Some(remaining) => {
+                        self.subscribed
+                            .insert(symbol.clone(), Timeframes::Only(remaining));
+                        Ok(format!("subscribed {}", self.describe(&symbol)))
+                    }
+                    None => {
+                        self.subscribed.remove(&symbol);
+                        Ok(format!("unsubscribed {}", symbol))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Subscribe to `symbol` without validation, for the connection's query parameters
+    pub fn insert(&mut self, symbol: String, timeframes: Option<Vec<String>>) {
+        let timeframes = match timeframes {
+            Some(timeframes) => Timeframes::Only(timeframes.into_iter().collect()),
+            None => Timeframes::All,
+        };
+        self.subscribed.insert(symbol, timeframes);
+    }
+
+    fn describe(&self, symbol: &str) -> String {
+        match self.subscribed.get(symbol) {
+            Some(Timeframes::Only(timeframes)) => format!(
+                "{} {}",
+                symbol,
+                timeframes.iter().cloned().collect::<Vec<_>>().join(",")
+            ),
+            _ => format!("{} all timeframes", symbol),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::candle_aggregator::Candle;
+    use rust_decimal::Decimal;
+
+    fn candle(symbol: &str, timeframe: &str) -> Candle {
+        Candle::new(
+            symbol.to_string(),
+            timeframe.to_string(),
+            Decimal::from(2000),
+            Decimal::ONE,
+            60_000,
+        )
+    }
+
+    fn update(symbol: &str, timeframe: &str) -> CandleUpdate {
+        CandleUpdate::from_candle(&candle(symbol, timeframe), false)
+    }
+
+    fn subscriptions() -> CandleSubscriptions {
+        let mut subs =
+            CandleSubscriptions::new(vec!["ETH/USDT".to_string(), "ETH/USDC".to_string()]);
+        subs.insert("ETH/USDT".to_string(), None);
+        subs
+    }
+
+    #[test]
+    fn test_subscribe_and_unsubscribe_timeframes() {
+        let mut subs = subscriptions();
+        assert!(!subs.wants(&update("ETH/USDC", "1m")));
+
+        let command = CandleCommand::parse(
+            r#"{"action":"subscribe","symbol":"ETH/USDC","timeframes":["1m","1h"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            subs.apply(command),
+            Ok("subscribed ETH/USDC 1h,1m".to_string())
+        );
+        assert!(subs.wants(&update("ETH/USDC", "1m")));
+        assert!(!subs.wants(&update("ETH/USDC", "5m")));
+        // The original subscription is untouched
+        assert!(subs.wants(&update("ETH/USDT", "5m")));
+
+        let command = CandleCommand::parse(
+            r#"{"action":"unsubscribe","symbol":"ETH/USDC","timeframes":["1m"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            subs.apply(command),
+            Ok("subscribed ETH/USDC 1h".to_string())
+        );
+        assert!(!subs.wants(&update("ETH/USDC", "1m")));
+
+        let command =
+            CandleCommand::parse(r#"{"action":"unsubscribe","symbol":"ETH/USDT"}"#).unwrap();
+        assert_eq!(subs.apply(command), Ok("unsubscribed ETH/USDT".to_string()));
+        assert!(!subs.has_symbol("ETH/USDT"));
+        assert!(!subs.is_empty());
+    }
+
+    #[test]
+    fn test_open_candles_follow_filters() {
+        let mut subs = CandleSubscriptions::new(vec!["ETH/USDT".to_string()]);
+        subs.insert(
+            "ETH/USDT".to_string(),
+            Some(vec!["1h".to_string(), "1m".to_string()]),
+        );
+
+        let snapshot: CandleSnapshot = [
+            ("ETH/USDT", "1h"),
+            ("ETH/USDT", "5m"),
+            ("ETH/USDT", "1m"),
+            ("DOT/USDT", "1m"),
+        ]
+        .into_iter()
+        .map(|(symbol, timeframe)| {
+            let key = (symbol.to_string(), timeframe.to_string());
+            (key, candle(symbol, timeframe))
+        })
+        .collect();
+        let open: Vec<(String, String)> = subs
+            .open_candles(&snapshot)
+            .into_iter()
+            .map(|update| (update.s, update.i))
+            .collect();
+        assert_eq!(
+            open,
+            vec![
+                ("ETH/USDT".to_string(), "1m".to_string()),
+                ("ETH/USDT".to_string(), "1h".to_string()),
+            ]
+        );
+        assert!(CandleSubscriptions::default()
+            .open_candles(&snapshot)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_malformed_commands_are_rejected() {
+        assert!(CandleCommand::parse("not json").is_err());
+        assert!(CandleCommand::parse(r#"{"action":"replace","symbol":"ETH/USDT"}"#).is_err());
+        assert!(CandleCommand::parse(r#"{"action":"subscribe"}"#).is_err());
+
+        let mut subs = subscriptions();
+        let unknown_symbol = CandleCommand::Subscribe {
+            symbol: "DOGE/USD".to_string(),
+            timeframes: None,
+        };
+        assert_eq!(
+            subs.apply(unknown_symbol),
+            Err("unknown symbol: DOGE/USD".to_string())
+        );
+        let unknown_timeframe = CandleCommand::Subscribe {
+            symbol: "ETH/USDC".to_string(),
+            timeframes: Some(vec!["1m".to_string(), "2m".to_string()]),
+        };
+        assert_eq!(
+            subs.apply(unknown_timeframe),
+            Err("unknown timeframe: 2m".to_string())
+        );
+        assert!(!subs.has_symbol("ETH/USDC"));
+
+        // Narrowing an all-timeframes subscription needs an explicit list first
+        let narrow = CandleCommand::Unsubscribe {
+            symbol: "ETH/USDT".to_string(),
+            timeframes: Some(vec!["1m".to_string()]),
+        };
+        assert!(subs.apply(narrow).is_err());
+        assert!(subs.wants(&update("ETH/USDT", "1m")));
+    }
+}
@@ -0,0 +1,123 @@
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Notification that a watched trader's WebSocket connection ended
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TraderDisconnected {
+    /// Always "trader_disconnected"
+    pub event: &'static str,
+    pub trader: String,
+    /// Milliseconds since epoch
+    pub connected_at: i64,
+    pub disconnected_at: i64,
+}
+
+/// Reacts to a watched trader dropping off, e.g. so an external system can cancel orders
+pub trait DisconnectHook: Send + Sync {
+    fn on_disconnect(&self, event: &TraderDisconnected);
+}
+
+/// POSTs each disconnect as JSON to a configured URL (`DISCONNECT_WEBHOOK_URL`)
+pub struct WebhookHook {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl WebhookHook {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+        }
+    }
+}
+
+impl DisconnectHook for WebhookHook {
+    fn on_disconnect(&self, event: &TraderDisconnected) {
+        let request = self.client.post(&self.url).json(event);
+        let trader = event.trader.clone();
+        // Fire and forget: the connection is already gone, nothing waits on the result
+        tokio::spawn(async move {
+            match request.send().await {
+                Ok(response) if !response.status().is_success() => warn!(
+                    "Disconnect webhook for {} returned {}",
+                    trader,
+                    response.status()
+                ),
+                Ok(_) => {}
+                Err(e) => warn!("Disconnect webhook for {} failed: {}", trader, e),
+            }
+        });
+    }
+}
+
+/// Ties a trader to one WebSocket connection; dropping it fires the disconnect hook.
+///
+/// Held for the lifetime of the socket task, so every way a connection can end (close
+/// frame, network error, failed send) produces exactly one notification. The trader is
+/// the account the client identified itself with; this is a notification integration,
+/// nothing is done on-chain.
+pub struct TraderSession {
+    trader: String,
+    connected_at: i64,
+    hook: Arc<dyn DisconnectHook>,
+}
+
+impl TraderSession {
+    pub fn start(trader: String, hook: Arc<dyn DisconnectHook>) -> Self {
+        info!("👤 Watching connection of trader {}", trader);
+        Self {
+            trader,
+            connected_at: chrono::Utc::now().timestamp_millis(),
+            hook,
+        }
+    }
+}
+
+impl Drop for TraderSession {
+    fn drop(&mut self) {
+        let event = TraderDisconnected {
+            event: "trader_disconnected",
+            trader: self.trader.clone(),
+            connected_at: self.connected_at,
+            disconnected_at: chrono::Utc::now().timestamp_millis(),
+        };
+        info!("👤 Trader {} disconnected", event.trader);
+        self.hook.on_disconnect(&event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordingHook {
+        events: Mutex<Vec<TraderDisconnected>>,
+    }
+
+    impl DisconnectHook for RecordingHook {
+        fn on_disconnect(&self, event: &TraderDisconnected) {
+            self.events.lock().unwrap().push(event.clone());
+        }
+    }
+
+    #[test]
+    fn test_disconnect_fires_hook_once() {
+        let hook = Arc::new(RecordingHook::default());
+
+        let session = TraderSession::start("5GrwvaEF".to_string(), hook.clone());
+        assert!(hook.events.lock().unwrap().is_empty());
+
+        // Simulate the socket task ending
+        drop(session);
+
+        let events = hook.events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event, "trader_disconnected");
+        assert_eq!(events[0].trader, "5GrwvaEF");
+        assert!(events[0].disconnected_at >= events[0].connected_at);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::indexer::orderbook_reducer::{bucket_price, OrderbookState};
+/// Depth aggregated onto a fixed price grid, streamed as per-bucket deltas
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+use super::messages::{BookAction, GridDepthUpdate, MarketDataMessage, WsPriceLevel};
+
+/// Quantity and order count resting in one price bucket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Bucket {
+    pub quantity: Decimal,
+    pub orders: usize,
+}
+
+/// Buckets keyed by their grid price
+type Buckets = BTreeMap<Decimal, Bucket>;
+
+/// Fold every price level of one side into grid buckets
+fn banded_depth(book: &OrderbookState, width: Decimal, side: &str) -> Buckets {
+    let levels = if side == "Buy" {
+        &book.bids
+    } else {
+        &book.asks
+    };
+    let mut buckets = Buckets::new();
+    for (price, orders) in levels {
+        let quantity = book.level_quantity(side, price);
+        if quantity.is_zero() {
+            continue;
+        }
+        let bucket = buckets
+            .entry(bucket_price(*price, width, side))
+            .or_default();
+        bucket.quantity += quantity;
+        bucket.orders += orders.len();
+    }
+    buckets
+}
+
+/// Buckets whose contents differ between `old` and `new`; emptied buckets come back with
+/// zero size so clients can clear them
+fn changed(old: &Buckets, new: &Buckets) -> Buckets {
+    let mut changes: Buckets = new
+        .iter()
+        .filter(|(price, bucket)| old.get(price) != Some(bucket))
+        .map(|(price, bucket)| (*price, *bucket))
+        .collect();
+    for price in old.keys().filter(|price| !new.contains_key(price)) {
+        changes.insert(*price, Bucket::default());
+    }
+    changes
+}
+
+fn ws_levels<'a>(buckets: impl Iterator<Item = (&'a Decimal, &'a Bucket)>) -> Vec<WsPriceLevel> {
+    buckets
+        .map(|(price, bucket)| WsPriceLevel {
+            px: price.to_string(),
+            sz: bucket.quantity.to_string(),
+            n: bucket.orders,
+            rest: false,
+        })
+        .collect()
+}
+
+/// Last grid sent to one subscriber, used to emit only the buckets that changed
+pub struct GridDepth {
+    symbol: String,
+    width: Decimal,
+    bids: Buckets,
+    asks: Buckets,
+}
+
+impl GridDepth {
+    /// None unless `width` is a positive bucket size
+    pub fn new(symbol: String, width: Decimal) -> Option<Self> {
+        (width > Decimal::ZERO).then(|| Self {
+            symbol,
+            width,
+            bids: Buckets::new(),
+            asks: Buckets::new(),
+        })
+    }
+
+    fn message(&self, snapshot: bool, bids: &Buckets, asks: &Buckets) -> MarketDataMessage {
+        MarketDataMessage::GridDepth(GridDepthUpdate {
+            action: if snapshot {
+                BookAction::Snapshot
+            } else {
+                BookAction::Update
+            },
+            symbol: self.symbol.clone(),
+            time: chrono::Utc::now().timestamp_millis(),
+            width: self.width.normalize().to_string(),
+            snapshot,
+            // Best bucket first on both sides, like the L2 book
+            levels: [ws_levels(bids.iter().rev()), ws_levels(asks.iter())],
+        })
+    }
+
+    /// The whole grid, replacing whatever the client held
+    pub fn snapshot(&mut self, book: &OrderbookState) -> MarketDataMessage {
+        self.bids = banded_depth(book, self.width, "Buy");
+        self.asks = banded_depth(book, self.width, "Sell");
+        self.message(true, &self.bids, &self.asks)
+    }
+
+    /// Buckets changed since the last message, None when the grid is unchanged
+    pub fn delta(&mut self, book: &OrderbookState) -> Option<MarketDataMessage> {
+        let bids = banded_depth(book, self.width, "Buy");
+        let asks = banded_depth(book, self.width, "Sell");
+        let bid_changes = changed(&self.bids, &bids);
+        let ask_changes = changed(&self.asks, &asks);
+        self.bids = bids;
+        self.asks = asks;
+
+        if bid_changes.is_empty() && ask_changes.is_empty() {
+            return None;
+        }
+        Some(self.message(false, &bid_changes, &ask_changes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::orderbook_reducer::OrderInfo;
+    use rust_decimal::prelude::ToPrimitive;
+
+    fn order(order_id: u64, side: &str, price: &str, quantity: i64) -> OrderInfo {
+        let price: Decimal = price.parse().unwrap();
+        OrderInfo {
+            order_id,
+            side: side.to_string(),
+            price,
+            quantity: Decimal::from(quantity),
+            filled_quantity: Decimal::ZERO,
+            status: "Open".to_string(),
+            raw_price: (price * Decimal::from(1_000_000)).to_u128().unwrap(),
+            raw_quantity: quantity as u128 * 1_000_000,
+        }
+    }
+
+    fn levels(message: MarketDataMessage) -> [Vec<WsPriceLevel>; 2] {
+        match message {
+            MarketDataMessage::GridDepth(update) => {
+                assert_eq!(update.snapshot, update.action == BookAction::Snapshot);
+                update.levels
+            }
+            other => panic!("expected grid depth, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_bucket_price_rounds_away_from_the_touch() {
+        let width = Decimal::new(5, 1);
+        assert_eq!(
+            bucket_price("100.37".parse().unwrap(), width, "Buy"),
+            Decimal::new(1000, 1)
+        );
+        assert_eq!(
+            bucket_price("100.37".parse().unwrap(), width, "Sell"),
+            Decimal::new(1005, 1)
+        );
+    }
+
+    #[test]
+    fn test_churn_within_a_bucket_is_one_update() {
+        let mut book = OrderbookState::new();
+        book.add_order(order(1, "Buy", "100.1", 2));
+        book.add_order(order(2, "Buy", "98.4", 1));
+        book.add_order(order(3, "Sell", "101.2", 3));
+
+        let mut grid = GridDepth::new("ETH/USDT".to_string(), Decimal::ONE).unwrap();
+        let [bids, asks] = levels(grid.snapshot(&book));
+        assert_eq!(bids.len(), 2);
+        assert_eq!(asks[0].px, "102");
+
+        // Several orders come and go inside the 100 bucket
+        book.add_order(order(4, "Buy", "100.5", 1));
+        book.add_order(order(5, "Buy", "100.9", 4));
+        book.cancel_order(1).unwrap();
+
+        let [bids, asks] = levels(grid.delta(&book).unwrap());
+        assert!(asks.is_empty());
+        assert_eq!(bids.len(), 1);
+        assert_eq!(bids[0].px, "100");
+        assert_eq!(bids[0].sz, "5");
+        assert_eq!(bids[0].n, 2);
+
+        // Nothing moved since: no update at all
+        assert!(grid.delta(&book).is_none());
+
+        // Emptying a bucket reports it with zero size
+        book.cancel_order(2).unwrap();
+        let [bids, _] = levels(grid.delta(&book).unwrap());
+        assert_eq!((bids[0].px.as_str(), bids[0].sz.as_str()), ("98", "0"));
+    }
+}
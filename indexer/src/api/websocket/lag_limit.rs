@@ -0,0 +1,72 @@
+use axum::extract::ws::{close_code, CloseFrame, Message};
+/// Disconnect subscribers that keep falling behind a broadcast channel
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Counts how often a subscriber lagged recently.
+///
+/// A lag means the client reads slower than updates are published; an occasional one is
+/// caught up on, but a client that lags over and over only wastes a slot in the channel.
+pub struct LagLimit {
+    max_lags: usize,
+    window: Duration,
+    lags: VecDeque<Instant>,
+}
+
+impl LagLimit {
+    pub fn new(max_lags: usize, window: Duration) -> Self {
+        Self {
+            max_lags,
+            window,
+            lags: VecDeque::new(),
+        }
+    }
+
+    /// Record a lag at `now`, true once the client lagged more than `max_lags` times
+    /// within the window and should be disconnected
+    pub fn record(&mut self, now: Instant) -> bool {
+        while self
+            .lags
+            .front()
+            .is_some_and(|lag| now.duration_since(*lag) >= self.window)
+        {
+            self.lags.pop_front();
+        }
+        self.lags.push_back(now);
+        self.lags.len() > self.max_lags
+    }
+}
+
+/// Close frame sent to a subscriber dropped for lagging
+pub fn slow_consumer_close() -> Message {
+    Message::Close(Some(CloseFrame {
+        code: close_code::POLICY,
+        reason: "slow consumer: lagged too often".into(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_lags_disconnect() {
+        let mut limit = LagLimit::new(2, Duration::from_secs(10));
+        let start = Instant::now();
+
+        assert!(!limit.record(start));
+        assert!(!limit.record(start + Duration::from_secs(1)));
+        // Third lag within ten seconds crosses the limit
+        assert!(limit.record(start + Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_occasional_lags_are_tolerated() {
+        let mut limit = LagLimit::new(2, Duration::from_secs(10));
+        let start = Instant::now();
+
+        for i in 0..10 {
+            assert!(!limit.record(start + Duration::from_secs(6 * i)));
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use crate::indexer::candle_aggregator::CandleUpdate;
 use crate::indexer::orderbook_reducer::OrderbookSnapshot;
+use crate::indexer::trade_clusters::MomentumSignal;
+use crate::indexer::trade_mapper::TradeUpdate;
 /// Unified WebSocket message types for orderbook and OHLCV updates
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
@@ -12,6 +14,16 @@ pub enum MarketDataMessage {
     Orderbook(OrderbookUpdate),
     /// OHLCV candle update
     Candle(CandleUpdate),
+    /// Deeper levels following a partial initial orderbook snapshot
+    DepthChunk(DepthChunk),
+    /// Depth bucketed onto a fixed price grid
+    GridDepth(GridDepthUpdate),
+    /// Changed L2 levels, for clients that asked for `mode=delta`
+    BookDelta(OrderbookDelta),
+    /// Burst of same-side aggressive trades
+    Momentum(MomentumSignal),
+    /// One executed trade, for a live trade tape
+    Trade(TradeUpdate),
     /// Connection status messages
     Status(StatusMessage),
 }
@@ -25,6 +37,19 @@ pub struct WsPriceLevel {
     pub sz: String,
     /// Number of orders at this level
     pub n: usize,
+    /// Aggregated tail beyond the configured level cap
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub rest: bool,
+}
+
+/// How a client applies a book frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BookAction {
+    /// Full book: replace whatever the client holds
+    Snapshot,
+    /// Changes only: apply on top of the current state
+    Update,
 }
 
 /// Orderbook update message (Hyperliquid L2 book format)
@@ -33,6 +58,7 @@ pub struct WsPriceLevel {
 /// ```json
 /// {
 ///   "type": "orderbook",
+///   "action": "snapshot",
 ///   "symbol": "ETH/USDT",
 ///   "time": 1754450974231,
 ///   "levels": [
@@ -49,24 +75,109 @@ pub struct WsPriceLevel {
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderbookUpdate {
+    /// Every L2 frame carries the whole (possibly capped) book, so this is always
+    /// `snapshot`; incremental frames are `book_delta` messages
+    pub action: BookAction,
     /// Trading pair identifier
     pub symbol: String,
     /// Snapshot timestamp in milliseconds
     pub time: i64,
     /// Two-element array: [bids, asks]
     pub levels: [Vec<WsPriceLevel>; 2],
+    /// Set on periodic re-sends of an unchanged book
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub keepalive: bool,
+    /// Only the top levels were sent; fetch `full_depth_url` for the whole book
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_depth_url: Option<String>,
+    /// Only the top of the book; `depth_chunk` messages with the rest follow
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+    /// Book version, one higher per change; consecutive frames may skip versions
+    /// that were coalesced, but never go back
+    #[serde(default)]
+    pub sequence: u64,
+    /// CRC32 of the book's best levels with per-level (not cumulative) sizes, see
+    /// `orderbook_reducer::book_checksum`
+    #[serde(default)]
+    pub checksum: u32,
+}
+
+/// Next levels of a partial initial snapshot, continuing where the previous frame ended
+///
+/// Sizes stay cumulative across frames. Once the frame with `last: true` arrived the
+/// client holds the full book and regular orderbook updates follow.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepthChunk {
+    pub symbol: String,
+    pub time: i64,
+    /// Index of the first level of this chunk on each side
+    pub offset: usize,
+    /// Two-element array: [bids, asks]
+    pub levels: [Vec<WsPriceLevel>; 2],
+    pub last: bool,
+}
+
+/// Depth aggregated into fixed-width price buckets
+///
+/// The first message (`snapshot: true`) carries the whole grid; later ones only the
+/// buckets that changed, with `sz` "0" for buckets that emptied. Bucket sizes are
+/// per-bucket, not cumulative.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GridDepthUpdate {
+    /// `snapshot` for the whole grid, `update` for changed buckets only
+    pub action: BookAction,
+    pub symbol: String,
+    pub time: i64,
+    /// Bucket width in price units
+    pub width: String,
+    /// Same as `action == snapshot`, kept for existing clients
+    pub snapshot: bool,
+    /// Two-element array: [bids, asks], best bucket first
+    pub levels: [Vec<WsPriceLevel>; 2],
+}
+
+/// L2 levels that changed since the previous frame on this connection
+///
+/// The first frame (`snapshot`) carries the whole book; later ones (`update`) only the
+/// levels that changed, with `sz` "0" for levels that emptied. Sizes are per level, not
+/// cumulative. `seq` goes up by one per frame, so a gap means the client missed a frame
+/// and should send `{"op":"resnapshot"}`; it should do the same when its own book
+/// checksum differs from `checksum`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderbookDelta {
+    pub action: BookAction,
+    pub symbol: String,
+    pub time: i64,
+    pub seq: u64,
+    /// Two-element array: [bids, asks], best level first
+    pub levels: [Vec<WsPriceLevel>; 2],
+    /// CRC32 of the top of the book after applying this frame, sent every
+    /// `BOOK_CHECKSUM_EVERY` frames (see `book_delta::book_checksum`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<u32>,
 }
 
 /// Status messages
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StatusMessage {
     pub message: String,
+    /// Set when the message reports a rejected client command
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub error: bool,
 }
 
 impl MarketDataMessage {
     /// Create orderbook message from OrderbookSnapshot (Hyperliquid L2 book format)
     /// with cumulative depth: bids accumulate as prices go down, asks accumulate as prices go up
     pub fn orderbook_from_snapshot(symbol: String, snapshot: OrderbookSnapshot) -> Self {
+        let keepalive = snapshot.keepalive;
+        let truncated = snapshot.truncated;
+        let full_depth_url = snapshot.full_depth_url;
+        let (sequence, checksum) = (snapshot.sequence, snapshot.checksum);
+
         // For bids: accumulate quantities as we go down in price (highest to lowest)
         // Bids are already sorted from highest to lowest
         let mut cumulative_bid_qty = Decimal::ZERO;
@@ -79,6 +190,7 @@ impl MarketDataMessage {
                     px: level.price.to_string(),
                     sz: cumulative_bid_qty.to_string(),
                     n: level.order_count,
+                    rest: level.rest,
                 }
             })
             .collect();
@@ -95,18 +207,184 @@ impl MarketDataMessage {
                     px: level.price.to_string(),
                     sz: cumulative_ask_qty.to_string(),
                     n: level.order_count,
+                    rest: level.rest,
                 }
             })
             .collect();
 
         MarketDataMessage::Orderbook(OrderbookUpdate {
+            action: BookAction::Snapshot,
             symbol,
             time: chrono::Utc::now().timestamp_millis(),
             levels: [bids, asks],
+            keepalive,
+            truncated,
+            full_depth_url,
+            partial: false,
+            sequence,
+            checksum,
         })
     }
 
     pub fn candle(update: CandleUpdate) -> Self {
         MarketDataMessage::Candle(update)
     }
+
+    /// Acknowledge a client command, or report why it was rejected
+    pub fn command_status(result: Result<String, String>) -> Self {
+        let (message, error) = match result {
+            Ok(message) => (message, false),
+            Err(message) => (message, true),
+        };
+        MarketDataMessage::Status(StatusMessage { message, error })
+    }
+
+    /// Market the message belongs to, None for connection-level messages
+    pub fn symbol(&self) -> Option<&str> {
+        match self {
+            MarketDataMessage::Orderbook(update) => Some(&update.symbol),
+            MarketDataMessage::Candle(update) => Some(&update.s),
+            MarketDataMessage::DepthChunk(chunk) => Some(&chunk.symbol),
+            MarketDataMessage::GridDepth(update) => Some(&update.symbol),
+            MarketDataMessage::BookDelta(delta) => Some(&delta.symbol),
+            MarketDataMessage::Momentum(signal) => Some(&signal.symbol),
+            MarketDataMessage::Trade(trade) => Some(&trade.symbol),
+            MarketDataMessage::Status(_) => None,
+        }
+    }
+
+    /// Split an orderbook message into a first frame of `initial` levels per side and
+    /// `depth_chunk` frames of `chunk` levels until the whole book is covered.
+    /// Other messages, and books no deeper than `initial`, come back as a single frame.
+    pub fn progressive(self, initial: usize, chunk: usize) -> Vec<Self> {
+        let MarketDataMessage::Orderbook(mut update) = self else {
+            return vec![self];
+        };
+        let [mut bids, mut asks] = std::mem::take(&mut update.levels);
+        let depth = bids.len().max(asks.len());
+        if initial == 0 || depth <= initial {
+            update.levels = [bids, asks];
+            return vec![MarketDataMessage::Orderbook(update)];
+        }
+
+        let chunk = chunk.max(1);
+        let mut rest = [
+            bids.split_off(initial.min(bids.len())),
+            asks.split_off(initial.min(asks.len())),
+        ];
+        update.levels = [bids, asks];
+        update.partial = true;
+        let symbol = update.symbol.clone();
+        let time = update.time;
+
+        let mut frames = vec![MarketDataMessage::Orderbook(update)];
+        let mut offset = initial;
+        while offset < depth {
+            let take = |side: &mut Vec<WsPriceLevel>| {
+                let tail = side.split_off(chunk.min(side.len()));
+                std::mem::replace(side, tail)
+            };
+            let levels = [take(&mut rest[0]), take(&mut rest[1])];
+            frames.push(MarketDataMessage::DepthChunk(DepthChunk {
+                symbol: symbol.clone(),
+                time,
+                offset,
+                levels,
+                last: offset + chunk >= depth,
+            }));
+            offset += chunk;
+        }
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::order;
+    use crate::indexer::orderbook_reducer::OrderbookState;
+
+    fn prices(levels: &[WsPriceLevel]) -> Vec<&str> {
+        levels.iter().map(|level| level.px.as_str()).collect()
+    }
+
+    #[test]
+    fn test_progressive_snapshot_sends_top_of_book_first() {
+        let mut book = OrderbookState::new();
+        for i in 0..10 {
+            book.add_order(order(i, "Buy", 100 - i as i64, 1));
+        }
+        for i in 0..8 {
+            book.add_order(order(100 + i, "Sell", 101 + i as i64, 1));
+        }
+        let message =
+            MarketDataMessage::orderbook_from_snapshot("ETH/USDT".to_string(), book.get_snapshot());
+
+        let frames = message.progressive(3, 4);
+        assert_eq!(frames.len(), 3);
+
+        let MarketDataMessage::Orderbook(top) = &frames[0] else {
+            panic!("first frame must be the orderbook snapshot");
+        };
+        assert!(top.partial);
+        assert_eq!(top.action, BookAction::Snapshot);
+        assert_eq!(prices(&top.levels[0]), ["100", "99", "98"]);
+        assert_eq!(prices(&top.levels[1]), ["101", "102", "103"]);
+
+        let MarketDataMessage::DepthChunk(middle) = &frames[1] else {
+            panic!("expected a depth chunk");
+        };
+        assert_eq!((middle.offset, middle.last), (3, false));
+        assert_eq!(prices(&middle.levels[0]), ["97", "96", "95", "94"]);
+        // Sizes keep accumulating from the first frame
+        assert_eq!(middle.levels[0][0].sz, "4");
+
+        let MarketDataMessage::DepthChunk(last) = &frames[2] else {
+            panic!("expected a depth chunk");
+        };
+        assert_eq!((last.offset, last.last), (7, true));
+        assert_eq!(prices(&last.levels[0]), ["93", "92", "91"]);
+        assert_eq!(prices(&last.levels[1]), ["108"]);
+    }
+
+    #[test]
+    fn test_initial_frame_is_marked_snapshot() {
+        let mut book = OrderbookState::new();
+        book.add_order(order(1, "Buy", 100, 1));
+        let message =
+            MarketDataMessage::orderbook_from_snapshot("ETH/USDT".to_string(), book.get_snapshot());
+
+        let frame: serde_json::Value = serde_json::to_value(&message).unwrap();
+        assert_eq!(frame["type"], "orderbook");
+        assert_eq!(frame["action"], "snapshot");
+    }
+
+    #[test]
+    fn test_shallow_book_is_one_frame() {
+        let mut book = OrderbookState::new();
+        book.add_order(order(1, "Buy", 100, 1));
+        let message =
+            MarketDataMessage::orderbook_from_snapshot("ETH/USDT".to_string(), book.get_snapshot());
+
+        let frames = message.progressive(3, 4);
+        assert!(matches!(&frames[..], [MarketDataMessage::Orderbook(update)] if !update.partial));
+    }
+
+    #[test]
+    fn test_trade_frame() {
+        let message = MarketDataMessage::Trade(TradeUpdate {
+            symbol: "ETH/USDT".to_string(),
+            trade_id: 7,
+            price: Decimal::new(20_005, 1),
+            quantity: Decimal::ONE,
+            side: Some("Buy".to_string()),
+            time: 1_754_450_974_231,
+        });
+        assert_eq!(message.symbol(), Some("ETH/USDT"));
+
+        let frame = serde_json::to_value(&message).unwrap();
+        assert_eq!(frame["type"], "trade");
+        assert_eq!(frame["price"], "2000.5");
+        assert_eq!(frame["side"], "Buy");
+    }
 }
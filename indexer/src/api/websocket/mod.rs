@@ -1,2 +1,12 @@
+pub mod book_delta;
+pub mod candle_subs;
+pub mod disconnect;
+pub mod grid_depth;
+pub mod lag_limit;
 pub mod messages;
+pub mod replay;
+pub mod resubscribe;
+pub mod symbol_lock;
+pub mod write_timeout;
+pub mod ws_l3;
 pub mod ws_unified;
@@ -0,0 +1,412 @@
+use axum::extract::ws::{Message, WebSocket};
+/// Historical trade-tape replay over WebSocket
+use axum::{
+    extract::{Query, State, WebSocketUpgrade},
+    response::{IntoResponse, Response},
+};
+use flate2::{write::GzEncoder, Compression};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error};
+
+use super::write_timeout::send_within;
+use crate::api::error::ApiError;
+use crate::config::IndexerConfig;
+use crate::telemetry;
+
+/// Trades read from the database per query
+const PAGE_SIZE: i64 = 1000;
+/// Largest batch a client may ask for
+const MAX_BATCH: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct ReplayQuery {
+    /// Symbol to replay (default: the configured `DEFAULT_SYMBOL`)
+    pub symbol: Option<String>,
+    /// Replay start (unix seconds)
+    pub from: Option<i64>,
+    /// Replay end, exclusive (unix seconds)
+    pub to: Option<i64>,
+    /// Resync from this trade sequence up to the latest trade, instead of a time range
+    pub from_seq: Option<i64>,
+    /// Trades per frame (default 1, max 1000)
+    pub batch: Option<usize>,
+    /// `gzip` to send frames as gzip-compressed JSON in binary messages
+    pub compress: Option<String>,
+}
+
+/// Which trades a replay covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayRange {
+    /// Milliseconds, `from..to`
+    Time { from_ms: i64, to_ms: i64 },
+    /// Every trade from this sequence number on
+    FromSequence(i64),
+}
+
+impl ReplayQuery {
+    /// Either a complete time range or a starting sequence, never both
+    pub fn range(&self) -> Result<ReplayRange, String> {
+        match (self.from, self.to, self.from_seq) {
+            (Some(from), Some(to), None) => Ok(ReplayRange::Time {
+                from_ms: from * 1000,
+                to_ms: to * 1000,
+            }),
+            (None, None, Some(seq)) => Ok(ReplayRange::FromSequence(seq)),
+            (None, None, None) => Err("Specify from/to or from_seq".to_string()),
+            (_, _, None) => Err("Both from and to are required".to_string()),
+            _ => Err("Use either a time range or from_seq, not both".to_string()),
+        }
+    }
+}
+
+/// How replay frames are written to the socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameEncoding {
+    /// JSON text messages
+    Text,
+    /// Gzip-compressed JSON in binary messages
+    Gzip,
+}
+
+impl FrameEncoding {
+    pub fn parse(compress: Option<&str>) -> Result<Self, String> {
+        match compress {
+            None => Ok(Self::Text),
+            Some(c) if c.eq_ignore_ascii_case("gzip") => Ok(Self::Gzip),
+            Some(other) => Err(format!("Unsupported compression: {}", other)),
+        }
+    }
+
+    pub fn encode(self, frame: &Value) -> std::io::Result<Message> {
+        match self {
+            Self::Text => Ok(Message::Text(frame.to_string().into())),
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+                serde_json::to_writer(&mut encoder, frame)?;
+                encoder.flush()?;
+                Ok(Message::Binary(encoder.finish()?.into()))
+            }
+        }
+    }
+}
+
+/// One replayed trade
+#[derive(Debug, Clone, Serialize, PartialEq, sqlx::FromRow)]
+pub struct ReplayTrade {
+    pub trade_id: i64,
+    pub block_number: i64,
+    /// Hash of the containing block, for checking the trade against the chain
+    pub block_hash: Option<String>,
+    pub event_index: i32,
+    pub extrinsic_index: Option<i32>,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    pub side: Option<String>,
+    /// Trade time in milliseconds
+    pub time: i64,
+    /// Per-market trade sequence
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<i64>,
+}
+
+/// Regroups trades arriving in database pages into fixed-size frames.
+///
+/// Trades keep their arrival order; a partial batch waits for the next page so every
+/// frame but the last holds exactly `batch` trades.
+pub struct ReplayBatcher {
+    batch: usize,
+    pending: Vec<ReplayTrade>,
+}
+
+impl ReplayBatcher {
+    pub fn new(batch: usize) -> Self {
+        Self {
+            batch: batch.clamp(1, MAX_BATCH),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Add a page of trades, returning every batch that is now full
+    pub fn push(&mut self, trades: Vec<ReplayTrade>) -> Vec<Vec<ReplayTrade>> {
+        self.pending.extend(trades);
+        let full = self.pending.len() / self.batch * self.batch;
+        let ready: Vec<ReplayTrade> = self.pending.drain(..full).collect();
+        ready.chunks(self.batch).map(<[_]>::to_vec).collect()
+    }
+
+    /// The trailing partial batch, if any
+    pub fn finish(self) -> Option<Vec<ReplayTrade>> {
+        (!self.pending.is_empty()).then_some(self.pending)
+    }
+}
+
+/// Next page of trades after the `(time, trade_id)` cursor, in replay order
+async fn fetch_page(
+    pool: &PgPool,
+    symbol: &str,
+    after: (i64, i64),
+    to_ms: i64,
+) -> Result<Vec<ReplayTrade>, sqlx::Error> {
+    sqlx::query_as::<_, ReplayTrade>(
+        "SELECT * FROM (
+            SELECT trade_id, block_number, block_hash, event_index, extrinsic_index,
+                price, quantity, side, seq,
+                (EXTRACT(EPOCH FROM created_at) * 1000)::bigint AS time
+            FROM trades
+            WHERE symbol = $1 AND price > 0 AND quantity > 0
+        ) replay
+        WHERE (time, trade_id) > ($2, $3) AND time < $4
+        ORDER BY time, trade_id
+        LIMIT $5",
+    )
+    .bind(symbol)
+    .bind(after.0)
+    .bind(after.1)
+    .bind(to_ms)
+    .bind(PAGE_SIZE)
+    .fetch_all(pool)
+    .await
+}
+
+/// Next page of trades with a sequence above `after`, in sequence order
+async fn fetch_sequence_page(
+    pool: &PgPool,
+    symbol: &str,
+    after: i64,
+) -> Result<Vec<ReplayTrade>, sqlx::Error> {
+    sqlx::query_as::<_, ReplayTrade>(
+        "SELECT trade_id, block_number, block_hash, event_index, extrinsic_index,
+            price, quantity, side, seq,
+            (EXTRACT(EPOCH FROM created_at) * 1000)::bigint AS time
+        FROM trades
+        WHERE symbol = $1 AND seq > $2 AND price > 0 AND quantity > 0
+        ORDER BY seq
+        LIMIT $3",
+    )
+    .bind(symbol)
+    .bind(after)
+    .bind(PAGE_SIZE)
+    .fetch_all(pool)
+    .await
+}
+
+/// Replay the trades of `[from, to)` in trade order, or every trade from `from_seq` on
+/// to resync after a reconnect, `batch` trades per frame.
+///
+/// The WebSocket stack does not negotiate permessage-deflate, so `compress=gzip` opts
+/// into compressing each frame instead; error frames stay plain text either way.
+pub async fn ws_replay_handler(
+    ws: WebSocketUpgrade,
+    Query(params): Query<ReplayQuery>,
+    State(pool): State<PgPool>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> Response {
+    let range = match params.range() {
+        Ok(ReplayRange::FromSequence(_)) if !config.trade_sequence => {
+            Err("Trade sequences are disabled".to_string())
+        }
+        range => range,
+    };
+    let range = match range {
+        Ok(range) => range,
+        Err(e) => return ApiError::BadRequest(e).into_response(),
+    };
+    let encoding = match FrameEncoding::parse(params.compress.as_deref()) {
+        Ok(encoding) => encoding,
+        Err(e) => return ApiError::BadRequest(e).into_response(),
+    };
+    let symbol = config
+        .symbol_or_default(params.symbol.as_deref())
+        .to_string();
+    let batch = params.batch.unwrap_or(1);
+    let write_timeout = config.ws_write_timeout();
+    let with_seq = config.trade_sequence;
+
+    ws.on_upgrade(move |socket| {
+        replay_trades(
+            socket,
+            pool,
+            symbol,
+            range,
+            batch,
+            encoding,
+            with_seq,
+            write_timeout,
+        )
+    })
+    .into_response()
+}
+
+/// Stream every trade in `range` as `replay` frames, then a `replay_end` frame
+async fn replay_trades(
+    mut socket: WebSocket,
+    pool: PgPool,
+    symbol: String,
+    range: ReplayRange,
+    batch: usize,
+    encoding: FrameEncoding,
+    with_seq: bool,
+    write_timeout: Option<Duration>,
+) {
+    let _connection = telemetry::ConnectionGauge::open("replay");
+    let mut batcher = ReplayBatcher::new(batch);
+    // Start just before the range so trades at exactly its start are included
+    let mut cursor = match range {
+        ReplayRange::Time { from_ms, .. } => (from_ms, i64::MIN),
+        ReplayRange::FromSequence(seq) => (seq.saturating_sub(1), 0),
+    };
+    let mut sent = 0usize;
+
+    loop {
+        let page = match range {
+            ReplayRange::Time { to_ms, .. } => fetch_page(&pool, &symbol, cursor, to_ms).await,
+            ReplayRange::FromSequence(_) => fetch_sequence_page(&pool, &symbol, cursor.0).await,
+        };
+        let mut page = match page {
+            Ok(page) => page,
+            Err(e) => {
+                error!("❌ Database error in replay: {}", e);
+                let error = json!({ "type": "error", "message": "Replay failed" });
+                let _ = send_within(
+                    &mut socket,
+                    Message::Text(error.to_string().into()),
+                    write_timeout,
+                )
+                .await;
+                return;
+            }
+        };
+        let last_page = (page.len() as i64) < PAGE_SIZE;
+        if let Some(last) = page.last() {
+            cursor = match range {
+                ReplayRange::Time { .. } => (last.time, last.trade_id),
+                ReplayRange::FromSequence(_) => (last.seq.unwrap_or(i64::MAX), 0),
+            };
+        }
+        if !with_seq {
+            page.iter_mut().for_each(|trade| trade.seq = None);
+        }
+
+        let mut batches = batcher.push(page);
+        if last_page {
+            batches.extend(std::mem::replace(&mut batcher, ReplayBatcher::new(batch)).finish());
+        }
+        for trades in batches {
+            sent += trades.len();
+            let frame = json!({ "type": "replay", "symbol": symbol, "trades": trades });
+            let message = match encoding.encode(&frame) {
+                Ok(message) => message,
+                Err(e) => {
+                    error!("❌ Failed to encode replay frame: {}", e);
+                    return;
+                }
+            };
+            if send_within(&mut socket, message, write_timeout)
+                .await
+                .is_err()
+            {
+                debug!("Replay client went away after {} trades", sent);
+                return;
+            }
+        }
+        if last_page {
+            break;
+        }
+    }
+
+    let end = json!({ "type": "replay_end", "symbol": symbol, "count": sent });
+    if let Ok(message) = encoding.encode(&end) {
+        let _ = send_within(&mut socket, message, write_timeout).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(trade_id: i64) -> ReplayTrade {
+        ReplayTrade {
+            trade_id,
+            block_number: trade_id / 10,
+            block_hash: None,
+            event_index: 0,
+            extrinsic_index: None,
+            price: Decimal::from(100 + trade_id),
+            quantity: Decimal::ONE,
+            side: None,
+            time: 1_000 + trade_id,
+            seq: Some(trade_id + 1),
+        }
+    }
+
+    #[test]
+    fn test_replay_range() {
+        let query = |from: Option<i64>, to: Option<i64>, from_seq: Option<i64>| ReplayQuery {
+            symbol: None,
+            from,
+            to,
+            from_seq,
+            batch: None,
+            compress: None,
+        };
+        assert_eq!(
+            query(Some(1), Some(2), None).range(),
+            Ok(ReplayRange::Time {
+                from_ms: 1_000,
+                to_ms: 2_000
+            })
+        );
+        assert_eq!(
+            query(None, None, Some(42)).range(),
+            Ok(ReplayRange::FromSequence(42))
+        );
+        assert!(query(None, None, None).range().is_err());
+        assert!(query(Some(1), None, None).range().is_err());
+        assert!(query(Some(1), Some(2), Some(42)).range().is_err());
+    }
+
+    #[test]
+    fn test_batched_replay_delivers_all_trades_in_order() {
+        let mut batcher = ReplayBatcher::new(4);
+        let mut frames = Vec::new();
+        // Pages of uneven size, as the last database page would be
+        for page in [0..7, 7..10, 10..11] {
+            frames.extend(batcher.push(page.map(trade).collect()));
+        }
+        frames.extend(batcher.finish());
+
+        let sizes: Vec<usize> = frames.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![4, 4, 3]);
+        let ids: Vec<i64> = frames.into_iter().flatten().map(|t| t.trade_id).collect();
+        assert_eq!(ids, (0..11).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_gzip_frames_decode_to_the_same_json() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+
+        assert_eq!(FrameEncoding::parse(None), Ok(FrameEncoding::Text));
+        assert_eq!(FrameEncoding::parse(Some("GZIP")), Ok(FrameEncoding::Gzip));
+        assert!(FrameEncoding::parse(Some("brotli")).is_err());
+
+        let trades: Vec<ReplayTrade> = (0..50).map(trade).collect();
+        let frame = json!({ "type": "replay", "symbol": "DOT/USDT", "trades": trades });
+        let Message::Binary(bytes) = FrameEncoding::Gzip.encode(&frame).unwrap() else {
+            panic!("gzip frames should be binary");
+        };
+        assert!(bytes.len() < frame.to_string().len());
+
+        let mut json = String::new();
+        GzDecoder::new(&bytes[..])
+            .read_to_string(&mut json)
+            .unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), frame);
+    }
+}
@@ -0,0 +1,117 @@
+use tokio::sync::{broadcast, watch};
+
+/// What a [`Resubscribing`] receiver produced
+#[derive(Debug, PartialEq)]
+pub enum Received<T> {
+    Message(T),
+    /// The channel was replaced and we now listen on the new one; state sent on the old
+    /// channel may have been missed, so callers should resend a fresh snapshot
+    Resubscribed,
+    Lagged(u64),
+    /// The channel closed and no replacement can arrive anymore
+    Closed,
+}
+
+/// Broadcast receiver that follows channel replacements.
+///
+/// Publishers keep the live `broadcast::Sender` in a `watch` channel; when a component
+/// restarts with a new broadcast channel it publishes the new sender there, and every
+/// subscriber moves over instead of seeing `Closed` and dropping its client.
+pub struct Resubscribing<T> {
+    /// Released once the publisher is gone: the watch holds a clone of the last sender,
+    /// which would otherwise keep the final channel open forever
+    senders: Option<watch::Receiver<broadcast::Sender<T>>>,
+    rx: broadcast::Receiver<T>,
+}
+
+/// Wait for a new sender, or forever when the publisher is already gone
+async fn changed<T>(
+    senders: &mut Option<watch::Receiver<broadcast::Sender<T>>>,
+) -> Result<(), watch::error::RecvError> {
+    match senders {
+        Some(senders) => senders.changed().await,
+        None => std::future::pending().await,
+    }
+}
+
+impl<T: Clone> Resubscribing<T> {
+    pub fn new(mut senders: watch::Receiver<broadcast::Sender<T>>) -> Self {
+        let rx = senders.borrow_and_update().subscribe();
+        Self {
+            senders: Some(senders),
+            rx,
+        }
+    }
+
+    fn resubscribe(&mut self) -> Received<T> {
+        if let Some(senders) = &mut self.senders {
+            self.rx = senders.borrow_and_update().subscribe();
+        }
+        Received::Resubscribed
+    }
+
+    /// Next message, switching to the replacement channel whenever one is published
+    pub async fn recv(&mut self) -> Received<T> {
+        loop {
+            tokio::select! {
+                biased;
+
+                changed = changed(&mut self.senders) => match changed {
+                    Ok(()) => return self.resubscribe(),
+                    // Publisher gone: the current channel is the last one
+                    Err(_) => self.senders = None,
+                },
+
+                result = self.rx.recv() => return match result {
+                    Ok(message) => Received::Message(message),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => Received::Lagged(skipped),
+                    Err(broadcast::error::RecvError::Closed) if self.senders.is_some() => {
+                        // Old channel is gone, wait for its replacement
+                        match changed(&mut self.senders).await {
+                            Ok(()) => self.resubscribe(),
+                            Err(_) => Received::Closed,
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => Received::Closed,
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_subscribers_recover_after_channel_swap() {
+        let (old_tx, _) = broadcast::channel::<u32>(8);
+        let (channel_tx, channel_rx) = watch::channel(old_tx.clone());
+        let mut first = Resubscribing::new(channel_rx.clone());
+        let mut second = Resubscribing::new(channel_rx);
+
+        old_tx.send(1).unwrap();
+        assert_eq!(first.recv().await, Received::Message(1));
+        assert_eq!(second.recv().await, Received::Message(1));
+
+        // Simulate an internal restart: the old channel closes, a new one takes over
+        let (new_tx, _) = broadcast::channel::<u32>(8);
+        drop(old_tx);
+        channel_tx.send(new_tx.clone()).unwrap();
+
+        assert_eq!(first.recv().await, Received::Resubscribed);
+        assert_eq!(second.recv().await, Received::Resubscribed);
+
+        new_tx.send(2).unwrap();
+        assert_eq!(first.recv().await, Received::Message(2));
+        assert_eq!(second.recv().await, Received::Message(2));
+
+        // Without a publisher left, closing the channel ends the stream
+        drop(channel_tx);
+        drop(new_tx);
+        assert_eq!(
+            tokio::join!(first.recv(), second.recv()),
+            (Received::Closed, Received::Closed)
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use super::messages::MarketDataMessage;
+/// Last check that a connection only receives frames for its own market
+use axum::extract::ws::Message;
+use std::collections::BTreeSet;
+use tracing::error;
+
+/// Ties a connection to the symbol it subscribed to.
+///
+/// Broadcast channels carry every market and each connection filters them, so a filter
+/// bug would leak other markets' data. Frames for another symbol trip a debug assertion;
+/// release builds log and drop them. Symbols the client subscribes to later are let
+/// through with `allow`.
+pub struct SymbolLock {
+    symbol: String,
+    allowed: BTreeSet<String>,
+    enabled: bool,
+}
+
+impl SymbolLock {
+    pub fn new(symbol: String, enabled: bool) -> Self {
+        Self {
+            symbol,
+            allowed: BTreeSet::new(),
+            enabled,
+        }
+    }
+
+    /// Let frames for `symbol` through as well
+    pub fn allow(&mut self, symbol: &str) {
+        if symbol != self.symbol {
+            self.allowed.insert(symbol.to_string());
+        }
+    }
+
+    /// Stop letting frames for `symbol` through; the connection's own symbol stays
+    pub fn revoke(&mut self, symbol: &str) {
+        self.allowed.remove(symbol);
+    }
+
+    /// Whether `message` may go out on this connection
+    pub fn admits(&self, message: &MarketDataMessage) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        match message.symbol() {
+            Some(symbol) if symbol != self.symbol && !self.allowed.contains(symbol) => {
+                debug_assert!(
+                    false,
+                    "{} frame leaked to a connection locked to {}",
+                    symbol, self.symbol
+                );
+                error!(
+                    "❌ Dropped {} frame on a connection locked to {}",
+                    symbol, self.symbol
+                );
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Serialize `message` for sending, None when it is dropped or fails to serialize
+    pub fn frame(&self, message: &MarketDataMessage) -> Option<Message> {
+        if !self.admits(message) {
+            return None;
+        }
+        let json = serde_json::to_string(message).ok()?;
+        Some(Message::Text(json.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::websocket::messages::StatusMessage;
+    use crate::indexer::orderbook_reducer::OrderbookState;
+
+    fn book(symbol: &str) -> MarketDataMessage {
+        MarketDataMessage::orderbook_from_snapshot(
+            symbol.to_string(),
+            OrderbookState::new().get_snapshot(),
+        )
+    }
+
+    #[test]
+    fn test_own_symbol_and_status_frames_pass() {
+        let lock = SymbolLock::new("ETH/USDT".to_string(), true);
+        assert!(lock.frame(&book("ETH/USDT")).is_some());
+
+        let status = MarketDataMessage::Status(StatusMessage {
+            message: "ok".to_string(),
+            error: false,
+        });
+        assert!(lock.frame(&status).is_some());
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic(expected = "leaked"))]
+    fn test_other_symbol_is_never_sent() {
+        let lock = SymbolLock::new("ETH/USDT".to_string(), true);
+        assert!(lock.frame(&book("BTC/USD")).is_none());
+    }
+
+    #[test]
+    fn test_allowed_symbol_passes() {
+        let mut lock = SymbolLock::new("ETH/USDT".to_string(), true);
+        lock.allow("BTC/USD");
+        assert!(lock.frame(&book("BTC/USD")).is_some());
+
+        lock.revoke("ETH/USDT");
+        assert!(lock.frame(&book("ETH/USDT")).is_some());
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, should_panic(expected = "leaked"))]
+    fn test_revoked_symbol_is_dropped() {
+        let mut lock = SymbolLock::new("ETH/USDT".to_string(), true);
+        lock.allow("BTC/USD");
+        lock.revoke("BTC/USD");
+        assert!(lock.frame(&book("BTC/USD")).is_none());
+    }
+
+    #[test]
+    fn test_disabled_lock_passes_everything() {
+        let lock = SymbolLock::new("ETH/USDT".to_string(), false);
+        assert!(lock.frame(&book("BTC/USD")).is_some());
+    }
+}
@@ -0,0 +1,103 @@
+use axum::extract::ws::Message;
+/// Bounded WebSocket writes, so a client that stops reading cannot stall its handler
+use futures::{Sink, SinkExt};
+use std::time::Duration;
+use tracing::warn;
+
+/// Why a WebSocket write failed
+#[derive(Debug, PartialEq, Eq)]
+pub enum WriteError {
+    /// The connection is gone
+    Closed,
+    /// The client did not drain its socket within the write timeout
+    TimedOut,
+}
+
+/// Send `message`, giving up after `timeout` (None waits indefinitely).
+///
+/// A send only blocks once the client's TCP window and our socket buffer are full, so a
+/// timeout means the client stopped reading; callers treat it like a closed connection
+/// and drop the socket, which also releases its broadcast subscriptions.
+pub async fn send_within<S>(
+    sink: &mut S,
+    message: Message,
+    timeout: Option<Duration>,
+) -> Result<(), WriteError>
+where
+    S: Sink<Message> + Unpin,
+{
+    let Some(timeout) = timeout else {
+        return sink.send(message).await.map_err(|_| WriteError::Closed);
+    };
+    match tokio::time::timeout(timeout, sink.send(message)).await {
+        Ok(result) => result.map_err(|_| WriteError::Closed),
+        Err(_) => {
+            warn!(
+                "✂️  WebSocket client did not read for {:?}, disconnecting",
+                timeout
+            );
+            Err(WriteError::TimedOut)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use std::time::Instant;
+
+    /// Writer whose peer never drains: it never has room for another message
+    struct StuckWriter;
+
+    impl Sink<Message> for StuckWriter {
+        type Error = axum::Error;
+
+        fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Pending
+        }
+
+        fn start_send(self: Pin<&mut Self>, _: Message) -> Result<(), Self::Error> {
+            Ok(())
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Pending
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_never_draining_client_times_out() {
+        let started = Instant::now();
+        let result = send_within(
+            &mut StuckWriter,
+            Message::Text("update".into()),
+            Some(Duration::from_millis(50)),
+        )
+        .await;
+
+        assert_eq!(result, Err(WriteError::TimedOut));
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_draining_client_is_unaffected() {
+        let (mut tx, mut rx) = futures::channel::mpsc::unbounded::<Message>();
+        let result = send_within(
+            &mut tx,
+            Message::Text("update".into()),
+            Some(Duration::from_millis(50)),
+        )
+        .await;
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(rx.next().await, Some(Message::Text("update".into())));
+    }
+}
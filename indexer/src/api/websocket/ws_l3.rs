@@ -0,0 +1,216 @@
+use axum::extract::ws::{Message, WebSocket};
+/// Order-by-order (L3) orderbook feed over WebSocket
+use axum::{
+    extract::{Query, State, WebSocketUpgrade},
+    response::{IntoResponse, Response},
+};
+use futures::stream::SplitSink;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use super::resubscribe::{Received, Resubscribing};
+use super::write_timeout::send_within;
+use super::ws_unified::wants_resnapshot;
+use crate::api::error::ApiError;
+use crate::api::state::{Channels, SharedOrderbook};
+use crate::config::IndexerConfig;
+use crate::indexer::orderbook_reducer::{L3Event, L3Snapshot};
+use crate::telemetry;
+
+#[derive(Debug, Deserialize)]
+pub struct L3Query {
+    /// Trading pair, defaults to (and currently must be) the tracked market
+    pub symbol: Option<String>,
+}
+
+/// Frames sent on `/ws/l3`
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum L3Message<'a> {
+    /// Every resting order; replaces whatever the client holds
+    L3Snapshot {
+        symbol: &'a str,
+        #[serde(flatten)]
+        snapshot: L3Snapshot,
+    },
+    /// One order change with the next sequence number
+    L3Update {
+        symbol: &'a str,
+        #[serde(flatten)]
+        event: L3Event,
+    },
+}
+
+impl L3Message<'_> {
+    fn frame(&self) -> Option<Message> {
+        let json = serde_json::to_string(self).ok()?;
+        Some(Message::Text(json.into()))
+    }
+}
+
+/// Stream individual orders of the tracked market: a full `l3_snapshot` on connect,
+/// then `l3_update` add/modify/remove events with consecutive `seq` numbers.
+///
+/// A client seeing a gap can send `{"op":"resnapshot"}`; lagging behind the broadcast
+/// also triggers a fresh snapshot.
+pub async fn ws_l3_handler(
+    ws: WebSocketUpgrade,
+    Query(params): Query<L3Query>,
+    State(orderbook): State<SharedOrderbook>,
+    State(channels): State<Channels>,
+    State(config): State<Arc<IndexerConfig>>,
+) -> Response {
+    let symbol = config
+        .symbol_or_default(params.symbol.as_deref())
+        .to_string();
+    if symbol != config.default_symbol {
+        return ApiError::untracked_market(&symbol).into_response();
+    }
+    let write_timeout = config.ws_write_timeout();
+
+    ws.on_upgrade(move |socket| {
+        stream_orders(
+            socket,
+            orderbook,
+            Resubscribing::new(channels.l3),
+            symbol,
+            write_timeout,
+        )
+    })
+    .into_response()
+}
+
+/// Send a fresh snapshot, returning its sequence, None when the client can't be written to
+async fn send_snapshot(
+    sender: &mut SplitSink<WebSocket, Message>,
+    orderbook: &SharedOrderbook,
+    symbol: &str,
+    write_timeout: Option<Duration>,
+) -> Option<u64> {
+    let snapshot = orderbook.lock().await.l3_snapshot();
+    let seq = snapshot.seq;
+    let frame = L3Message::L3Snapshot { symbol, snapshot }.frame()?;
+    if send_within(sender, frame, write_timeout).await.is_err() {
+        error!("Failed to send L3 snapshot");
+        return None;
+    }
+    Some(seq)
+}
+
+async fn stream_orders(
+    socket: WebSocket,
+    orderbook: SharedOrderbook,
+    // Subscribed before the snapshot is taken, so no event falls between the two
+    mut l3_rx: Resubscribing<L3Event>,
+    symbol: String,
+    write_timeout: Option<Duration>,
+) {
+    let _connection = telemetry::ConnectionGauge::open("l3");
+    let (mut sender, mut receiver) = socket.split();
+
+    let Some(mut last_seq) = send_snapshot(&mut sender, &orderbook, &symbol, write_timeout).await
+    else {
+        return;
+    };
+
+    loop {
+        tokio::select! {
+            received = l3_rx.recv() => {
+                match received {
+                    // Already part of the snapshot
+                    Received::Message(event) if event.seq <= last_seq => {}
+                    Received::Message(event) => {
+                        last_seq = event.seq;
+                        let Some(frame) = L3Message::L3Update { symbol: &symbol, event }.frame() else {
+                            continue;
+                        };
+                        if send_within(&mut sender, frame, write_timeout).await.is_err() {
+                            error!("Failed to send L3 update");
+                            break;
+                        }
+                    }
+                    resync @ (Received::Resubscribed | Received::Lagged(_)) => {
+                        match resync {
+                            Received::Lagged(skipped) => {
+                                warn!("L3: Client lagged, skipped {} events, resending snapshot", skipped);
+                                telemetry::record_broadcast_lag("l3", skipped);
+                            }
+                            _ => info!("L3 broadcast channel replaced, resending snapshot"),
+                        }
+                        match send_snapshot(&mut sender, &orderbook, &symbol, write_timeout).await {
+                            Some(seq) => last_seq = seq,
+                            None => break,
+                        }
+                    }
+                    Received::Closed => {
+                        info!("L3 broadcast channel closed");
+                        break;
+                    }
+                }
+            }
+
+            msg = receiver.next() => {
+                match msg {
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(Message::Ping(data)))
+                        if send_within(&mut sender, Message::Pong(data.clone()), write_timeout).await.is_err() =>
+                    {
+                        break;
+                    }
+                    Some(Ok(Message::Text(text))) if wants_resnapshot(&text) => {
+                        match send_snapshot(&mut sender, &orderbook, &symbol, write_timeout).await {
+                            Some(seq) => last_seq = seq,
+                            None => break,
+                        }
+                    }
+                    Some(Err(e)) => {
+                        error!("WebSocket error: {:?}", e);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    info!("L3 WebSocket connection closed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_support::order;
+    use crate::indexer::orderbook_reducer::OrderbookState;
+    use tokio::sync::broadcast;
+
+    #[test]
+    fn test_l3_frames() {
+        let (l3_tx, mut l3_rx) = broadcast::channel(4);
+        let mut state = OrderbookState::new().with_l3_feed(l3_tx);
+        state.add_order(order(9, "Buy", 2000, 2));
+
+        let snapshot = serde_json::to_value(L3Message::L3Snapshot {
+            symbol: "ETH/USDT",
+            snapshot: state.l3_snapshot(),
+        })
+        .unwrap();
+        assert_eq!(snapshot["type"], "l3_snapshot");
+        assert_eq!(snapshot["symbol"], "ETH/USDT");
+        assert_eq!(snapshot["seq"], 1);
+        assert_eq!(snapshot["orders"][0]["order_id"], 9);
+
+        let update = serde_json::to_value(L3Message::L3Update {
+            symbol: "ETH/USDT",
+            event: l3_rx.try_recv().unwrap(),
+        })
+        .unwrap();
+        assert_eq!(update["type"], "l3_update");
+        assert_eq!(update["seq"], 1);
+        assert_eq!(update["action"], "add");
+        assert_eq!(update["order"]["price"], "2000");
+        assert_eq!(update["order"]["remaining_quantity"], "2");
+    }
+}
@@ -2,86 +2,268 @@ use axum::extract::ws::{Message, WebSocket};
 /// Unified WebSocket handler for both orderbook and OHLCV updates
 use axum::{
     extract::{Query, State, WebSocketUpgrade},
-    response::IntoResponse,
+    response::{IntoResponse, Response},
 };
-use futures::{SinkExt, StreamExt};
+use futures::stream::SplitSink;
+use futures::StreamExt;
+use rust_decimal::Decimal;
 use serde::Deserialize;
 use std::sync::Arc;
-use tokio::sync::broadcast;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, watch};
 use tracing::{debug, error, info, warn};
 
+use super::book_delta::{BookDeltas, BookMode};
+use super::candle_subs::{CandleCommand, CandleSubscriptions};
+use super::disconnect::TraderSession;
+use super::grid_depth::GridDepth;
+use super::lag_limit::{slow_consumer_close, LagLimit};
 use super::messages::MarketDataMessage;
-use crate::indexer::candle_aggregator::CandleUpdate;
+use super::resubscribe::{Received, Resubscribing};
+use super::symbol_lock::SymbolLock;
+use super::write_timeout::send_within;
+use crate::api::error::ApiError;
+use crate::api::state::{Channels, SharedDisconnectHook, SharedOrderbook};
+use crate::config::IndexerConfig;
+use crate::indexer::candle_aggregator::{CandleReader, CandleUpdate};
 use crate::indexer::orderbook_reducer::{OrderbookSnapshot, OrderbookState};
-
-pub type UnifiedState = (
-    Arc<Mutex<OrderbookState>>,
-    broadcast::Sender<OrderbookSnapshot>,
-    broadcast::Sender<CandleUpdate>,
-);
+use crate::indexer::trade_clusters::MomentumSignal;
+use crate::indexer::trade_mapper::TradeUpdate;
+use crate::telemetry;
 
 #[derive(Debug, Deserialize)]
 pub struct SubscriptionQuery {
-    /// Subscribe to orderbook updates (default: true)
+    /// Subscribe to orderbook updates (default: true when the market has a tracked book)
     pub orderbook: Option<bool>,
     /// Subscribe to OHLCV updates (default: true)
     pub ohlcv: Option<bool>,
-    /// Symbol filter (default: "ETH/USDT")
+    /// Symbol filter (default: the configured `DEFAULT_SYMBOL`)
     pub symbol: Option<String>,
     /// OHLCV timeframes filter, comma-separated (e.g., "1m,5m")
     pub timeframes: Option<String>,
+    /// Add an RFC3339 `time_iso` field to candle updates (default: false)
+    pub with_iso: Option<bool>,
+    /// Trader account to watch; its disconnect fires the configured webhook
+    pub trader: Option<String>,
+    /// Stream orderbook depth bucketed to this price width instead of the L2 book
+    pub grid: Option<Decimal>,
+    /// Subscribe to momentum signals from the trade cluster detector (default: false)
+    pub signals: Option<bool>,
+    /// Subscribe to executed trades of the symbol (default: false)
+    pub trades: Option<bool>,
+    /// Merge L2 levels into buckets of this price step, a multiple of the tick size
+    pub agg: Option<Decimal>,
+    /// "snapshot" (default) for whole-book frames, "delta" for changed levels only
+    pub mode: Option<String>,
 }
 
 /// Configuration struct for unified WebSocket handler
 pub struct UnifiedSocketConfig {
     pub socket: WebSocket,
-    pub orderbook: Arc<Mutex<OrderbookState>>,
-    pub ob_broadcast: broadcast::Sender<OrderbookSnapshot>,
-    pub candle_broadcast: broadcast::Sender<CandleUpdate>,
+    pub orderbook: SharedOrderbook,
+    pub ob_channel: watch::Receiver<broadcast::Sender<OrderbookSnapshot>>,
+    pub candle_channel: watch::Receiver<broadcast::Sender<CandleUpdate>>,
+    /// Open candles sent before the first update, so a new chart isn't blank
+    pub candles: CandleReader,
+    pub signal_channel: watch::Receiver<broadcast::Sender<MomentumSignal>>,
+    pub trade_channel: watch::Receiver<broadcast::Sender<TradeUpdate>>,
     pub subscribe_orderbook: bool,
     pub subscribe_ohlcv: bool,
+    pub subscribe_signals: bool,
+    pub subscribe_trades: bool,
     pub symbol_filter: String,
-    pub timeframe_filter: Option<Vec<String>>,
+    /// Candles to send, changed by the client's subscribe/unsubscribe commands
+    pub candle_subs: CandleSubscriptions,
+    pub with_iso: bool,
+    /// Watched trader session, notifies on drop
+    pub session: Option<TraderSession>,
+    /// Grid depth state when the client asked for bucketed depth
+    pub grid: Option<GridDepth>,
+    /// Aggregation step applied to L2 snapshots
+    pub agg: Option<Decimal>,
+    /// Per-level delta state when the client asked for `mode=delta`
+    pub deltas: Option<BookDeltas>,
+    /// Disconnect the client when a write blocks this long
+    pub write_timeout: Option<Duration>,
+    /// Drop frames for any symbol but `symbol_filter` and the client's candle subscriptions
+    pub symbol_lock: bool,
+    /// Disconnect OHLCV subscribers that lag too often, None to always catch up
+    pub ohlcv_lag_limit: Option<LagLimit>,
+    /// Levels per side in the first snapshot frame, 0 for the whole book
+    pub initial_depth: usize,
+    /// Levels per side in each following depth chunk
+    pub chunk_levels: usize,
 }
 
 pub async fn ws_unified_handler(
     ws: WebSocketUpgrade,
     Query(params): Query<SubscriptionQuery>,
-    State((orderbook, ob_broadcast, candle_broadcast)): State<UnifiedState>,
-) -> impl IntoResponse {
-    let subscribe_orderbook = params.orderbook.unwrap_or(true);
+    State(orderbook): State<SharedOrderbook>,
+    State(channels): State<Channels>,
+    State(candles): State<CandleReader>,
+    State(config): State<Arc<IndexerConfig>>,
+    State(disconnect_hook): State<SharedDisconnectHook>,
+) -> Response {
+    let agg = match params
+        .agg
+        .map(|agg| config.aggregation_step(agg))
+        .transpose()
+    {
+        Ok(agg) => agg,
+        Err(e) => return ApiError::BadRequest(e).into_response(),
+    };
+    let symbol_filter = config
+        .symbol_or_default(params.symbol.as_deref())
+        .to_string();
+    // Only the default market has a book; other markets still stream candles and signals
+    let tracked = symbol_filter == config.default_symbol;
+    if !tracked && (params.orderbook == Some(true) || params.grid.is_some()) {
+        return ApiError::untracked_market(&symbol_filter).into_response();
+    }
+    let mode = match params.mode.as_deref().map(BookMode::parse) {
+        None => BookMode::default(),
+        Some(Some(mode)) => mode,
+        Some(None) => {
+            return ApiError::BadRequest("mode must be snapshot or delta".to_string())
+                .into_response();
+        }
+    };
+    if mode == BookMode::Delta && params.grid.is_some() {
+        // Grid depth already streams per-bucket changes
+        return ApiError::BadRequest("mode=delta and grid cannot be combined".to_string())
+            .into_response();
+    }
+    let subscribe_orderbook = params.orderbook.unwrap_or(tracked);
     let subscribe_ohlcv = params.ohlcv.unwrap_or(true);
-    let symbol_filter = params.symbol.unwrap_or_else(|| "ETH/USDT".to_string());
+    let subscribe_signals = params.signals.unwrap_or(false);
+    let subscribe_trades = params.trades.unwrap_or(false);
     let timeframe_filter: Option<Vec<String>> = params
         .timeframes
         .map(|tf| tf.split(',').map(|s| s.trim().to_string()).collect());
+    let mut candle_subs = CandleSubscriptions::new(
+        config
+            .markets
+            .iter()
+            .map(|market| market.symbol.clone())
+            .collect(),
+    );
+    if subscribe_ohlcv {
+        candle_subs.insert(symbol_filter.clone(), timeframe_filter);
+    }
+    let with_iso = params.with_iso.unwrap_or(false);
+    let trader = params.trader.filter(|t| !t.is_empty());
+    let write_timeout = config.ws_write_timeout();
+    let grid = params
+        .grid
+        .and_then(|width| GridDepth::new(symbol_filter.clone(), width));
+    let deltas = (mode == BookMode::Delta)
+        .then(|| BookDeltas::new(symbol_filter.clone()).with_checksums(config.book_checksums()));
 
     ws.on_upgrade(move |socket| {
+        // Started only once the upgrade succeeds, so failed handshakes don't notify
+        let session = trader
+            .zip(disconnect_hook)
+            .map(|(trader, hook)| TraderSession::start(trader, hook));
         handle_unified_socket(UnifiedSocketConfig {
             socket,
             orderbook,
-            ob_broadcast,
-            candle_broadcast,
+            ob_channel: channels.orderbook,
+            candle_channel: channels.candles,
+            candles,
+            signal_channel: channels.signals,
+            trade_channel: channels.trades,
             subscribe_orderbook,
             subscribe_ohlcv,
+            subscribe_signals,
+            subscribe_trades,
             symbol_filter,
-            timeframe_filter,
+            candle_subs,
+            with_iso,
+            session,
+            grid,
+            agg,
+            deltas,
+            write_timeout,
+            symbol_lock: config.ws_symbol_lock,
+            ohlcv_lag_limit: config.ohlcv_lag_limit(),
+            initial_depth: config.snapshot_initial_depth,
+            chunk_levels: config.snapshot_chunk_levels,
         })
     })
+    .into_response()
+}
+
+/// Whether a client message is `{"op":"resnapshot"}`
+pub fn wants_resnapshot(text: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(text)
+        .is_ok_and(|message| message["op"] == "resnapshot")
+}
+
+/// Send candle updates, false when the client can't be written to
+async fn send_candles(
+    sender: &mut SplitSink<WebSocket, Message>,
+    lock: &SymbolLock,
+    updates: Vec<CandleUpdate>,
+    with_iso: bool,
+    write_timeout: Option<Duration>,
+) -> bool {
+    for update in updates {
+        let update = if with_iso { update.with_iso() } else { update };
+        if let Some(frame) = lock.frame(&MarketDataMessage::candle(update)) {
+            if send_within(sender, frame, write_timeout).await.is_err() {
+                error!("Failed to send open candle");
+                return false;
+            }
+        }
+    }
+    true
 }
 
 async fn handle_unified_socket(config: UnifiedSocketConfig) {
     let UnifiedSocketConfig {
         socket,
         orderbook,
-        ob_broadcast,
-        candle_broadcast,
+        ob_channel,
+        candle_channel,
+        candles,
+        signal_channel,
+        trade_channel,
         subscribe_orderbook,
         subscribe_ohlcv,
+        subscribe_signals,
+        subscribe_trades,
         symbol_filter,
-        timeframe_filter,
+        mut candle_subs,
+        with_iso,
+        session: _session,
+        mut grid,
+        agg,
+        mut deltas,
+        write_timeout,
+        symbol_lock,
+        mut ohlcv_lag_limit,
+        initial_depth,
+        chunk_levels,
     } = config;
+    let _connection = telemetry::ConnectionGauge::open("market");
+    let mut lock = SymbolLock::new(symbol_filter.clone(), symbol_lock);
+
+    let aggregate = |snapshot: OrderbookSnapshot| match agg {
+        Some(step) => snapshot.aggregated(step),
+        None => snapshot,
+    };
+    let l2 = |snapshot: OrderbookSnapshot| {
+        MarketDataMessage::orderbook_from_snapshot(symbol_filter.clone(), aggregate(snapshot))
+    };
+
+    // Whole book in the connection's format, replacing whatever the client held
+    let full_book = |ob: &OrderbookState,
+                     grid: &mut Option<GridDepth>,
+                     deltas: &mut Option<BookDeltas>| match (grid, deltas) {
+        (Some(grid), _) => grid.snapshot(ob),
+        (None, Some(deltas)) => deltas.snapshot(aggregate(ob.get_snapshot())),
+        (None, None) => l2(ob.get_snapshot()),
+    };
 
     let (mut sender, mut receiver) = socket.split();
 
@@ -90,30 +272,59 @@ async fn handle_unified_socket(config: UnifiedSocketConfig) {
         subscribe_orderbook, subscribe_ohlcv, symbol_filter
     );
 
-    // Send initial orderbook snapshot if subscribed
+    // Send initial orderbook snapshot if subscribed, top of book first for deep books
     if subscribe_orderbook {
         let ob = orderbook.lock().await;
-        let snapshot = ob.get_snapshot();
+        let frames = match (grid.as_mut(), deltas.as_mut()) {
+            (Some(grid), _) => vec![grid.snapshot(&ob)],
+            (None, Some(deltas)) => vec![deltas.snapshot(aggregate(ob.get_snapshot()))],
+            (None, None) => l2(ob.get_snapshot()).progressive(initial_depth, chunk_levels),
+        };
         drop(ob); // Release lock immediately
 
-        let message = MarketDataMessage::orderbook_from_snapshot(symbol_filter.clone(), snapshot);
-        if let Ok(json) = serde_json::to_string(&message) {
-            if sender.send(Message::Text(json.into())).await.is_err() {
+        for message in frames {
+            let Some(frame) = lock.frame(&message) else {
+                continue;
+            };
+            if send_within(&mut sender, frame, write_timeout)
+                .await
+                .is_err()
+            {
                 error!("Failed to send initial orderbook snapshot");
                 return;
             }
         }
     }
 
-    // Subscribe to update channels
+    // Subscribe to update channels, following them if they are replaced
     let mut ob_rx = if subscribe_orderbook {
-        Some(ob_broadcast.subscribe())
+        Some(Resubscribing::new(ob_channel))
     } else {
         None
     };
 
+    // Kept open after the last unsubscribe so a later subscribe needs no new receiver
     let mut candle_rx = if subscribe_ohlcv {
-        Some(candle_broadcast.subscribe())
+        Some(Resubscribing::new(candle_channel.clone()))
+    } else {
+        None
+    };
+
+    // Open candles go out once subscribed, so no update in between is missed; a client
+    // may see one bar twice, but its latest state always arrives last
+    let open = candle_subs.open_candles(&candles.snapshot());
+    if !send_candles(&mut sender, &lock, open, with_iso, write_timeout).await {
+        return;
+    }
+
+    let mut signal_rx = if subscribe_signals {
+        Some(Resubscribing::new(signal_channel))
+    } else {
+        None
+    };
+
+    let mut trade_rx = if subscribe_trades {
+        Some(Resubscribing::new(trade_channel))
     } else {
         None
     };
@@ -130,23 +341,66 @@ async fn handle_unified_socket(config: UnifiedSocketConfig) {
                 }
             } => {
                 match ob_result {
-                    Ok(snapshot) => {
+                    Received::Message(_) if grid.is_some() => {
+                        // Bucket the full book rather than the possibly capped snapshot
+                        let ob = orderbook.lock().await;
+                        let delta = grid.as_mut().and_then(|grid| grid.delta(&ob));
+                        drop(ob);
+                        let Some(message) = delta else {
+                            continue;
+                        };
+                        if let Some(frame) = lock.frame(&message) {
+                            if send_within(&mut sender, frame, write_timeout).await.is_err() {
+                                error!("Failed to send grid depth update");
+                                break;
+                            }
+                        }
+                    }
+                    Received::Message(snapshot) if deltas.is_some() => {
+                        let delta = deltas.as_mut().and_then(|deltas| deltas.delta(aggregate(snapshot)));
+                        let Some(message) = delta else {
+                            continue;
+                        };
+                        if let Some(frame) = lock.frame(&message) {
+                            if send_within(&mut sender, frame, write_timeout).await.is_err() {
+                                error!("Failed to send orderbook delta");
+                                break;
+                            }
+                        }
+                    }
+                    Received::Message(snapshot) => {
                         // Received orderbook snapshot from broadcast channel
                         debug!("Received orderbook snapshot: {:?}", snapshot);
 
-                        let message = MarketDataMessage::orderbook_from_snapshot(symbol_filter.clone(), snapshot);
+                        let message = l2(snapshot);
                         debug!("Sending orderbook update: {:?}", message);
-                        if let Ok(json) = serde_json::to_string(&message) {
-                            if sender.send(Message::Text(json.into())).await.is_err() {
+                        if let Some(frame) = lock.frame(&message) {
+                            if send_within(&mut sender, frame, write_timeout).await.is_err() {
                                 error!("Failed to send orderbook update");
                                 break;
                             }
                         }
                     }
-                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
-                        warn!("Orderbook: Client lagged, skipped {} updates", skipped);
+                    resync @ (Received::Resubscribed | Received::Lagged(_)) => {
+                        // Updates may have been lost, resend the full book
+                        match resync {
+                            Received::Lagged(skipped) => {
+                                warn!("Orderbook: Client lagged, skipped {} updates, resending snapshot", skipped);
+                                telemetry::record_broadcast_lag("orderbook", skipped);
+                            }
+                            _ => info!("Orderbook broadcast channel replaced, resending snapshot"),
+                        }
+                        let ob = orderbook.lock().await;
+                        let message = full_book(&ob, &mut grid, &mut deltas);
+                        drop(ob);
+                        if let Some(frame) = lock.frame(&message) {
+                            if send_within(&mut sender, frame, write_timeout).await.is_err() {
+                                error!("Failed to send orderbook snapshot");
+                                break;
+                            }
+                        }
                     }
-                    Err(broadcast::error::RecvError::Closed) => {
+                    Received::Closed => {
                         info!("Orderbook broadcast channel closed");
                         break;
                     }
@@ -162,39 +416,112 @@ async fn handle_unified_socket(config: UnifiedSocketConfig) {
                 }
             } => {
                 match candle_result {
-                    Ok(update) => {
-                        // Filter by symbol
-                        if update.s != symbol_filter {
+                    Received::Message(update) => {
+                        // Filter by subscribed symbols and timeframes
+                        if !candle_subs.wants(&update) {
                             continue;
                         }
 
-                        // Filter by timeframe if specified
-                        if let Some(ref timeframes) = timeframe_filter {
-                            if !timeframes.contains(&update.i) {
-                                continue;
-                            }
-                        }
-
                         // Send candle update
+                        let update = if with_iso { update.with_iso() } else { update };
                         let message = MarketDataMessage::candle(update);
 
-                        if let Ok(json) = serde_json::to_string(&message) {
-                            if sender.send(Message::Text(json.into())).await.is_err() {
+                        if let Some(frame) = lock.frame(&message) {
+                            if send_within(&mut sender, frame, write_timeout).await.is_err() {
                                 error!("Failed to send candle update");
                                 break;
                             }
                         }
                     }
-                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    Received::Resubscribed => {
+                        info!("OHLCV broadcast channel replaced");
+                    }
+                    Received::Lagged(skipped) => {
                         warn!("OHLCV: Client lagged, skipped {} updates", skipped);
+                        telemetry::record_broadcast_lag("ohlcv", skipped);
+                        if ohlcv_lag_limit.as_mut().is_some_and(|limit| limit.record(Instant::now())) {
+                            warn!("✂️  OHLCV: Client keeps lagging, disconnecting");
+                            let _ = send_within(&mut sender, slow_consumer_close(), write_timeout).await;
+                            break;
+                        }
                     }
-                    Err(broadcast::error::RecvError::Closed) => {
+                    Received::Closed => {
                         info!("OHLCV broadcast channel closed");
                         break;
                     }
                 }
             }
 
+            // Momentum signals
+            Some(signal_result) = async {
+                if let Some(ref mut rx) = signal_rx {
+                    Some(rx.recv().await)
+                } else {
+                    None
+                }
+            } => {
+                match signal_result {
+                    Received::Message(signal) => {
+                        if signal.symbol != symbol_filter {
+                            continue;
+                        }
+                        let message = MarketDataMessage::Momentum(signal);
+                        if let Some(frame) = lock.frame(&message) {
+                            if send_within(&mut sender, frame, write_timeout).await.is_err() {
+                                error!("Failed to send momentum signal");
+                                break;
+                            }
+                        }
+                    }
+                    Received::Resubscribed => {
+                        info!("Signal broadcast channel replaced");
+                    }
+                    Received::Lagged(skipped) => {
+                        warn!("Signals: Client lagged, skipped {} signals", skipped);
+                        telemetry::record_broadcast_lag("signals", skipped);
+                    }
+                    Received::Closed => {
+                        info!("Signal broadcast channel closed");
+                        break;
+                    }
+                }
+            }
+
+            // Executed trades
+            Some(trade_result) = async {
+                if let Some(ref mut rx) = trade_rx {
+                    Some(rx.recv().await)
+                } else {
+                    None
+                }
+            } => {
+                match trade_result {
+                    Received::Message(trade) => {
+                        if trade.symbol != symbol_filter {
+                            continue;
+                        }
+                        let message = MarketDataMessage::Trade(trade);
+                        if let Some(frame) = lock.frame(&message) {
+                            if send_within(&mut sender, frame, write_timeout).await.is_err() {
+                                error!("Failed to send trade");
+                                break;
+                            }
+                        }
+                    }
+                    Received::Resubscribed => {
+                        info!("Trade broadcast channel replaced");
+                    }
+                    Received::Lagged(skipped) => {
+                        warn!("Trades: Client lagged, skipped {} trades", skipped);
+                        telemetry::record_broadcast_lag("trades", skipped);
+                    }
+                    Received::Closed => {
+                        info!("Trade broadcast channel closed");
+                        break;
+                    }
+                }
+            }
+
             // Handle client messages
             msg = receiver.next() => {
                 match msg {
@@ -203,13 +530,62 @@ async fn handle_unified_socket(config: UnifiedSocketConfig) {
                         break;
                     }
                     Some(Ok(Message::Ping(data))) => {
-                        if sender.send(Message::Pong(data)).await.is_err() {
+                        let pong = send_within(&mut sender, Message::Pong(data), write_timeout).await;
+                        if pong.is_err() {
                             break;
                         }
                     }
-                    Some(Ok(Message::Text(_text))) => {
-                        // Could implement subscription changes here
-                        // For now, just acknowledge
+                    Some(Ok(Message::Text(text))) if subscribe_orderbook && wants_resnapshot(&text) => {
+                        // Client saw a sequence gap: start over from a full book
+                        let ob = orderbook.lock().await;
+                        let message = full_book(&ob, &mut grid, &mut deltas);
+                        drop(ob);
+                        if let Some(frame) = lock.frame(&message) {
+                            if send_within(&mut sender, frame, write_timeout).await.is_err() {
+                                error!("Failed to send orderbook snapshot");
+                                break;
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Text(text))) if !wants_resnapshot(&text) => {
+                        // Candle subscription change, answered with a status either way
+                        let command = CandleCommand::parse(&text);
+                        let symbol = command.as_ref().ok().map(|command| command.symbol().to_string());
+                        let result = command.and_then(|command| candle_subs.apply(command));
+                        let changed = match (&result, symbol) {
+                            (Ok(_), Some(symbol)) => Some(symbol),
+                            _ => None,
+                        };
+                        if let Some(ref symbol) = changed {
+                            if candle_subs.has_symbol(symbol) {
+                                lock.allow(symbol);
+                            } else {
+                                lock.revoke(symbol);
+                            }
+                        }
+                        if candle_rx.is_none() && !candle_subs.is_empty() {
+                            candle_rx = Some(Resubscribing::new(candle_channel.clone()));
+                        }
+                        let message = MarketDataMessage::command_status(result);
+                        if let Some(frame) = lock.frame(&message) {
+                            if send_within(&mut sender, frame, write_timeout).await.is_err() {
+                                error!("Failed to send command status");
+                                break;
+                            }
+                        }
+
+                        // Open candles of the subscribed market, as on connect
+                        let open = match changed {
+                            Some(symbol) => candle_subs
+                                .open_candles(&candles.snapshot())
+                                .into_iter()
+                                .filter(|update| update.s == symbol)
+                                .collect(),
+                            None => Vec::new(),
+                        };
+                        if !send_candles(&mut sender, &lock, open, with_iso, write_timeout).await {
+                            break;
+                        }
                     }
                     Some(Err(e)) => {
                         error!("WebSocket error: {:?}", e);
@@ -0,0 +1,1090 @@
+use crate::api::handlers::tape_hand::parse_window;
+use crate::api::handlers::udf::{MarkScheme, NumberFormat};
+use crate::api::websocket::book_delta::ChecksumSchedule;
+use crate::api::websocket::lag_limit::LagLimit;
+use crate::indexer::candle_aggregator::{timeframe_ms, CandleSource};
+use crate::indexer::event_hooks::{parse_event_kinds, MarketEventKind};
+use crate::indexer::index_price::ReferencePrice;
+use crate::indexer::metadata_check::MetadataCheck;
+use crate::indexer::node_failover::Backoff;
+use crate::indexer::orderbook_reducer::{BroadcastLimit, LevelCap, TailMode};
+use crate::indexer::scaling::AmountScale;
+use crate::indexer::trade_clusters::ClusterSides;
+use crate::indexer::trade_mapper::InvalidTradePolicy;
+use anyhow::{bail, Result};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::env;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tracing::warn;
+
+/// Timeframes aggregated when neither `CANDLE_TIMEFRAMES` nor a per-market override is set
+pub const DEFAULT_TIMEFRAMES: &[&str] = &["1m", "5m", "15m", "30m", "1h", "4h", "1d", "1w", "1M"];
+
+const DAY_SECS: i64 = 86_400;
+
+/// One on-chain unit at 10^6 scaling
+const DEFAULT_FILL_MISMATCH_TOLERANCE: Decimal = Decimal::from_parts(1, 0, 0, false, 6);
+/// Smallest price step: one on-chain unit at 10^6 scaling
+const DEFAULT_TICK_SIZE: Decimal = Decimal::from_parts(1, 0, 0, false, 6);
+
+/// Longest window (seconds) one history request may span, per candle interval.
+/// Fine intervals get short windows so a single request can't scan years of 1m buckets.
+pub const DEFAULT_MAX_HISTORY_RANGES: &[(&str, i64)] = &[
+    ("1m", 7 * DAY_SECS),
+    ("5m", 30 * DAY_SECS),
+    ("15m", 90 * DAY_SECS),
+    ("30m", 180 * DAY_SECS),
+    ("1h", 365 * DAY_SECS),
+    ("4h", 2 * 365 * DAY_SECS),
+    ("1d", 10 * 365 * DAY_SECS),
+    ("1w", 20 * 365 * DAY_SECS),
+    ("1M", 20 * 365 * DAY_SECS),
+];
+
+/// Most price decimals a UDF pricescale carries; 10^15 is the largest power of ten below
+/// JavaScript's exact integer limit, so charts render wrong digits beyond it
+pub const MAX_PRICE_DECIMALS: u32 = 15;
+
+/// UDF pricescale showing `price_decimals` decimals, clamped to `MAX_PRICE_DECIMALS`
+pub fn pricescale(price_decimals: u32) -> u64 {
+    if price_decimals > MAX_PRICE_DECIMALS {
+        warn!(
+            "Price decimals {} exceed the charting limit, showing {}",
+            price_decimals, MAX_PRICE_DECIMALS
+        );
+    }
+    10u64.pow(price_decimals.min(MAX_PRICE_DECIMALS))
+}
+
+/// Per-market settings
+#[derive(Debug, Clone)]
+pub struct MarketConfig {
+    /// Trading pair symbol (e.g., "ETH/USDT")
+    pub symbol: String,
+    /// Base asset, the part of the symbol before the separator (e.g., "ETH")
+    pub base: String,
+    /// Quote asset, the part after it (e.g., "USDT")
+    pub quote: String,
+    /// Price decimals shown on charts (`MARKET_PRICE_DECIMALS_<SYMBOL>`, default: `quote_decimals`)
+    pub price_decimals: u32,
+    /// Charting price scale, 10^price_decimals unless set (`MARKET_PRICESCALE_<SYMBOL>`)
+    pub pricescale: u64,
+    /// Smallest price move in `1 / pricescale` units (`MARKET_MINMOVE_<SYMBOL>`, default: 1)
+    pub minmove: u64,
+    /// Decimals of on-chain amounts (`MARKET_DECIMALS_<SYMBOL>`, default: 6)
+    pub decimals: u32,
+    /// Decimals of on-chain quantities, the base asset's (`ASSET_DECIMALS_<BASE>`,
+    /// default: `decimals`)
+    pub base_decimals: u32,
+    /// Decimals of on-chain prices, the quote asset's (`ASSET_DECIMALS_<QUOTE>`,
+    /// default: `decimals`)
+    pub quote_decimals: u32,
+    /// Trading hours in TradingView session format (`MARKET_SESSION_<SYMBOL>`, default: "24x7")
+    pub session: String,
+    /// Candle timeframes aggregated for this market (e.g., "1m", "1h")
+    pub timeframes: Vec<String>,
+}
+
+impl MarketConfig {
+    /// A market with the default display and scaling settings
+    pub fn new(symbol: &str, timeframes: Vec<String>) -> Self {
+        let (base, quote) = symbol.split_once(['/', '-', '_']).unwrap_or((symbol, ""));
+        Self {
+            symbol: symbol.to_string(),
+            base: base.to_string(),
+            quote: quote.to_string(),
+            price_decimals: 6,
+            pricescale: pricescale(6),
+            minmove: 1,
+            decimals: 6,
+            base_decimals: 6,
+            quote_decimals: 6,
+            session: "24x7".to_string(),
+            timeframes,
+        }
+    }
+
+    /// How this market's raw prices and quantities convert to display units
+    pub fn amount_scale(&self) -> AmountScale {
+        AmountScale {
+            base_decimals: self.base_decimals,
+            quote_decimals: self.quote_decimals,
+        }
+    }
+
+    /// Human-readable pair name, e.g. "ETH / USDT"
+    pub fn description(&self) -> String {
+        if self.quote.is_empty() {
+            self.base.clone()
+        } else {
+            format!("{} / {}", self.base, self.quote)
+        }
+    }
+}
+
+/// Indexer configuration loaded once at startup
+#[derive(Debug, Clone)]
+pub struct IndexerConfig {
+    /// Node WebSocket endpoints in failover order (`NODE_WS_URL`, comma-separated,
+    /// default: "ws://127.0.0.1:9944")
+    pub node_urls: Vec<String>,
+    /// Reconnect delay after every node failed, doubled per failed round
+    /// (`NODE_RETRY_BASE_MS`, default: 1000)
+    pub node_retry_base_ms: u64,
+    /// Upper bound on the reconnect delay (`NODE_RETRY_MAX_MS`, default: 60000)
+    pub node_retry_max_ms: u64,
+    /// Compare each node's runtime with the compiled metadata on connect: "off", "warn",
+    /// or "strict" to stop instead of indexing with stale types (`METADATA_CHECK`, default: "warn")
+    pub metadata_check: MetadataCheck,
+    /// Markets served by this indexer
+    pub markets: Vec<MarketConfig>,
+    /// Symbol used wherever a request omits one (`DEFAULT_SYMBOL`, default: first market)
+    pub default_symbol: String,
+    /// Timeframes used for markets without an override
+    pub default_timeframes: Vec<String>,
+    /// Build higher timeframes from trades or by rolling up 1m candles
+    /// (`CANDLE_SOURCE`, "trades", "rollup" or "external", default: "trades")
+    pub candle_source: CandleSource,
+    /// Table or view of 1m candles read in external candle mode
+    /// (`MINUTE_CANDLE_TABLE`, default: "one_minute_candles")
+    pub minute_candle_table: String,
+    /// Infer the aggressor side of trades from the book (`INFER_TRADE_SIDE`, default: true)
+    pub infer_trade_side: bool,
+    /// Skip re-indexed trades with a known (block, event index, trade id) (`SKIP_DUPLICATE_TRADES`, default: true)
+    pub skip_duplicate_trades: bool,
+    /// Trade enrichment hook: "none" or "size_bucket" (`TRADE_ENRICHER`, default: "none")
+    pub trade_enricher: String,
+    /// Reference price in quotes and tickers: "mid" or "microprice" (`REFERENCE_PRICE`,
+    /// default: "mid")
+    pub reference_price: ReferencePrice,
+    /// Index price computation: "mid" or "microprice" (`INDEX_PRICE`, default: `REFERENCE_PRICE`)
+    pub index_price: String,
+    /// UDF bar source: "auto", "candles" or "trades" (`UDF_BAR_SOURCE`, default: "auto")
+    pub udf_bar_source: String,
+    /// UDF bar values as "number" or exact decimal "string" (`UDF_NUMBER_FORMAT`,
+    /// default: "number", the TradingView protocol)
+    pub udf_number_format: NumberFormat,
+    /// Resolve UDF symbols ignoring case and separators, e.g. "btc-usd" -> "BTC/USD"
+    /// (`UDF_FUZZY_SYMBOLS`, default: true)
+    pub udf_fuzzy_symbols: bool,
+    /// UDF trade mark colors and size tiers (`UDF_MARK_COLORS`, default:
+    /// "buy=green,sell=red,unknown=blue"; `UDF_MARK_TIERS`, default: "S=1,M=10,L=100")
+    pub udf_marks: MarkScheme,
+    /// Include per-level order counts in UDF depth; `false` anonymizes it to price and
+    /// quantity (`UDF_DEPTH_COUNTS`, default: true)
+    pub udf_depth_counts: bool,
+    /// Zero price/quantity trades: "reject" keeps them out of `trades`, "flag" stores them
+    /// flagged; both dead-letter them and skip candles (`INVALID_TRADE_POLICY`, default: "reject")
+    pub invalid_trade_policy: InvalidTradePolicy,
+    /// Store each trade's block hash and extrinsic index (`CAPTURE_TRADE_ORIGIN`, default: true)
+    pub capture_trade_origin: bool,
+    /// Include per-market trade sequence numbers in `/api/trades` and `/ws/replay`, and
+    /// allow replaying from a sequence (`TRADE_SEQUENCE`, default: true)
+    pub trade_sequence: bool,
+    /// Price bins in `/api/volume-profile` when a request sets none
+    /// (`VOLUME_PROFILE_BINS`, default: 24)
+    pub volume_profile_bins: usize,
+    /// Same-side trades that make a momentum burst, 0 disables detection
+    /// (`CLUSTER_MIN_TRADES`, default: 0)
+    pub cluster_min_trades: usize,
+    /// Window a burst must fit in, in milliseconds (`CLUSTER_WINDOW_MS`, default: 2000)
+    pub cluster_window_ms: i64,
+    /// Aggressor sides watched for bursts: "both", "buy" or "sell" (`CLUSTER_SIDE`, default: "both")
+    pub cluster_sides: ClusterSides,
+    /// Load resting orders from chain storage on startup (`SEED_FROM_CHAIN`, default: true)
+    pub seed_from_chain: bool,
+    /// Suppress per-order broadcasts while seeding, sending one snapshot at the end
+    /// (`SEED_WARMUP`, default: true)
+    pub seed_warmup: bool,
+    /// Persist order changes and the last indexed block, and restore the book from them on
+    /// startup (`PERSIST_ORDERBOOK`, default: true)
+    pub persist_orderbook: bool,
+    /// Apply pending schema migrations on startup (`RUN_MIGRATIONS`, default: true)
+    pub run_migrations: bool,
+    /// Start the API only once the collector seeded the book and indexed its first block
+    /// (`WAIT_FOR_READY`, default: false)
+    pub wait_for_ready: bool,
+    /// Re-broadcast the orderbook after this many idle seconds, 0 disables
+    /// (`SNAPSHOT_KEEPALIVE_SECS`, default: 0)
+    pub snapshot_keepalive_secs: u64,
+    /// Disconnect WebSocket clients whose writes block this many milliseconds, 0 waits
+    /// indefinitely (`WS_WRITE_TIMEOUT_MS`, default: 10000)
+    pub ws_write_timeout_ms: u64,
+    /// Check every outbound WebSocket frame against the connection's symbol and drop
+    /// mismatches (`WS_SYMBOL_LOCK`, default: true)
+    pub ws_symbol_lock: bool,
+    /// Disconnect an OHLCV subscriber that lags more than this many times within
+    /// `WS_OHLCV_LAG_WINDOW_SECS` (`WS_OHLCV_MAX_LAGS`, default: 0 = always catch up)
+    pub ws_ohlcv_max_lags: usize,
+    /// Window over which OHLCV lags are counted (`WS_OHLCV_LAG_WINDOW_SECS`, default: 60)
+    pub ws_ohlcv_lag_window_secs: u64,
+    /// Orderbook snapshots the broadcast channel keeps for slow receivers
+    /// (`OB_BROADCAST_CAPACITY`, default: 1000). The buffer is shared by all receivers and
+    /// holds full snapshots, so raising it costs memory per slot of book depth; in return
+    /// clients can fall further behind during bursts before they lag and get resynced.
+    pub ob_broadcast_capacity: usize,
+    /// Candle updates the broadcast channel keeps for slow receivers
+    /// (`CANDLE_BROADCAST_CAPACITY`, default: 1000). Candle updates are small, but a
+    /// lagging OHLCV client silently misses the updates that were overwritten.
+    pub candle_broadcast_capacity: usize,
+    /// Levels per side covered by book checksums in snapshots and `mode=delta` streams
+    /// (`BOOK_CHECKSUM_LEVELS`, default: 25)
+    pub book_checksum_levels: usize,
+    /// Send a book checksum every this many delta frames (`BOOK_CHECKSUM_EVERY`,
+    /// default: 10, 0 disables)
+    pub book_checksum_every: u64,
+    /// Price levels per side in published snapshots, 0 for unlimited (`MAX_PRICE_LEVELS`, default: 0)
+    pub max_price_levels: usize,
+    /// Levels beyond the cap: aggregated into one "rest" level or dropped
+    /// (`PRICE_LEVEL_TAIL`, "aggregate" or "drop", default: "aggregate")
+    pub price_level_tail: TailMode,
+    /// Broadcast only the top levels when either side is deeper than this, 0 disables
+    /// (`SNAPSHOT_TRUNCATE_LEVELS`, default: 0)
+    pub snapshot_truncate_levels: usize,
+    /// Levels per side kept in a truncated broadcast (`SNAPSHOT_TOP_N`, default: 50)
+    pub snapshot_top_n: usize,
+    /// Levels per side in a new WebSocket client's first frame, the rest following in
+    /// chunks; 0 sends the whole book at once (`SNAPSHOT_INITIAL_DEPTH`, default: 0)
+    pub snapshot_initial_depth: usize,
+    /// Levels per side in each follow-up depth chunk (`SNAPSHOT_CHUNK_LEVELS`, default: 100)
+    pub snapshot_chunk_levels: usize,
+    /// Delete raw trades older than this many seconds once their candles are materialized,
+    /// 0 keeps everything (`TRADE_RETENTION`, e.g. "90d", default: 0)
+    pub trade_retention_secs: i64,
+    /// Seconds between retention runs (`TRADE_RETENTION_INTERVAL`, default: "1h")
+    pub trade_retention_interval_secs: i64,
+    /// Allowed gap between a partial fill's filled + remaining and the tracked quantity
+    /// (`FILL_MISMATCH_TOLERANCE`, default: 0.000001, one on-chain unit)
+    pub fill_mismatch_tolerance: Decimal,
+    /// Re-read an order from chain storage when its fill is inconsistent
+    /// (`RECONCILE_ON_FILL_MISMATCH`, default: false)
+    pub reconcile_on_fill_mismatch: bool,
+    /// Price tick; depth aggregation steps must be a multiple of it
+    /// (`TICK_SIZE`, default: 0.000001, one on-chain unit)
+    pub tick_size: Decimal,
+    /// Record top-of-book samples for `/api/orderbook/range` every this many seconds,
+    /// 0 disables (`BOOK_SAMPLE_SECS`, default: 10)
+    pub book_sample_secs: u64,
+    /// Pause block processing while more database connections than this are busy, 0 disables
+    /// (`BACKPRESSURE_DB_BUSY`, default: 0)
+    pub backpressure_db_busy: usize,
+    /// Pause block processing while a push channel holds more unread messages than this,
+    /// 0 disables (`BACKPRESSURE_BROADCAST_BACKLOG`, default: 0)
+    pub backpressure_broadcast_backlog: usize,
+    /// Serve Prometheus metrics at `/metrics` (`METRICS_ENABLED`, default: true)
+    pub metrics_enabled: bool,
+    /// Max history window in seconds per candle interval (`MAX_HISTORY_RANGES`)
+    pub max_history_ranges: HashMap<String, i64>,
+    /// Clamp over-range history requests to the allowed window instead of rejecting them
+    /// (`CLAMP_HISTORY_RANGE`, default: true)
+    pub clamp_history_range: bool,
+    /// Notify this URL when a watched trader's WebSocket disconnects (`DISCONNECT_WEBHOOK_URL`,
+    /// unset disables cancel-on-disconnect notifications)
+    pub disconnect_webhook_url: Option<String>,
+    /// POST market events as JSON to this URL (`EVENT_WEBHOOK_URL`, unset disables event hooks)
+    pub event_webhook_url: Option<String>,
+    /// Events posted to the event webhook (`EVENT_WEBHOOK_EVENTS`, comma-separated
+    /// "large_trade", "indexer_stall", "decode_failures", default: all)
+    pub event_webhook_events: Vec<MarketEventKind>,
+    /// Timeout of one event webhook request in milliseconds (`EVENT_WEBHOOK_TIMEOUT_MS`,
+    /// default: 5000)
+    pub event_webhook_timeout_ms: u64,
+    /// Retries of a failed event webhook request (`EVENT_WEBHOOK_RETRIES`, default: 3)
+    pub event_webhook_retries: u32,
+    /// Trade quantity from which a trade is reported as large (`LARGE_TRADE_QUANTITY`,
+    /// unset disables large trade events)
+    pub large_trade_quantity: Option<Decimal>,
+    /// Report an indexer stall after this many seconds without a new block
+    /// (`STALL_ALERT_SECS`, default: 0 = disabled)
+    pub stall_alert_secs: u64,
+    /// Report a block in which at least this many events failed to decode
+    /// (`DECODE_FAILURE_ALERT`, default: 0 = disabled)
+    pub decode_failure_alert: usize,
+    /// Serve the gRPC market data API (`GRPC_ENABLED`, default: false)
+    pub grpc_enabled: bool,
+    /// Port of the gRPC server (`GRPC_PORT`, default: 50051)
+    pub grpc_port: u16,
+    /// Separate listen address for `/metrics`, `/api/info` and `/ready`, e.g. "127.0.0.1:9090"
+    /// (`ADMIN_ADDR`, unset serves them on the public port)
+    pub admin_addr: Option<SocketAddr>,
+    /// How long a computed `/api/ticker` result is served, in milliseconds
+    /// (`TICKER_CACHE_MS`, default: 2000)
+    pub ticker_cache_ms: u64,
+}
+
+impl IndexerConfig {
+    /// Load configuration from the environment
+    ///
+    /// - `NODE_WS_URL`: comma-separated node endpoints, tried in order on connection loss
+    /// - `NODE_RETRY_BASE_MS` / `NODE_RETRY_MAX_MS`: exponential reconnect backoff and its cap
+    /// - `METADATA_CHECK`: "strict" refuses a node whose runtime doesn't match `metadata.scale`
+    /// - `MARKETS`: comma-separated symbols (default: "ETH/USDT")
+    /// - `DEFAULT_SYMBOL`: symbol assumed when a request has none (default: first market)
+    /// - `CANDLE_TIMEFRAMES`: comma-separated global default timeframes
+    /// - `CANDLE_TIMEFRAMES_<SYMBOL>`: per-market override, e.g. `CANDLE_TIMEFRAMES_ETH_USDT=1m,1h`
+    /// - `MARKET_PRICE_DECIMALS_<SYMBOL>` / `MARKET_PRICESCALE_<SYMBOL>` / `MARKET_MINMOVE_<SYMBOL>` /
+    ///   `MARKET_DECIMALS_<SYMBOL>`: per-market charting scale and on-chain amount decimals
+    /// - `MARKET_SESSION_<SYMBOL>`: per-market trading hours reported to charts (default: "24x7")
+    /// - `ASSET_DECIMALS_<ASSET>`: on-chain decimals of one asset, e.g. `ASSET_DECIMALS_ETH=18`,
+    ///   scaling quantities of markets it is the base of and prices of those it quotes
+    /// - `CANDLE_SOURCE`: "rollup" derives higher timeframes from 1m candles only,
+    ///   "external" rolls them up from the 1m candles in `MINUTE_CANDLE_TABLE`
+    /// - `INFER_TRADE_SIDE`: set to `false` to leave the trade side unset
+    /// - `SKIP_DUPLICATE_TRADES`: set to `false` to insert every decoded trade
+    /// - `TRADE_ENRICHER`: enrichment hook applied before trades are stored
+    /// - `REFERENCE_PRICE`: fair value shown by UDF quotes and the gRPC ticker
+    /// - `INDEX_PRICE`: index (mark) price method served at `/api/index`
+    /// - `UDF_BAR_SOURCE`: serve UDF bars from persisted candles, raw trades, or either
+    /// - `UDF_NUMBER_FORMAT`: set to `string` for lossless UDF bar values
+    /// - `UDF_FUZZY_SYMBOLS`: set to `false` to resolve UDF symbols by exact match only
+    /// - `UDF_MARK_COLORS` / `UDF_MARK_TIERS`: trade mark colors by side and labels by size
+    /// - `UDF_DEPTH_COUNTS`: set to `false` to leave order counts out of UDF depth
+    /// - `INVALID_TRADE_POLICY`: "reject" or "flag" for zero price/quantity trades
+    /// - `CAPTURE_TRADE_ORIGIN`: set to `false` to skip storing block hash and extrinsic index
+    /// - `TRADE_SEQUENCE`: set to `false` to leave trade sequence numbers out of the API
+    /// - `VOLUME_PROFILE_BINS`: default price bins of `/api/volume-profile`
+    /// - `CLUSTER_MIN_TRADES` / `CLUSTER_WINDOW_MS` / `CLUSTER_SIDE`: momentum burst detection
+    /// - `SEED_FROM_CHAIN` / `SEED_WARMUP`: cold-start seeding and its broadcast warmup
+    /// - `PERSIST_ORDERBOOK`: set to `false` to keep the book in memory only
+    /// - `RUN_MIGRATIONS`: set to `false` when the schema is managed outside the indexer
+    /// - `WAIT_FOR_READY`: hold the API back until the collector is ready
+    /// - `SNAPSHOT_KEEPALIVE_SECS`: periodic keepalive snapshots while the book is idle
+    /// - `WS_WRITE_TIMEOUT_MS`: disconnect WebSocket clients that stop reading
+    /// - `WS_SYMBOL_LOCK`: drop WebSocket frames for a symbol the connection isn't on
+    /// - `WS_OHLCV_MAX_LAGS` / `WS_OHLCV_LAG_WINDOW_SECS`: disconnect chronically slow OHLCV clients
+    /// - `OB_BROADCAST_CAPACITY` / `CANDLE_BROADCAST_CAPACITY`: updates buffered for slow clients
+    /// - `BOOK_CHECKSUM_LEVELS` / `BOOK_CHECKSUM_EVERY`: CRC32 book checksums in snapshots and delta streams
+    /// - `MAX_PRICE_LEVELS` / `PRICE_LEVEL_TAIL`: cap on snapshot depth per side and its tail handling
+    /// - `SNAPSHOT_TRUNCATE_LEVELS` / `SNAPSHOT_TOP_N`: truncated broadcasts for very deep books
+    /// - `SNAPSHOT_INITIAL_DEPTH` / `SNAPSHOT_CHUNK_LEVELS`: progressive initial snapshots
+    /// - `TRADE_RETENTION` / `TRADE_RETENTION_INTERVAL`: optional pruning of old raw trades
+    /// - `FILL_MISMATCH_TOLERANCE` / `RECONCILE_ON_FILL_MISMATCH`: partial fill consistency check
+    /// - `TICK_SIZE`: price tick that `?agg=` depth aggregation steps must be a multiple of
+    /// - `BOOK_SAMPLE_SECS`: top-of-book sampling interval behind book-based price ranges
+    /// - `BACKPRESSURE_DB_BUSY` / `BACKPRESSURE_BROADCAST_BACKLOG`: pause indexing while consumers lag
+    /// - `METRICS_ENABLED`: set to `false` to disable the `/metrics` export
+    /// - `MAX_HISTORY_RANGES`: per-interval overrides, e.g. `1m=3d,1h=180d`
+    /// - `CLAMP_HISTORY_RANGE`: set to `false` to reject over-range history requests
+    /// - `DISCONNECT_WEBHOOK_URL`: opt-in webhook for watched trader disconnects
+    /// - `EVENT_WEBHOOK_URL` / `EVENT_WEBHOOK_EVENTS`: opt-in webhook for market events, with
+    ///   `EVENT_WEBHOOK_TIMEOUT_MS` / `EVENT_WEBHOOK_RETRIES` for delivery and
+    ///   `LARGE_TRADE_QUANTITY` / `STALL_ALERT_SECS` / `DECODE_FAILURE_ALERT` as triggers
+    /// - `GRPC_ENABLED` / `GRPC_PORT`: optional gRPC server on its own port
+    /// - `ADMIN_ADDR`: internal listener for admin and diagnostics routes
+    /// - `TICKER_CACHE_MS`: how long a 24h ticker is cached
+    pub fn from_env() -> Self {
+        let default_timeframes = env::var("CANDLE_TIMEFRAMES")
+            .ok()
+            .map(|tf| parse_timeframes(&tf))
+            .filter(|tf| !tf.is_empty())
+            .unwrap_or_else(|| DEFAULT_TIMEFRAMES.iter().map(|s| s.to_string()).collect());
+
+        let symbols = env::var("MARKETS").unwrap_or_else(|_| "ETH/USDT".to_string());
+        let markets = symbols
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|symbol| {
+                let timeframes = env::var(format!("CANDLE_TIMEFRAMES_{}", env_key(symbol)))
+                    .ok()
+                    .map(|tf| parse_timeframes(&tf))
+                    .filter(|tf| !tf.is_empty())
+                    .unwrap_or_else(|| default_timeframes.clone());
+
+                let key = env_key(symbol);
+                let setting = |name: &str| env::var(format!("MARKET_{}_{}", name, key)).ok();
+                let defaults = MarketConfig::new(symbol, timeframes);
+                let decimals = setting("DECIMALS")
+                    .and_then(|decimals| decimals.parse().ok())
+                    .filter(|decimals| *decimals <= 18)
+                    .unwrap_or(defaults.decimals);
+                let asset_decimals = |asset: &str| {
+                    env::var(format!("ASSET_DECIMALS_{}", env_key(asset)))
+                        .ok()
+                        .filter(|_| !asset.is_empty())
+                        .and_then(|decimals| decimals.parse().ok())
+                        .filter(|decimals| *decimals <= 18)
+                };
+                let base_decimals = asset_decimals(&defaults.base).unwrap_or(decimals);
+                let quote_decimals = asset_decimals(&defaults.quote).unwrap_or(decimals);
+                let price_decimals = setting("PRICE_DECIMALS")
+                    .and_then(|decimals| decimals.parse().ok())
+                    .unwrap_or(quote_decimals);
+                MarketConfig {
+                    price_decimals,
+                    pricescale: setting("PRICESCALE")
+                        .and_then(|scale| scale.parse().ok())
+                        .filter(|scale| *scale > 0)
+                        .unwrap_or_else(|| pricescale(price_decimals)),
+                    minmove: setting("MINMOVE")
+                        .and_then(|minmove| minmove.parse().ok())
+                        .filter(|minmove| *minmove > 0)
+                        .unwrap_or(defaults.minmove),
+                    decimals,
+                    base_decimals,
+                    quote_decimals,
+                    session: setting("SESSION")
+                        .map(|session| session.trim().to_string())
+                        .filter(|session| !session.is_empty())
+                        .unwrap_or_else(|| defaults.session.clone()),
+                    ..defaults
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let default_symbol = env::var("DEFAULT_SYMBOL")
+            .ok()
+            .or_else(|| markets.first().map(|m| m.symbol.clone()))
+            .unwrap_or_else(|| "ETH/USDT".to_string());
+
+        let reference_price = env::var("REFERENCE_PRICE")
+            .ok()
+            .and_then(|mode| {
+                let parsed = ReferencePrice::parse(&mode);
+                if parsed.is_none() {
+                    warn!("Invalid REFERENCE_PRICE {:?}, using mid", mode);
+                }
+                parsed
+            })
+            .unwrap_or_default();
+
+        Self {
+            node_urls: env::var("NODE_WS_URL")
+                .ok()
+                .map(|urls| parse_list(&urls))
+                .filter(|urls| !urls.is_empty())
+                .unwrap_or_else(|| vec!["ws://127.0.0.1:9944".to_string()]),
+            node_retry_base_ms: env::var("NODE_RETRY_BASE_MS")
+                .ok()
+                .and_then(|ms| ms.parse().ok())
+                .unwrap_or(1000),
+            node_retry_max_ms: env::var("NODE_RETRY_MAX_MS")
+                .ok()
+                .and_then(|ms| ms.parse().ok())
+                .unwrap_or(60_000),
+            metadata_check: env::var("METADATA_CHECK")
+                .ok()
+                .and_then(|mode| {
+                    let parsed = MetadataCheck::parse(&mode);
+                    if parsed.is_none() {
+                        warn!("Invalid METADATA_CHECK {:?}, using warn", mode);
+                    }
+                    parsed
+                })
+                .unwrap_or_default(),
+            markets,
+            default_symbol,
+            default_timeframes,
+            candle_source: env::var("CANDLE_SOURCE")
+                .ok()
+                .and_then(|source| {
+                    let parsed = CandleSource::parse(&source);
+                    if parsed.is_none() {
+                        warn!("Invalid CANDLE_SOURCE {:?}, using trades", source);
+                    }
+                    parsed
+                })
+                .unwrap_or_default(),
+            minute_candle_table: env::var("MINUTE_CANDLE_TABLE")
+                .ok()
+                .filter(|table| {
+                    // Interpolated into SQL, so only plain (optionally schema-qualified) names
+                    let valid = !table.is_empty()
+                        && table
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+                    if !valid {
+                        warn!(
+                            "Invalid MINUTE_CANDLE_TABLE {:?}, using one_minute_candles",
+                            table
+                        );
+                    }
+                    valid
+                })
+                .unwrap_or_else(|| "one_minute_candles".to_string()),
+            infer_trade_side: env_flag("INFER_TRADE_SIDE", true),
+            skip_duplicate_trades: env_flag("SKIP_DUPLICATE_TRADES", true),
+            trade_enricher: env::var("TRADE_ENRICHER").unwrap_or_else(|_| "none".to_string()),
+            reference_price,
+            index_price: env::var("INDEX_PRICE")
+                .ok()
+                .filter(|method| !method.trim().is_empty())
+                .unwrap_or_else(|| reference_price.as_str().to_string()),
+            udf_bar_source: env::var("UDF_BAR_SOURCE").unwrap_or_else(|_| "auto".to_string()),
+            udf_number_format: env::var("UDF_NUMBER_FORMAT")
+                .ok()
+                .and_then(|format| {
+                    let parsed = NumberFormat::parse(&format);
+                    if parsed.is_none() {
+                        warn!("Invalid UDF_NUMBER_FORMAT {:?}, using number", format);
+                    }
+                    parsed
+                })
+                .unwrap_or_default(),
+            udf_fuzzy_symbols: env_flag("UDF_FUZZY_SYMBOLS", true),
+            udf_marks: MarkScheme::parse(
+                &env::var("UDF_MARK_COLORS").unwrap_or_default(),
+                &env::var("UDF_MARK_TIERS").unwrap_or_default(),
+            ),
+            udf_depth_counts: env_flag("UDF_DEPTH_COUNTS", true),
+            invalid_trade_policy: env::var("INVALID_TRADE_POLICY")
+                .ok()
+                .and_then(|policy| {
+                    let parsed = InvalidTradePolicy::parse(&policy);
+                    if parsed.is_none() {
+                        warn!("Invalid INVALID_TRADE_POLICY {:?}, using reject", policy);
+                    }
+                    parsed
+                })
+                .unwrap_or_default(),
+            capture_trade_origin: env_flag("CAPTURE_TRADE_ORIGIN", true),
+            trade_sequence: env_flag("TRADE_SEQUENCE", true),
+            volume_profile_bins: env::var("VOLUME_PROFILE_BINS")
+                .ok()
+                .and_then(|bins| bins.parse().ok())
+                .filter(|bins| (1..=500).contains(bins))
+                .unwrap_or(24),
+            cluster_min_trades: env::var("CLUSTER_MIN_TRADES")
+                .ok()
+                .and_then(|trades| trades.parse().ok())
+                .unwrap_or(0),
+            cluster_window_ms: env::var("CLUSTER_WINDOW_MS")
+                .ok()
+                .and_then(|ms| ms.parse().ok())
+                .filter(|ms| *ms > 0)
+                .unwrap_or(2000),
+            cluster_sides: env::var("CLUSTER_SIDE")
+                .ok()
+                .and_then(|sides| {
+                    let parsed = ClusterSides::parse(&sides);
+                    if parsed.is_none() {
+                        warn!("Invalid CLUSTER_SIDE {:?}, using both", sides);
+                    }
+                    parsed
+                })
+                .unwrap_or_default(),
+            seed_from_chain: env_flag("SEED_FROM_CHAIN", true),
+            seed_warmup: env_flag("SEED_WARMUP", true),
+            persist_orderbook: env_flag("PERSIST_ORDERBOOK", true),
+            run_migrations: env_flag("RUN_MIGRATIONS", true),
+            wait_for_ready: env_flag("WAIT_FOR_READY", false),
+            snapshot_keepalive_secs: env::var("SNAPSHOT_KEEPALIVE_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(0),
+            ws_write_timeout_ms: env::var("WS_WRITE_TIMEOUT_MS")
+                .ok()
+                .and_then(|ms| ms.parse().ok())
+                .unwrap_or(10_000),
+            ws_symbol_lock: env_flag("WS_SYMBOL_LOCK", true),
+            ws_ohlcv_max_lags: env::var("WS_OHLCV_MAX_LAGS")
+                .ok()
+                .and_then(|lags| lags.parse().ok())
+                .unwrap_or(0),
+            ws_ohlcv_lag_window_secs: env::var("WS_OHLCV_LAG_WINDOW_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .filter(|secs| *secs > 0)
+                .unwrap_or(60),
+            // tokio rejects a zero-capacity broadcast channel
+            ob_broadcast_capacity: env::var("OB_BROADCAST_CAPACITY")
+                .ok()
+                .and_then(|capacity| capacity.parse().ok())
+                .filter(|capacity| *capacity > 0)
+                .unwrap_or(1000),
+            candle_broadcast_capacity: env::var("CANDLE_BROADCAST_CAPACITY")
+                .ok()
+                .and_then(|capacity| capacity.parse().ok())
+                .filter(|capacity| *capacity > 0)
+                .unwrap_or(1000),
+            book_checksum_levels: env::var("BOOK_CHECKSUM_LEVELS")
+                .ok()
+                .and_then(|levels| levels.parse().ok())
+                .filter(|levels| *levels > 0)
+                .unwrap_or(25),
+            book_checksum_every: env::var("BOOK_CHECKSUM_EVERY")
+                .ok()
+                .and_then(|every| every.parse().ok())
+                .unwrap_or(10),
+            max_price_levels: env::var("MAX_PRICE_LEVELS")
+                .ok()
+                .and_then(|levels| levels.parse().ok())
+                .unwrap_or(0),
+            price_level_tail: match env::var("PRICE_LEVEL_TAIL").as_deref() {
+                Ok("drop") => TailMode::Drop,
+                _ => TailMode::Aggregate,
+            },
+            snapshot_truncate_levels: env::var("SNAPSHOT_TRUNCATE_LEVELS")
+                .ok()
+                .and_then(|levels| levels.parse().ok())
+                .unwrap_or(0),
+            snapshot_top_n: env::var("SNAPSHOT_TOP_N")
+                .ok()
+                .and_then(|levels| levels.parse().ok())
+                .unwrap_or(50),
+            snapshot_initial_depth: env::var("SNAPSHOT_INITIAL_DEPTH")
+                .ok()
+                .and_then(|levels| levels.parse().ok())
+                .unwrap_or(0),
+            snapshot_chunk_levels: env::var("SNAPSHOT_CHUNK_LEVELS")
+                .ok()
+                .and_then(|levels| levels.parse().ok())
+                .filter(|levels| *levels > 0)
+                .unwrap_or(100),
+            trade_retention_secs: env::var("TRADE_RETENTION")
+                .ok()
+                .and_then(|window| parse_window(&window))
+                .unwrap_or(0),
+            trade_retention_interval_secs: env::var("TRADE_RETENTION_INTERVAL")
+                .ok()
+                .and_then(|window| parse_window(&window))
+                .unwrap_or(3_600),
+            fill_mismatch_tolerance: env::var("FILL_MISMATCH_TOLERANCE")
+                .ok()
+                .and_then(|tolerance| tolerance.parse().ok())
+                .unwrap_or(DEFAULT_FILL_MISMATCH_TOLERANCE),
+            reconcile_on_fill_mismatch: env_flag("RECONCILE_ON_FILL_MISMATCH", false),
+            tick_size: env::var("TICK_SIZE")
+                .ok()
+                .and_then(|tick| tick.parse().ok())
+                .filter(|tick: &Decimal| *tick > Decimal::ZERO)
+                .unwrap_or(DEFAULT_TICK_SIZE),
+            book_sample_secs: env::var("BOOK_SAMPLE_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(10),
+            backpressure_db_busy: env::var("BACKPRESSURE_DB_BUSY")
+                .ok()
+                .and_then(|busy| busy.parse().ok())
+                .unwrap_or(0),
+            backpressure_broadcast_backlog: env::var("BACKPRESSURE_BROADCAST_BACKLOG")
+                .ok()
+                .and_then(|backlog| backlog.parse().ok())
+                .unwrap_or(0),
+            metrics_enabled: env_flag("METRICS_ENABLED", true),
+            max_history_ranges: env::var("MAX_HISTORY_RANGES")
+                .map(|ranges| parse_history_ranges(&ranges))
+                .unwrap_or_else(|_| default_history_ranges()),
+            clamp_history_range: env_flag("CLAMP_HISTORY_RANGE", true),
+            disconnect_webhook_url: env::var("DISCONNECT_WEBHOOK_URL")
+                .ok()
+                .filter(|url| !url.trim().is_empty()),
+            event_webhook_url: env::var("EVENT_WEBHOOK_URL")
+                .ok()
+                .filter(|url| !url.trim().is_empty()),
+            event_webhook_events: env::var("EVENT_WEBHOOK_EVENTS")
+                .map(|events| parse_event_kinds(&events))
+                .unwrap_or_else(|_| MarketEventKind::ALL.to_vec()),
+            event_webhook_timeout_ms: env::var("EVENT_WEBHOOK_TIMEOUT_MS")
+                .ok()
+                .and_then(|ms| ms.parse().ok())
+                .filter(|ms| *ms > 0)
+                .unwrap_or(5000),
+            event_webhook_retries: env::var("EVENT_WEBHOOK_RETRIES")
+                .ok()
+                .and_then(|retries| retries.parse().ok())
+                .unwrap_or(3),
+            large_trade_quantity: env::var("LARGE_TRADE_QUANTITY")
+                .ok()
+                .and_then(|quantity| quantity.parse().ok())
+                .filter(|quantity: &Decimal| *quantity > Decimal::ZERO),
+            stall_alert_secs: env::var("STALL_ALERT_SECS")
+                .ok()
+                .and_then(|secs| secs.parse().ok())
+                .unwrap_or(0),
+            decode_failure_alert: env::var("DECODE_FAILURE_ALERT")
+                .ok()
+                .and_then(|failures| failures.parse().ok())
+                .unwrap_or(0),
+            grpc_enabled: env_flag("GRPC_ENABLED", false),
+            grpc_port: env::var("GRPC_PORT")
+                .ok()
+                .and_then(|port| port.parse().ok())
+                .unwrap_or(50051),
+            admin_addr: env::var("ADMIN_ADDR")
+                .ok()
+                .filter(|addr| !addr.trim().is_empty())
+                .and_then(|addr| match addr.trim().parse() {
+                    Ok(addr) => Some(addr),
+                    Err(_) => {
+                        warn!(
+                            "Invalid ADMIN_ADDR {:?}, serving admin routes on the public port",
+                            addr
+                        );
+                        None
+                    }
+                }),
+            ticker_cache_ms: env::var("TICKER_CACHE_MS")
+                .ok()
+                .and_then(|ms| ms.parse().ok())
+                .unwrap_or(2_000),
+        }
+    }
+
+    /// Check settings that would otherwise fail confusingly at request time
+    pub fn validate(&self) -> Result<()> {
+        if self.market(&self.default_symbol).is_none() {
+            bail!(
+                "DEFAULT_SYMBOL {} is not one of the configured MARKETS",
+                self.default_symbol
+            );
+        }
+        Ok(())
+    }
+
+    /// The requested symbol, or the configured default when omitted
+    pub fn symbol_or_default<'a>(&'a self, symbol: Option<&'a str>) -> &'a str {
+        symbol
+            .filter(|s| !s.is_empty())
+            .unwrap_or(&self.default_symbol)
+    }
+
+    /// Validate a depth aggregation step: positive and a whole multiple of the tick size
+    pub fn aggregation_step(&self, agg: Decimal) -> Result<Decimal, String> {
+        if agg <= Decimal::ZERO {
+            return Err(format!("agg must be positive, got {}", agg));
+        }
+        if !(agg % self.tick_size).is_zero() {
+            return Err(format!(
+                "agg must be a multiple of the tick size {}, got {}",
+                self.tick_size, agg
+            ));
+        }
+        Ok(agg)
+    }
+
+    /// WebSocket write timeout, None when disabled
+    pub fn ws_write_timeout(&self) -> Option<Duration> {
+        (self.ws_write_timeout_ms > 0).then(|| Duration::from_millis(self.ws_write_timeout_ms))
+    }
+
+    /// Reconnect backoff once every node endpoint failed
+    pub fn node_retry_backoff(&self) -> Backoff {
+        let base = Duration::from_millis(self.node_retry_base_ms);
+        Backoff {
+            base,
+            cap: Duration::from_millis(self.node_retry_max_ms).max(base),
+        }
+    }
+
+    /// Lag limit for OHLCV WebSocket subscribers, if slow clients are disconnected
+    pub fn ohlcv_lag_limit(&self) -> Option<LagLimit> {
+        (self.ws_ohlcv_max_lags > 0).then(|| {
+            LagLimit::new(
+                self.ws_ohlcv_max_lags,
+                Duration::from_secs(self.ws_ohlcv_lag_window_secs),
+            )
+        })
+    }
+
+    /// Snapshot level cap, if one is configured
+    pub fn level_cap(&self) -> Option<LevelCap> {
+        (self.max_price_levels > 0).then_some(LevelCap {
+            max_levels: self.max_price_levels,
+            tail: self.price_level_tail,
+        })
+    }
+
+    /// Book checksum schedule for delta streams, if checksums are enabled
+    pub fn book_checksums(&self) -> Option<ChecksumSchedule> {
+        (self.book_checksum_every > 0).then_some(ChecksumSchedule {
+            levels: self.book_checksum_levels,
+            every: self.book_checksum_every,
+        })
+    }
+
+    /// Broadcast truncation limit, if one is configured
+    pub fn broadcast_limit(&self) -> Option<BroadcastLimit> {
+        (self.snapshot_truncate_levels > 0).then_some(BroadcastLimit {
+            threshold: self.snapshot_truncate_levels,
+            top_n: self.snapshot_top_n,
+        })
+    }
+
+    /// Look up a configured market by symbol
+    pub fn market(&self, symbol: &str) -> Option<&MarketConfig> {
+        self.markets.iter().find(|m| m.symbol == symbol)
+    }
+
+    /// Scaling of `symbol`'s on-chain amounts, 6 decimals for unconfigured markets
+    pub fn amount_scale(&self, symbol: &str) -> AmountScale {
+        self.market(symbol)
+            .map(MarketConfig::amount_scale)
+            .unwrap_or_default()
+    }
+
+    /// Market for a client-supplied symbol: an exact match, else (with `UDF_FUZZY_SYMBOLS`)
+    /// one whose symbol is equal ignoring case and separators
+    pub fn find_market(&self, symbol: &str) -> Option<&MarketConfig> {
+        self.market(symbol).or_else(|| {
+            let key = env_key(symbol);
+            self.udf_fuzzy_symbols
+                .then(|| self.markets.iter().find(|m| env_key(&m.symbol) == key))
+                .flatten()
+        })
+    }
+
+    /// Candle timeframes for `symbol`, falling back to the global default
+    pub fn timeframes_for(&self, symbol: &str) -> &[String] {
+        self.market(symbol)
+            .map(|m| m.timeframes.as_slice())
+            .unwrap_or(&self.default_timeframes)
+    }
+
+    /// Enforce the max history window for `interval` on a `from..to` request (seconds).
+    ///
+    /// Returns the start time to query with, moved forward to `to - max` when clamping,
+    /// or an error message when the request is over range and clamping is disabled.
+    pub fn limit_history_range(&self, interval: &str, from: i64, to: i64) -> Result<i64, String> {
+        let Some(&max_range) = self.max_history_ranges.get(interval) else {
+            return Ok(from);
+        };
+        if to.saturating_sub(from) <= max_range {
+            return Ok(from);
+        }
+
+        if self.clamp_history_range {
+            Ok(to - max_range)
+        } else {
+            Err(format!(
+                "Requested range of {}s exceeds the {}s maximum for interval {}",
+                to - from,
+                max_range,
+                interval
+            ))
+        }
+    }
+}
+
+impl Default for IndexerConfig {
+    fn default() -> Self {
+        let default_timeframes: Vec<String> =
+            DEFAULT_TIMEFRAMES.iter().map(|s| s.to_string()).collect();
+        Self {
+            node_urls: vec!["ws://127.0.0.1:9944".to_string()],
+            node_retry_base_ms: 1000,
+            node_retry_max_ms: 60_000,
+            metadata_check: MetadataCheck::Warn,
+            markets: vec![MarketConfig::new("ETH/USDT", default_timeframes.clone())],
+            default_symbol: "ETH/USDT".to_string(),
+            default_timeframes,
+            candle_source: CandleSource::Trades,
+            minute_candle_table: "one_minute_candles".to_string(),
+            infer_trade_side: true,
+            skip_duplicate_trades: true,
+            trade_enricher: "none".to_string(),
+            reference_price: ReferencePrice::Mid,
+            index_price: "mid".to_string(),
+            udf_bar_source: "auto".to_string(),
+            udf_number_format: NumberFormat::Number,
+            udf_fuzzy_symbols: true,
+            udf_marks: MarkScheme::default(),
+            udf_depth_counts: true,
+            invalid_trade_policy: InvalidTradePolicy::Reject,
+            capture_trade_origin: true,
+            trade_sequence: true,
+            volume_profile_bins: 24,
+            cluster_min_trades: 0,
+            cluster_window_ms: 2000,
+            cluster_sides: ClusterSides::Both,
+            seed_from_chain: true,
+            seed_warmup: true,
+            persist_orderbook: true,
+            run_migrations: true,
+            wait_for_ready: false,
+            snapshot_keepalive_secs: 0,
+            ws_write_timeout_ms: 10_000,
+            ws_symbol_lock: true,
+            ws_ohlcv_max_lags: 0,
+            ws_ohlcv_lag_window_secs: 60,
+            ob_broadcast_capacity: 1000,
+            candle_broadcast_capacity: 1000,
+            book_checksum_levels: 25,
+            book_checksum_every: 10,
+            max_price_levels: 0,
+            price_level_tail: TailMode::Aggregate,
+            snapshot_truncate_levels: 0,
+            snapshot_top_n: 50,
+            snapshot_initial_depth: 0,
+            snapshot_chunk_levels: 100,
+            trade_retention_secs: 0,
+            trade_retention_interval_secs: 3_600,
+            fill_mismatch_tolerance: DEFAULT_FILL_MISMATCH_TOLERANCE,
+            reconcile_on_fill_mismatch: false,
+            tick_size: DEFAULT_TICK_SIZE,
+            book_sample_secs: 10,
+            backpressure_db_busy: 0,
+            backpressure_broadcast_backlog: 0,
+            metrics_enabled: true,
+            max_history_ranges: default_history_ranges(),
+            clamp_history_range: true,
+            disconnect_webhook_url: None,
+            event_webhook_url: None,
+            event_webhook_events: MarketEventKind::ALL.to_vec(),
+            event_webhook_timeout_ms: 5000,
+            event_webhook_retries: 3,
+            large_trade_quantity: None,
+            stall_alert_secs: 0,
+            decode_failure_alert: 0,
+            grpc_enabled: false,
+            grpc_port: 50051,
+            admin_addr: None,
+            ticker_cache_ms: 2_000,
+        }
+    }
+}
+
+/// Environment variable suffix for a symbol: "ETH/USDT" -> "ETH_USDT"
+pub fn env_key(symbol: &str) -> String {
+    symbol
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Read a boolean flag, accepting true/false, 1/0, yes/no and on/off
+pub fn env_flag(name: &str, default: bool) -> bool {
+    match env::var(name) {
+        Ok(value) => match value.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => true,
+            "false" | "0" | "no" | "off" => false,
+            other => {
+                warn!("Invalid value {:?} for {}, using {}", other, name, default);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+fn default_history_ranges() -> HashMap<String, i64> {
+    DEFAULT_MAX_HISTORY_RANGES
+        .iter()
+        .map(|(interval, secs)| (interval.to_string(), *secs))
+        .collect()
+}
+
+/// Parse `interval=window` pairs (e.g. "1m=3d,1h=180d") on top of the defaults
+fn parse_history_ranges(list: &str) -> HashMap<String, i64> {
+    let mut ranges = default_history_ranges();
+    for entry in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        match entry
+            .split_once('=')
+            .and_then(|(interval, window)| Some((interval.trim(), parse_window(window)?)))
+        {
+            Some((interval, secs)) => {
+                ranges.insert(interval.to_string(), secs);
+            }
+            None => warn!("Ignoring invalid history range: {}", entry),
+        }
+    }
+    ranges
+}
+
+/// Split a comma-separated list, dropping blanks
+fn parse_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parse a comma-separated timeframe list, dropping (and warning about) unknown entries
+fn parse_timeframes(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .filter(|tf| {
+            let known = timeframe_ms(tf).is_some();
+            if !known {
+                warn!("Ignoring unknown candle timeframe: {}", tf);
+            }
+            known
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timeframes() {
+        assert_eq!(parse_timeframes("1m, 1h,,bogus"), vec!["1m", "1h"]);
+        assert_eq!(env_key("ETH/USDT"), "ETH_USDT");
+    }
+
+    #[test]
+    fn test_default_symbol_must_be_configured() {
+        let mut config = IndexerConfig::default();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.symbol_or_default(None), "ETH/USDT");
+        assert_eq!(config.symbol_or_default(Some("")), "ETH/USDT");
+        assert_eq!(config.symbol_or_default(Some("BTC/USD")), "BTC/USD");
+
+        config.default_symbol = "BTC/USD".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_history_ranges_per_interval() {
+        let mut config = IndexerConfig::default();
+        let to = 1_700_000_000;
+
+        for (interval, max_range) in DEFAULT_MAX_HISTORY_RANGES {
+            let in_range = to - max_range;
+            assert_eq!(
+                config.limit_history_range(interval, in_range, to),
+                Ok(in_range)
+            );
+
+            let over_range = to - max_range - 1;
+            assert_eq!(
+                config.limit_history_range(interval, over_range, to),
+                Ok(in_range)
+            );
+        }
+
+        config.clamp_history_range = false;
+        assert!(config
+            .limit_history_range("1m", to - 30 * DAY_SECS, to)
+            .is_err());
+        assert!(config
+            .limit_history_range("1d", to - 30 * DAY_SECS, to)
+            .is_ok());
+        // Unknown intervals are left to the handlers to reject
+        assert_eq!(config.limit_history_range("2m", 0, to), Ok(0));
+    }
+
+    #[test]
+    fn test_parse_history_ranges_overrides_defaults() {
+        let ranges = parse_history_ranges("1m=3d, 1h = 180d, bogus");
+        assert_eq!(ranges["1m"], 3 * DAY_SECS);
+        assert_eq!(ranges["1h"], 180 * DAY_SECS);
+        assert_eq!(ranges["1d"], 10 * 365 * DAY_SECS);
+    }
+
+    #[test]
+    fn test_pricescale_follows_price_decimals() {
+        assert_eq!(pricescale(0), 1);
+        assert_eq!(pricescale(2), 100);
+        assert_eq!(pricescale(6), 1_000_000);
+        assert_eq!(pricescale(15), 1_000_000_000_000_000);
+        // Clamped rather than overflowing or exceeding what charts display
+        assert_eq!(pricescale(18), 1_000_000_000_000_000);
+        assert_eq!(pricescale(40), pricescale(MAX_PRICE_DECIMALS));
+
+        let market = MarketConfig::new("ETH/USDT", Vec::new());
+        assert_eq!(market.pricescale, 10u64.pow(market.decimals));
+    }
+}
@@ -1,4 +1,8 @@
+pub mod orders;
+pub mod retention;
+
 use anyhow::Result;
+use sqlx::migrate::Migrator;
 use sqlx::postgres::PgPool;
 use tracing::info;
 
@@ -8,18 +12,60 @@ pub async fn init_pool(database_url: &str) -> Result<PgPool> {
     Ok(pool)
 }
 
-// Deprecated: Use `just db-setup` instead
-// pub async fn run_migrations(pool: &PgPool) -> Result<()> {
-//     // Read and execute the migrations SQL file
-//     let sql = include_str!("../../db/migrations/001_create_trades.sql");
-
-//     // Split by statements and execute
-//     for statement in sql.split(';').filter(|s| !s.trim().is_empty()) {
-//         sqlx::query(statement)
-//             .execute(pool)
-//             .await?;
-//     }
-
-//     info!("✅ Migrations completed");
-//     Ok(())
-// }
+/// Schema migrations from `db/migrations`, embedded at build time
+static MIGRATOR: Migrator = sqlx::migrate!("db/migrations");
+
+/// Bring the schema up to date, logging every migration applied.
+///
+/// Applied migrations are recorded in `_sqlx_migrations` and skipped on later starts.
+/// Databases set up with `just db-migrate` have no such record yet; every migration is
+/// idempotent, so they simply run once more and get recorded.
+pub async fn run_migrations(pool: &PgPool) -> Result<()> {
+    // Errors before the first run, when the bookkeeping table doesn't exist yet
+    let applied: Vec<i64> =
+        sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+            .fetch_all(pool)
+            .await
+            .unwrap_or_default();
+
+    MIGRATOR.run(pool).await?;
+
+    let mut count = 0;
+    for migration in MIGRATOR
+        .iter()
+        .filter(|migration| !applied.contains(&migration.version))
+    {
+        info!(
+            "📜 Applied migration {} ({})",
+            migration.version, migration.description
+        );
+        count += 1;
+    }
+    if count == 0 {
+        info!("✅ Database schema up to date");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test(migrations = false)]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_migrations_create_the_schema_once(pool: PgPool) {
+        run_migrations(&pool).await.unwrap();
+        // A second start finds nothing to do
+        run_migrations(&pool).await.unwrap();
+
+        let applied: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM _sqlx_migrations")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(applied as usize, MIGRATOR.iter().count());
+        sqlx::query("SELECT trade_id FROM trades LIMIT 1")
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,182 @@
+use crate::telemetry;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::future::Future;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Storage operations the retention task needs, so the ordering can be tested without
+/// a TimescaleDB instance
+pub trait TradeStore {
+    /// Materialize candles for every trade before `cutoff`, returning the time up to
+    /// which candles are durable and no longer need the raw trades
+    fn persist_candles(
+        &self,
+        cutoff: DateTime<Utc>,
+    ) -> impl Future<Output = Result<DateTime<Utc>>> + Send;
+
+    /// Delete raw trades older than `before`, returning how many rows went away
+    fn prune_trades(&self, before: DateTime<Utc>) -> impl Future<Output = Result<u64>> + Send;
+}
+
+/// The `trades` hypertable and the continuous aggregates built on it.
+///
+/// Only `one_minute_candles_cs` reads `trades` directly; every coarser view rolls up the
+/// 1m aggregate, so materializing 1m is enough to keep all candle history once the raw
+/// trades are gone. Trades are removed with `drop_chunks`, which unlike `DELETE` does not
+/// invalidate the materialized candles, so whole chunks (7 days by default) are dropped
+/// and up to one chunk interval past the window may be kept. Block-range candles, the
+/// tape and flow statistics read raw trades and only cover the retained period.
+pub struct TimescaleStore {
+    pool: PgPool,
+}
+
+impl TimescaleStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl TradeStore for TimescaleStore {
+    async fn persist_candles(&self, cutoff: DateTime<Utc>) -> Result<DateTime<Utc>> {
+        // Start at the oldest remaining chunk: refreshing a range whose trades were
+        // already dropped would erase its candles
+        let oldest: Option<DateTime<Utc>> = sqlx::query_scalar(
+            "SELECT MIN(range_start) FROM timescaledb_information.chunks
+            WHERE hypertable_name = 'trades'",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        if let Some(oldest) = oldest.filter(|oldest| *oldest < cutoff) {
+            sqlx::query("CALL refresh_continuous_aggregate('one_minute_candles_cs', $1, $2)")
+                .bind(oldest)
+                .bind(cutoff)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(cutoff)
+    }
+
+    async fn prune_trades(&self, before: DateTime<Utc>) -> Result<u64> {
+        // drop_chunks only removes chunks that end before the cutoff, count exactly those
+        let boundary: Option<DateTime<Utc>> = sqlx::query_scalar(
+            "SELECT MAX(range_end) FROM timescaledb_information.chunks
+            WHERE hypertable_name = 'trades' AND range_end <= $1",
+        )
+        .bind(before)
+        .fetch_one(&self.pool)
+        .await?;
+        let Some(boundary) = boundary else {
+            return Ok(0);
+        };
+
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM trades WHERE created_at < $1")
+            .bind(boundary)
+            .fetch_one(&self.pool)
+            .await?;
+        sqlx::query("SELECT drop_chunks('trades', older_than => $1)")
+            .bind(before)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(rows as u64)
+    }
+}
+
+/// Prune trades older than `retention`, but never past what the candles already cover
+pub async fn run_retention_once(
+    store: &impl TradeStore,
+    now: DateTime<Utc>,
+    retention: Duration,
+) -> Result<u64> {
+    let cutoff = now - chrono::Duration::from_std(retention)?;
+    let persisted_through = store.persist_candles(cutoff).await?;
+    let pruned = store.prune_trades(cutoff.min(persisted_through)).await?;
+
+    info!(
+        "🧹 Retention pruned {} trades older than {}",
+        pruned,
+        cutoff.min(persisted_through)
+    );
+    telemetry::record_trades_pruned(pruned);
+    Ok(pruned)
+}
+
+/// Periodically prune raw trades past the retention window
+pub async fn run_retention(pool: PgPool, retention: Duration, every: Duration) {
+    let store = TimescaleStore::new(pool);
+    let mut ticker = tokio::time::interval(every);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        if let Err(e) = run_retention_once(&store, Utc::now(), retention).await {
+            warn!("⚠️  Trade retention run failed, nothing pruned: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// In-memory trades (by timestamp) with candles materialized up to a point
+    struct MemoryStore {
+        trades: Mutex<Vec<DateTime<Utc>>>,
+        /// Furthest time candles can be materialized to, None when persistence fails
+        materializable: Option<DateTime<Utc>>,
+    }
+
+    impl TradeStore for MemoryStore {
+        async fn persist_candles(&self, cutoff: DateTime<Utc>) -> Result<DateTime<Utc>> {
+            match self.materializable {
+                Some(limit) => Ok(cutoff.min(limit)),
+                None => anyhow::bail!("refresh failed"),
+            }
+        }
+
+        async fn prune_trades(&self, before: DateTime<Utc>) -> Result<u64> {
+            let mut trades = self.trades.lock().unwrap();
+            let count = trades.len();
+            trades.retain(|time| *time >= before);
+            Ok((count - trades.len()) as u64)
+        }
+    }
+
+    fn days_ago(now: DateTime<Utc>, days: i64) -> DateTime<Utc> {
+        now - chrono::Duration::days(days)
+    }
+
+    #[tokio::test]
+    async fn test_prunes_only_after_candle_persistence() {
+        let now = Utc::now();
+        let retention = Duration::from_secs(30 * 86_400);
+        let trades = || Mutex::new(vec![days_ago(now, 60), days_ago(now, 40), days_ago(now, 1)]);
+
+        // Candles fully persisted: everything past the window goes
+        let store = MemoryStore {
+            trades: trades(),
+            materializable: Some(now),
+        };
+        assert_eq!(run_retention_once(&store, now, retention).await.unwrap(), 2);
+        assert_eq!(*store.trades.lock().unwrap(), vec![days_ago(now, 1)]);
+
+        // Candles only materialized up to 50 days ago: the 40-day-old trade stays
+        let store = MemoryStore {
+            trades: trades(),
+            materializable: Some(days_ago(now, 50)),
+        };
+        assert_eq!(run_retention_once(&store, now, retention).await.unwrap(), 1);
+        assert_eq!(store.trades.lock().unwrap().len(), 2);
+
+        // Persistence failed: nothing is pruned
+        let store = MemoryStore {
+            trades: trades(),
+            materializable: None,
+        };
+        assert!(run_retention_once(&store, now, retention).await.is_err());
+        assert_eq!(store.trades.lock().unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::telemetry;
+use sqlx::PgPool;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+/// How often a paused collector re-checks its backlogs
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A downstream queue the collector must not outrun
+pub trait Backlog: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &'static str;
+    /// Work waiting on the consumer right now
+    fn backlog(&self) -> usize;
+}
+
+/// Database writes in flight: connections checked out of the pool
+pub struct DbBacklog(pub PgPool);
+
+impl Backlog for DbBacklog {
+    fn name(&self) -> &'static str {
+        "database"
+    }
+
+    fn backlog(&self) -> usize {
+        (self.0.size() as usize).saturating_sub(self.0.num_idle())
+    }
+}
+
+/// Messages queued on a broadcast channel that the slowest subscriber has not read yet.
+///
+/// One stalled subscriber holds this up on its own, so a limit here pauses indexing
+/// until that client reads or disconnects.
+pub struct BroadcastBacklog<T>(pub &'static str, pub broadcast::Sender<T>);
+
+impl<T: Send> Backlog for BroadcastBacklog<T> {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    fn backlog(&self) -> usize {
+        self.1.len()
+    }
+}
+
+/// One backlog with the level that pauses the collector
+struct Limit {
+    backlog: Box<dyn Backlog>,
+    max: usize,
+}
+
+impl Limit {
+    fn over(&self) -> bool {
+        self.backlog.backlog() > self.max
+    }
+
+    /// Resume only once the backlog fell to half the limit, so the collector does not
+    /// flap around the threshold
+    fn drained(&self) -> bool {
+        self.backlog.backlog() <= self.max / 2
+    }
+}
+
+/// Pauses block processing while any watched backlog is over its limit.
+///
+/// Nothing is dropped: the finalized block subscription simply isn't polled while
+/// paused, so the node buffers the blocks instead of the indexer's memory.
+#[derive(Default)]
+pub struct Backpressure {
+    limits: Vec<Limit>,
+    paused: AtomicBool,
+}
+
+impl Backpressure {
+    /// Watch `backlog`, pausing above `max`; a `max` of 0 leaves it unwatched
+    pub fn watch(mut self, backlog: impl Backlog + 'static, max: usize) -> Self {
+        if max > 0 {
+            self.limits.push(Limit {
+                backlog: Box::new(backlog),
+                max,
+            });
+        }
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Return immediately when every backlog is within its limit, otherwise wait until
+    /// all of them drained
+    pub async fn wait_for_capacity(&self) {
+        let Some(limit) = self.limits.iter().find(|limit| limit.over()) else {
+            return;
+        };
+        warn!(
+            "⏸️  Pausing block processing: {} backlog {} over {}",
+            limit.backlog.name(),
+            limit.backlog.backlog(),
+            limit.max
+        );
+        self.paused.store(true, Ordering::Relaxed);
+        telemetry::set_collector_paused(true);
+
+        while !self.limits.iter().all(Limit::drained) {
+            tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+        }
+
+        self.paused.store(false, Ordering::Relaxed);
+        telemetry::set_collector_paused(false);
+        info!("▶️  Backlogs drained, resuming block processing");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    /// Write queue of a database whose length the test controls
+    struct SlowDb(Arc<AtomicUsize>);
+
+    impl Backlog for SlowDb {
+        fn name(&self) -> &'static str {
+            "slow db"
+        }
+
+        fn backlog(&self) -> usize {
+            self.0.load(Ordering::Relaxed)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_db_throttles_collector() {
+        let pending = Arc::new(AtomicUsize::new(0));
+        let backpressure = Arc::new(Backpressure::default().watch(SlowDb(pending.clone()), 4));
+
+        // Within the limit: no waiting
+        pending.store(4, Ordering::Relaxed);
+        backpressure.wait_for_capacity().await;
+        assert!(!backpressure.is_paused());
+
+        // The collector queued more writes than the database keeps up with
+        pending.store(10, Ordering::Relaxed);
+        let collector = tokio::spawn({
+            let backpressure = backpressure.clone();
+            async move { backpressure.wait_for_capacity().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(backpressure.is_paused());
+        assert!(!collector.is_finished());
+
+        // Draining just under the limit is not enough to resume
+        pending.store(3, Ordering::Relaxed);
+        tokio::time::sleep(DRAIN_POLL_INTERVAL * 2).await;
+        assert!(!collector.is_finished());
+
+        pending.store(2, Ordering::Relaxed);
+        tokio::time::timeout(Duration::from_secs(1), collector)
+            .await
+            .expect("collector should resume once drained")
+            .unwrap();
+        assert!(!backpressure.is_paused());
+    }
+}
@@ -0,0 +1,97 @@
+use crate::indexer::orderbook_reducer::OrderbookState;
+use crate::telemetry;
+use anyhow::Result;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// Top of book at one instant
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookSample {
+    pub best_bid: Decimal,
+    pub best_ask: Decimal,
+    pub mid: Decimal,
+}
+
+impl BookSample {
+    /// Sample the book, None while either side is empty
+    pub fn from_book(book: &OrderbookState) -> Option<Self> {
+        let (best_bid, best_ask) = book.get_spread()?;
+        Some(Self {
+            best_bid,
+            best_ask,
+            mid: (best_bid + best_ask) / Decimal::TWO,
+        })
+    }
+}
+
+pub async fn insert_sample(pool: &PgPool, symbol: &str, sample: &BookSample) -> Result<()> {
+    let insert_started = Instant::now();
+    let inserted = sqlx::query(
+        "INSERT INTO book_samples (symbol, best_bid, best_ask, mid) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(symbol)
+    .bind(sample.best_bid)
+    .bind(sample.best_ask)
+    .bind(sample.mid)
+    .execute(pool)
+    .await;
+    telemetry::observe_db_write(insert_started.elapsed());
+
+    inserted?;
+    Ok(())
+}
+
+/// Record a top-of-book sample every `every`, skipping moments without two-sided liquidity
+pub async fn run_sampler(
+    pool: PgPool,
+    orderbook: Arc<Mutex<OrderbookState>>,
+    symbol: String,
+    every: Duration,
+) {
+    let mut ticker = tokio::time::interval(every);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        let Some(sample) = BookSample::from_book(&*orderbook.lock().await) else {
+            continue;
+        };
+        if let Err(e) = insert_sample(&pool, &symbol, &sample).await {
+            warn!("⚠️  Failed to record book sample: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::orderbook_reducer::OrderInfo;
+
+    fn order(order_id: u64, side: &str, price: i64) -> OrderInfo {
+        OrderInfo {
+            order_id,
+            side: side.to_string(),
+            price: Decimal::from(price),
+            quantity: Decimal::ONE,
+            filled_quantity: Decimal::ZERO,
+            status: "Open".to_string(),
+            raw_price: price as u128 * 1_000_000,
+            raw_quantity: 1_000_000,
+        }
+    }
+
+    #[test]
+    fn test_sample_needs_both_sides() {
+        let mut book = OrderbookState::new();
+        book.add_order(order(1, "Sell", 101));
+        assert_eq!(BookSample::from_book(&book), None);
+
+        book.add_order(order(2, "Buy", 98));
+        let sample = BookSample::from_book(&book).unwrap();
+        assert_eq!(sample.best_bid, Decimal::from(98));
+        assert_eq!(sample.mid, Decimal::new(995, 1));
+    }
+}
@@ -1,8 +1,16 @@
 use anyhow::Result;
+use arc_swap::ArcSwap;
+use chrono::Datelike;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
 use std::collections::HashMap;
-use tokio::sync::broadcast;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, Mutex};
+use tracing::warn;
+
+use crate::config::IndexerConfig;
 
 /// Internal candle representation with metadata
 #[derive(Debug, Clone)]
@@ -14,6 +22,8 @@ pub struct Candle {
     pub low: Decimal,
     pub close: Decimal,
     pub volume: Decimal,
+    /// Sum of price × quantity over the candle's trades
+    pub notional: Decimal,
     pub open_time: i64, // Unix timestamp in milliseconds
     pub close_time: i64,
     pub trade_count: u64,
@@ -35,6 +45,7 @@ impl Candle {
             low: price,
             close: price,
             volume: quantity,
+            notional: price * quantity,
             open_time: timestamp,
             close_time: timestamp,
             trade_count: 1,
@@ -47,14 +58,52 @@ impl Candle {
         self.low = self.low.min(price);
         self.close = price;
         self.volume += quantity;
+        self.notional += price * quantity;
         self.close_time = timestamp;
         self.trade_count += 1;
     }
 
+    /// Fold a later candle of the same bucket into this one
+    pub fn merge(&mut self, later: &Candle) {
+        self.high = self.high.max(later.high);
+        self.low = self.low.min(later.low);
+        self.close = later.close;
+        self.volume += later.volume;
+        self.notional += later.notional;
+        self.close_time = later.close_time;
+        self.trade_count += later.trade_count;
+    }
+
+    /// Flat, tradeless candle for an empty bucket starting at `open_time`, carrying the
+    /// previous close
+    pub fn filler(previous: &Candle, open_time: i64, timeframe_ms: i64) -> Self {
+        Self {
+            symbol: previous.symbol.clone(),
+            timeframe: previous.timeframe.clone(),
+            open: previous.close,
+            high: previous.close,
+            low: previous.close,
+            close: previous.close,
+            volume: Decimal::ZERO,
+            notional: Decimal::ZERO,
+            open_time,
+            close_time: next_bucket(open_time, timeframe_ms) - 1,
+            trade_count: 0,
+        }
+    }
+
+    /// Volume-weighted average price, the close for a candle without volume
+    pub fn vwap(&self) -> Decimal {
+        if self.volume.is_zero() {
+            return self.close;
+        }
+        (self.notional / self.volume).normalize()
+    }
+
     /// Check if this timestamp belongs to the current candle
     pub fn is_in_timeframe(&self, timestamp: i64, timeframe_ms: i64) -> bool {
-        let candle_start = (self.open_time / timeframe_ms) * timeframe_ms;
-        let candle_end = candle_start + timeframe_ms;
+        let candle_start = bucket_start(self.open_time, timeframe_ms);
+        let candle_end = next_bucket(candle_start, timeframe_ms);
         timestamp >= candle_start && timestamp < candle_end
     }
 }
@@ -77,12 +126,18 @@ pub struct CandleUpdate {
     pub c: String,
     /// Volume
     pub v: String,
+    /// Volume-weighted average price
+    #[serde(default)]
+    pub vwap: String,
     /// Interval/timeframe (e.g., "1m", "5m")
     pub i: String,
     /// Symbol
     pub s: String,
     /// Number of trades
     pub n: u64,
+    /// Start time as RFC3339 UTC, only present when requested with `?with_iso=true`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_iso: Option<String>,
 }
 
 impl CandleUpdate {
@@ -95,42 +150,332 @@ impl CandleUpdate {
             l: candle.low.to_string(),
             c: candle.close.to_string(),
             v: candle.volume.to_string(),
+            vwap: candle.vwap().to_string(),
             i: candle.timeframe.clone(),
             s: candle.symbol.clone(),
             n: candle.trade_count,
+            time_iso: None,
         }
     }
+
+    /// Add the human-readable `time_iso` field: the start of the bucket holding `t`,
+    /// which for a live candle is the time of its first trade
+    pub fn with_iso(mut self) -> Self {
+        let start = timeframe_ms(&self.i).map_or(self.t, |ms| bucket_start(self.t, ms));
+        self.time_iso = chrono::DateTime::from_timestamp_millis(start)
+            .map(|time| time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true));
+        self
+    }
+}
+
+/// Where higher-timeframe candles are aggregated from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CandleSource {
+    /// Every timeframe aggregates raw trades
+    #[default]
+    Trades,
+    /// Trades build 1m candles only; higher timeframes roll up closed 1m candles
+    Rollup,
+    /// 1m candles persisted elsewhere are read back and rolled up; trades build no candles
+    External,
+}
+
+impl CandleSource {
+    /// Parse "trades", "rollup" or "external" (case-insensitive)
+    pub fn parse(source: &str) -> Option<Self> {
+        match source.trim().to_ascii_lowercase().as_str() {
+            "trades" => Some(Self::Trades),
+            "rollup" => Some(Self::Rollup),
+            "external" => Some(Self::External),
+            _ => None,
+        }
+    }
+}
+
+/// Length of a 1m candle, the unit higher timeframes roll up from
+const MINUTE_MS: i64 = 60_000;
+
+/// How often external 1m candles are polled for newly closed minutes
+const MINUTE_FEED_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Most filler candles broadcast for one gap; a longer gap only gets its latest buckets
+/// filled so a long quiet period can't flood subscribers
+const MAX_GAP_FILL: i64 = 1_000;
+
+/// Length of a 1w candle
+const WEEK_MS: i64 = 604_800_000;
+/// Nominal length of a 1M candle; months are bucketed by calendar, this only orders them
+const MONTH_MS: i64 = 2_592_000_000;
+/// 1970-01-05, the first Monday after the epoch, where weekly buckets are anchored
+const FIRST_MONDAY_MS: i64 = 345_600_000;
+
+/// Length of a supported timeframe in milliseconds
+pub fn timeframe_ms(timeframe: &str) -> Option<i64> {
+    match timeframe {
+        "1m" => Some(60_000),     // 1 minute
+        "5m" => Some(300_000),    // 5 minutes
+        "15m" => Some(900_000),   // 15 minutes
+        "30m" => Some(1_800_000), // 30 minutes
+        "1h" => Some(3_600_000),  // 1 hour
+        "4h" => Some(14_400_000), // 4 hours
+        "1d" => Some(86_400_000), // 1 day
+        "1w" => Some(WEEK_MS),    // 1 week, Monday 00:00 UTC
+        "1M" => Some(MONTH_MS),   // 1 calendar month, the 1st 00:00 UTC
+        _ => None,
+    }
+}
+
+/// The first of the month `months` after the one holding `timestamp`, 00:00 UTC
+fn month_start(timestamp: i64, months: u32) -> i64 {
+    let date = chrono::DateTime::from_timestamp_millis(timestamp)
+        .unwrap_or_default()
+        .date_naive()
+        .with_day(1)
+        .unwrap_or_default();
+    (date + chrono::Months::new(months))
+        .and_time(chrono::NaiveTime::MIN)
+        .and_utc()
+        .timestamp_millis()
+}
+
+/// Start of the bucket holding `timestamp`. Weeks start on Monday and months on the 1st,
+/// both UTC; shorter timeframes align to multiples of their length since the epoch.
+pub fn bucket_start(timestamp: i64, timeframe_ms: i64) -> i64 {
+    match timeframe_ms {
+        MONTH_MS => month_start(timestamp, 0),
+        WEEK_MS => (timestamp - FIRST_MONDAY_MS).div_euclid(WEEK_MS) * WEEK_MS + FIRST_MONDAY_MS,
+        _ => timestamp.div_euclid(timeframe_ms) * timeframe_ms,
+    }
+}
+
+/// Start of the bucket after the one starting at `start`
+pub fn next_bucket(start: i64, timeframe_ms: i64) -> i64 {
+    match timeframe_ms {
+        MONTH_MS => month_start(start, 1),
+        _ => start + timeframe_ms,
+    }
+}
+
+/// Immutable view of the open candles, keyed by (symbol, timeframe)
+pub type CandleSnapshot = HashMap<(String, String), Candle>;
+
+/// Aggregate of the trades in one bucket, all None when there were none
+#[derive(sqlx::FromRow)]
+struct BucketRow {
+    open: Option<Decimal>,
+    high: Option<Decimal>,
+    low: Option<Decimal>,
+    close: Option<Decimal>,
+    volume: Option<Decimal>,
+    notional: Option<Decimal>,
+    open_time: Option<i64>,
+    close_time: Option<i64>,
+    trade_count: i64,
+}
+
+/// One externally persisted 1m candle
+#[derive(sqlx::FromRow)]
+struct MinuteRow {
+    symbol: String,
+    open_time: i64,
+    open: Decimal,
+    high: Decimal,
+    low: Decimal,
+    close: Decimal,
+    volume: Decimal,
+    notional: Decimal,
+    trade_count: i64,
+}
+
+/// Lock-free read handle on the aggregator's open candles
+///
+/// The aggregator publishes a fresh snapshot after every trade, so readers always see
+/// a consistent set of bars and never wait on the collector's aggregator lock.
+#[derive(Clone)]
+pub struct CandleReader {
+    snapshot: Arc<ArcSwap<CandleSnapshot>>,
+}
+
+impl CandleReader {
+    /// All open candles as of the last processed trade
+    pub fn snapshot(&self) -> Arc<CandleSnapshot> {
+        self.snapshot.load_full()
+    }
+
+    /// The open candle for one symbol and timeframe
+    pub fn current(&self, symbol: &str, timeframe: &str) -> Option<Candle> {
+        self.snapshot
+            .load()
+            .get(&(symbol.to_string(), timeframe.to_string()))
+            .cloned()
+    }
 }
 
 pub struct CandleAggregator {
     // Map of (symbol, timeframe) -> current candle
     current_candles: HashMap<(String, String), Candle>,
+    // Copy of current_candles published for readers
+    published: Arc<ArcSwap<CandleSnapshot>>,
     broadcast_tx: broadcast::Sender<CandleUpdate>,
     // Supported timeframes in milliseconds
     timeframes: Vec<(String, i64)>,
+    // Per-market timeframe overrides, keyed by symbol
+    market_timeframes: HashMap<String, Vec<(String, i64)>>,
+    // Whether higher timeframes come from trades or from 1m candles
+    source: CandleSource,
 }
 
 impl CandleAggregator {
+    #[allow(dead_code)]
     pub fn new(broadcast_tx: broadcast::Sender<CandleUpdate>) -> Self {
-        // Define supported timeframes
-        let timeframes = vec![
-            ("1m".to_string(), 60_000),     // 1 minute
-            ("5m".to_string(), 300_000),    // 5 minutes
-            ("15m".to_string(), 900_000),   // 15 minutes
-            ("30m".to_string(), 1_800_000), // 30 minutes
-            ("1h".to_string(), 3_600_000),  // 1 hour
-            ("4h".to_string(), 14_400_000), // 4 hours
-            ("1d".to_string(), 86_400_000), // 1 day
-        ];
+        Self::with_config(broadcast_tx, &IndexerConfig::default())
+    }
+
+    /// Create an aggregator that only builds the timeframes configured for each market
+    pub fn with_config(
+        broadcast_tx: broadcast::Sender<CandleUpdate>,
+        config: &IndexerConfig,
+    ) -> Self {
+        let resolve = |names: &[String]| -> Vec<(String, i64)> {
+            names
+                .iter()
+                .filter_map(|name| timeframe_ms(name).map(|ms| (name.clone(), ms)))
+                .collect()
+        };
+
+        let market_timeframes = config
+            .markets
+            .iter()
+            .map(|market| (market.symbol.clone(), resolve(&market.timeframes)))
+            .collect();
 
         Self {
             current_candles: HashMap::new(),
+            published: Arc::new(ArcSwap::from_pointee(HashMap::new())),
             broadcast_tx,
-            timeframes,
+            timeframes: resolve(&config.default_timeframes),
+            market_timeframes,
+            source: config.candle_source,
         }
     }
 
+    /// Rebuild the open candle of every market and timeframe from the `trades` table, so
+    /// bars are warm before the first new trade. Returns the number of candles restored.
+    pub async fn backfill_from_db(&mut self, pool: &PgPool) -> Result<usize> {
+        self.backfill_at(pool, chrono::Utc::now().timestamp_millis())
+            .await
+    }
+
+    /// Backfill as of `now_ms`.
+    ///
+    /// Only trades in each timeframe's current bucket are read, so a candle the collector
+    /// extends afterwards holds every stored trade exactly once. Trades flagged invalid
+    /// never reach candles and are skipped here too. In rollup mode higher timeframes stop
+    /// at the open minute, which they only take in once it closes. In external mode nothing
+    /// is read from trades; [`run_minute_feed`] restores the open candles instead.
+    async fn backfill_at(&mut self, pool: &PgPool, now_ms: i64) -> Result<usize> {
+        let minute_start = now_ms / MINUTE_MS * MINUTE_MS;
+        let symbols: Vec<String> = self.market_timeframes.keys().cloned().collect();
+        let mut restored = 0;
+
+        for symbol in symbols {
+            for (timeframe_name, timeframe_ms) in self.timeframes_for(&symbol) {
+                let until = match self.source {
+                    CandleSource::Trades => None,
+                    CandleSource::Rollup if timeframe_ms > MINUTE_MS => Some(minute_start),
+                    CandleSource::Rollup if timeframe_ms == MINUTE_MS => None,
+                    // Rollup mode keeps no sub-minute candles
+                    CandleSource::Rollup => continue,
+                    CandleSource::External => continue,
+                };
+                let bucket_start = bucket_start(now_ms, timeframe_ms);
+                let row: BucketRow = sqlx::query_as(
+                    "SELECT
+                        (array_agg(price ORDER BY created_at, trade_id))[1] AS open,
+                        MAX(price) AS high,
+                        MIN(price) AS low,
+                        (array_agg(price ORDER BY created_at DESC, trade_id DESC))[1] AS close,
+                        SUM(quantity) AS volume,
+                        SUM(price * quantity) AS notional,
+                        (EXTRACT(EPOCH FROM MIN(created_at)) * 1000)::bigint AS open_time,
+                        (EXTRACT(EPOCH FROM MAX(created_at)) * 1000)::bigint AS close_time,
+                        COUNT(*) AS trade_count
+                    FROM trades
+                    WHERE symbol = $1
+                        AND created_at >= to_timestamp($2::bigint / 1000.0)
+                        AND ($3::bigint IS NULL OR created_at < to_timestamp($3::bigint / 1000.0))
+                        AND price > 0 AND quantity > 0",
+                )
+                .bind(&symbol)
+                .bind(bucket_start)
+                .bind(until)
+                .fetch_one(pool)
+                .await?;
+
+                let (
+                    Some(open),
+                    Some(high),
+                    Some(low),
+                    Some(close),
+                    Some(volume),
+                    Some(notional),
+                    Some(open_time),
+                    Some(close_time),
+                ) = (
+                    row.open,
+                    row.high,
+                    row.low,
+                    row.close,
+                    row.volume,
+                    row.notional,
+                    row.open_time,
+                    row.close_time,
+                )
+                else {
+                    continue;
+                };
+                let candle = Candle {
+                    symbol: symbol.clone(),
+                    timeframe: timeframe_name.clone(),
+                    open,
+                    high,
+                    low,
+                    close,
+                    volume,
+                    notional,
+                    open_time,
+                    close_time,
+                    trade_count: row.trade_count as u64,
+                };
+                self.current_candles
+                    .insert((symbol.clone(), timeframe_name), candle);
+                restored += 1;
+            }
+        }
+
+        self.published.store(Arc::new(self.current_candles.clone()));
+        Ok(restored)
+    }
+
+    /// Handle for reading open candles without locking the aggregator
+    pub fn reader(&self) -> CandleReader {
+        CandleReader {
+            snapshot: self.published.clone(),
+        }
+    }
+
+    fn timeframes_for(&self, symbol: &str) -> Vec<(String, i64)> {
+        self.market_timeframes
+            .get(symbol)
+            .unwrap_or(&self.timeframes)
+            .clone()
+    }
+
     /// Process a new trade and update all timeframe candles
+    ///
+    /// In [`CandleSource::Rollup`] mode the trade only updates the 1m candle; higher
+    /// timeframes follow once that minute closes. In [`CandleSource::External`] mode
+    /// trades leave candles alone.
     pub fn process_trade(
         &mut self,
         symbol: &str,
@@ -138,62 +483,212 @@ impl CandleAggregator {
         quantity: Decimal,
         timestamp_ms: i64,
     ) -> Result<()> {
-        for (timeframe_name, timeframe_ms) in &self.timeframes {
-            let key = (symbol.to_string(), timeframe_name.clone());
-
-            let mut is_closed = false;
-
-            match self.current_candles.get_mut(&key) {
-                Some(candle) => {
-                    // Check if trade belongs to current candle
-                    if candle.is_in_timeframe(timestamp_ms, *timeframe_ms) {
-                        candle.update(price, quantity, timestamp_ms);
-                    } else {
-                        // Candle closed, broadcast the closed candle first
-                        let closed_candle = candle.clone();
-                        let _ = self
-                            .broadcast_tx
-                            .send(CandleUpdate::from_candle(&closed_candle, true));
-
-                        // Start new candle
-                        *candle = Candle::new(
-                            symbol.to_string(),
-                            timeframe_name.clone(),
-                            price,
-                            quantity,
-                            timestamp_ms,
-                        );
-                        is_closed = false; // This is the new candle, not closed
-                    }
+        let trade = |timeframe: &str| {
+            Candle::new(
+                symbol.to_string(),
+                timeframe.to_string(),
+                price,
+                quantity,
+                timestamp_ms,
+            )
+        };
+
+        match self.source {
+            CandleSource::Trades => {
+                for (timeframe_name, timeframe_ms) in self.timeframes_for(symbol) {
+                    self.apply(trade(&timeframe_name), timeframe_ms);
                 }
-                None => {
-                    // First trade for this symbol/timeframe
-                    let candle = Candle::new(
-                        symbol.to_string(),
-                        timeframe_name.clone(),
-                        price,
-                        quantity,
-                        timestamp_ms,
-                    );
-                    self.current_candles.insert(key.clone(), candle);
+            }
+            CandleSource::Rollup => {
+                if let Some(minute) = self.apply(trade("1m"), MINUTE_MS) {
+                    self.roll_up_minute(&minute);
                 }
             }
+            CandleSource::External => return Ok(()),
+        }
+
+        self.published.store(Arc::new(self.current_candles.clone()));
 
-            // Broadcast updated candle
-            if let Some(candle) = self.current_candles.get(&key) {
+        Ok(())
+    }
+
+    /// Take in a closed 1m candle from an external source: it becomes the symbol's 1m
+    /// candle, is rolled into every higher timeframe and is then visible to readers
+    pub fn ingest_minute_candle(&mut self, minute: &Candle) {
+        let timeframes = self.timeframes_for(&minute.symbol);
+        if timeframes.iter().any(|(_, ms)| *ms == MINUTE_MS) {
+            let mut candle = minute.clone();
+            candle.timeframe = "1m".to_string();
+            self.apply(candle, MINUTE_MS);
+        }
+        self.roll_up_minute(minute);
+
+        self.published.store(Arc::new(self.current_candles.clone()));
+    }
+
+    /// Start of the oldest bucket still open at `now_ms` across every market and
+    /// timeframe, where an external minute feed starts reading
+    pub fn minute_feed_start(&self, now_ms: i64) -> i64 {
+        self.market_timeframes
+            .keys()
+            .flat_map(|symbol| self.timeframes_for(symbol))
+            .map(|(_, timeframe_ms)| bucket_start(now_ms, timeframe_ms))
+            .min()
+            .unwrap_or_else(|| bucket_start(now_ms, MINUTE_MS))
+    }
+
+    /// Roll a closed 1m candle into every higher timeframe of its symbol
+    fn roll_up_minute(&mut self, minute: &Candle) {
+        for (timeframe_name, timeframe_ms) in self.timeframes_for(&minute.symbol) {
+            if timeframe_ms <= MINUTE_MS {
+                continue;
+            }
+            let mut candle = minute.clone();
+            candle.timeframe = timeframe_name;
+            self.apply(candle, timeframe_ms);
+        }
+    }
+
+    /// Fold `incoming` into the open candle of its symbol and timeframe and broadcast the
+    /// result. When `incoming` starts a new bucket the previous candle is broadcast as
+    /// closed and returned, followed by filler candles for any buckets skipped in between.
+    fn apply(&mut self, incoming: Candle, timeframe_ms: i64) -> Option<Candle> {
+        let key = (incoming.symbol.clone(), incoming.timeframe.clone());
+        let mut closed = None;
+
+        match self.current_candles.get_mut(&key) {
+            // Check if the update belongs to the current candle
+            Some(candle) if candle.is_in_timeframe(incoming.open_time, timeframe_ms) => {
+                candle.merge(&incoming);
+            }
+            Some(candle) => {
+                // Candle closed, broadcast the closed candle first
                 let _ = self
                     .broadcast_tx
-                    .send(CandleUpdate::from_candle(candle, is_closed));
+                    .send(CandleUpdate::from_candle(candle, true));
+                // Then a flat candle for every bucket without trades, so the series
+                // has no holes
+                let first_empty =
+                    next_bucket(bucket_start(candle.open_time, timeframe_ms), timeframe_ms);
+                let next = bucket_start(incoming.open_time, timeframe_ms);
+                let mut open_time = first_empty.max(next - MAX_GAP_FILL * timeframe_ms);
+                if timeframe_ms == MONTH_MS {
+                    // Too few months for the cap to matter, and they vary in length
+                    open_time = first_empty;
+                }
+                while open_time < next {
+                    let filler = Candle::filler(candle, open_time, timeframe_ms);
+                    let _ = self
+                        .broadcast_tx
+                        .send(CandleUpdate::from_candle(&filler, true));
+                    open_time = next_bucket(open_time, timeframe_ms);
+                }
+                closed = Some(std::mem::replace(candle, incoming));
+            }
+            None => {
+                // First update for this symbol/timeframe
+                self.current_candles.insert(key.clone(), incoming);
             }
         }
 
-        Ok(())
+        // Broadcast updated candle
+        if let Some(candle) = self.current_candles.get(&key) {
+            let _ = self
+                .broadcast_tx
+                .send(CandleUpdate::from_candle(candle, false));
+        }
+
+        closed
+    }
+}
+
+/// The 1m candles of `symbols` in `table` with a bucket in `[from_ms, until_ms)`, oldest
+/// first. The table needs the columns of the `one_minute_candles` view: `bucket`,
+/// `symbol`, `open`, `high`, `low`, `close`, `volume`, `vwap` and `trade_count`.
+pub async fn fetch_minute_candles(
+    pool: &PgPool,
+    table: &str,
+    symbols: &[String],
+    from_ms: i64,
+    until_ms: i64,
+) -> Result<Vec<Candle>> {
+    let rows = sqlx::query_as::<_, MinuteRow>(&format!(
+        "SELECT symbol,
+            (EXTRACT(EPOCH FROM bucket) * 1000)::bigint AS open_time,
+            open::numeric AS open,
+            high::numeric AS high,
+            low::numeric AS low,
+            close::numeric AS close,
+            volume::numeric AS volume,
+            COALESCE(vwap * volume, 0)::numeric AS notional,
+            trade_count::bigint AS trade_count
+        FROM {}
+        WHERE symbol = ANY($1)
+            AND bucket >= to_timestamp($2::bigint / 1000.0)
+            AND bucket < to_timestamp($3::bigint / 1000.0)
+        ORDER BY bucket, symbol",
+        table
+    ))
+    .bind(symbols)
+    .bind(from_ms)
+    .bind(until_ms)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Candle {
+            symbol: row.symbol,
+            timeframe: "1m".to_string(),
+            open: row.open,
+            high: row.high,
+            low: row.low,
+            close: row.close,
+            volume: row.volume,
+            notional: row.notional,
+            open_time: row.open_time,
+            close_time: row.open_time + MINUTE_MS - 1,
+            trade_count: row.trade_count as u64,
+        })
+        .collect())
+}
+
+/// Feed every 1m candle of `table` into the aggregator as its minute closes, for
+/// [`CandleSource::External`]. The first read covers the buckets already open, so
+/// higher timeframes start out complete.
+pub async fn run_minute_feed(
+    pool: PgPool,
+    candles: Arc<Mutex<CandleAggregator>>,
+    table: String,
+    symbols: Vec<String>,
+) {
+    let now_ms = || chrono::Utc::now().timestamp_millis();
+    let mut from_ms = candles.lock().await.minute_feed_start(now_ms());
+    let mut ticker = tokio::time::interval(MINUTE_FEED_INTERVAL);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        let until_ms = bucket_start(now_ms(), MINUTE_MS);
+        if until_ms <= from_ms {
+            continue;
+        }
+        match fetch_minute_candles(&pool, &table, &symbols, from_ms, until_ms).await {
+            Ok(minutes) => {
+                let mut candles = candles.lock().await;
+                for minute in &minutes {
+                    candles.ingest_minute_candle(minute);
+                }
+                from_ms = until_ms;
+            }
+            Err(e) => warn!("⚠️  Failed to read 1m candles from {}: {}", table, e),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::MarketConfig;
 
     #[test]
     fn test_candle_creation() {
@@ -236,6 +731,207 @@ mod tests {
         assert_eq!(candle.close, Decimal::from(1900));
     }
 
+    #[test]
+    fn test_vwap_and_trade_count_accumulate() {
+        let (tx, mut rx) = broadcast::channel(16);
+        let config = IndexerConfig {
+            markets: vec![MarketConfig::new("ETH/USDT", vec!["1m".to_string()])],
+            ..IndexerConfig::default()
+        };
+        let mut agg = CandleAggregator::with_config(tx, &config);
+        agg.process_trade("ETH/USDT", Decimal::from(2000), Decimal::ONE, 60_000)
+            .unwrap();
+        agg.process_trade("ETH/USDT", Decimal::from(2100), Decimal::from(3), 61_000)
+            .unwrap();
+
+        // (2000 × 1 + 2100 × 3) / 4
+        let candle = agg.reader().current("ETH/USDT", "1m").unwrap();
+        assert_eq!(candle.vwap(), Decimal::from(2075));
+        assert_eq!(candle.trade_count, 2);
+
+        let update = std::iter::from_fn(|| rx.try_recv().ok()).last().unwrap();
+        let json: serde_json::Value = serde_json::to_value(&update).unwrap();
+        assert_eq!(json["vwap"], "2075");
+        assert_eq!(json["n"], 2);
+        // Fields older clients know are unchanged
+        assert_eq!(
+            (json["c"].as_str(), json["v"].as_str()),
+            (Some("2100"), Some("4"))
+        );
+    }
+
+    #[test]
+    fn test_readers_see_consistent_snapshots_during_writes() {
+        let (tx, _) = broadcast::channel(16);
+        let mut agg = CandleAggregator::new(tx);
+        let reader = agg.reader();
+        assert!(reader.current("ETH/USDT", "1m").is_none());
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let reader = reader.clone();
+                std::thread::spawn(move || {
+                    let mut last_count = 0;
+                    for _ in 0..10_000 {
+                        let snapshot = reader.snapshot();
+                        let one_min = snapshot.get(&("ETH/USDT".to_string(), "1m".to_string()));
+                        let one_hour = snapshot.get(&("ETH/USDT".to_string(), "1h".to_string()));
+                        if let (Some(m), Some(h)) = (one_min, one_hour) {
+                            // Both bars come from the same trade, never a half-applied update
+                            assert_eq!(m.trade_count, h.trade_count);
+                            assert_eq!(m.close, h.close);
+                            assert!(m.trade_count >= last_count);
+                            last_count = m.trade_count;
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for i in 0..1_000 {
+            agg.process_trade(
+                "ETH/USDT",
+                Decimal::from(2000 + i),
+                Decimal::ONE,
+                60_000 + i,
+            )
+            .unwrap();
+        }
+
+        for handle in readers {
+            handle.join().unwrap();
+        }
+
+        let candle = reader.current("ETH/USDT", "1m").unwrap();
+        assert_eq!(candle.trade_count, 1_000);
+        assert_eq!(candle.close, Decimal::from(2999));
+    }
+
+    #[test]
+    fn test_time_iso_matches_bucket_start() {
+        let candle = Candle::new(
+            "ETH/USDT".to_string(),
+            "1m".to_string(),
+            Decimal::from(2000),
+            Decimal::from(10),
+            // 22:14:37.123, mid-minute like any live candle's first trade
+            1_700_000_077_123,
+        );
+
+        let plain = CandleUpdate::from_candle(&candle, false);
+        assert!(plain.time_iso.is_none());
+        assert!(!serde_json::to_string(&plain).unwrap().contains("time_iso"));
+
+        let update = plain.with_iso();
+        let iso = update.time_iso.as_deref().unwrap();
+        assert_eq!(iso, "2023-11-14T22:14:00Z");
+        let parsed = chrono::DateTime::parse_from_rfc3339(iso).unwrap();
+        assert_eq!(parsed.timestamp_millis(), bucket_start(update.t, 60_000));
+    }
+
+    fn rollup_config(timeframes: &[&str]) -> IndexerConfig {
+        let timeframes: Vec<String> = timeframes.iter().map(|tf| tf.to_string()).collect();
+        IndexerConfig {
+            markets: vec![MarketConfig::new("ETH/USDT", timeframes.clone())],
+            default_timeframes: timeframes,
+            candle_source: CandleSource::Rollup,
+            ..IndexerConfig::default()
+        }
+    }
+
+    fn minute(index: i64, open: i64, high: i64, low: i64, close: i64) -> Candle {
+        // 10:00 UTC on 2023-11-14, plus `index` minutes
+        let open_time = 1_699_956_000_000 + index * 60_000;
+        Candle {
+            symbol: "ETH/USDT".to_string(),
+            timeframe: "1m".to_string(),
+            open: Decimal::from(open),
+            high: Decimal::from(high),
+            low: Decimal::from(low),
+            close: Decimal::from(close),
+            volume: Decimal::from(index + 1),
+            notional: Decimal::from(close * (index + 1)),
+            open_time,
+            close_time: open_time + 59_000,
+            trade_count: 2,
+        }
+    }
+
+    #[test]
+    fn test_rolls_up_external_minutes_into_an_hour() {
+        let config = IndexerConfig {
+            candle_source: CandleSource::External,
+            ..rollup_config(&["1m", "5m", "1h"])
+        };
+        let (tx, mut rx) = broadcast::channel(256);
+        let mut agg = CandleAggregator::with_config(tx, &config);
+        let reader = agg.reader();
+
+        for i in 0..12 {
+            let (open, close) = (2000 + i * 10, 2005 + i * 10);
+            // Highest high in minute 7, lowest low in minute 3
+            let high = if i == 7 { 2500 } else { close + 1 };
+            let low = if i == 3 { 1500 } else { open - 1 };
+            agg.ingest_minute_candle(&minute(i, open, high, low, close));
+        }
+
+        let hour = reader.current("ETH/USDT", "1h").unwrap();
+        assert_eq!(hour.open, Decimal::from(2000));
+        assert_eq!(hour.high, Decimal::from(2500));
+        assert_eq!(hour.low, Decimal::from(1500));
+        assert_eq!(hour.close, Decimal::from(2115));
+        // 1 + 2 + ... + 12
+        assert_eq!(hour.volume, Decimal::from(78));
+        assert_eq!(hour.trade_count, 24);
+        assert_eq!(hour.open_time, 1_699_956_000_000);
+
+        // Minutes 10 and 11 form the current 5m bar
+        let five = reader.current("ETH/USDT", "5m").unwrap();
+        assert_eq!(five.open, Decimal::from(2100));
+        assert_eq!(five.volume, Decimal::from(11 + 12));
+        // The latest external minute is the 1m candle; trades don't touch it
+        agg.process_trade("ETH/USDT", Decimal::ONE, Decimal::ONE, 1_699_956_700_000)
+            .unwrap();
+        let last_minute = reader.current("ETH/USDT", "1m").unwrap();
+        assert_eq!(last_minute.open, Decimal::from(2110));
+        assert_eq!(last_minute.volume, Decimal::from(12));
+
+        // The next hour's first minute closes the rolled-up hour
+        agg.ingest_minute_candle(&minute(60, 3000, 3000, 3000, 3000));
+        let closed: Vec<CandleUpdate> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter(|update| update.i == "1h" && update.t == 1_699_956_000_000)
+            .collect();
+        let last = closed.last().unwrap();
+        assert_eq!((last.h.as_str(), last.l.as_str()), ("2500", "1500"));
+        assert_eq!(last.c, "2115");
+    }
+
+    #[test]
+    fn test_rollup_mode_builds_higher_timeframes_from_closed_minutes() {
+        let config = rollup_config(&["1m", "1h"]);
+        let (tx, _rx) = broadcast::channel(256);
+        let mut agg = CandleAggregator::with_config(tx, &config);
+        let reader = agg.reader();
+
+        agg.process_trade("ETH/USDT", Decimal::from(2000), Decimal::ONE, 60_000)
+            .unwrap();
+        agg.process_trade("ETH/USDT", Decimal::from(2100), Decimal::ONE, 61_000)
+            .unwrap();
+        // Minute still open: nothing rolled up yet
+        assert!(reader.current("ETH/USDT", "1h").is_none());
+
+        agg.process_trade("ETH/USDT", Decimal::from(1900), Decimal::TWO, 120_000)
+            .unwrap();
+        let hour = reader.current("ETH/USDT", "1h").unwrap();
+        assert_eq!(hour.high, Decimal::from(2100));
+        assert_eq!(hour.close, Decimal::from(2100));
+        assert_eq!(hour.volume, Decimal::TWO);
+        assert_eq!(
+            reader.current("ETH/USDT", "1m").unwrap().volume,
+            Decimal::TWO
+        );
+    }
+
     #[test]
     fn test_candle_timeframe() {
         let candle = Candle::new(
@@ -253,4 +949,278 @@ mod tests {
         // Next minute
         assert!(!candle.is_in_timeframe(120_000, 60_000));
     }
+
+    #[test]
+    fn test_empty_buckets_get_filler_candles() {
+        let config = IndexerConfig {
+            markets: vec![MarketConfig::new(
+                "ETH/USDT",
+                vec!["1m".to_string(), "5m".to_string()],
+            )],
+            ..IndexerConfig::default()
+        };
+        let (tx, mut rx) = broadcast::channel(256);
+        let mut agg = CandleAggregator::with_config(tx, &config);
+
+        agg.process_trade("ETH/USDT", Decimal::from(2000), Decimal::ONE, 60_500)
+            .unwrap();
+        agg.process_trade("ETH/USDT", Decimal::from(2010), Decimal::ONE, 61_000)
+            .unwrap();
+        // Nothing traded in minutes 2 to 4
+        agg.process_trade("ETH/USDT", Decimal::from(1990), Decimal::TWO, 300_000)
+            .unwrap();
+
+        let updates: Vec<CandleUpdate> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        let fillers: Vec<&CandleUpdate> = updates.iter().filter(|update| update.n == 0).collect();
+        let starts: Vec<i64> = fillers.iter().map(|update| update.t).collect();
+        assert_eq!(starts, vec![120_000, 180_000, 240_000]);
+        for filler in &fillers {
+            assert_eq!(filler.i, "1m");
+            assert_eq!((filler.o.as_str(), filler.h.as_str()), ("2010", "2010"));
+            assert_eq!((filler.l.as_str(), filler.c.as_str()), ("2010", "2010"));
+            assert_eq!(filler.v, "0");
+            assert_eq!(filler.end_time, filler.t + 59_999);
+        }
+        // Fillers come after the closed candle and before the new bucket's first update
+        let position = |t: i64, n: u64| {
+            updates
+                .iter()
+                .position(|update| update.i == "1m" && update.t == t && update.n == n)
+                .unwrap()
+        };
+        assert!(position(60_500, 2) < position(120_000, 0));
+        assert!(position(240_000, 0) < position(300_000, 1));
+
+        // The 5m series is unbroken (minute 5 opens the next 5m bucket), so no fillers
+        assert!(updates
+            .iter()
+            .all(|update| update.i != "5m" || update.n > 0));
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_backfill_rebuilds_open_candles(pool: PgPool) {
+        // 10:30:30 UTC on 2023-11-14
+        let now = 1_699_957_830_000;
+        let trades = [
+            // 10:29:10, in the open hour but the previous minute
+            (1_i64, now - 80_000, 2000, 1),
+            // 10:30:05 and 10:30:20, in the open minute
+            (2, now - 25_000, 2100, 2),
+            (3, now - 10_000, 1950, 3),
+            // Zero quantity, flagged and kept out of candles
+            (4, now - 5_000, 9999, 0),
+            // 09:59:59, the previous hour
+            (5, now - 1_831_000, 1000, 5),
+        ];
+        for (trade_id, created_ms, price, quantity) in trades {
+            sqlx::query(
+                "INSERT INTO trades
+                (trade_id, block_number, buy_order_id, sell_order_id, buyer, seller, price, quantity, value, symbol, created_at)
+                VALUES ($1, 3, 5, 6, '0xb', '0xs', $2, $3, $2 * $3, 'ETH/USDT', to_timestamp($4::bigint / 1000.0))",
+            )
+            .bind(trade_id)
+            .bind(Decimal::from(price))
+            .bind(Decimal::from(quantity))
+            .bind(created_ms)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let config = IndexerConfig {
+            markets: vec![MarketConfig::new(
+                "ETH/USDT",
+                vec!["1m".to_string(), "1h".to_string()],
+            )],
+            ..IndexerConfig::default()
+        };
+        let (tx, _rx) = broadcast::channel(16);
+        let mut agg = CandleAggregator::with_config(tx, &config);
+        assert_eq!(agg.backfill_at(&pool, now).await.unwrap(), 2);
+
+        let reader = agg.reader();
+        let minute = reader.current("ETH/USDT", "1m").unwrap();
+        assert_eq!(
+            (minute.open, minute.close),
+            (Decimal::from(2100), Decimal::from(1950))
+        );
+        assert_eq!(minute.volume, Decimal::from(5));
+        assert_eq!(minute.trade_count, 2);
+        let hour = reader.current("ETH/USDT", "1h").unwrap();
+        assert_eq!(
+            (hour.open, hour.high),
+            (Decimal::from(2000), Decimal::from(2100))
+        );
+        assert_eq!(hour.low, Decimal::from(1950));
+        assert_eq!(hour.trade_count, 3);
+
+        // The next live trade extends the restored candles instead of starting over
+        agg.process_trade("ETH/USDT", Decimal::from(2050), Decimal::ONE, now)
+            .unwrap();
+        let hour = agg.reader().current("ETH/USDT", "1h").unwrap();
+        assert_eq!((hour.trade_count, hour.volume), (4, Decimal::from(7)));
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_external_minutes_feed_higher_timeframes(pool: PgPool) {
+        sqlx::query(
+            "CREATE TABLE external_minutes (
+                bucket TIMESTAMPTZ, symbol TEXT, open NUMERIC, high NUMERIC, low NUMERIC,
+                close NUMERIC, volume NUMERIC, vwap NUMERIC, trade_count BIGINT
+            )",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        // 10:30:30 UTC on 2023-11-14
+        let now = 1_699_957_830_000;
+        let minutes = [
+            // 09:59, the previous hour
+            ("ETH/USDT", now - 1_890_000, 900, 1),
+            // 10:28 and 10:29, closed minutes of the open hour
+            ("ETH/USDT", now - 150_000, 2000, 2),
+            ("ETH/USDT", now - 90_000, 2100, 3),
+            // 10:30, still open
+            ("ETH/USDT", now - 30_000, 2200, 4),
+            ("DOGE/USD", now - 90_000, 1, 100),
+        ];
+        for (symbol, bucket_ms, price, volume) in minutes {
+            sqlx::query(
+                "INSERT INTO external_minutes
+                VALUES (to_timestamp($1::bigint / 1000.0), $2, $3, $3, $3, $3, $4, $3, 1)",
+            )
+            .bind(bucket_ms / 60_000 * 60_000)
+            .bind(symbol)
+            .bind(Decimal::from(price))
+            .bind(Decimal::from(volume))
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let config = IndexerConfig {
+            candle_source: CandleSource::External,
+            ..rollup_config(&["1m", "1h"])
+        };
+        let (tx, _rx) = broadcast::channel(16);
+        let mut agg = CandleAggregator::with_config(tx, &config);
+        let from = agg.minute_feed_start(now);
+        assert_eq!(from, 1_699_956_000_000);
+
+        let symbols = vec!["ETH/USDT".to_string()];
+        let until = now / 60_000 * 60_000;
+        let read = fetch_minute_candles(&pool, "external_minutes", &symbols, from, until)
+            .await
+            .unwrap();
+        let opens: Vec<Decimal> = read.iter().map(|minute| minute.open).collect();
+        assert_eq!(opens, vec![Decimal::from(2000), Decimal::from(2100)]);
+        for minute in &read {
+            agg.ingest_minute_candle(minute);
+        }
+
+        let hour = agg.reader().current("ETH/USDT", "1h").unwrap();
+        assert_eq!(
+            (hour.open, hour.close),
+            (Decimal::from(2000), Decimal::from(2100))
+        );
+        assert_eq!(hour.volume, Decimal::from(5));
+        // Notional comes back from vwap × volume
+        assert_eq!(hour.vwap(), Decimal::from(2060));
+    }
+
+    /// Milliseconds at 00:00 UTC on the given date, plus `secs`
+    fn utc(year: i32, month: u32, day: u32, secs: i64) -> i64 {
+        chrono::NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_time(chrono::NaiveTime::MIN)
+            .and_utc()
+            .timestamp_millis()
+            + secs * 1000
+    }
+
+    #[test]
+    fn test_weeks_start_on_monday() {
+        let week = timeframe_ms("1w").unwrap();
+        // Sunday 2023-12-31 23:59:59 is still in the week of Monday the 25th
+        assert_eq!(
+            bucket_start(utc(2023, 12, 31, 86_399), week),
+            utc(2023, 12, 25, 0)
+        );
+        assert_eq!(bucket_start(utc(2024, 1, 1, 0), week), utc(2024, 1, 1, 0));
+        assert_eq!(
+            bucket_start(utc(2024, 1, 3, 3600), week),
+            utc(2024, 1, 1, 0)
+        );
+        // Before the first Monday after the epoch
+        assert_eq!(bucket_start(utc(1970, 1, 1, 0), week), utc(1969, 12, 29, 0));
+    }
+
+    #[test]
+    fn test_month_rolls_over_at_year_end() {
+        let month = timeframe_ms("1M").unwrap();
+        let last_second = utc(2023, 12, 31, 86_399);
+        assert_eq!(bucket_start(last_second, month), utc(2023, 12, 1, 0));
+        assert_eq!(next_bucket(utc(2023, 12, 1, 0), month), utc(2024, 1, 1, 0));
+        assert_eq!(bucket_start(utc(2024, 1, 1, 0), month), utc(2024, 1, 1, 0));
+
+        let (tx, mut rx) = broadcast::channel(256);
+        let config = IndexerConfig {
+            markets: vec![MarketConfig::new("ETH/USDT", vec!["1M".to_string()])],
+            ..IndexerConfig::default()
+        };
+        let mut agg = CandleAggregator::with_config(tx, &config);
+        agg.process_trade("ETH/USDT", Decimal::from(2000), Decimal::ONE, last_second)
+            .unwrap();
+        agg.process_trade(
+            "ETH/USDT",
+            Decimal::from(2100),
+            Decimal::ONE,
+            last_second + 1000,
+        )
+        .unwrap();
+
+        let updates: Vec<CandleUpdate> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert!(updates.iter().all(|update| update.i == "1M"));
+        // December closes, January opens; no filler between adjacent months
+        let starts: Vec<(i64, u64)> = updates.iter().map(|update| (update.t, update.n)).collect();
+        assert_eq!(
+            starts,
+            vec![(last_second, 1), (last_second, 1), (last_second + 1000, 1)]
+        );
+        let january = agg.reader().current("ETH/USDT", "1M").unwrap();
+        assert_eq!(january.close, Decimal::from(2100));
+        assert_eq!(bucket_start(january.open_time, month), utc(2024, 1, 1, 0));
+    }
+
+    #[test]
+    fn test_leap_year_february() {
+        let month = timeframe_ms("1M").unwrap();
+        let leap_day = utc(2024, 2, 29, 43_200);
+        assert_eq!(bucket_start(leap_day, month), utc(2024, 2, 1, 0));
+        assert_eq!(next_bucket(utc(2024, 2, 1, 0), month), utc(2024, 3, 1, 0));
+        assert_eq!(
+            next_bucket(utc(2024, 2, 1, 0), month) - utc(2024, 2, 1, 0),
+            29 * 86_400_000
+        );
+        assert_eq!(
+            next_bucket(utc(2023, 2, 1, 0), month) - utc(2023, 2, 1, 0),
+            28 * 86_400_000
+        );
+
+        let candle = Candle::new(
+            "ETH/USDT".to_string(),
+            "1M".to_string(),
+            Decimal::from(2000),
+            Decimal::ONE,
+            utc(2024, 2, 3, 0),
+        );
+        assert!(candle.is_in_timeframe(leap_day, month));
+        assert!(!candle.is_in_timeframe(utc(2024, 3, 1, 0), month));
+
+        // A quiet March gets a filler spanning exactly March
+        let filler = Candle::filler(&candle, utc(2024, 3, 1, 0), month);
+        assert_eq!(filler.close_time, utc(2024, 4, 1, 0) - 1);
+    }
 }
@@ -1,44 +1,473 @@
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex, Notify};
+use tokio_util::sync::CancellationToken;
 
+use crate::config::IndexerConfig;
+use crate::db::orders;
+use crate::indexer::backpressure::Backpressure;
 use crate::indexer::candle_aggregator::CandleAggregator;
+use crate::indexer::event_hooks::{EventHook, MarketEvent};
+use crate::indexer::metadata_check;
+use crate::indexer::order_events::{record_order_event, OrderEventKind};
 use crate::indexer::orderbook_reducer::{OrderInfo, OrderbookState};
 use crate::indexer::runtime;
-use crate::indexer::trade_mapper::{process_trade, TradeProcessingContext};
-use anyhow::Result;
+use crate::indexer::runtime::polkadot::runtime_types;
+use crate::indexer::scaling::AmountScale;
+use crate::indexer::trade_clusters::{ClusterDetector, MomentumSignal};
+use crate::indexer::trade_enricher;
+use crate::indexer::trade_mapper::{
+    process_trade, EventOrigin, TradeProcessingContext, TradeUpdate,
+};
+use crate::telemetry;
+use anyhow::{bail, Result};
+use futures::{stream, Stream, StreamExt};
 use rust_decimal::Decimal;
 use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::time::Instant;
+use subxt::backend::{legacy::LegacyRpcMethods, rpc::RpcClient};
+use subxt::blocks::Block;
+use subxt::events::Phase;
 use subxt::{OnlineClient, PolkadotConfig};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
-pub async fn start(
-    node_url: &str,
-    pool: PgPool,
-    orderbook_state: Arc<Mutex<OrderbookState>>,
-    candle_aggregator: Arc<Mutex<CandleAggregator>>,
+/// Convert an order from the pallet's `Orders` storage into book form
+fn chain_order_info(
+    order: &runtime_types::pallet_orderbook::types::Order,
+    scale: AmountScale,
+) -> OrderInfo {
+    use runtime_types::pallet_orderbook::types::OrderStatus;
+
+    let status = match order.status {
+        OrderStatus::Open => "Open",
+        OrderStatus::PartiallyFilled => "PartiallyFilled",
+        OrderStatus::Filled => "Filled",
+        OrderStatus::Cancelled => "Cancelled",
+        OrderStatus::Expired => "Expired",
+    };
+    OrderInfo {
+        order_id: order.order_id,
+        side: order.side.to_string(),
+        price: scale.price(order.price),
+        quantity: scale.quantity(order.quantity),
+        filled_quantity: scale.quantity(order.filled_quantity),
+        status: status.to_string(),
+        raw_price: order.price,
+        raw_quantity: order.quantity,
+    }
+}
+
+/// Load every resting order from the pallet's `Orders` storage into the book, so the
+/// indexer serves the live book immediately instead of only orders placed after startup.
+pub async fn seed_from_chain(
+    api: &OnlineClient<PolkadotConfig>,
+    orderbook_state: &Mutex<OrderbookState>,
+    scale: AmountScale,
+    warmup: bool,
+) -> Result<usize> {
+    let query = runtime::polkadot::storage().orderbook().orders_iter();
+    let mut entries = api.storage().at_latest().await?.iter(query).await?;
+
+    let mut orders = Vec::new();
+    while let Some(entry) = entries.next().await {
+        let order = chain_order_info(&entry?.value, scale);
+        if order.is_resting() {
+            orders.push(order);
+        }
+    }
+
+    let count = orders.len();
+    orderbook_state.lock().await.seed_orders(orders, warmup);
+    Ok(count)
+}
+
+/// What the collector has done so far, kept across node failovers
+#[derive(Debug, Default)]
+pub struct CollectorProgress {
+    /// The book was seeded from chain storage; later connections must not seed again
+    seeded: AtomicBool,
+    /// Last finalized block processed, 0 before the first one
+    last_block: AtomicU32,
+    /// The cold-start seed finished, or was not configured
+    seed_complete: AtomicBool,
+    /// At least one block was fully processed
+    block_complete: AtomicBool,
+    /// Blocks and events indexed by this process
+    blocks_processed: AtomicU64,
+    events_processed: AtomicU64,
+    ready_changed: Notify,
+}
+
+impl CollectorProgress {
+    /// True once the book holds seeded state and the first block was indexed
+    pub fn is_ready(&self) -> bool {
+        self.seed_complete.load(Ordering::Relaxed) && self.block_complete.load(Ordering::Relaxed)
+    }
+
+    /// Last finalized block processed, 0 before the first one
+    pub fn last_block(&self) -> u32 {
+        self.last_block.load(Ordering::Relaxed)
+    }
+
+    /// Resolve once `is_ready` holds
+    pub async fn wait_ready(&self) {
+        loop {
+            let changed = self.ready_changed.notified();
+            if self.is_ready() {
+                return;
+            }
+            changed.await;
+        }
+    }
+
+    /// `(blocks, events)` indexed by this process
+    pub fn processed(&self) -> (u64, u64) {
+        (
+            self.blocks_processed.load(Ordering::Relaxed),
+            self.events_processed.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Continue after `last_block` instead of at the finalized head: blocks up to it count
+    /// as indexed and the chain seed is skipped, as the book already reflects them
+    pub fn resume_from(&self, last_block: u32) {
+        self.seeded.store(true, Ordering::Relaxed);
+        self.last_block.store(last_block, Ordering::Relaxed);
+    }
+
+    fn mark_seed_complete(&self) {
+        self.seed_complete.store(true, Ordering::Relaxed);
+        self.ready_changed.notify_waiters();
+    }
+
+    fn mark_block_complete(&self) {
+        if !self.block_complete.swap(true, Ordering::Relaxed) {
+            self.ready_changed.notify_waiters();
+        }
+    }
+}
+
+/// Where the collector looks up an order's current state on chain
+trait ChainOrders {
+    /// The order as stored on chain, None once it is no longer stored
+    async fn order(&self, order_id: u64) -> Result<Option<OrderInfo>>;
+}
+
+/// Chain storage as of the latest block, which may be slightly ahead of the finalized
+/// block being indexed
+struct LatestStorage<'a> {
+    api: &'a OnlineClient<PolkadotConfig>,
+    scale: AmountScale,
+}
+
+impl ChainOrders for LatestStorage<'_> {
+    async fn order(&self, order_id: u64) -> Result<Option<OrderInfo>> {
+        let query = runtime::polkadot::storage().orderbook().orders(order_id);
+        let order = self.api.storage().at_latest().await?.fetch(&query).await?;
+        Ok(order.map(|order| chain_order_info(&order, self.scale)))
+    }
+}
+
+/// Replace one tracked order with its state in chain storage, after the book was found
+/// inconsistent with an event
+async fn reconcile_order(
+    chain: &impl ChainOrders,
+    orderbook_state: &Mutex<OrderbookState>,
+    order_id: u64,
 ) -> Result<()> {
-    let api = OnlineClient::<PolkadotConfig>::from_url(node_url).await?;
+    let order = chain.order(order_id).await?;
+
+    let mut state = orderbook_state.lock().await;
+    match order {
+        Some(order) => state.reconcile_order(order),
+        // Gone from storage means no longer resting
+        None => {
+            let _ = state.cancel_order(order_id);
+        }
+    }
+    Ok(())
+}
+
+/// Apply an `OrderFilled` event. An order the book doesn't track was placed before the
+/// indexer started or its placement was missed, so its final state is taken from chain
+/// storage instead. Returns whether the order was tracked.
+async fn settle_fill(
+    chain: &impl ChainOrders,
+    orderbook_state: &Mutex<OrderbookState>,
+    changed: &mut ChangedOrders,
+    fill: &runtime::OrderFilled,
+    origin: &EventOrigin,
+) -> bool {
+    let tracked = orderbook_state.lock().await.fill_order(fill.order_id);
+    if tracked {
+        info!("✅ Order #{} marked as filled", fill.order_id);
+    } else {
+        warn!(
+            "⚠️  OrderFilled for untracked order #{} (trader {}) in block {}, reconciling from chain storage",
+            fill.order_id, fill.trader, origin.block_number
+        );
+        telemetry::record_untracked_fill();
+        if let Err(e) = reconcile_order(chain, orderbook_state, fill.order_id).await {
+            warn!("⚠️  Reconciling order #{} failed: {}", fill.order_id, e);
+        }
+    }
+    mark_changed(changed, fill.order_id, origin);
+    tracked
+}
+
+/// Orders changed by the block being applied, each with the first event that touched it
+/// (its placement, for an order placed in the block)
+type ChangedOrders = BTreeMap<u64, EventOrigin>;
+
+fn mark_changed(changed: &mut ChangedOrders, order_id: u64, origin: &EventOrigin) {
+    changed.entry(order_id).or_insert_with(|| origin.clone());
+}
+
+/// Persist the block's changed orders along with `block_number` as the last applied block
+async fn persist_block(
+    pool: &PgPool,
+    config: &IndexerConfig,
+    orderbook_state: &Mutex<OrderbookState>,
+    changed: ChangedOrders,
+    block_number: u32,
+) {
+    let changed: Vec<(OrderInfo, EventOrigin)> = if config.persist_orderbook {
+        let state = orderbook_state.lock().await;
+        changed
+            .into_iter()
+            .filter_map(|(order_id, origin)| Some((state.orders.get(&order_id)?.clone(), origin)))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    if let Err(e) = orders::save_block(pool, &config.default_symbol, &changed, block_number).await {
+        warn!("⚠️  Failed to persist block {}: {}", block_number, e);
+    }
+}
+
+type FinalizedBlock = Block<PolkadotConfig, OnlineClient<PolkadotConfig>>;
+type FinalizedBlocks = Pin<Box<dyn Stream<Item = Result<FinalizedBlock, subxt::Error>> + Send>>;
+
+/// The finalized block at `number` on the canonical chain
+async fn block_at_height(
+    api: &OnlineClient<PolkadotConfig>,
+    rpc: &LegacyRpcMethods<PolkadotConfig>,
+    number: u32,
+) -> Result<FinalizedBlock, subxt::Error> {
+    let hash = rpc
+        .chain_get_block_hash(Some(number.into()))
+        .await?
+        .ok_or_else(|| format!("node has no block at height {}", number))?;
+    api.blocks().at(hash).await
+}
+
+/// Finalized blocks to index after `last_block`.
+///
+/// Blocks finalized since `last_block` are fetched by height up to the first block of the
+/// live subscription, which is followed from there. With no prior block (0) indexing
+/// starts at the current finalized head.
+async fn finalized_blocks(
+    api: &OnlineClient<PolkadotConfig>,
+    rpc: &LegacyRpcMethods<PolkadotConfig>,
+    last_block: u32,
+) -> Result<FinalizedBlocks> {
+    let mut live = api.blocks().subscribe_finalized().await?;
+    if last_block == 0 {
+        info!("🆕 No block indexed yet, starting at the finalized head");
+        return Ok(Box::pin(live));
+    }
+    let Some(head) = live.next().await else {
+        return Ok(Box::pin(live));
+    };
+    let head = head?;
+    let head_number = head.header().number;
+    if head_number <= last_block + 1 {
+        return Ok(Box::pin(stream::iter([Ok(head)]).chain(live)));
+    }
+
+    info!(
+        "⏪ Catching up on blocks {}..{} finalized since block {}",
+        last_block + 1,
+        head_number - 1,
+        last_block
+    );
+    let (api, rpc) = (api.clone(), rpc.clone());
+    let missed = stream::iter(last_block + 1..head_number).then(move |number| {
+        let (api, rpc) = (api.clone(), rpc.clone());
+        async move { block_at_height(&api, &rpc, number).await }
+    });
+    Ok(Box::pin(missed.chain(stream::iter([Ok(head)])).chain(live)))
+}
+
+/// Everything a collector run shares with the rest of the indexer, cloned per connection
+#[derive(Clone)]
+pub struct CollectorContext {
+    pub pool: PgPool,
+    pub orderbook: Arc<Mutex<OrderbookState>>,
+    pub candles: Arc<Mutex<CandleAggregator>>,
+    pub config: Arc<IndexerConfig>,
+    pub progress: Arc<CollectorProgress>,
+    pub backpressure: Arc<Backpressure>,
+    /// Momentum signals from the trade cluster detector
+    pub signals: broadcast::Sender<MomentumSignal>,
+    /// Every stored trade, for the websocket trade feed
+    pub trades: broadcast::Sender<TradeUpdate>,
+    /// Market event webhook, None when not configured
+    pub events: Option<Arc<EventHook>>,
+    /// Cancelled on shutdown; the collector stops after the block in flight
+    pub shutdown: CancellationToken,
+}
+
+/// Index finalized blocks from `node_url` until the connection fails.
+///
+/// Returns an error when the node goes away so the caller can fail over, and Ok once
+/// shutdown was requested and the block in flight is done. A new
+/// connection first indexes the blocks finalized since the last processed one, then
+/// follows the node's finalized head. Before that the node's runtime is checked against
+/// the compiled metadata, a fatal error under `METADATA_CHECK=strict`.
+#[allow(clippy::result_large_err)] // subxt decode errors, matched in place
+pub async fn start(node_url: &str, ctx: CollectorContext) -> Result<()> {
+    let CollectorContext {
+        pool,
+        orderbook: orderbook_state,
+        candles: candle_aggregator,
+        config,
+        progress,
+        backpressure,
+        signals,
+        trades,
+        events,
+        shutdown,
+    } = ctx;
+    let rpc_client = RpcClient::from_url(node_url).await?;
+    let rpc = LegacyRpcMethods::<PolkadotConfig>::new(rpc_client.clone());
+    let api = OnlineClient::<PolkadotConfig>::from_rpc_client(rpc_client).await?;
 
     info!("✅ Connected to chain: {:?}", api.runtime_version());
+    metadata_check::verify(&api, config.metadata_check)?;
+    // Every event is indexed into the default market
+    let scale = config.amount_scale(&config.default_symbol);
+    let chain = LatestStorage { api: &api, scale };
 
-    let mut blocks = api.blocks().subscribe_finalized().await?;
+    if config.seed_from_chain && !progress.seeded.swap(true, Ordering::Relaxed) {
+        match seed_from_chain(&api, &orderbook_state, scale, config.seed_warmup).await {
+            Ok(count) => {
+                info!("🌱 Cold-start seed loaded {} open orders", count);
+                if config.persist_orderbook {
+                    // Seeded orders predate every indexed event
+                    let seeded: Vec<OrderInfo> = orderbook_state
+                        .lock()
+                        .await
+                        .orders
+                        .values()
+                        .cloned()
+                        .collect();
+                    for order in &seeded {
+                        if let Err(e) = orders::save_order(
+                            &pool,
+                            &config.default_symbol,
+                            order,
+                            &EventOrigin::default(),
+                        )
+                        .await
+                        {
+                            warn!(
+                                "⚠️  Failed to persist seeded order #{}: {}",
+                                order.order_id, e
+                            );
+                        }
+                    }
+                }
+            }
+            Err(e) => warn!("⚠️  Cold-start seed from chain storage failed: {}", e),
+        }
+    }
+    progress.mark_seed_complete();
+
+    let mut enricher = trade_enricher::from_config(&config);
+    let mut clusters = (config.cluster_min_trades > 0).then(|| {
+        ClusterDetector::new(
+            config.cluster_window_ms,
+            config.cluster_min_trades,
+            config.cluster_sides,
+            signals.clone(),
+        )
+    });
+
+    let mut blocks = finalized_blocks(&api, &rpc, progress.last_block()).await?;
 
     info!("📡 Listening for events...");
 
-    while let Some(block) = blocks.next().await {
+    loop {
+        // Only stop between blocks, so a block is never left half-applied
+        let next = tokio::select! {
+            _ = shutdown.cancelled() => None,
+            block = async {
+                // Leave further blocks with the node while consumers catch up
+                backpressure.wait_for_capacity().await;
+                blocks.next().await
+            } => Some(block),
+        };
+        let Some(block) = next else {
+            info!("🛑 Collector stopped after block {}", progress.last_block());
+            return Ok(());
+        };
+        let Some(block) = block else {
+            break;
+        };
         let block = block?;
         let block_number = block.header().number;
+        let block_hash = format!("0x{}", hex::encode(block.hash().0));
+        let block_started = Instant::now();
+
+        let last_block = progress.last_block();
+        if block_number <= last_block {
+            debug!("⏭️  Block {} already indexed, skipping", block_number);
+            continue;
+        }
+        if last_block != 0 && block_number > last_block + 1 {
+            warn!(
+                "⚠️  Skipped blocks {}..{} while reconnecting",
+                last_block + 1,
+                block_number
+            );
+        }
 
         info!("📦 Processing block number: {}", block_number);
 
+        // Trades take their block's time rather than the time they are indexed, so blocks
+        // caught up after downtime don't all land in the current candle
+        let timestamp_ms = block
+            .storage()
+            .fetch(&runtime::polkadot::storage().timestamp().now())
+            .await?
+            .map(|now| now as i64);
+
         // Get events directly from block
-        let events = block.events().await?;
+        let block_events = block.events().await?;
+        let mut decode_failures = 0;
+        let mut changed_orders = ChangedOrders::new();
 
         debug!("   EVENTS:");
-        for evt in events.iter() {
+        for (event_index, evt) in block_events.iter().enumerate() {
             let evt = evt?;
+            progress.events_processed.fetch_add(1, Ordering::Relaxed);
+            let event_index = event_index as u32;
+            let origin = EventOrigin {
+                block_number,
+                block_hash: block_hash.clone(),
+                event_index,
+                extrinsic_index: match evt.phase() {
+                    Phase::ApplyExtrinsic(index) => Some(index),
+                    Phase::Initialization | Phase::Finalization => None,
+                },
+                timestamp_ms,
+            };
             let pallet_name = evt.pallet_name();
             let event_name = evt.variant_name();
+            telemetry::record_event(pallet_name, event_name);
 
             // Route to appropriate handler
             match (pallet_name, event_name) {
@@ -46,16 +475,28 @@ pub async fn start(
                     println!("🎯 TradeExecuted event detected!");
 
                     // Decode event using generated types
-                    match evt.as_event::<runtime::TradeExecuted>() {
+                    match telemetry::timed_decode(|| evt.as_event::<runtime::TradeExecuted>()) {
                         Ok(Some(trade_event)) => {
                             // Create context and process trade
                             let mut candle_agg = candle_aggregator.lock().await;
                             let mut ctx = TradeProcessingContext {
                                 pool: &pool,
                                 candle_agg: &mut candle_agg,
+                                orderbook: &orderbook_state,
+                                infer_side: config.infer_trade_side,
+                                skip_duplicates: config.skip_duplicate_trades,
+                                symbol: &config.default_symbol,
+                                scale,
+                                enricher: enricher.as_mut(),
+                                invalid_policy: config.invalid_trade_policy,
+                                capture_origin: config.capture_trade_origin,
+                                clusters: clusters.as_mut(),
+                                events: events.as_deref(),
+                                large_trade_quantity: config.large_trade_quantity,
+                                trades: Some(&trades),
                             };
 
-                            match process_trade(&mut ctx, block_number, &trade_event).await {
+                            match process_trade(&mut ctx, &origin, &trade_event).await {
                                 Ok(_) => {
                                     println!("✅ Trade inserted successfully!");
                                     info!("✅ Trade executed in block {}", block_number);
@@ -69,19 +510,17 @@ pub async fn start(
                             debug!("❌ TradeExecuted event is None (filtered?)");
                         }
                         Err(e) => {
+                            decode_failures += 1;
                             debug!("❌ Failed to decode trade event: {}", e);
                         }
                     }
                 }
                 ("Orderbook", "OrderPlaced") => {
                     info!("📦 Order placed in block {}", block_number);
-                    match evt.as_event::<runtime::OrderPlaced>() {
+                    match telemetry::timed_decode(|| evt.as_event::<runtime::OrderPlaced>()) {
                         Ok(Some(place_order_event)) => {
-                            // Convert u128 to Decimal by dividing by 10^6
-                            let price =
-                                Decimal::from(place_order_event.price) / Decimal::from(1_000_000);
-                            let quantity = Decimal::from(place_order_event.quantity)
-                                / Decimal::from(1_000_000);
+                            let price = scale.price(place_order_event.price);
+                            let quantity = scale.quantity(place_order_event.quantity);
 
                             info!(
                                 "📦 OrderPlaced: id={}, side={}, price={}, qty={}",
@@ -95,17 +534,38 @@ pub async fn start(
                                 quantity,
                                 filled_quantity: Decimal::ZERO,
                                 status: "Open".to_string(),
+                                raw_price: place_order_event.price,
+                                raw_quantity: place_order_event.quantity,
                             };
                             state.add_order(order);
+                            drop(state);
                             info!("✅ Order #{} added to state", place_order_event.order_id);
+                            mark_changed(&mut changed_orders, place_order_event.order_id, &origin);
+
+                            if let Err(e) = record_order_event(
+                                &pool,
+                                &config.default_symbol,
+                                place_order_event.order_id,
+                                OrderEventKind::Placed,
+                                Some(Decimal::ZERO),
+                                block_number,
+                                event_index,
+                            )
+                            .await
+                            {
+                                warn!("⚠️  Failed to record order placement: {}", e);
+                            }
                         }
                         Ok(None) => debug!("❌ OrderPlaced event is None (filtered?)"),
-                        Err(e) => debug!("❌ Failed to parse orderplaced: {}", e),
+                        Err(e) => {
+                            decode_failures += 1;
+                            debug!("❌ Failed to parse orderplaced: {}", e);
+                        }
                     }
                 }
                 ("Orderbook", "OrderCancelled") => {
                     info!("❌ Order cancelled in block {}", block_number);
-                    match evt.as_event::<runtime::OrderCancelled>() {
+                    match telemetry::timed_decode(|| evt.as_event::<runtime::OrderCancelled>()) {
                         Ok(Some(data)) => {
                             println!(
                                 "❌ OrderCancelled: id={}, trader={}",
@@ -113,43 +573,95 @@ pub async fn start(
                             );
 
                             let mut state = orderbook_state.lock().await;
+                            if let Some(order) =
+                                state.orders.get(&data.order_id).filter(|o| !o.is_resting())
+                            {
+                                debug!(
+                                    "⏭️  Order #{} already {}, ignoring cancellation",
+                                    data.order_id, order.status
+                                );
+                                continue;
+                            }
                             let _ = state.cancel_order(data.order_id);
+                            let filled_quantity =
+                                state.orders.get(&data.order_id).map(|o| o.filled_quantity);
+                            drop(state);
                             info!("✅ Order #{} cancelled", data.order_id);
+                            mark_changed(&mut changed_orders, data.order_id, &origin);
+
+                            if let Err(e) = record_order_event(
+                                &pool,
+                                &config.default_symbol,
+                                data.order_id,
+                                OrderEventKind::Cancelled,
+                                filled_quantity,
+                                block_number,
+                                event_index,
+                            )
+                            .await
+                            {
+                                warn!("⚠️  Failed to record order cancellation: {}", e);
+                            }
                         }
                         Ok(None) => debug!("❌ OrderCancelled event is None (filtered?)"),
-                        Err(e) => debug!("❌ Failed to parse orderCancelled: {}", e),
+                        Err(e) => {
+                            decode_failures += 1;
+                            debug!("❌ Failed to parse orderCancelled: {}", e);
+                        }
                     }
                 }
                 ("Orderbook", "OrderFilled") => {
                     info!("✅ Order filled in block {}", block_number);
-                    match evt.as_event::<runtime::OrderFilled>() {
+                    match telemetry::timed_decode(|| evt.as_event::<runtime::OrderFilled>()) {
                         Ok(Some(data)) => {
                             println!(
                                 "✅ OrderFilled: id={}, trader={}",
                                 data.order_id, data.trader
                             );
-                            let mut state = orderbook_state.lock().await;
-                            let quantity =
-                                { state.orders.get(&data.order_id).map(|order| order.quantity) };
+                            settle_fill(
+                                &chain,
+                                &orderbook_state,
+                                &mut changed_orders,
+                                &data,
+                                &origin,
+                            )
+                            .await;
 
-                            // Now use the mutable state, doing this to fix clash of mut and immut borrow from before
-                            if let Some(qty) = quantity {
-                                let _ = state.update_order(data.order_id, qty, "Filled");
+                            let filled_quantity = orderbook_state
+                                .lock()
+                                .await
+                                .orders
+                                .get(&data.order_id)
+                                .map(|o| o.filled_quantity);
+                            if let Err(e) = record_order_event(
+                                &pool,
+                                &config.default_symbol,
+                                data.order_id,
+                                OrderEventKind::Filled,
+                                filled_quantity,
+                                block_number,
+                                event_index,
+                            )
+                            .await
+                            {
+                                warn!("⚠️  Failed to record order fill: {}", e);
                             }
-                            info!("✅ Order #{} marked as filled", data.order_id);
                         }
                         Ok(None) => debug!("❌ OrderFilled event is None (filtered?)"),
-                        Err(e) => debug!("❌ Failed to parse order filled: {}", e),
+                        Err(e) => {
+                            decode_failures += 1;
+                            debug!("❌ Failed to parse order filled: {}", e);
+                        }
                     }
                 }
                 ("Orderbook", "OrderPartiallyFilled") => {
-                    match evt.as_event::<runtime::OrderPartiallyFilled>() {
+                    match telemetry::timed_decode(|| {
+                        evt.as_event::<runtime::OrderPartiallyFilled>()
+                    }) {
                         Ok(Some(data)) => {
-                            // Convert u128 to Decimal by dividing by 10^6
-                            let filled_quantity =
-                                Decimal::from(data.filled_quantity) / Decimal::from(1_000_000);
-                            let remaining_quantity =
-                                Decimal::from(data.remaining_quantity) / Decimal::from(1_000_000);
+                            // Both are running totals of the order, not of this match
+                            let filled_quantity = scale.quantity(data.filled_quantity);
+                            let remaining_quantity = scale.quantity(data.remaining_quantity);
 
                             println!(
                                 "📊 OrderPartiallyFilled: id={}, filled={}, remaining={}",
@@ -157,20 +669,68 @@ pub async fn start(
                             );
 
                             let mut state = orderbook_state.lock().await;
+                            let mismatch = state.fill_mismatch(
+                                data.order_id,
+                                filled_quantity,
+                                remaining_quantity,
+                                config.fill_mismatch_tolerance,
+                            );
                             let _ = state.update_order(
                                 data.order_id,
                                 filled_quantity,
                                 "PartiallyFilled",
                             );
+                            drop(state);
                             info!(
                                 "✅ Order #{} partially filled ({}/{})",
                                 data.order_id,
                                 filled_quantity,
                                 filled_quantity + remaining_quantity
                             );
+
+                            if let Some(difference) = mismatch {
+                                warn!(
+                                    "⚠️  Order #{} fill mismatch in block {}: filled {} + remaining {} is off the tracked quantity by {}",
+                                    data.order_id,
+                                    block_number,
+                                    filled_quantity,
+                                    remaining_quantity,
+                                    difference
+                                );
+                                telemetry::record_fill_mismatch();
+                                if config.reconcile_on_fill_mismatch {
+                                    if let Err(e) =
+                                        reconcile_order(&chain, &orderbook_state, data.order_id)
+                                            .await
+                                    {
+                                        warn!(
+                                            "⚠️  Reconciling order #{} failed: {}",
+                                            data.order_id, e
+                                        );
+                                    }
+                                }
+                            }
+                            mark_changed(&mut changed_orders, data.order_id, &origin);
+
+                            if let Err(e) = record_order_event(
+                                &pool,
+                                &config.default_symbol,
+                                data.order_id,
+                                OrderEventKind::PartiallyFilled,
+                                Some(filled_quantity),
+                                block_number,
+                                event_index,
+                            )
+                            .await
+                            {
+                                warn!("⚠️  Failed to record partial fill: {}", e);
+                            }
                         }
                         Ok(None) => debug!("❌ OrderPartiallyFilled event is None (filtered?)"),
-                        Err(e) => debug!("❌ Failed: {}", e),
+                        Err(e) => {
+                            decode_failures += 1;
+                            debug!("❌ Failed: {}", e);
+                        }
                     }
                 }
                 _ => {
@@ -178,7 +738,130 @@ pub async fn start(
                 }
             }
         }
+
+        if config.decode_failure_alert > 0 && decode_failures >= config.decode_failure_alert {
+            warn!(
+                "⚠️  {} events failed to decode in block {}",
+                decode_failures, block_number
+            );
+            if let Some(events) = &events {
+                events.fire(MarketEvent::DecodeFailures {
+                    block_number,
+                    failures: decode_failures,
+                });
+            }
+        }
+
+        persist_block(
+            &pool,
+            &config,
+            &orderbook_state,
+            changed_orders,
+            block_number,
+        )
+        .await;
+        // Only now is the block done: one that failed halfway is fetched again on reconnect
+        progress.last_block.store(block_number, Ordering::Relaxed);
+
+        telemetry::observe_block(block_started.elapsed());
+        telemetry::record_block_processed();
+        telemetry::set_open_orders(orderbook_state.lock().await.open_order_count());
+        progress.blocks_processed.fetch_add(1, Ordering::Relaxed);
+        progress.mark_block_complete();
     }
 
-    Ok(())
+    bail!("finalized block subscription to {} ended", node_url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    /// Chain storage holding a fixed set of orders
+    struct StubChain(HashMap<u64, OrderInfo>);
+
+    impl ChainOrders for StubChain {
+        async fn order(&self, order_id: u64) -> Result<Option<OrderInfo>> {
+            Ok(self.0.get(&order_id).cloned())
+        }
+    }
+
+    fn order(order_id: u64, side: &str, price: i64, quantity: i64) -> OrderInfo {
+        OrderInfo {
+            order_id,
+            side: side.to_string(),
+            price: Decimal::from(price),
+            quantity: Decimal::from(quantity),
+            filled_quantity: Decimal::ZERO,
+            status: "Open".to_string(),
+            raw_price: price as u128,
+            raw_quantity: quantity as u128,
+        }
+    }
+
+    fn filled(order_id: u64) -> runtime::OrderFilled {
+        runtime::OrderFilled {
+            order_id,
+            trader: subxt::utils::AccountId32([1; 32]),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_not_ready_until_seed_completes() {
+        let progress = Arc::new(CollectorProgress::default());
+        let waiter = tokio::spawn({
+            let progress = progress.clone();
+            async move { progress.wait_ready().await }
+        });
+
+        // A block processed before the seed finished does not make the book ready
+        progress.mark_block_complete();
+        assert!(!progress.is_ready());
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!waiter.is_finished());
+
+        progress.mark_seed_complete();
+        assert!(progress.is_ready());
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("waiter should resolve once ready")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_untracked_fill_settles_from_chain_storage() {
+        let mut on_chain = order(2, "Buy", 100, 5);
+        on_chain.filled_quantity = Decimal::from(5);
+        on_chain.status = "Filled".to_string();
+        let chain = StubChain(HashMap::from([(2, on_chain)]));
+
+        let state = Mutex::new(OrderbookState::new());
+        state.lock().await.add_order(order(1, "Sell", 101, 3));
+        let origin = EventOrigin {
+            block_number: 7,
+            ..Default::default()
+        };
+        let mut changed = ChangedOrders::new();
+
+        // A tracked order is filled from the book alone
+        assert!(settle_fill(&chain, &state, &mut changed, &filled(1), &origin).await);
+        assert_eq!(state.lock().await.orders[&1].status, "Filled");
+
+        // An untracked one takes its final state from chain storage, leaving the levels alone
+        assert!(!settle_fill(&chain, &state, &mut changed, &filled(2), &origin).await);
+        {
+            let state = state.lock().await;
+            assert_eq!(state.orders[&2].status, "Filled");
+            assert!(state.bids.is_empty() && state.asks.is_empty());
+        }
+
+        // One no longer in storage stays off the book
+        assert!(!settle_fill(&chain, &state, &mut changed, &filled(3), &origin).await);
+        assert!(!state.lock().await.orders.contains_key(&3));
+
+        assert_eq!(changed.keys().copied().collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(changed[&2].block_number, 7);
+    }
 }
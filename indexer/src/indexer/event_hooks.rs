@@ -0,0 +1,332 @@
+use crate::config::IndexerConfig;
+use crate::indexer::event_collector::CollectorProgress;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Market events a webhook can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MarketEventKind {
+    /// A trade at or above `LARGE_TRADE_QUANTITY`
+    LargeTrade,
+    /// No finalized block indexed for `STALL_ALERT_SECS`
+    IndexerStall,
+    /// At least `DECODE_FAILURE_ALERT` events in one block failed to decode
+    DecodeFailures,
+}
+
+impl MarketEventKind {
+    pub const ALL: [MarketEventKind; 3] = [
+        MarketEventKind::LargeTrade,
+        MarketEventKind::IndexerStall,
+        MarketEventKind::DecodeFailures,
+    ];
+
+    /// Parse "large_trade", "indexer_stall" or "decode_failures" (case-insensitive)
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind.trim().to_ascii_lowercase().as_str() {
+            "large_trade" => Some(MarketEventKind::LargeTrade),
+            "indexer_stall" => Some(MarketEventKind::IndexerStall),
+            "decode_failures" => Some(MarketEventKind::DecodeFailures),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a comma-separated event list, skipping unknown names; "all" selects every event
+pub fn parse_event_kinds(kinds: &str) -> Vec<MarketEventKind> {
+    if kinds.trim().eq_ignore_ascii_case("all") {
+        return MarketEventKind::ALL.to_vec();
+    }
+    let mut parsed = Vec::new();
+    for kind in kinds.split(',').filter(|kind| !kind.trim().is_empty()) {
+        match MarketEventKind::parse(kind) {
+            Some(kind) if !parsed.contains(&kind) => parsed.push(kind),
+            Some(_) => {}
+            None => warn!("Ignoring unknown webhook event {:?}", kind),
+        }
+    }
+    parsed
+}
+
+/// Body POSTed to the event webhook
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum MarketEvent {
+    LargeTrade {
+        symbol: String,
+        trade_id: u128,
+        price: Decimal,
+        quantity: Decimal,
+        side: Option<String>,
+        block_number: u32,
+    },
+    IndexerStall {
+        /// Last finalized block indexed, 0 when none was
+        last_block: u32,
+        stalled_secs: u64,
+    },
+    DecodeFailures {
+        block_number: u32,
+        failures: usize,
+    },
+}
+
+impl MarketEvent {
+    pub fn kind(&self) -> MarketEventKind {
+        match self {
+            MarketEvent::LargeTrade { .. } => MarketEventKind::LargeTrade,
+            MarketEvent::IndexerStall { .. } => MarketEventKind::IndexerStall,
+            MarketEvent::DecodeFailures { .. } => MarketEventKind::DecodeFailures,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    #[serde(flatten)]
+    event: &'a MarketEvent,
+    /// Milliseconds since epoch
+    time: i64,
+}
+
+/// POSTs subscribed market events as JSON to a configured URL (`EVENT_WEBHOOK_URL`).
+///
+/// Deliveries run on spawned tasks so indexing never waits on the receiver. A request
+/// that fails or gets a server error is retried with doubling backoff; client errors are
+/// final.
+pub struct EventHook {
+    client: reqwest::Client,
+    url: String,
+    events: Vec<MarketEventKind>,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl EventHook {
+    pub fn new(url: String, events: Vec<MarketEventKind>, timeout: Duration, retries: u32) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap_or_else(|e| {
+                warn!("Event webhook client without timeout: {}", e);
+                reqwest::Client::new()
+            });
+        Self {
+            client,
+            url,
+            events,
+            retries,
+            backoff: Duration::from_millis(500),
+        }
+    }
+
+    /// The hook described by `EVENT_WEBHOOK_*`, None when no URL is set
+    pub fn from_config(config: &IndexerConfig) -> Option<Self> {
+        let url = config.event_webhook_url.clone()?;
+        info!(
+            "🪝 Posting {:?} events to the event webhook",
+            config.event_webhook_events
+        );
+        Some(Self::new(
+            url,
+            config.event_webhook_events.clone(),
+            Duration::from_millis(config.event_webhook_timeout_ms),
+            config.event_webhook_retries,
+        ))
+    }
+
+    pub fn subscribes(&self, kind: MarketEventKind) -> bool {
+        self.events.contains(&kind)
+    }
+
+    /// Deliver `event` in the background if the hook subscribes to it
+    pub fn fire(&self, event: MarketEvent) {
+        if !self.subscribes(event.kind()) {
+            return;
+        }
+        let client = self.client.clone();
+        let url = self.url.clone();
+        let (retries, mut backoff) = (self.retries, self.backoff);
+        tokio::spawn(async move {
+            let payload = Payload {
+                event: &event,
+                time: chrono::Utc::now().timestamp_millis(),
+            };
+            for attempt in 0..=retries {
+                if attempt > 0 {
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                match client.post(&url).json(&payload).send().await {
+                    Ok(response) if response.status().is_success() => return,
+                    Ok(response) if !response.status().is_server_error() => {
+                        warn!(
+                            "Event webhook rejected {:?} with {}",
+                            event.kind(),
+                            response.status()
+                        );
+                        return;
+                    }
+                    Ok(response) => warn!(
+                        "Event webhook returned {} for {:?} (attempt {})",
+                        response.status(),
+                        event.kind(),
+                        attempt + 1
+                    ),
+                    Err(e) => warn!(
+                        "Event webhook failed for {:?} (attempt {}): {}",
+                        event.kind(),
+                        attempt + 1,
+                        e
+                    ),
+                }
+            }
+            warn!(
+                "⚠️  Gave up delivering {:?} to the event webhook",
+                event.kind()
+            );
+        });
+    }
+}
+
+/// Fire `IndexerStall` when no finalized block was indexed for `threshold`, once per stall.
+///
+/// Checks once per `threshold`, so a stall is reported between one and two thresholds
+/// after the last block.
+pub async fn run_stall_watch(
+    progress: Arc<CollectorProgress>,
+    hook: Arc<EventHook>,
+    threshold: Duration,
+) {
+    let mut ticker = tokio::time::interval(threshold);
+    ticker.tick().await;
+    let mut last_seen = progress.last_block();
+    let mut stalled_for = Duration::ZERO;
+
+    loop {
+        ticker.tick().await;
+        let last_block = progress.last_block();
+        if last_block != last_seen {
+            last_seen = last_block;
+            stalled_for = Duration::ZERO;
+            continue;
+        }
+        stalled_for += threshold;
+        if stalled_for == threshold {
+            warn!(
+                "⚠️  No block indexed for {}s (last block {})",
+                stalled_for.as_secs(),
+                last_block
+            );
+            hook.fire(MarketEvent::IndexerStall {
+                last_block,
+                stalled_secs: stalled_for.as_secs(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+    use serde_json::Value;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::mpsc;
+
+    /// Local HTTP server answering the first `failures` requests with a 503, returning its
+    /// URL and every body it received
+    pub(crate) async fn mock_receiver(failures: usize) -> (String, mpsc::UnboundedReceiver<Value>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let seen = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route(
+                "/hook",
+                post(
+                    move |State((tx, seen)): State<(
+                        mpsc::UnboundedSender<Value>,
+                        Arc<AtomicUsize>,
+                    )>,
+                          Json(body): Json<Value>| async move {
+                        if seen.fetch_add(1, Ordering::SeqCst) < failures {
+                            return StatusCode::SERVICE_UNAVAILABLE;
+                        }
+                        let _ = tx.send(body);
+                        StatusCode::OK
+                    },
+                ),
+            )
+            .with_state((tx, seen));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}/hook", addr), rx)
+    }
+
+    fn hook(url: String, events: Vec<MarketEventKind>) -> EventHook {
+        EventHook {
+            backoff: Duration::from_millis(10),
+            ..EventHook::new(url, events, Duration::from_secs(2), 3)
+        }
+    }
+
+    fn large_trade(quantity: i64) -> MarketEvent {
+        MarketEvent::LargeTrade {
+            symbol: "ETH/USDT".to_string(),
+            trade_id: 7,
+            price: Decimal::from(2000),
+            quantity: Decimal::from(quantity),
+            side: Some("Buy".to_string()),
+            block_number: 12,
+        }
+    }
+
+    pub(crate) async fn next(rx: &mut mpsc::UnboundedReceiver<Value>) -> Value {
+        tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .expect("webhook should be delivered")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_are_retried() {
+        let (url, mut rx) = mock_receiver(2).await;
+        hook(url, MarketEventKind::ALL.to_vec()).fire(large_trade(50));
+
+        let body = next(&mut rx).await;
+        assert_eq!(body["event"], "large_trade");
+        assert_eq!(body["trade_id"], 7);
+        assert_eq!(body["quantity"], "50");
+        assert_eq!(body["side"], "Buy");
+        assert!(body["time"].as_i64().unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_unsubscribed_events_are_not_posted() {
+        let (url, mut rx) = mock_receiver(0).await;
+        let hook = hook(url, vec![MarketEventKind::IndexerStall]);
+        hook.fire(large_trade(50));
+        hook.fire(MarketEvent::IndexerStall {
+            last_block: 3,
+            stalled_secs: 60,
+        });
+
+        // Only the stall arrives
+        let body = next(&mut rx).await;
+        assert_eq!(body["event"], "indexer_stall");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_parse_event_kinds() {
+        assert_eq!(parse_event_kinds("all"), MarketEventKind::ALL.to_vec());
+        assert_eq!(
+            parse_event_kinds("Large_Trade, bogus,large_trade,decode_failures"),
+            vec![MarketEventKind::LargeTrade, MarketEventKind::DecodeFailures]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use crate::config::IndexerConfig;
+use crate::indexer::orderbook_reducer::OrderbookState;
+use rust_decimal::Decimal;
+use std::sync::Arc;
+use tracing::warn;
+
+/// Pluggable index (mark) price computation.
+///
+/// Unlike the last trade price, an index price is derived from current market state and
+/// is what liquidation marks are based on. Implementations can combine the book with
+/// external reference prices they hold themselves. `None` means no index can be
+/// computed right now, e.g. because one side of the book is empty.
+pub trait IndexPricer: Send + Sync {
+    /// Method name reported alongside the price (e.g. "mid")
+    fn name(&self) -> &'static str;
+    fn index_price(&self, book: &OrderbookState) -> Option<Decimal>;
+}
+
+/// Reference (fair value) price derived from the top of book
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReferencePrice {
+    /// `(bid + ask) / 2`
+    #[default]
+    Mid,
+    /// Mid weighted by the opposite touch sizes, see [`OrderbookState::microprice`]
+    Microprice,
+}
+
+impl ReferencePrice {
+    /// Parse "mid" or "microprice" (case-insensitive)
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode.trim().to_ascii_lowercase().as_str() {
+            "mid" => Some(Self::Mid),
+            "microprice" => Some(Self::Microprice),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Mid => "mid",
+            Self::Microprice => "microprice",
+        }
+    }
+
+    /// Reference price of the book, None without two-sided liquidity
+    pub fn price(&self, book: &OrderbookState) -> Option<Decimal> {
+        let price = match self {
+            Self::Mid => book.mid_price(),
+            Self::Microprice => book.microprice(),
+        };
+        price.map(|price| price.normalize())
+    }
+}
+
+/// Default index: midpoint of the best bid and best ask
+#[derive(Debug, Default)]
+pub struct MidIndex;
+
+impl IndexPricer for MidIndex {
+    fn name(&self) -> &'static str {
+        "mid"
+    }
+
+    fn index_price(&self, book: &OrderbookState) -> Option<Decimal> {
+        ReferencePrice::Mid.price(book)
+    }
+}
+
+/// Index at the top-of-book microprice
+#[derive(Debug, Default)]
+pub struct MicropriceIndex;
+
+impl IndexPricer for MicropriceIndex {
+    fn name(&self) -> &'static str {
+        "microprice"
+    }
+
+    fn index_price(&self, book: &OrderbookState) -> Option<Decimal> {
+        ReferencePrice::Microprice.price(book)
+    }
+}
+
+/// Build the pricer selected by `INDEX_PRICE` ("mid" or "microprice")
+pub fn from_config(config: &IndexerConfig) -> Arc<dyn IndexPricer> {
+    match ReferencePrice::parse(&config.index_price) {
+        Some(ReferencePrice::Mid) => Arc::new(MidIndex),
+        Some(ReferencePrice::Microprice) => Arc::new(MicropriceIndex),
+        None => {
+            warn!("Unknown INDEX_PRICE {:?}, using mid", config.index_price);
+            Arc::new(MidIndex)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::orderbook_reducer::OrderInfo;
+
+    fn order(order_id: u64, side: &str, price: i64) -> OrderInfo {
+        sized(order_id, side, price, 1)
+    }
+
+    fn sized(order_id: u64, side: &str, price: i64, quantity: i64) -> OrderInfo {
+        OrderInfo {
+            order_id,
+            side: side.to_string(),
+            price: Decimal::from(price),
+            quantity: Decimal::from(quantity),
+            filled_quantity: Decimal::ZERO,
+            status: "Open".to_string(),
+            raw_price: price as u128 * 1_000_000,
+            raw_quantity: quantity as u128 * 1_000_000,
+        }
+    }
+
+    #[test]
+    fn test_mid_index_and_no_liquidity() {
+        let mut book = OrderbookState::new();
+        assert_eq!(MidIndex.index_price(&book), None);
+
+        book.add_order(order(1, "Buy", 99));
+        // One-sided book has no mid
+        assert_eq!(MidIndex.index_price(&book), None);
+
+        book.add_order(order(2, "Sell", 102));
+        book.add_order(order(3, "Buy", 98));
+        assert_eq!(MidIndex.index_price(&book), Some(Decimal::new(1005, 1)));
+    }
+
+    #[test]
+    fn test_microprice_leans_towards_thin_side() {
+        let mut book = OrderbookState::new();
+        // 9 bid vs 1 ask: buying pressure pulls fair value towards the ask
+        book.add_order(sized(1, "Buy", 100, 9));
+        book.add_order(sized(2, "Sell", 102, 1));
+
+        assert_eq!(ReferencePrice::Mid.price(&book), Some(Decimal::from(101)));
+        assert_eq!(
+            ReferencePrice::Microprice.price(&book),
+            Some(Decimal::new(1018, 1))
+        );
+        assert_eq!(
+            MicropriceIndex.index_price(&book),
+            Some(Decimal::new(1018, 1))
+        );
+
+        // Both touch orders fully filled: no size to weight by, fall back to the mid
+        book.orders.get_mut(&1).unwrap().filled_quantity = Decimal::from(9);
+        book.orders.get_mut(&2).unwrap().filled_quantity = Decimal::ONE;
+        assert_eq!(
+            ReferencePrice::Microprice.price(&book),
+            Some(Decimal::from(101))
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use crate::indexer::node_failover::Fatal;
+use crate::indexer::runtime::polkadot;
+use anyhow::Result;
+use subxt::ext::codec::Decode;
+use subxt::{Metadata, OnlineClient, PolkadotConfig};
+use tracing::{info, warn};
+
+/// Metadata the runtime types in `runtime.rs` were generated from
+const COMPILED_METADATA: &[u8] = include_bytes!("../../../metadata.scale");
+
+/// What to do when the node's runtime no longer matches the compiled metadata
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MetadataCheck {
+    /// Skip the check
+    Off,
+    /// Log a warning and index anyway
+    #[default]
+    Warn,
+    /// Refuse to index from the node
+    Strict,
+}
+
+impl MetadataCheck {
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode.trim().to_ascii_lowercase().as_str() {
+            "off" => Some(Self::Off),
+            "warn" => Some(Self::Warn),
+            "strict" => Some(Self::Strict),
+            _ => None,
+        }
+    }
+}
+
+/// The compiled metadata compared against a node's runtime
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeComparison {
+    /// Spec version recorded in the compiled metadata, None if it can't be read
+    pub compiled_spec_version: Option<u32>,
+    pub node_spec_version: u32,
+    /// The node's pallets and runtime APIs hash the same as the ones the types were
+    /// generated from
+    pub codegen_valid: bool,
+}
+
+impl RuntimeComparison {
+    /// Compare the compiled metadata with the runtime `api` is connected to
+    pub fn for_node(api: &OnlineClient<PolkadotConfig>) -> Self {
+        Self {
+            compiled_spec_version: compiled_spec_version(),
+            node_spec_version: api.runtime_version().spec_version,
+            codegen_valid: polkadot::is_codegen_valid_for(&api.metadata()),
+        }
+    }
+
+    /// Why events from this node may fail to decode, None when the types still fit.
+    ///
+    /// Only a hash mismatch counts: a runtime upgrade that left the indexed pallets alone
+    /// bumps the spec version without breaking decoding.
+    pub fn mismatch(&self) -> Option<String> {
+        if self.codegen_valid {
+            return None;
+        }
+        let compiled = self
+            .compiled_spec_version
+            .map_or_else(|| "unknown".to_string(), |version| version.to_string());
+        Some(format!(
+            "node runtime (spec version {}) does not match the compiled metadata (spec version {}); regenerate metadata.scale",
+            self.node_spec_version, compiled
+        ))
+    }
+}
+
+/// `spec_version` from the `System::Version` constant of the compiled metadata
+fn compiled_spec_version() -> Option<u32> {
+    let metadata = Metadata::decode(&mut &COMPILED_METADATA[..]).ok()?;
+    let version = metadata
+        .pallet_by_name("System")?
+        .constant_by_name("Version")?
+        .value();
+    // RuntimeVersion starts with spec_name, impl_name, authoring_version, spec_version
+    let mut input = version;
+    String::decode(&mut input).ok()?;
+    String::decode(&mut input).ok()?;
+    u32::decode(&mut input).ok()?;
+    u32::decode(&mut input).ok()
+}
+
+/// Check the node behind `api` before any block is indexed. A mismatch is logged, or in
+/// strict mode returned as a fatal error that stops the indexer.
+pub fn verify(api: &OnlineClient<PolkadotConfig>, mode: MetadataCheck) -> Result<()> {
+    if mode == MetadataCheck::Off {
+        return Ok(());
+    }
+    verify_comparison(&RuntimeComparison::for_node(api), mode)
+}
+
+fn verify_comparison(comparison: &RuntimeComparison, mode: MetadataCheck) -> Result<()> {
+    let Some(mismatch) = comparison.mismatch() else {
+        if comparison.compiled_spec_version != Some(comparison.node_spec_version) {
+            info!(
+                "🧬 Node runs spec version {}, indexed pallets unchanged",
+                comparison.node_spec_version
+            );
+        }
+        return Ok(());
+    };
+    match mode {
+        MetadataCheck::Strict => Err(Fatal(mismatch).into()),
+        _ => {
+            warn!("⚠️⚠️⚠️  Metadata mismatch: {}", mismatch);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comparison(node_spec_version: u32, codegen_valid: bool) -> RuntimeComparison {
+        RuntimeComparison {
+            compiled_spec_version: compiled_spec_version(),
+            node_spec_version,
+            codegen_valid,
+        }
+    }
+
+    #[test]
+    fn test_compiled_spec_version_is_readable() {
+        assert!(compiled_spec_version().is_some());
+    }
+
+    #[test]
+    fn test_mismatch_detected() {
+        let compiled = compiled_spec_version().unwrap();
+        let upgraded = comparison(compiled + 1, false);
+        let mismatch = upgraded.mismatch().unwrap();
+        assert!(mismatch.contains(&format!("spec version {}", compiled + 1)));
+
+        // Strict mode stops the indexer, warn mode carries on
+        let error = verify_comparison(&upgraded, MetadataCheck::Strict).unwrap_err();
+        assert!(error.is::<Fatal>());
+        assert!(verify_comparison(&upgraded, MetadataCheck::Warn).is_ok());
+
+        // A newer runtime whose indexed pallets are unchanged still decodes
+        assert!(comparison(compiled + 1, true).mismatch().is_none());
+        assert!(verify_comparison(&comparison(compiled, true), MetadataCheck::Strict).is_ok());
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(MetadataCheck::parse("Strict"), Some(MetadataCheck::Strict));
+        assert_eq!(MetadataCheck::parse("off"), Some(MetadataCheck::Off));
+        assert_eq!(MetadataCheck::parse("fail"), None);
+    }
+}
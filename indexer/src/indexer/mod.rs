@@ -1,5 +1,15 @@
+pub mod backpressure;
+pub mod book_sampler;
 pub mod candle_aggregator;
 pub mod event_collector;
+pub mod event_hooks;
+pub mod index_price;
+pub mod metadata_check;
+pub mod node_failover;
+pub mod order_events;
 pub mod orderbook_reducer;
 pub mod runtime;
+pub mod scaling;
+pub mod trade_clusters;
+pub mod trade_enricher;
 pub mod trade_mapper;
@@ -0,0 +1,294 @@
+use anyhow::Result;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// Node endpoints in failover order, tracking which one is in use
+#[derive(Debug)]
+pub struct NodeEndpoints {
+    urls: Vec<String>,
+    active: AtomicUsize,
+    failovers: AtomicU64,
+}
+
+impl NodeEndpoints {
+    pub fn new(urls: Vec<String>) -> Self {
+        assert!(!urls.is_empty(), "at least one node endpoint is required");
+        Self {
+            urls,
+            active: AtomicUsize::new(0),
+            failovers: AtomicU64::new(0),
+        }
+    }
+
+    pub fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    /// Endpoint currently connected to, or being connected to
+    pub fn active(&self) -> &str {
+        &self.urls[self.active.load(Ordering::Relaxed)]
+    }
+
+    /// Number of times we moved off a failing endpoint
+    pub fn failovers(&self) -> u64 {
+        self.failovers.load(Ordering::Relaxed)
+    }
+
+    /// Switch to the next endpoint, returning true when that wraps back to the first
+    fn rotate(&self) -> bool {
+        let next = (self.active.load(Ordering::Relaxed) + 1) % self.urls.len();
+        self.active.store(next, Ordering::Relaxed);
+        self.failovers.fetch_add(1, Ordering::Relaxed);
+        next == 0
+    }
+}
+
+/// An error failing over can't fix; `run_with_failover` returns it instead of retrying
+#[derive(Debug)]
+pub struct Fatal(pub String);
+
+impl std::fmt::Display for Fatal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Fatal {}
+
+/// Delay before reconnecting once every endpoint failed, doubling per failed round
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    /// Delay after the first failed round
+    pub base: Duration,
+    /// Upper bound on the delay
+    pub cap: Duration,
+}
+
+impl Backoff {
+    /// Delay after `round` consecutive failed rounds (0 for the first)
+    pub fn delay(&self, round: u32) -> Duration {
+        self.base
+            .saturating_mul(2u32.saturating_pow(round))
+            .min(self.cap)
+    }
+}
+
+/// Run `connect_and_run` against the active endpoint, rotating through the rest whenever
+/// it fails, until one run completes successfully or `shutdown` is cancelled. A [`Fatal`]
+/// error ends the loop and is returned.
+///
+/// Each endpoint is tried in turn before the first is retried, and the backoff is only
+/// waited after a full round failed, so a healthy standby is picked up immediately. A
+/// run that stayed up for longer than `backoff.cap` resets the backoff to its base.
+pub async fn run_with_failover<F, Fut>(
+    endpoints: &NodeEndpoints,
+    backoff: Backoff,
+    shutdown: &CancellationToken,
+    mut connect_and_run: F,
+) -> Result<()>
+where
+    F: FnMut(String) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let mut failed_rounds = 0;
+    loop {
+        let url = endpoints.active().to_string();
+        info!("🔌 Connecting to node at {}", url);
+
+        let started = Instant::now();
+        match connect_and_run(url.clone()).await {
+            Ok(()) => return Ok(()),
+            Err(e) if e.is::<Fatal>() => return Err(e),
+            Err(e) => {
+                if started.elapsed() > backoff.cap {
+                    failed_rounds = 0;
+                }
+                let wrapped = endpoints.rotate();
+                warn!(
+                    "⚠️  Node {} failed: {}, failing over to {}",
+                    url,
+                    e,
+                    endpoints.active()
+                );
+                if wrapped {
+                    let delay = backoff.delay(failed_rounds);
+                    failed_rounds += 1;
+                    info!(
+                        "⏳ Every node failed, reconnect attempt {} in {:?}",
+                        failed_rounds, delay
+                    );
+                    tokio::select! {
+                        _ = shutdown.cancelled() => return Ok(()),
+                        _ = tokio::time::sleep(delay) => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::ws::WebSocketUpgrade, routing::get, Router};
+    use std::sync::{Arc, Mutex};
+    use subxt::backend::{legacy::LegacyRpcMethods, rpc::RpcClient};
+    use subxt::PolkadotConfig;
+
+    const NO_BACKOFF: Backoff = Backoff {
+        base: Duration::ZERO,
+        cap: Duration::ZERO,
+    };
+
+    /// Node that completes the WebSocket handshake and then hangs up, counting connections
+    async fn dropping_node() -> (String, Arc<AtomicUsize>) {
+        let connections = Arc::new(AtomicUsize::new(0));
+        let app = Router::new().route(
+            "/",
+            get({
+                let connections = connections.clone();
+                move |ws: WebSocketUpgrade| async move {
+                    connections.fetch_add(1, Ordering::SeqCst);
+                    ws.on_upgrade(|socket| async move { drop(socket) })
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("ws://{}", addr), connections)
+    }
+
+    #[tokio::test]
+    async fn test_rotates_through_endpoints_on_failure() {
+        let endpoints = NodeEndpoints::new(vec![
+            "ws://a:9944".to_string(),
+            "ws://b:9944".to_string(),
+            "ws://c:9944".to_string(),
+        ]);
+        let attempts = Mutex::new(Vec::new());
+
+        // Every endpoint fails once, then the first one comes back
+        run_with_failover(&endpoints, NO_BACKOFF, &CancellationToken::new(), |url| {
+            let mut attempts = attempts.lock().unwrap();
+            attempts.push(url);
+            let fail = attempts.len() <= 3;
+            async move {
+                if fail {
+                    anyhow::bail!("connection lost");
+                }
+                Ok(())
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            *attempts.lock().unwrap(),
+            vec!["ws://a:9944", "ws://b:9944", "ws://c:9944", "ws://a:9944"]
+        );
+        assert_eq!(endpoints.active(), "ws://a:9944");
+        assert_eq!(endpoints.failovers(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_stays_on_standby_that_works() {
+        let endpoints =
+            NodeEndpoints::new(vec!["ws://a:9944".to_string(), "ws://b:9944".to_string()]);
+
+        run_with_failover(
+            &endpoints,
+            NO_BACKOFF,
+            &CancellationToken::new(),
+            |url| async move {
+                if url == "ws://a:9944" {
+                    anyhow::bail!("refused");
+                }
+                Ok(())
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(endpoints.active(), "ws://b:9944");
+        assert_eq!(endpoints.failovers(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fatal_errors_are_not_retried() {
+        let endpoints =
+            NodeEndpoints::new(vec!["ws://a:9944".to_string(), "ws://b:9944".to_string()]);
+        let error = run_with_failover(
+            &endpoints,
+            NO_BACKOFF,
+            &CancellationToken::new(),
+            |_| async { Err(Fatal("wrong runtime".to_string()).into()) },
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(error.to_string(), "wrong runtime");
+        assert_eq!(endpoints.failovers(), 0);
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap() {
+        let backoff = Backoff {
+            base: Duration::from_secs(1),
+            cap: Duration::from_secs(10),
+        };
+        let delays: Vec<u64> = (0..6).map(|round| backoff.delay(round).as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 10, 10]);
+        assert_eq!(backoff.delay(u32::MAX), Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_after_node_drops_connection() {
+        let (url, connections) = dropping_node().await;
+        let endpoints = NodeEndpoints::new(vec![url]);
+        let backoff = Backoff {
+            base: Duration::from_millis(1),
+            cap: Duration::from_millis(20),
+        };
+
+        // Keep reconnecting until the node was reached three times
+        run_with_failover(&endpoints, backoff, &CancellationToken::new(), |url| {
+            let connections = connections.clone();
+            async move {
+                if connections.load(Ordering::SeqCst) >= 3 {
+                    return Ok(());
+                }
+                let rpc = LegacyRpcMethods::<PolkadotConfig>::new(RpcClient::from_url(&url).await?);
+                rpc.chain_get_block_hash(None).await?;
+                anyhow::bail!("node answered")
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(connections.load(Ordering::SeqCst), 3);
+        assert_eq!(endpoints.failovers(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_interrupts_backoff() {
+        let endpoints = NodeEndpoints::new(vec!["ws://a:9944".to_string()]);
+        let shutdown = CancellationToken::new();
+        let backoff = Backoff {
+            base: Duration::from_secs(3600),
+            cap: Duration::from_secs(3600),
+        };
+
+        let run = run_with_failover(&endpoints, backoff, &shutdown, |_| async {
+            anyhow::bail!("refused")
+        });
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(1), run)
+            .await
+            .expect("shutdown should end the backoff wait")
+            .unwrap();
+    }
+}
@@ -0,0 +1,126 @@
+use crate::telemetry;
+use anyhow::Result;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::time::Instant;
+
+/// Order lifecycle event persisted for flow statistics and order history
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderEventKind {
+    Placed,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+}
+
+impl OrderEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Placed => "placed",
+            Self::PartiallyFilled => "partially_filled",
+            Self::Filled => "filled",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Record an order state transition with the time it was indexed. `filled_quantity` is
+/// the order's cumulative fill after the transition, None when it isn't known.
+pub async fn record_order_event(
+    pool: &PgPool,
+    symbol: &str,
+    order_id: u64,
+    kind: OrderEventKind,
+    filled_quantity: Option<Decimal>,
+    block_number: u32,
+    event_index: u32,
+) -> Result<()> {
+    let insert_started = Instant::now();
+    let inserted = sqlx::query(
+        "INSERT INTO order_events (order_id, event_type, symbol, filled_quantity, block_number, event_index)
+        VALUES ($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(order_id as i64)
+    .bind(kind.as_str())
+    .bind(symbol)
+    .bind(filled_quantity)
+    .bind(block_number as i64)
+    .bind(event_index as i32)
+    .execute(pool)
+    .await;
+    telemetry::observe_db_write(insert_started.elapsed());
+
+    inserted?;
+    Ok(())
+}
+
+/// One recorded state transition of an order
+#[derive(Debug, Clone, Serialize, PartialEq, sqlx::FromRow)]
+pub struct OrderTransition {
+    /// "placed", "partially_filled", "filled" or "cancelled"
+    pub event_type: String,
+    pub symbol: String,
+    /// Cumulative fill after the transition, when known
+    pub filled_quantity: Option<Decimal>,
+    pub block_number: i64,
+    pub event_index: i32,
+    /// When the transition was indexed, in milliseconds
+    pub time: i64,
+}
+
+/// Every recorded transition of `order_id`, in chain order
+pub async fn fetch_order_history(
+    pool: &PgPool,
+    order_id: u64,
+) -> Result<Vec<OrderTransition>, sqlx::Error> {
+    sqlx::query_as::<_, OrderTransition>(
+        "SELECT event_type, symbol, filled_quantity, block_number, event_index,
+            (EXTRACT(EPOCH FROM created_at) * 1000)::bigint AS time
+        FROM order_events
+        WHERE order_id = $1
+        ORDER BY block_number, event_index, created_at",
+    )
+    .bind(order_id as i64)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_order_history_in_chain_order(pool: PgPool) {
+        // Recorded out of order: the fill of block 7 was indexed before the partial fill
+        let transitions = [
+            (OrderEventKind::Placed, None, 5, 2),
+            (OrderEventKind::Filled, Some(Decimal::from(3)), 7, 0),
+            (OrderEventKind::PartiallyFilled, Some(Decimal::ONE), 6, 4),
+        ];
+        for (kind, filled, block, index) in transitions {
+            record_order_event(&pool, "ETH/USDT", 42, kind, filled, block, index)
+                .await
+                .unwrap();
+        }
+        record_order_event(&pool, "ETH/USDT", 43, OrderEventKind::Placed, None, 5, 3)
+            .await
+            .unwrap();
+
+        let history = fetch_order_history(&pool, 42).await.unwrap();
+        let steps: Vec<(&str, Option<Decimal>, i64)> = history
+            .iter()
+            .map(|t| (t.event_type.as_str(), t.filled_quantity, t.block_number))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                ("placed", None, 5),
+                ("partially_filled", Some(Decimal::ONE), 6),
+                ("filled", Some(Decimal::from(3)), 7),
+            ]
+        );
+        assert!(fetch_order_history(&pool, 44).await.unwrap().is_empty());
+    }
+}
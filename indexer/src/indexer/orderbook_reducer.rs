@@ -1,9 +1,42 @@
 use anyhow::Result;
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
-use tokio::sync::broadcast;
-use tracing::info;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Mutex};
+use tracing::{info, warn};
+
+/// Minimum gap between repeated "broadcast failed" warnings
+const SEND_FAILURE_LOG_INTERVAL: Duration = Duration::from_secs(30);
+/// Levels per side covered by snapshot checksums unless configured otherwise
+const DEFAULT_CHECKSUM_LEVELS: usize = 25;
+
+const CRC_32: crc::Crc<u32> = crc::Crc::<u32>::new(&crc::CRC_32_ISO_HDLC);
+
+/// Checksum of the best `depth` levels per side, for clients to verify their local book.
+///
+/// The input is the `px` and `sz` strings exactly as sent in book frames, taken from the
+/// bids (highest first) and asks (lowest first) the client holds, without emptied levels.
+/// Level `i` contributes `bid_px:bid_sz:ask_px:ask_sz`, leaving out a side that has no
+/// level `i`, and the parts for `i = 0..depth` are joined with `:`. The checksum is the
+/// CRC-32 (IEEE 802.3, as in zlib) of that string's UTF-8 bytes, as an unsigned integer.
+///
+/// Bids `100:2, 99:1` and ask `101:3` at depth 25 hash `"100:2:101:3:99:1"`.
+pub fn book_checksum(bids: &[PriceLevel], asks: &[PriceLevel], depth: usize) -> u32 {
+    let level = |level: &PriceLevel| format!("{}:{}", level.price, level.total_quantity);
+    let mut parts = Vec::new();
+    for i in 0..depth {
+        let (bid, ask) = (bids.get(i), asks.get(i));
+        if bid.is_none() && ask.is_none() {
+            break;
+        }
+        parts.extend(bid.map(level));
+        parts.extend(ask.map(level));
+    }
+    CRC_32.checksum(parts.join(":").as_bytes())
+}
 
 /// Price level in orderbook snapshot
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +44,79 @@ pub struct PriceLevel {
     pub price: Decimal,
     pub total_quantity: Decimal,
     pub order_count: usize,
+    /// Aggregate of every level beyond the configured cap, priced at the farthest of them
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub rest: bool,
+    /// Price times quantity, present only when the client asked for it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notional: Option<Decimal>,
+}
+
+/// What happens to price levels beyond the cap on each side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TailMode {
+    /// Fold the tail into a single `rest` level so total depth is preserved
+    Aggregate,
+    /// Leave the tail out of snapshots entirely
+    Drop,
+}
+
+/// Cap on the number of price levels per side published in snapshots.
+///
+/// Only snapshots are capped: every order stays in the book so far-from-mid fills and
+/// cancellations still apply exactly, and the summary keeps describing the whole book.
+/// The trade-off is reconciliation: a capped snapshot can only be checked level-by-level
+/// against chain storage up to the cap, beyond it only the `rest` total (or nothing when
+/// dropping) is available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelCap {
+    pub max_levels: usize,
+    pub tail: TailMode,
+}
+
+impl LevelCap {
+    /// Apply the cap to levels ordered best-first
+    fn apply(&self, mut levels: Vec<PriceLevel>) -> Vec<PriceLevel> {
+        if levels.len() <= self.max_levels {
+            return levels;
+        }
+        let tail = levels.split_off(self.max_levels);
+        if self.tail == TailMode::Aggregate {
+            let farthest = tail.last().map(|level| level.price).unwrap_or_default();
+            levels.push(PriceLevel {
+                price: farthest,
+                total_quantity: tail.iter().map(|level| level.total_quantity).sum(),
+                order_count: tail.iter().map(|level| level.order_count).sum(),
+                rest: true,
+                notional: None,
+            });
+        }
+        levels
+    }
+}
+
+/// REST endpoint clients fetch the full book from when a broadcast was truncated
+pub const FULL_DEPTH_PATH: &str = "/api/orderbook";
+
+/// Bound on broadcast snapshot size: books deeper than `threshold` levels on either
+/// side are broadcast as their top `top_n` levels only
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BroadcastLimit {
+    pub threshold: usize,
+    pub top_n: usize,
+}
+
+impl BroadcastLimit {
+    fn apply(&self, snapshot: OrderbookSnapshot) -> OrderbookSnapshot {
+        if snapshot.bids.len().max(snapshot.asks.len()) <= self.threshold {
+            return snapshot;
+        }
+        OrderbookSnapshot {
+            truncated: true,
+            full_depth_url: Some(FULL_DEPTH_PATH.to_string()),
+            ..snapshot.truncated(self.top_n)
+        }
+    }
 }
 
 /// Spread information
@@ -38,6 +144,215 @@ pub struct OrderbookSnapshot {
     pub asks: Vec<PriceLevel>,
     pub spread: Option<Spread>,
     pub summary: OrderbookSummary,
+    /// Periodic re-send of an unchanged book rather than a change
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub keepalive: bool,
+    /// Broadcast cut down to the top levels because the full book was too deep
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// REST path serving the full depth, set on truncated snapshots
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_depth_url: Option<String>,
+    /// Book version, one higher after every change; a jump means updates were missed
+    #[serde(default)]
+    pub sequence: u64,
+    /// `book_checksum` of the book's own best levels (per-level sizes, before any cap,
+    /// truncation or aggregation), `BOOK_CHECKSUM_LEVELS` per side
+    #[serde(default)]
+    pub checksum: u32,
+}
+
+/// Why a snapshot could not be published
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SinkError {
+    /// Nobody is listening, which is normal when no client is connected
+    NoSubscribers,
+    /// Any other failure of the underlying transport
+    Failed(String),
+}
+
+/// Destination for snapshots published by the reducer
+pub trait SnapshotSink: Send + Sync + std::fmt::Debug {
+    /// Publish a snapshot, returning how many subscribers will receive it
+    fn publish(&self, snapshot: OrderbookSnapshot) -> Result<usize, SinkError>;
+
+    /// Number of currently attached subscribers
+    fn subscriber_count(&self) -> usize;
+}
+
+impl SnapshotSink for broadcast::Sender<OrderbookSnapshot> {
+    fn publish(&self, snapshot: OrderbookSnapshot) -> Result<usize, SinkError> {
+        // tokio only rejects a send when every receiver has been dropped
+        self.send(snapshot).map_err(|_| SinkError::NoSubscribers)
+    }
+
+    fn subscriber_count(&self) -> usize {
+        self.receiver_count()
+    }
+}
+
+/// Health counters for the snapshot push pipeline
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BroadcastMetrics {
+    /// Snapshots delivered to at least one subscriber
+    pub sends_ok: u64,
+    /// Snapshots dropped because nobody was subscribed
+    pub sends_no_subscribers: u64,
+    /// Snapshots that failed for any other reason
+    pub sends_failed: u64,
+    /// Subscribers attached at the last send
+    pub subscribers: usize,
+}
+
+/// Price of the `width`-wide bucket holding `price`: bids round down and asks round up,
+/// so a bucket never advertises a better price than the orders inside it
+pub fn bucket_price(price: Decimal, width: Decimal, side: &str) -> Decimal {
+    let steps = price / width;
+    let steps = if side == "Buy" {
+        steps.floor()
+    } else {
+        steps.ceil()
+    };
+    (steps * width).normalize()
+}
+
+/// Merge best-first levels into `step`-wide buckets; the `rest` level is kept as is
+fn merge_levels(levels: Vec<PriceLevel>, step: Decimal, side: &str) -> Vec<PriceLevel> {
+    let mut merged: Vec<PriceLevel> = Vec::with_capacity(levels.len());
+    for level in levels {
+        if level.rest {
+            merged.push(level);
+            continue;
+        }
+        let price = bucket_price(level.price, step, side);
+        // Rounding keeps the order, so levels of one bucket are adjacent
+        match merged.last_mut() {
+            Some(last) if !last.rest && last.price == price => {
+                last.total_quantity += level.total_quantity;
+                last.order_count += level.order_count;
+            }
+            _ => merged.push(PriceLevel { price, ..level }),
+        }
+    }
+    merged
+}
+
+/// Levels of one side that differ from `previous`, best first. Levels gone from the book
+/// come back with zero quantity and no orders so clients can clear them.
+fn changed_levels(previous: &[PriceLevel], current: &[PriceLevel], side: &str) -> Vec<PriceLevel> {
+    let before: BTreeMap<Decimal, &PriceLevel> =
+        previous.iter().map(|level| (level.price, level)).collect();
+    let mut changes: BTreeMap<Decimal, PriceLevel> = current
+        .iter()
+        .filter(|level| {
+            before.get(&level.price).is_none_or(|old| {
+                (old.total_quantity, old.order_count, old.rest)
+                    != (level.total_quantity, level.order_count, level.rest)
+            })
+        })
+        .map(|level| (level.price, level.clone()))
+        .collect();
+    let after: HashSet<Decimal> = current.iter().map(|level| level.price).collect();
+    for (price, level) in before
+        .into_iter()
+        .filter(|(price, _)| !after.contains(price))
+    {
+        changes.insert(
+            price,
+            PriceLevel {
+                total_quantity: Decimal::ZERO,
+                order_count: 0,
+                ..level.clone()
+            },
+        );
+    }
+    if side == "Buy" {
+        changes.into_values().rev().collect()
+    } else {
+        changes.into_values().collect()
+    }
+}
+
+impl OrderbookSnapshot {
+    /// Price levels that changed since `previous`, as `(bids, asks)` best first
+    pub fn diff(&self, previous: &OrderbookSnapshot) -> (Vec<PriceLevel>, Vec<PriceLevel>) {
+        (
+            changed_levels(&previous.bids, &self.bids, "Buy"),
+            changed_levels(&previous.asks, &self.asks, "Sell"),
+        )
+    }
+
+    /// Keep only the best `depth` levels per side; the summary still describes the whole book
+    pub fn truncated(mut self, depth: usize) -> Self {
+        self.bids.truncate(depth);
+        self.asks.truncate(depth);
+        self
+    }
+
+    /// Merge levels onto a `step` price grid, summing quantities and order counts.
+    /// Spread and summary still describe the unaggregated book.
+    pub fn aggregated(mut self, step: Decimal) -> Self {
+        self.bids = merge_levels(std::mem::take(&mut self.bids), step, "Buy");
+        self.asks = merge_levels(std::mem::take(&mut self.asks), step, "Sell");
+        self
+    }
+
+    /// Fill in each level's notional, price times quantity. Both are already scaled
+    /// decimals, so the product is exact; trailing zeros from the scales are dropped.
+    pub fn with_notional(mut self) -> Self {
+        for level in self.bids.iter_mut().chain(self.asks.iter_mut()) {
+            level.notional = level
+                .price
+                .checked_mul(level.total_quantity)
+                .map(|notional| notional.normalize());
+        }
+        self
+    }
+}
+
+/// What happened to an order in an L3 event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum L3Action {
+    /// Placed, or put back on the book; clients key orders by id, so this is an upsert
+    Add,
+    /// Partially filled or otherwise updated while still resting
+    Modify,
+    /// Filled or cancelled, off the book
+    Remove,
+}
+
+/// A resting order as published on the L3 feed
+#[derive(Debug, Clone, Serialize)]
+pub struct L3Order {
+    #[serde(flatten)]
+    pub order: OrderInfo,
+    pub remaining_quantity: Decimal,
+}
+
+impl From<OrderInfo> for L3Order {
+    fn from(order: OrderInfo) -> Self {
+        Self {
+            remaining_quantity: order.quantity - order.filled_quantity,
+            order,
+        }
+    }
+}
+
+/// Change to a single order, numbered so clients can apply it on top of an `L3Snapshot`
+#[derive(Debug, Clone, Serialize)]
+pub struct L3Event {
+    pub seq: u64,
+    pub action: L3Action,
+    pub order: L3Order,
+}
+
+/// Every resting order, bids then asks in price-time priority
+#[derive(Debug, Clone, Serialize)]
+pub struct L3Snapshot {
+    /// Sequence of the last event already reflected in `orders`
+    pub seq: u64,
+    pub orders: Vec<L3Order>,
 }
 
 #[derive(Debug)]
@@ -46,10 +361,28 @@ pub struct OrderbookState {
     pub asks: BTreeMap<Decimal, Vec<u64>>,
     pub orders: BTreeMap<u64, OrderInfo>,
     /// Optional broadcast channel for push-based snapshot updates
-    broadcast_tx: Option<broadcast::Sender<OrderbookSnapshot>>,
+    broadcast_tx: Option<Box<dyn SnapshotSink>>,
+    broadcast_metrics: BroadcastMetrics,
+    last_send_failure_log: Option<Instant>,
+    /// When a snapshot was last published, change-driven or keepalive
+    last_broadcast: Option<Instant>,
+    /// While set, mutations do not broadcast (used during cold-start seeding)
+    warming_up: bool,
+    /// Max price levels per side in snapshots, unlimited when unset
+    level_cap: Option<LevelCap>,
+    /// Truncate broadcasts of very deep books, REST snapshots stay complete
+    broadcast_limit: Option<BroadcastLimit>,
+    /// Optional channel for per-order (L3) events
+    l3_tx: Option<broadcast::Sender<L3Event>>,
+    /// Sequence of the last L3 event sent
+    l3_seq: u64,
+    /// Number of state changes so far, published as the snapshot `sequence`
+    sequence: u64,
+    /// Levels per side covered by snapshot checksums
+    checksum_levels: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize)]
 pub struct OrderInfo {
     pub order_id: u64,
     //pub trade: String,
@@ -58,6 +391,32 @@ pub struct OrderInfo {
     pub quantity: Decimal,
     pub filled_quantity: Decimal,
     pub status: String,
+    /// Price exactly as emitted on chain, before decimal scaling
+    #[serde(skip)]
+    pub raw_price: u128,
+    /// Quantity exactly as emitted on chain, before decimal scaling
+    #[serde(skip)]
+    pub raw_quantity: u128,
+}
+
+impl OrderInfo {
+    /// Still on the book, i.e. open or partially filled
+    pub fn is_resting(&self) -> bool {
+        matches!(self.status.as_str(), "Open" | "PartiallyFilled")
+    }
+
+    /// Remaining quantity in on-chain units; fills are tracked scaled, so they are
+    /// converted back using this order's own raw/scaled ratio
+    pub fn raw_remaining(&self) -> u128 {
+        if self.quantity.is_zero() {
+            return 0;
+        }
+        let raw_filled = (self.filled_quantity * Decimal::from(self.raw_quantity) / self.quantity)
+            .round()
+            .to_u128()
+            .unwrap_or(0);
+        self.raw_quantity.saturating_sub(raw_filled)
+    }
 }
 
 impl OrderbookState {
@@ -68,71 +427,233 @@ impl OrderbookState {
             asks: BTreeMap::new(),
             orders: BTreeMap::new(),
             broadcast_tx: None,
+            broadcast_metrics: BroadcastMetrics::default(),
+            last_send_failure_log: None,
+            last_broadcast: None,
+            warming_up: false,
+            level_cap: None,
+            broadcast_limit: None,
+            l3_tx: None,
+            l3_seq: 0,
+            sequence: 0,
+            checksum_levels: DEFAULT_CHECKSUM_LEVELS,
         }
     }
 
     /// Create a new OrderbookState with broadcast channel for push-based updates
     pub fn with_broadcast(broadcast_tx: broadcast::Sender<OrderbookSnapshot>) -> Self {
+        Self::with_sink(Box::new(broadcast_tx))
+    }
+
+    /// Create a new OrderbookState publishing snapshots to an arbitrary sink
+    pub fn with_sink(sink: Box<dyn SnapshotSink>) -> Self {
         Self {
-            bids: BTreeMap::new(),
-            asks: BTreeMap::new(),
-            orders: BTreeMap::new(),
-            broadcast_tx: Some(broadcast_tx),
+            broadcast_tx: Some(sink),
+            ..Self::new()
+        }
+    }
+
+    /// Limit snapshots to `cap.max_levels` price levels per side
+    pub fn with_level_cap(mut self, cap: Option<LevelCap>) -> Self {
+        self.level_cap = cap;
+        self
+    }
+
+    /// Broadcast only the top levels of books deeper than `limit.threshold`
+    pub fn with_broadcast_limit(mut self, limit: Option<BroadcastLimit>) -> Self {
+        self.broadcast_limit = limit;
+        self
+    }
+
+    /// Cover `levels` levels per side in snapshot checksums
+    pub fn with_checksum_levels(mut self, levels: usize) -> Self {
+        self.checksum_levels = levels;
+        self
+    }
+
+    /// Also publish every order change as a sequenced L3 event
+    pub fn with_l3_feed(mut self, l3_tx: broadcast::Sender<L3Event>) -> Self {
+        self.l3_tx = Some(l3_tx);
+        self
+    }
+
+    /// Counters describing how snapshot publishing has gone so far
+    pub fn broadcast_metrics(&self) -> &BroadcastMetrics {
+        &self.broadcast_metrics
+    }
+
+    /// Load a batch of existing orders, e.g. from chain storage at startup.
+    ///
+    /// With `warmup` set, per-order broadcasts are suppressed and subscribers get a
+    /// single full snapshot once every order is in place; otherwise each order is
+    /// broadcast as it is added.
+    pub fn seed_orders(&mut self, orders: impl IntoIterator<Item = OrderInfo>, warmup: bool) {
+        self.warming_up = warmup;
+        let mut seeded = 0usize;
+        for order in orders {
+            self.add_order(order);
+            seeded += 1;
+        }
+        self.warming_up = false;
+
+        info!("🌱 Seeded {} orders into the orderbook", seeded);
+        if warmup {
+            self.notify();
         }
     }
 
     /// Notify subscribers of orderbook change by sending full snapshot
-    fn notify(&self) {
-        if let Some(ref tx) = self.broadcast_tx {
-            let snapshot = self.get_snapshot();
-            tracing::debug!(
-                "Broadcasting orderbook snapshot: {} bid levels, {} ask levels, {} orders",
-                snapshot.summary.total_bid_levels,
-                snapshot.summary.total_ask_levels,
-                snapshot.summary.total_orders
-            );
-            if tx.send(snapshot).is_err() {
+    fn notify(&mut self) {
+        // Every mutation ends here, including those not broadcast while warming up
+        self.sequence += 1;
+        if self.warming_up {
+            return;
+        }
+        if self.broadcast_tx.is_none() {
+            return;
+        }
+
+        let snapshot = self.get_snapshot();
+        tracing::debug!(
+            "Broadcasting orderbook snapshot: {} bid levels, {} ask levels, {} orders",
+            snapshot.summary.total_bid_levels,
+            snapshot.summary.total_ask_levels,
+            snapshot.summary.total_orders
+        );
+        self.publish(snapshot, Instant::now());
+    }
+
+    /// Send the current state of `order_id` on the L3 feed under the next sequence
+    fn publish_l3(&mut self, action: L3Action, order_id: u64) {
+        let (Some(tx), Some(order)) = (&self.l3_tx, self.orders.get(&order_id)) else {
+            return;
+        };
+        self.l3_seq += 1;
+        // Nobody listening is fine, connecting clients start from `l3_snapshot`
+        let _ = tx.send(L3Event {
+            seq: self.l3_seq,
+            action,
+            order: order.clone().into(),
+        });
+    }
+
+    /// Resting orders with the sequence of the last L3 event they include. Taken under
+    /// the same lock as the events are sent, so clients subscribed before taking it can
+    /// skip events up to `seq` and apply the rest.
+    pub fn l3_snapshot(&self) -> L3Snapshot {
+        let orders = self
+            .orders_by_priority("Buy")
+            .into_iter()
+            .chain(self.orders_by_priority("Sell"))
+            .map(|order| order.clone().into())
+            .collect();
+        L3Snapshot {
+            seq: self.l3_seq,
+            orders,
+        }
+    }
+
+    /// Re-broadcast the unchanged book, flagged as a keepalive, when nothing has been
+    /// published for `interval`. Returns whether a keepalive was sent.
+    pub fn keepalive_if_idle(&mut self, now: Instant, interval: Duration) -> bool {
+        if self.warming_up || self.broadcast_tx.is_none() {
+            return false;
+        }
+        let recently_published = self
+            .last_broadcast
+            .is_some_and(|last| now.saturating_duration_since(last) < interval);
+        if recently_published {
+            return false;
+        }
+
+        let snapshot = OrderbookSnapshot {
+            keepalive: true,
+            ..self.get_snapshot()
+        };
+        self.publish(snapshot, now);
+        true
+    }
+
+    fn publish(&mut self, snapshot: OrderbookSnapshot, now: Instant) {
+        let Some(ref tx) = self.broadcast_tx else {
+            return;
+        };
+        self.last_broadcast = Some(now);
+
+        let snapshot = match self.broadcast_limit {
+            Some(limit) => limit.apply(snapshot),
+            None => snapshot,
+        };
+        let result = tx.publish(snapshot);
+        self.broadcast_metrics.subscribers = tx.subscriber_count();
+
+        match result {
+            Ok(_) => self.broadcast_metrics.sends_ok += 1,
+            Err(SinkError::NoSubscribers) => {
+                self.broadcast_metrics.sends_no_subscribers += 1;
                 tracing::debug!("No subscribers for orderbook updates");
             }
+            Err(SinkError::Failed(reason)) => {
+                self.broadcast_metrics.sends_failed += 1;
+                let should_log = self
+                    .last_send_failure_log
+                    .is_none_or(|last| last.elapsed() >= SEND_FAILURE_LOG_INTERVAL);
+                if should_log {
+                    warn!(
+                        "Failed to broadcast orderbook snapshot ({} failures so far): {}",
+                        self.broadcast_metrics.sends_failed, reason
+                    );
+                    self.last_send_failure_log = Some(Instant::now());
+                }
+            }
         }
     }
 
     /// Generate orderbook snapshot from current state
     pub fn get_snapshot(&self) -> OrderbookSnapshot {
-        let bids: Vec<PriceLevel> = self
-            .bids
-            .iter()
-            .rev()
-            .map(|(price, orders)| {
-                let total_quantity: Decimal = orders
-                    .iter()
-                    .filter_map(|id| self.orders.get(id).map(|o| o.quantity - o.filled_quantity))
-                    .sum();
-
-                PriceLevel {
-                    price: *price,
-                    total_quantity,
-                    order_count: orders.len(),
-                }
-            })
-            .collect();
+        let snapshot = self.snapshot_of_depth(usize::MAX);
+        match self.level_cap {
+            Some(cap) => OrderbookSnapshot {
+                bids: cap.apply(snapshot.bids),
+                asks: cap.apply(snapshot.asks),
+                ..snapshot
+            },
+            None => snapshot,
+        }
+    }
 
-        let asks: Vec<PriceLevel> = self
-            .asks
-            .iter()
-            .map(|(price, orders)| {
-                let total_quantity: Decimal = orders
-                    .iter()
-                    .filter_map(|id| self.orders.get(id).map(|o| o.quantity - o.filled_quantity))
-                    .sum();
-
-                PriceLevel {
-                    price: *price,
-                    total_quantity,
-                    order_count: orders.len(),
-                }
+    /// Snapshot of the best `levels` price levels per side (bids descending, asks
+    /// ascending); the summary still describes the whole book. Only the kept levels are
+    /// summed, so this stays cheap on deep books.
+    pub fn get_snapshot_depth(&self, levels: usize) -> OrderbookSnapshot {
+        match self.level_cap {
+            // The cap's `rest` level sums everything past it, which needs every level
+            Some(cap) if cap.max_levels < levels => self.get_snapshot(),
+            _ => self.snapshot_of_depth(levels),
+        }
+    }
+
+    /// Best `depth` levels of one side with their remaining quantity and order count
+    fn side_levels(&self, side: &str, depth: usize) -> Vec<PriceLevel> {
+        let book: Box<dyn Iterator<Item = (&Decimal, &Vec<u64>)>> = if side == "Buy" {
+            Box::new(self.bids.iter().rev())
+        } else {
+            Box::new(self.asks.iter())
+        };
+        book.take(depth)
+            .map(|(price, orders)| PriceLevel {
+                price: *price,
+                total_quantity: self.level_quantity(side, price),
+                order_count: orders.len(),
+                rest: false,
+                notional: None,
             })
-            .collect();
+            .collect()
+    }
+
+    fn snapshot_of_depth(&self, depth: usize) -> OrderbookSnapshot {
+        let bids = self.side_levels("Buy", depth);
+        let asks = self.side_levels("Sell", depth);
 
         let (total_bid_volume, total_ask_volume): (Decimal, Decimal) =
             self.orders
@@ -163,10 +684,30 @@ impl OrderbookState {
                 total_bid_volume,
                 total_ask_volume,
             },
+            keepalive: false,
+            truncated: false,
+            full_depth_url: None,
+            sequence: self.sequence,
+            checksum: book_checksum(
+                &self.side_levels("Buy", self.checksum_levels),
+                &self.side_levels("Sell", self.checksum_levels),
+                self.checksum_levels,
+            ),
         }
     }
 
+    /// Track a newly placed order. An id that is already tracked is left alone, so a
+    /// block replayed over a restored book doesn't queue its orders twice.
     pub fn add_order(&mut self, order: OrderInfo) {
+        if self.orders.contains_key(&order.order_id) {
+            tracing::debug!("Order #{} already tracked, skipping", order.order_id);
+            return;
+        }
+        self.queue_order(order);
+    }
+
+    /// Queue `order` at the back of its level and track it
+    fn queue_order(&mut self, order: OrderInfo) {
         let order_id = order.order_id;
         let price = order.price;
         let side = order.side.as_str();
@@ -184,9 +725,86 @@ impl OrderbookState {
         self.orders.insert(order_id, order);
 
         info!("Added order with order_id {}", order_id);
+        self.publish_l3(L3Action::Add, order_id);
         self.notify();
     }
 
+    /// Resting orders on one side in price-time priority: best price first, and within a
+    /// level in placement order, the order the matching engine fills them in
+    pub fn orders_by_priority(&self, side: &str) -> Vec<&OrderInfo> {
+        let levels: Box<dyn Iterator<Item = &Vec<u64>>> = match side {
+            "Buy" => Box::new(self.bids.values().rev()),
+            "Sell" => Box::new(self.asks.values()),
+            _ => return Vec::new(),
+        };
+        levels
+            .flatten()
+            .filter_map(|order_id| self.orders.get(order_id))
+            .collect()
+    }
+
+    /// Compare a partial fill event against the tracked order: `filled + remaining` must
+    /// equal the order's quantity. Returns the difference when it exceeds `tolerance`,
+    /// None when consistent or the order is unknown.
+    pub fn fill_mismatch(
+        &self,
+        order_id: u64,
+        filled_quantity: Decimal,
+        remaining_quantity: Decimal,
+        tolerance: Decimal,
+    ) -> Option<Decimal> {
+        let order = self.orders.get(&order_id)?;
+        let difference = filled_quantity + remaining_quantity - order.quantity;
+        (difference.abs() > tolerance).then_some(difference)
+    }
+
+    /// Overwrite a tracked order with authoritative state from chain storage, keeping its
+    /// queue position when it still rests at the same price
+    pub fn reconcile_order(&mut self, order: OrderInfo) {
+        let order_id = order.order_id;
+        let mut was_resting = false;
+        if let Some(tracked) = self.orders.get(&order_id) {
+            was_resting = tracked.is_resting();
+            let (side, price) = (tracked.side.clone(), tracked.price);
+            if !order.is_resting() || price != order.price || side != order.side {
+                self.remove_order_from_level(order_id, &side, price);
+            }
+        }
+
+        let requeue = order.is_resting() && !self.level_contains(&order);
+        if requeue {
+            // queue_order queues it, publishes it and notifies
+            self.queue_order(order);
+        } else {
+            let resting = order.is_resting();
+            self.orders.insert(order_id, order);
+            if resting {
+                self.publish_l3(L3Action::Modify, order_id);
+            } else if was_resting {
+                self.publish_l3(L3Action::Remove, order_id);
+            }
+            self.notify();
+        }
+    }
+
+    fn level_contains(&self, order: &OrderInfo) -> bool {
+        let levels = if order.side == "Buy" {
+            &self.bids
+        } else {
+            &self.asks
+        };
+        levels
+            .get(&order.price)
+            .is_some_and(|ids| ids.contains(&order.order_id))
+    }
+
+    /// Record a fill of a tracked order.
+    ///
+    /// `filled_quantity` is cumulative: the pallet emits the order's total filled amount
+    /// (with `remaining = quantity - filled`), not the amount of the latest match. A fill
+    /// never shrinks, so a stale or replayed event can't give back filled quantity, and
+    /// an order that is already filled or cancelled stays off the book; corrections from
+    /// chain storage go through `reconcile_order`.
     pub fn update_order(
         &mut self,
         order_id: u64,
@@ -194,7 +812,16 @@ impl OrderbookState {
         status: &str,
     ) -> Result<()> {
         let (side, price) = if let Some(order) = self.orders.get_mut(&order_id) {
-            order.filled_quantity = filled_quantity;
+            if !order.is_resting() {
+                tracing::debug!(
+                    "Ignoring {} for order #{}, already {}",
+                    status,
+                    order_id,
+                    order.status
+                );
+                return Ok(());
+            }
+            order.filled_quantity = order.filled_quantity.max(filled_quantity);
             order.status = status.to_string();
             (order.side.clone(), order.price)
         } else {
@@ -203,12 +830,24 @@ impl OrderbookState {
 
         if status == "Filled" {
             self.remove_order_from_level(order_id, &side, price);
+            self.publish_l3(L3Action::Remove, order_id);
+        } else {
+            self.publish_l3(L3Action::Modify, order_id);
         }
 
         self.notify();
         Ok(())
     }
 
+    /// Mark a tracked order completely filled, taking it off the book. Returns false when
+    /// the order isn't tracked, e.g. it was placed before the indexer started.
+    pub fn fill_order(&mut self, order_id: u64) -> bool {
+        let Some(quantity) = self.orders.get(&order_id).map(|order| order.quantity) else {
+            return false;
+        };
+        self.update_order(order_id, quantity, "Filled").is_ok()
+    }
+
     pub fn remove_order_from_level(&mut self, order_id: u64, side: &str, price: Decimal) {
         match side {
             "Buy" => {
@@ -231,8 +870,18 @@ impl OrderbookState {
         }
     }
 
+    /// Take a resting order off the book. A late or duplicate cancellation of an order that
+    /// is already filled or cancelled leaves it as it is.
     pub fn cancel_order(&mut self, order_id: u64) -> Result<()> {
         let (side, price) = if let Some(order) = self.orders.get_mut(&order_id) {
+            if !order.is_resting() {
+                tracing::debug!(
+                    "Ignoring cancellation of order #{}, already {}",
+                    order_id,
+                    order.status
+                );
+                return Ok(());
+            }
             order.status = "Cancelled".to_string();
             (order.side.clone(), order.price)
         } else {
@@ -241,6 +890,7 @@ impl OrderbookState {
 
         self.remove_order_from_level(order_id, &side, price);
         info!(" Order #{} cancelled", order_id);
+        self.publish_l3(L3Action::Remove, order_id);
         self.notify();
 
         Ok(())
@@ -263,10 +913,806 @@ impl OrderbookState {
             .collect()
     }
 
+    /// Best `depth` bid levels with the raw remaining quantity resting at or above each
+    /// price, so totals grow as prices fall
+    pub fn cumulative_bid_depth(&self, depth: usize) -> Vec<(Decimal, u128)> {
+        self.cumulative_depth("Buy", self.get_bid_depth(depth))
+    }
+
+    /// Best `depth` ask levels with the raw remaining quantity resting at or below each
+    /// price, so totals grow as prices rise
+    pub fn cumulative_ask_depth(&self, depth: usize) -> Vec<(Decimal, u128)> {
+        self.cumulative_depth("Sell", self.get_ask_depth(depth))
+    }
+
+    /// Running raw quantity over `levels`, which are ordered best price first
+    fn cumulative_depth(&self, side: &str, levels: Vec<(Decimal, usize)>) -> Vec<(Decimal, u128)> {
+        let mut total = 0u128;
+        levels
+            .into_iter()
+            .filter_map(|(price, _)| {
+                let (_, raw_quantity) = self.raw_level(side, &price)?;
+                total += raw_quantity;
+                Some((price, total))
+            })
+            .collect()
+    }
+
+    /// Orders still resting on the book (filled and cancelled orders stay in `orders`)
+    pub fn open_order_count(&self) -> usize {
+        self.orders
+            .values()
+            .filter(|o| o.status == "Open" || o.status == "PartiallyFilled")
+            .count()
+    }
+
+    /// Raw (unscaled) price and remaining quantity of a price level, from the orders'
+    /// on-chain integers. Returns None for an empty or unknown level.
+    pub fn raw_level(&self, side: &str, price: &Decimal) -> Option<(u128, u128)> {
+        let book = if side == "Buy" {
+            &self.bids
+        } else {
+            &self.asks
+        };
+        let orders: Vec<&OrderInfo> = book
+            .get(price)?
+            .iter()
+            .filter_map(|id| self.orders.get(id))
+            .collect();
+
+        let raw_price = orders.first()?.raw_price;
+        let raw_remaining = orders.iter().map(|o| o.raw_remaining()).sum();
+        Some((raw_price, raw_remaining))
+    }
+
     /// Get best bid/ask spread
     pub fn get_spread(&self) -> Option<(Decimal, Decimal)> {
         let best_bid = self.bids.keys().next_back()?;
         let best_ask = self.asks.keys().next()?;
         Some((*best_bid, *best_ask))
     }
+
+    /// Remaining quantity resting at a price level, zero for an unknown level
+    pub fn level_quantity(&self, side: &str, price: &Decimal) -> Decimal {
+        let book = if side == "Buy" {
+            &self.bids
+        } else {
+            &self.asks
+        };
+        book.get(price)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.orders.get(id))
+            .map(|o| o.quantity - o.filled_quantity)
+            .sum()
+    }
+
+    /// Midpoint of the best bid and best ask
+    pub fn mid_price(&self) -> Option<Decimal> {
+        let (best_bid, best_ask) = self.get_spread()?;
+        Some((best_bid + best_ask) / Decimal::TWO)
+    }
+
+    /// Size-weighted fair value at the touch,
+    /// `(bid * ask_size + ask * bid_size) / (bid_size + ask_size)`: a heavy bid pulls it
+    /// towards the ask. Falls back to the mid when neither touch level has size left.
+    pub fn microprice(&self) -> Option<Decimal> {
+        let (best_bid, best_ask) = self.get_spread()?;
+        let bid_size = self.level_quantity("Buy", &best_bid);
+        let ask_size = self.level_quantity("Sell", &best_ask);
+        let total = bid_size + ask_size;
+        if total.is_zero() {
+            return self.mid_price();
+        }
+        Some((best_bid * ask_size + best_ask * bid_size) / total)
+    }
+
+    /// Price and resting size of the best `levels` levels of one side
+    fn top_levels(&self, side: &str, levels: usize) -> Vec<(Decimal, Decimal)> {
+        let prices: Box<dyn Iterator<Item = &Decimal>> = if side == "Buy" {
+            Box::new(self.bids.keys().rev())
+        } else {
+            Box::new(self.asks.keys())
+        };
+        prices
+            .take(levels)
+            .map(|price| (*price, self.level_quantity(side, price)))
+            .collect()
+    }
+
+    /// Order-flow imbalance over the top `levels` levels per side,
+    /// `(bid_size - ask_size) / (bid_size + ask_size)`: +1 is all bids, -1 all asks.
+    /// None when neither side has size.
+    pub fn imbalance(&self, levels: usize) -> Option<Decimal> {
+        let size = |side| -> Decimal {
+            self.top_levels(side, levels)
+                .iter()
+                .map(|(_, quantity)| *quantity)
+                .sum()
+        };
+        let (bid_size, ask_size) = (size("Buy"), size("Sell"));
+        let total = bid_size + ask_size;
+        (!total.is_zero()).then(|| (bid_size - ask_size) / total)
+    }
+
+    /// Microprice over the top `levels` levels: each side's volume-weighted price,
+    /// weighted by the opposite side's size. Equals `microprice` for one level; None
+    /// unless both sides have size.
+    pub fn weighted_mid(&self, levels: usize) -> Option<Decimal> {
+        let vwap = |side| -> Option<(Decimal, Decimal)> {
+            let top = self.top_levels(side, levels);
+            let size: Decimal = top.iter().map(|(_, quantity)| *quantity).sum();
+            if size.is_zero() {
+                return None;
+            }
+            let notional: Decimal = top.iter().map(|(price, quantity)| price * quantity).sum();
+            Some((notional / size, size))
+        };
+        let (bid, bid_size) = vwap("Buy")?;
+        let (ask, ask_size) = vwap("Sell")?;
+        Some((bid * ask_size + ask * bid_size) / (bid_size + ask_size))
+    }
+}
+
+/// Send keepalive snapshots while the book is idle, at most once per `interval`
+pub async fn run_keepalive(orderbook: Arc<Mutex<OrderbookState>>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        orderbook
+            .lock()
+            .await
+            .keepalive_if_idle(Instant::now(), interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Sink that records every published snapshot and can be told to fail
+    #[derive(Debug, Default, Clone)]
+    struct RecordingSink {
+        published: Arc<Mutex<Vec<OrderbookSnapshot>>>,
+        fail_with: Option<SinkError>,
+    }
+
+    impl SnapshotSink for RecordingSink {
+        fn publish(&self, snapshot: OrderbookSnapshot) -> Result<usize, SinkError> {
+            if let Some(err) = &self.fail_with {
+                return Err(err.clone());
+            }
+            self.published.lock().unwrap().push(snapshot);
+            Ok(1)
+        }
+
+        fn subscriber_count(&self) -> usize {
+            1
+        }
+    }
+
+    fn order(order_id: u64, side: &str, price: i64, quantity: i64) -> OrderInfo {
+        OrderInfo {
+            order_id,
+            side: side.to_string(),
+            price: Decimal::from(price),
+            quantity: Decimal::from(quantity),
+            filled_quantity: Decimal::ZERO,
+            status: "Open".to_string(),
+            raw_price: price as u128 * 1_000_000,
+            raw_quantity: quantity as u128 * 1_000_000,
+        }
+    }
+
+    #[test]
+    fn test_send_metrics_increment_on_mutation() {
+        let sink = RecordingSink::default();
+        let mut state = OrderbookState::with_sink(Box::new(sink.clone()));
+
+        state.add_order(order(1, "Buy", 100, 5));
+        state.add_order(order(2, "Sell", 101, 3));
+        state.cancel_order(1).unwrap();
+
+        assert_eq!(sink.published.lock().unwrap().len(), 3);
+        let metrics = state.broadcast_metrics();
+        assert_eq!(metrics.sends_ok, 3);
+        assert_eq!(metrics.sends_failed, 0);
+        assert_eq!(metrics.subscribers, 1);
+    }
+
+    #[test]
+    fn test_aggregated_snapshot_merges_levels_into_steps() {
+        let mut state = OrderbookState::new();
+        for (id, price, quantity) in [(1, 1009, 1), (2, 1001, 2), (3, 1000, 3), (4, 995, 4)] {
+            state.add_order(order(id, "Buy", price, quantity));
+        }
+        for (id, price, quantity) in [(5, 1011, 5), (6, 1020, 6), (7, 1021, 7)] {
+            state.add_order(order(id, "Sell", price, quantity));
+        }
+
+        let snapshot = state.get_snapshot().aggregated(Decimal::from(10));
+        let levels = |levels: &[PriceLevel]| -> Vec<(Decimal, Decimal, usize)> {
+            levels
+                .iter()
+                .map(|l| (l.price, l.total_quantity, l.order_count))
+                .collect()
+        };
+        let level = |price: i64, quantity: i64, orders| {
+            (Decimal::from(price), Decimal::from(quantity), orders)
+        };
+        // Bids floor to the step, asks ceil to it
+        assert_eq!(
+            levels(&snapshot.bids),
+            vec![level(1000, 6, 3), level(990, 4, 1)]
+        );
+        assert_eq!(
+            levels(&snapshot.asks),
+            vec![level(1020, 11, 2), level(1030, 7, 1)]
+        );
+        // Spread still reflects the real touch
+        let spread = snapshot.spread.unwrap();
+        assert_eq!(
+            (spread.best_bid, spread.best_ask),
+            (Decimal::from(1009), Decimal::from(1011))
+        );
+    }
+
+    #[test]
+    fn test_diff_reports_changed_and_removed_levels() {
+        let mut state = OrderbookState::new();
+        for (id, side, price, quantity) in [
+            (1, "Buy", 100, 1),
+            (2, "Buy", 99, 2),
+            (3, "Buy", 98, 3),
+            (4, "Sell", 101, 4),
+        ] {
+            state.add_order(order(id, side, price, quantity));
+        }
+        let before = state.get_snapshot();
+        let (bids, asks) = before.diff(&before);
+        assert!(bids.is_empty() && asks.is_empty());
+
+        state.add_order(order(5, "Buy", 99, 1));
+        state.cancel_order(3).unwrap();
+        state.add_order(order(6, "Buy", 97, 1));
+        let after = state.get_snapshot();
+
+        let (bids, asks) = after.diff(&before);
+        assert!(asks.is_empty());
+        let bids: Vec<(i64, i64, usize)> = bids
+            .iter()
+            .map(|l| {
+                (
+                    l.price.to_i64().unwrap(),
+                    l.total_quantity.to_i64().unwrap(),
+                    l.order_count,
+                )
+            })
+            .collect();
+        // Best first; the emptied 98 level is reported with nothing left
+        assert_eq!(bids, vec![(99, 3, 2), (98, 0, 0), (97, 1, 1)]);
+    }
+
+    #[test]
+    fn test_notional_is_price_times_quantity() {
+        let mut state = OrderbookState::new();
+        state.add_order(OrderInfo {
+            price: Decimal::new(20_005, 1),
+            quantity: Decimal::new(125, 2),
+            ..order(1, "Buy", 0, 0)
+        });
+
+        // Left out unless requested
+        let snapshot = state.get_snapshot();
+        assert!(snapshot.bids[0].notional.is_none());
+        assert!(serde_json::to_value(&snapshot.bids[0])
+            .unwrap()
+            .get("notional")
+            .is_none());
+
+        let level = &state.get_snapshot().with_notional().bids[0];
+        assert_eq!(level.notional, Some(level.price * level.total_quantity));
+        assert_eq!(serde_json::to_value(level).unwrap()["notional"], "2500.625");
+    }
+
+    #[test]
+    fn test_raw_values_correspond_to_scaled_values() {
+        let mut state = OrderbookState::new();
+        state.add_order(OrderInfo {
+            order_id: 1,
+            side: "Sell".to_string(),
+            price: Decimal::new(20_005, 1),
+            quantity: Decimal::new(1_25, 2),
+            filled_quantity: Decimal::ZERO,
+            status: "Open".to_string(),
+            raw_price: 2_000_500_000,
+            raw_quantity: 1_250_000,
+        });
+        state.add_order(order(2, "Sell", 2000, 2));
+        state
+            .update_order(1, Decimal::new(25, 2), "PartiallyFilled")
+            .unwrap();
+
+        let decimals = Decimal::from(1_000_000);
+        let (raw_price, raw_quantity) = state.raw_level("Sell", &Decimal::new(20_005, 1)).unwrap();
+        assert_eq!(Decimal::from(raw_price) / decimals, Decimal::new(20_005, 1));
+        assert_eq!(Decimal::from(raw_quantity) / decimals, Decimal::ONE);
+        assert!(state.raw_level("Buy", &Decimal::from(2000)).is_none());
+    }
+
+    #[test]
+    fn test_cumulative_depth_runs_outward_from_the_touch() {
+        let mut state = OrderbookState::new();
+        state.add_order(order(1, "Buy", 99, 3));
+        state.add_order(order(2, "Buy", 98, 1));
+        state.add_order(order(3, "Buy", 99, 2));
+        state.add_order(order(4, "Sell", 101, 1));
+        state.add_order(order(5, "Sell", 104, 3));
+
+        let unit = 1_000_000u128;
+        // Bids accumulate downward in price, asks upward
+        assert_eq!(
+            state.cumulative_bid_depth(10),
+            vec![(Decimal::from(99), 5 * unit), (Decimal::from(98), 6 * unit)]
+        );
+        assert_eq!(
+            state.cumulative_ask_depth(10),
+            vec![(Decimal::from(101), unit), (Decimal::from(104), 4 * unit)]
+        );
+        assert_eq!(state.cumulative_ask_depth(1).len(), 1);
+
+        // Each step is that level's own quantity
+        let bids = state.cumulative_bid_depth(10);
+        let (_, second_level) = state.raw_level("Buy", &Decimal::from(98)).unwrap();
+        assert_eq!(bids[1].1 - bids[0].1, second_level);
+    }
+
+    #[test]
+    fn test_repeated_partial_fills_are_cumulative() {
+        let mut state = OrderbookState::new();
+        state.add_order(order(1, "Sell", 2000, 10));
+
+        // Two matches of 3 and 4: the events carry the running totals
+        state
+            .update_order(1, Decimal::from(3), "PartiallyFilled")
+            .unwrap();
+        state
+            .update_order(1, Decimal::from(7), "PartiallyFilled")
+            .unwrap();
+        assert_eq!(state.orders[&1].filled_quantity, Decimal::from(7));
+        assert_eq!(
+            state.get_snapshot().asks[0].total_quantity,
+            Decimal::from(3)
+        );
+
+        // A replayed older event doesn't give back filled quantity
+        state
+            .update_order(1, Decimal::from(3), "PartiallyFilled")
+            .unwrap();
+        assert_eq!(
+            state.get_snapshot().asks[0].total_quantity,
+            Decimal::from(3)
+        );
+
+        // Nor does one replayed after the final fill put the order back on the book
+        let (l3_tx, mut l3_rx) = broadcast::channel(16);
+        state = state.with_l3_feed(l3_tx);
+        state.update_order(1, Decimal::from(10), "Filled").unwrap();
+        assert_eq!(l3_rx.try_recv().unwrap().action, L3Action::Remove);
+        state
+            .update_order(1, Decimal::from(7), "PartiallyFilled")
+            .unwrap();
+        assert_eq!(state.orders[&1].status, "Filled");
+        assert_eq!(state.orders[&1].filled_quantity, Decimal::from(10));
+        assert!(state.get_snapshot().asks.is_empty());
+        assert!(l3_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_cancel_after_fill_is_ignored() {
+        let (l3_tx, mut l3_rx) = broadcast::channel(16);
+        let mut state = OrderbookState::new().with_l3_feed(l3_tx);
+        state.add_order(order(1, "Sell", 100, 10));
+        state.add_order(order(2, "Buy", 99, 5));
+        assert!(state.fill_order(1));
+        state.cancel_order(2).unwrap();
+        let seq = state.l3_snapshot().seq;
+        while l3_rx.try_recv().is_ok() {}
+
+        // A late cancellation neither reopens the fill nor removes the order a second time
+        state.cancel_order(1).unwrap();
+        assert_eq!(state.orders[&1].status, "Filled");
+        // Nor does a duplicate one of an order already cancelled
+        state.cancel_order(2).unwrap();
+        assert_eq!(state.orders[&2].status, "Cancelled");
+
+        assert!(l3_rx.try_recv().is_err());
+        assert_eq!(state.l3_snapshot().seq, seq);
+    }
+
+    #[test]
+    fn test_l3_events_follow_the_snapshot_sequence() {
+        let (l3_tx, mut l3_rx) = broadcast::channel(16);
+        let mut state = OrderbookState::new().with_l3_feed(l3_tx);
+        state.add_order(order(1, "Buy", 1990, 5));
+        state.add_order(order(2, "Sell", 2010, 4));
+
+        let snapshot = state.l3_snapshot();
+        assert_eq!(snapshot.seq, 2);
+        let ids: Vec<u64> = snapshot.orders.iter().map(|o| o.order.order_id).collect();
+        assert_eq!(ids, vec![1, 2]);
+
+        state
+            .update_order(2, Decimal::ONE, "PartiallyFilled")
+            .unwrap();
+        state.cancel_order(1).unwrap();
+        state.fill_order(2);
+
+        let events: Vec<(u64, L3Action, u64, Decimal)> =
+            std::iter::from_fn(|| l3_rx.try_recv().ok())
+                .map(|e| {
+                    (
+                        e.seq,
+                        e.action,
+                        e.order.order.order_id,
+                        e.order.remaining_quantity,
+                    )
+                })
+                .collect();
+        assert_eq!(
+            events,
+            vec![
+                (1, L3Action::Add, 1, Decimal::from(5)),
+                (2, L3Action::Add, 2, Decimal::from(4)),
+                (3, L3Action::Modify, 2, Decimal::from(3)),
+                (4, L3Action::Remove, 1, Decimal::from(5)),
+                (5, L3Action::Remove, 2, Decimal::ZERO),
+            ]
+        );
+        assert_eq!(state.l3_snapshot().seq, 5);
+        assert!(state.l3_snapshot().orders.is_empty());
+    }
+
+    #[test]
+    fn test_l3_order_serializes_remaining_quantity() {
+        let json = serde_json::to_value(L3Order::from(OrderInfo {
+            filled_quantity: Decimal::ONE,
+            ..order(7, "Sell", 2000, 3)
+        }))
+        .unwrap();
+        assert_eq!(json["order_id"], 7);
+        assert_eq!(json["remaining_quantity"], "2");
+        assert!(json.get("raw_price").is_none());
+    }
+
+    #[test]
+    fn test_imbalance_and_weighted_mid() {
+        let mut state = OrderbookState::new();
+        assert_eq!(state.imbalance(5), None);
+        assert_eq!(state.weighted_mid(5), None);
+        assert_eq!(state.microprice(), None);
+
+        // One-sided: imbalance is defined, prices are not
+        state.add_order(order(1, "Buy", 99, 3));
+        state.add_order(order(2, "Buy", 98, 1));
+        assert_eq!(state.imbalance(5), Some(Decimal::ONE));
+        assert_eq!(state.weighted_mid(5), None);
+        assert_eq!(state.microprice(), None);
+
+        state.add_order(order(3, "Sell", 101, 1));
+        state.add_order(order(4, "Sell", 104, 3));
+        // Top level only: 3 bid vs 1 ask
+        assert_eq!(state.imbalance(1), Some(Decimal::new(5, 1)));
+        assert_eq!(state.weighted_mid(1), state.microprice());
+        assert_eq!(state.microprice(), Some(Decimal::new(10050, 2)));
+        // Both levels: 4 vs 4, bid vwap 98.75, ask vwap 103.25
+        assert_eq!(state.imbalance(2), Some(Decimal::ZERO));
+        assert_eq!(state.weighted_mid(2), Some(Decimal::from(101)));
+    }
+
+    #[test]
+    fn test_snapshot_depth_keeps_the_best_levels() {
+        let mut state = OrderbookState::new();
+        for (id, price) in (1..=10).zip(90..100) {
+            state.add_order(order(id, "Buy", price, 1));
+            state.add_order(order(100 + id, "Sell", price + 10, 2));
+        }
+        // Second order at the best bid, partially filled
+        state.add_order(order(50, "Buy", 99, 4));
+        state
+            .update_order(50, Decimal::ONE, "PartiallyFilled")
+            .unwrap();
+
+        let snapshot = state.get_snapshot_depth(3);
+        let prices = |levels: &[PriceLevel]| levels.iter().map(|l| l.price).collect::<Vec<_>>();
+        assert_eq!(prices(&snapshot.bids), [99, 98, 97].map(Decimal::from));
+        assert_eq!(prices(&snapshot.asks), [100, 101, 102].map(Decimal::from));
+        assert_eq!(snapshot.bids[0].total_quantity, Decimal::from(4));
+        assert_eq!(snapshot.bids[0].order_count, 2);
+        assert_eq!(snapshot.asks[0].total_quantity, Decimal::TWO);
+        assert_eq!(snapshot.summary.total_bid_levels, 10);
+        assert_eq!(snapshot.spread.unwrap().spread, Decimal::ONE);
+
+        let full = state.get_snapshot();
+        assert_eq!(state.get_snapshot_depth(100).bids.len(), full.bids.len());
+        assert_eq!(
+            full.truncated(3).bids[0].total_quantity,
+            snapshot.bids[0].total_quantity
+        );
+    }
+
+    #[test]
+    fn test_snapshot_depth_beyond_the_cap_keeps_the_rest_level() {
+        let mut state = OrderbookState::new().with_level_cap(Some(LevelCap {
+            max_levels: 2,
+            tail: TailMode::Aggregate,
+        }));
+        for id in 1..=5 {
+            state.add_order(order(id, "Buy", 100 - id as i64, 1));
+        }
+        let snapshot = state.get_snapshot_depth(10);
+        assert_eq!(snapshot.bids.len(), 3);
+        assert!(snapshot.bids[2].rest);
+        assert_eq!(snapshot.bids[2].total_quantity, Decimal::from(3));
+        assert_eq!(state.get_snapshot_depth(1).bids.len(), 1);
+    }
+
+    #[test]
+    fn test_checksum_of_known_book() {
+        let mut book = OrderbookState::new();
+        book.add_order(order(1, "Buy", 100, 2));
+        book.add_order(order(2, "Buy", 99, 1));
+        book.add_order(order(3, "Sell", 101, 3));
+        let snapshot = book.get_snapshot();
+
+        // zlib.crc32(b"100:2:101:3:99:1")
+        assert_eq!(
+            book_checksum(&snapshot.bids, &snapshot.asks, 25),
+            0x8CAE_A830
+        );
+        // Depth 1 covers only the best bid and ask
+        assert_eq!(
+            book_checksum(&snapshot.bids, &snapshot.asks, 1),
+            CRC_32.checksum(b"100:2:101:3")
+        );
+    }
+
+    #[test]
+    fn test_snapshots_carry_sequence_and_checksum() {
+        let mut book = OrderbookState::new().with_level_cap(Some(LevelCap {
+            max_levels: 1,
+            tail: TailMode::Aggregate,
+        }));
+        assert_eq!(book.get_snapshot().sequence, 0);
+
+        book.add_order(order(1, "Buy", 100, 2));
+        book.add_order(order(2, "Buy", 99, 1));
+        book.add_order(order(3, "Sell", 101, 3));
+        book.update_order(3, Decimal::ONE, "PartiallyFilled")
+            .unwrap();
+        book.cancel_order(2).unwrap();
+
+        let snapshot = book.get_snapshot();
+        assert_eq!(snapshot.sequence, 5);
+        // Over the book's own levels, whatever depth is returned
+        assert_eq!(snapshot.checksum, CRC_32.checksum(b"100:2:101:2"));
+        assert_eq!(book.get_snapshot_depth(1).checksum, snapshot.checksum);
+
+        // Reads and keepalives don't change the book
+        book.keepalive_if_idle(Instant::now(), Duration::ZERO);
+        assert_eq!(book.get_snapshot().sequence, 5);
+
+        let mut copy = OrderbookState::new();
+        copy.add_order(order(7, "Sell", 101, 2));
+        copy.add_order(order(8, "Buy", 100, 2));
+        assert_eq!(copy.get_snapshot().checksum, snapshot.checksum);
+    }
+
+    #[test]
+    fn test_seeding_during_warmup_broadcasts_once() {
+        let sink = RecordingSink::default();
+        let mut state = OrderbookState::with_sink(Box::new(sink.clone()));
+
+        state.seed_orders(
+            (1..=50).map(|id| order(id, "Buy", 100 + id as i64, 1)),
+            true,
+        );
+
+        let published = sink.published.lock().unwrap();
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0].summary.total_orders, 50);
+        drop(published);
+
+        // Back to one broadcast per mutation afterwards
+        state.add_order(order(51, "Sell", 500, 1));
+        assert_eq!(sink.published.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_send_failures_are_counted_separately() {
+        let sink = RecordingSink {
+            fail_with: Some(SinkError::Failed("closed".to_string())),
+            ..Default::default()
+        };
+        let mut state = OrderbookState::with_sink(Box::new(sink));
+        state.add_order(order(1, "Buy", 100, 5));
+
+        let (tx, _) = broadcast::channel(4);
+        let mut unsubscribed = OrderbookState::with_broadcast(tx);
+        unsubscribed.add_order(order(1, "Buy", 100, 5));
+
+        assert_eq!(state.broadcast_metrics().sends_failed, 1);
+        assert_eq!(unsubscribed.broadcast_metrics().sends_no_subscribers, 1);
+        assert_eq!(unsubscribed.broadcast_metrics().sends_failed, 0);
+    }
+
+    #[test]
+    fn test_keepalive_fires_on_interval_absent_changes() {
+        let sink = RecordingSink::default();
+        let mut state = OrderbookState::with_sink(Box::new(sink.clone()));
+        let interval = Duration::from_secs(10);
+
+        state.add_order(order(1, "Buy", 100, 5));
+        let changed_at = Instant::now();
+
+        // A change was just published, so no keepalive yet
+        assert!(!state.keepalive_if_idle(changed_at + Duration::from_secs(5), interval));
+
+        let first = changed_at + Duration::from_secs(11);
+        assert!(state.keepalive_if_idle(first, interval));
+        assert!(!state.keepalive_if_idle(first + Duration::from_secs(3), interval));
+        assert!(state.keepalive_if_idle(first + interval, interval));
+
+        let published = sink.published.lock().unwrap();
+        assert_eq!(published.len(), 3);
+        assert!(!published[0].keepalive);
+        assert!(published[1].keepalive && published[2].keepalive);
+        assert_eq!(published[2].summary.total_orders, 1);
+    }
+
+    #[test]
+    fn test_oversized_broadcast_is_truncated_and_flagged() {
+        let sink = RecordingSink::default();
+        let mut state = OrderbookState::with_sink(Box::new(sink.clone())).with_broadcast_limit(
+            Some(BroadcastLimit {
+                threshold: 5,
+                top_n: 2,
+            }),
+        );
+
+        for id in 0..5 {
+            state.add_order(order(id, "Buy", 100 + id as i64, 1));
+        }
+        // One level past the threshold
+        state.add_order(order(5, "Buy", 90, 1));
+
+        let published = sink.published.lock().unwrap();
+        let within = &published[4];
+        assert!(!within.truncated && within.full_depth_url.is_none());
+        assert_eq!(within.bids.len(), 5);
+
+        let oversized = &published[5];
+        assert!(oversized.truncated);
+        assert_eq!(oversized.full_depth_url.as_deref(), Some(FULL_DEPTH_PATH));
+        assert_eq!(oversized.bids.len(), 2);
+        assert_eq!(oversized.bids[0].price, Decimal::from(104));
+        assert_eq!(oversized.summary.total_bid_levels, 6);
+
+        // REST snapshots are never truncated
+        assert_eq!(state.get_snapshot().bids.len(), 6);
+    }
+
+    #[test]
+    fn test_partial_fill_mismatch_trips_check() {
+        let mut state = OrderbookState::new();
+        state.add_order(order(1, "Buy", 100, 10));
+        let tolerance = Decimal::new(1, 6);
+
+        // 4 filled + 6 remaining matches the tracked quantity of 10
+        let consistent = state.fill_mismatch(1, Decimal::from(4), Decimal::from(6), tolerance);
+        assert_eq!(consistent, None);
+        // Rounding within tolerance is accepted
+        let rounded =
+            state.fill_mismatch(1, Decimal::new(4_000_001, 6), Decimal::from(6), tolerance);
+        assert_eq!(rounded, None);
+
+        // A missed event leaves the totals 2 short
+        let mismatch = state.fill_mismatch(1, Decimal::from(4), Decimal::from(4), tolerance);
+        assert_eq!(mismatch, Some(Decimal::from(-2)));
+        assert_eq!(
+            state.fill_mismatch(99, Decimal::ONE, Decimal::ONE, tolerance),
+            None
+        );
+
+        // Reconciling with chain state fixes the order in place
+        let mut on_chain = order(1, "Buy", 100, 8);
+        on_chain.filled_quantity = Decimal::from(4);
+        on_chain.status = "PartiallyFilled".to_string();
+        state.reconcile_order(on_chain);
+        assert_eq!(state.orders[&1].quantity, Decimal::from(8));
+        assert_eq!(state.bids[&Decimal::from(100)], vec![1]);
+    }
+
+    #[test]
+    fn test_replayed_placement_is_queued_once() {
+        let mut state = OrderbookState::new();
+        state.add_order(order(1, "Buy", 100, 2));
+        state.add_order(order(2, "Buy", 100, 3));
+        state.add_order(order(1, "Buy", 100, 2));
+
+        assert_eq!(state.bids[&Decimal::from(100)], vec![1, 2]);
+        let level = &state.get_snapshot().bids[0];
+        assert_eq!(
+            (level.total_quantity, level.order_count),
+            (Decimal::from(5), 2)
+        );
+    }
+
+    #[test]
+    fn test_untracked_fill_leaves_levels_untouched() {
+        let mut state = OrderbookState::new();
+        state.add_order(order(1, "Sell", 101, 3));
+        assert!(state.fill_order(1));
+        assert_eq!(state.orders[&1].status, "Filled");
+        assert_eq!(state.orders[&1].filled_quantity, Decimal::from(3));
+        assert!(state.asks.is_empty());
+
+        // Placed before the indexer started: nothing to fill, the book is untouched
+        assert!(!state.fill_order(2));
+        assert!(!state.orders.contains_key(&2));
+
+        // Its final state from chain storage is recorded without touching the levels
+        let mut on_chain = order(2, "Buy", 100, 5);
+        on_chain.filled_quantity = Decimal::from(5);
+        on_chain.status = "Filled".to_string();
+        state.reconcile_order(on_chain);
+        assert_eq!(state.orders[&2].status, "Filled");
+        assert!(state.bids.is_empty());
+        assert_eq!(state.open_order_count(), 0);
+    }
+
+    #[test]
+    fn test_level_cap_keeps_near_touch_precise() {
+        let cap = |tail| LevelCap {
+            max_levels: 3,
+            tail,
+        };
+        let mut aggregated = OrderbookState::new().with_level_cap(Some(cap(TailMode::Aggregate)));
+        let mut dropped = OrderbookState::new().with_level_cap(Some(cap(TailMode::Drop)));
+        for (id, price) in (100..110).enumerate() {
+            aggregated.add_order(order(id as u64, "Buy", price, 1));
+            aggregated.add_order(order(100 + id as u64, "Sell", price + 20, 2));
+            dropped.add_order(order(id as u64, "Buy", price, 1));
+        }
+
+        let snapshot = aggregated.get_snapshot();
+        assert_eq!(snapshot.bids.len(), 4);
+        assert_eq!(snapshot.asks.len(), 4);
+        // Top of book and near-touch levels are exact
+        let top: Vec<_> = snapshot.bids[..3].iter().map(|l| l.price).collect();
+        assert_eq!(
+            top,
+            vec![Decimal::from(109), Decimal::from(108), Decimal::from(107)]
+        );
+        assert!(snapshot.bids[..3]
+            .iter()
+            .all(|l| !l.rest && l.total_quantity == Decimal::ONE));
+        // The tail folds into one level at the farthest price
+        let rest = &snapshot.bids[3];
+        assert!(rest.rest);
+        assert_eq!(rest.price, Decimal::from(100));
+        assert_eq!(rest.total_quantity, Decimal::from(7));
+        assert_eq!(rest.order_count, 7);
+        assert_eq!(snapshot.asks[3].total_quantity, Decimal::from(14));
+        // The summary and the book itself still cover every level
+        assert_eq!(snapshot.summary.total_bid_levels, 10);
+        assert_eq!(aggregated.bids.len(), 10);
+
+        let snapshot = dropped.get_snapshot();
+        assert_eq!(snapshot.bids.len(), 3);
+        assert!(snapshot.bids.iter().all(|l| !l.rest));
+        assert_eq!(snapshot.summary.total_bid_volume, Decimal::from(10));
+    }
 }
@@ -0,0 +1,72 @@
+use rust_decimal::Decimal;
+
+/// On-chain integer amount with `decimals` decimal places, in display units
+pub fn scale_amount(raw: u128, decimals: u32) -> Decimal {
+    Decimal::from_i128_with_scale(raw as i128, decimals).normalize()
+}
+
+/// Decimals of one market's on-chain amounts.
+///
+/// The pallet emits prices in the quote asset's fixed point and quantities in the base
+/// asset's, so the two scale independently (e.g. an 18-decimal base quoted in a
+/// 6-decimal stablecoin).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AmountScale {
+    pub base_decimals: u32,
+    pub quote_decimals: u32,
+}
+
+impl Default for AmountScale {
+    fn default() -> Self {
+        Self {
+            base_decimals: 6,
+            quote_decimals: 6,
+        }
+    }
+}
+
+impl AmountScale {
+    pub fn price(&self, raw: u128) -> Decimal {
+        scale_amount(raw, self.quote_decimals)
+    }
+
+    pub fn quantity(&self, raw: u128) -> Decimal {
+        scale_amount(raw, self.base_decimals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_amount() {
+        assert_eq!(scale_amount(2_000_500_000, 6), Decimal::new(20_005, 1));
+        assert_eq!(scale_amount(1_000_000, 6), Decimal::ONE);
+        assert_eq!(scale_amount(0, 18), Decimal::ZERO);
+        assert_eq!(scale_amount(42, 0), Decimal::from(42));
+        // Matches the previous fixed division by 10^6
+        assert_eq!(
+            scale_amount(123_456_789, 6),
+            Decimal::from(123_456_789) / Decimal::from(1_000_000)
+        );
+    }
+
+    #[test]
+    fn test_price_and_quantity_scale_independently() {
+        let scale = AmountScale {
+            base_decimals: 18,
+            quote_decimals: 6,
+        };
+        // 2000.5 USDC per unit, 1.5 units of an 18-decimal asset
+        assert_eq!(scale.price(2_000_500_000), Decimal::new(20_005, 1));
+        assert_eq!(
+            scale.quantity(1_500_000_000_000_000_000),
+            Decimal::new(15, 1)
+        );
+        assert_eq!(
+            AmountScale::default().quantity(1_500_000),
+            Decimal::new(15, 1)
+        );
+    }
+}
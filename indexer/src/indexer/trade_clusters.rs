@@ -0,0 +1,184 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use tokio::sync::broadcast;
+use tracing::info;
+
+/// Which aggressor sides the detector watches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClusterSides {
+    #[default]
+    Both,
+    Buy,
+    Sell,
+}
+
+impl ClusterSides {
+    /// Parse "both", "buy" or "sell" (case-insensitive)
+    pub fn parse(sides: &str) -> Option<Self> {
+        match sides.trim().to_ascii_lowercase().as_str() {
+            "both" => Some(Self::Both),
+            "buy" => Some(Self::Buy),
+            "sell" => Some(Self::Sell),
+            _ => None,
+        }
+    }
+
+    fn watches(&self, side: &str) -> bool {
+        match self {
+            Self::Both => side == "Buy" || side == "Sell",
+            Self::Buy => side == "Buy",
+            Self::Sell => side == "Sell",
+        }
+    }
+}
+
+/// A burst of same-side aggressive trades
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MomentumSignal {
+    pub symbol: String,
+    /// Aggressor side of the burst ("Buy" or "Sell")
+    pub side: String,
+    /// Trades in the burst
+    pub trades: usize,
+    /// Total quantity of the burst
+    pub volume: Decimal,
+    /// Window the burst fit in, in milliseconds
+    pub window_ms: i64,
+    /// Time of the trade that completed the burst, in milliseconds
+    pub time: i64,
+}
+
+/// Rolling detector for clusters of same-side aggressive trades, e.g. 10 buys in 2s.
+///
+/// Only the most recent trades per side are kept. A burst fires once; trades that
+/// continue it count towards the next signal, so a long run yields one signal per
+/// `min_trades` trades rather than one per trade.
+pub struct ClusterDetector {
+    window_ms: i64,
+    min_trades: usize,
+    sides: ClusterSides,
+    /// `(time, quantity)` of recent buys and sells
+    buys: VecDeque<(i64, Decimal)>,
+    sells: VecDeque<(i64, Decimal)>,
+    signals: broadcast::Sender<MomentumSignal>,
+}
+
+impl ClusterDetector {
+    pub fn new(
+        window_ms: i64,
+        min_trades: usize,
+        sides: ClusterSides,
+        signals: broadcast::Sender<MomentumSignal>,
+    ) -> Self {
+        Self {
+            window_ms,
+            min_trades,
+            sides,
+            buys: VecDeque::new(),
+            sells: VecDeque::new(),
+            signals,
+        }
+    }
+
+    /// Record a trade, publishing and returning a signal when it completes a burst
+    pub fn observe(
+        &mut self,
+        symbol: &str,
+        side: Option<&str>,
+        quantity: Decimal,
+        time_ms: i64,
+    ) -> Option<MomentumSignal> {
+        let side = side.filter(|side| self.sides.watches(side))?;
+        let window_ms = self.window_ms;
+        let recent = if side == "Buy" {
+            &mut self.buys
+        } else {
+            &mut self.sells
+        };
+
+        recent.push_back((time_ms, quantity));
+        while recent
+            .front()
+            .is_some_and(|(time, _)| time_ms - time > window_ms)
+        {
+            recent.pop_front();
+        }
+        if recent.len() < self.min_trades {
+            return None;
+        }
+
+        let signal = MomentumSignal {
+            symbol: symbol.to_string(),
+            side: side.to_string(),
+            trades: recent.len(),
+            volume: recent.iter().map(|(_, quantity)| *quantity).sum(),
+            window_ms,
+            time: time_ms,
+        };
+        recent.clear();
+
+        info!(
+            "🚀 Momentum: {} {} trades ({} volume) within {}ms on {}",
+            signal.trades, signal.side, signal.volume, window_ms, symbol
+        );
+        // Nobody listening is fine, the signal is advisory
+        let _ = self.signals.send(signal.clone());
+        Some(signal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_of_buys_fires_signal() {
+        let (tx, mut rx) = broadcast::channel(8);
+        let mut detector = ClusterDetector::new(2_000, 10, ClusterSides::Both, tx);
+
+        // Sells interleaved with the burst do not count towards it
+        for i in 0..9 {
+            assert_eq!(
+                detector.observe("ETH/USDT", Some("Buy"), Decimal::ONE, 1_000 + i * 100),
+                None
+            );
+            detector.observe("ETH/USDT", Some("Sell"), Decimal::ONE, 1_050 + i * 100);
+        }
+        let signal = detector
+            .observe("ETH/USDT", Some("Buy"), Decimal::TWO, 1_900)
+            .expect("tenth buy within 2s completes the burst");
+        assert_eq!(signal.side, "Buy");
+        assert_eq!(signal.trades, 10);
+        assert_eq!(signal.volume, Decimal::from(11));
+        assert_eq!(rx.try_recv().unwrap(), signal);
+
+        // The burst was consumed: the next buy starts counting afresh
+        assert_eq!(
+            detector.observe("ETH/USDT", Some("Buy"), Decimal::ONE, 2_000),
+            None
+        );
+    }
+
+    #[test]
+    fn test_slow_trades_and_unwatched_sides_never_fire() {
+        let (tx, _rx) = broadcast::channel(8);
+        let mut detector = ClusterDetector::new(2_000, 3, ClusterSides::Sell, tx);
+
+        for i in 0..10 {
+            // One sell every 1.5s never puts three inside a 2s window
+            assert_eq!(
+                detector.observe("ETH/USDT", Some("Sell"), Decimal::ONE, i * 1_500),
+                None
+            );
+            assert_eq!(
+                detector.observe("ETH/USDT", Some("Buy"), Decimal::ONE, i * 10),
+                None
+            );
+        }
+        assert_eq!(
+            detector.observe("ETH/USDT", None, Decimal::ONE, 20_000),
+            None
+        );
+    }
+}
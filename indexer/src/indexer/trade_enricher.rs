@@ -0,0 +1,85 @@
+use crate::config::IndexerConfig;
+use crate::indexer::trade_mapper::TradeData;
+use rust_decimal::Decimal;
+use serde_json::json;
+
+/// Hook for adding computed fields to a trade before it is persisted.
+///
+/// `process_trade` calls [`TradeEnricher::enrich`] once per decoded trade, after the
+/// aggressor side is inferred and before the row is inserted. Implementations write into
+/// `trade.enrichment`, which is stored as JSON alongside the trade. Enrichers take
+/// `&mut self` so they can keep rolling state (e.g. moving averages) across trades.
+pub trait TradeEnricher: Send {
+    fn enrich(&mut self, trade: &mut TradeData);
+}
+
+/// Default enricher that leaves trades untouched
+#[derive(Debug, Default)]
+pub struct NoopEnricher;
+
+impl TradeEnricher for NoopEnricher {
+    fn enrich(&mut self, _trade: &mut TradeData) {}
+}
+
+/// Example enricher tagging each trade as "small", "medium" or "large" by quantity
+#[derive(Debug, Clone)]
+pub struct SizeBucketEnricher {
+    /// Quantities below this are "small"
+    pub small_below: Decimal,
+    /// Quantities at or above this are "large"
+    pub large_from: Decimal,
+}
+
+impl Default for SizeBucketEnricher {
+    fn default() -> Self {
+        Self {
+            small_below: Decimal::ONE,
+            large_from: Decimal::from(10),
+        }
+    }
+}
+
+impl TradeEnricher for SizeBucketEnricher {
+    fn enrich(&mut self, trade: &mut TradeData) {
+        let bucket = if trade.quantity < self.small_below {
+            "small"
+        } else if trade.quantity >= self.large_from {
+            "large"
+        } else {
+            "medium"
+        };
+        trade
+            .enrichment
+            .insert("size_bucket".to_string(), json!(bucket));
+    }
+}
+
+/// Build the enricher selected by `TRADE_ENRICHER` ("none" or "size_bucket")
+pub fn from_config(config: &IndexerConfig) -> Box<dyn TradeEnricher> {
+    match config.trade_enricher.as_str() {
+        "size_bucket" => Box::new(SizeBucketEnricher::default()),
+        _ => Box::new(NoopEnricher),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_bucket_tagging() {
+        let mut enricher = SizeBucketEnricher::default();
+        for (quantity, expected) in [
+            (Decimal::new(5, 1), "small"),
+            (Decimal::from(3), "medium"),
+            (Decimal::from(10), "large"),
+        ] {
+            let mut trade = TradeData {
+                quantity,
+                ..Default::default()
+            };
+            enricher.enrich(&mut trade);
+            assert_eq!(trade.enrichment["size_bucket"], expected);
+        }
+    }
+}
@@ -1,49 +1,165 @@
 use crate::indexer::candle_aggregator::CandleAggregator;
+use crate::indexer::event_hooks::{EventHook, MarketEvent};
+use crate::indexer::orderbook_reducer::OrderbookState;
 use crate::indexer::runtime::TradeExecuted;
+use crate::indexer::scaling::AmountScale;
+use crate::indexer::trade_clusters::ClusterDetector;
+use crate::indexer::trade_enricher::TradeEnricher;
+use crate::telemetry;
 use anyhow::Result;
 use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use sqlx::PgPool;
-use tracing::info;
+use std::time::Instant;
+use tokio::sync::{broadcast, Mutex};
+use tracing::{debug, info, warn};
 
 // TODO: This should come from the event
 // I'm using a placeholder for now
-const SYMBOL: &str = "ETH/USDT";
 
 /// Context for processing trades - holds shared resources
 pub struct TradeProcessingContext<'a> {
     pub pool: &'a PgPool,
     pub candle_agg: &'a mut CandleAggregator,
+    /// Book as it stood before this trade, used to infer the aggressor side
+    pub orderbook: &'a Mutex<OrderbookState>,
+    /// Whether to infer the aggressor side at all
+    pub infer_side: bool,
+    /// Skip trades already stored under the same (block, event index, trade id)
+    pub skip_duplicates: bool,
+    /// Market the trades belong to
+    pub symbol: &'a str,
+    /// Decimals of the market's on-chain prices and quantities
+    pub scale: AmountScale,
+    /// Custom enrichment applied to every trade before it is stored
+    pub enricher: &'a mut dyn TradeEnricher,
+    /// What to do with trades carrying a zero price or quantity
+    pub invalid_policy: InvalidTradePolicy,
+    /// Store the block hash and extrinsic index with each trade
+    pub capture_origin: bool,
+    /// Momentum detector fed with every aggregated trade, None when disabled
+    pub clusters: Option<&'a mut ClusterDetector>,
+    /// Webhook told about large trades, None when event hooks are off
+    pub events: Option<&'a EventHook>,
+    /// Quantity from which a trade counts as large, None to never report one
+    pub large_trade_quantity: Option<Decimal>,
+    /// Live trade feed for websocket subscribers, None when nothing listens
+    pub trades: Option<&'a broadcast::Sender<TradeUpdate>>,
+}
+
+/// A stored trade as pushed to websocket subscribers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TradeUpdate {
+    pub symbol: String,
+    pub trade_id: u64,
+    pub price: Decimal,
+    pub quantity: Decimal,
+    /// Aggressor side ("Buy" or "Sell"), None when unknown
+    pub side: Option<String>,
+    /// Time of the trade's block, in milliseconds
+    pub time: i64,
+}
+
+/// Where an event was emitted on chain
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventOrigin {
+    pub block_number: u32,
+    /// Hex-encoded block hash
+    pub block_hash: String,
+    /// Position of the event within its block
+    pub event_index: u32,
+    /// Extrinsic that emitted the event, None for block initialization/finalization
+    pub extrinsic_index: Option<u32>,
+    /// Block time from the `Timestamp` pallet in milliseconds, None when unknown
+    pub timestamp_ms: Option<i64>,
+}
+
+/// Handling of trades that would corrupt candle volume and VWAP (zero price or quantity)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvalidTradePolicy {
+    /// Record the trade in the dead-letter table only
+    #[default]
+    Reject,
+    /// Store the trade flagged in its enrichment, but keep it out of candles, statistics
+    /// and replays
+    Flag,
+}
+
+impl InvalidTradePolicy {
+    /// Parse "reject" or "flag" (case-insensitive)
+    pub fn parse(policy: &str) -> Option<Self> {
+        match policy.trim().to_ascii_lowercase().as_str() {
+            "reject" => Some(Self::Reject),
+            "flag" => Some(Self::Flag),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Reject => "reject",
+            Self::Flag => "flag",
+        }
+    }
 }
 
 /// Parsed trade data from an event
+#[derive(Debug, Clone, Default)]
 pub struct TradeData {
     pub trade_id: u128,
     pub block_number: u32,
+    /// Position of the event within its block
+    pub event_index: u32,
+    /// Hash of the containing block, None when origin capture is off
+    pub block_hash: Option<String>,
+    /// Extrinsic that emitted the event, None when unknown or capture is off
+    pub extrinsic_index: Option<u32>,
     pub buy_order_id: u128,
     pub sell_order_id: u128,
     pub buyer: String,
     pub seller: String,
     pub price: Decimal,
     pub quantity: Decimal,
+    /// Aggressor side ("Buy" or "Sell"), None when unknown
+    pub side: Option<String>,
+    /// Price and quantity exactly as emitted on chain, before decimal scaling
+    pub raw_price: u128,
+    pub raw_quantity: u128,
+    /// Extra fields added by the configured `TradeEnricher`, stored as JSON
+    pub enrichment: Map<String, Value>,
+    /// Block time in milliseconds; None stores the trade at its insert time
+    pub timestamp_ms: Option<i64>,
 }
 
 impl TradeData {
     /// Parse trade data from a TradeExecuted event using generated types
-    /// Converts u128 values (in 10^6 representation) to Decimal
-    pub fn from_typed_event(event: &TradeExecuted, block_number: u32) -> Self {
-        // Convert u128 to Decimal by dividing by 10^6
-        let price = Decimal::from(event.price) / Decimal::from(1_000_000);
-        let quantity = Decimal::from(event.quantity) / Decimal::from(1_000_000);
+    /// Converts the u128 price and quantity to Decimal at the market's decimals
+    pub fn from_typed_event(
+        event: &TradeExecuted,
+        origin: &EventOrigin,
+        scale: AmountScale,
+    ) -> Self {
+        let price = scale.price(event.price);
+        let quantity = scale.quantity(event.quantity);
 
         Self {
             trade_id: event.trade_id as u128,
-            block_number,
+            block_number: origin.block_number,
+            event_index: origin.event_index,
+            block_hash: Some(origin.block_hash.clone()),
+            extrinsic_index: origin.extrinsic_index,
             buy_order_id: event.buy_order_id as u128,
             sell_order_id: event.sell_order_id as u128,
             buyer: format!("0x{}", hex::encode(event.buyer.0)),
             seller: format!("0x{}", hex::encode(event.seller.0)),
             price,
             quantity,
+            side: None,
+            raw_price: event.price,
+            raw_quantity: event.quantity,
+            enrichment: Map::new(),
+            timestamp_ms: origin.timestamp_ms,
         }
     }
 
@@ -51,15 +167,204 @@ impl TradeData {
     pub fn value(&self) -> Decimal {
         self.price * self.quantity
     }
+
+    /// Why this trade must not be aggregated, if anything is wrong with it
+    pub fn invalid_reason(&self) -> Option<&'static str> {
+        if self.price <= Decimal::ZERO {
+            Some("non-positive price")
+        } else if self.quantity <= Decimal::ZERO {
+            Some("non-positive quantity")
+        } else {
+            None
+        }
+    }
+}
+
+/// Infer which side took liquidity in a trade from the pre-trade book.
+///
+/// The pallet executes every trade at the resting (maker) order's price, so:
+/// - a trade at the sell order's resting price means a buyer lifted the ask -> "Buy"
+/// - a trade at the buy order's resting price means a seller hit the bid -> "Sell"
+///
+/// When both orders rest at the trade price, or the book never saw them (e.g. the
+/// indexer started mid-life), we fall back to order age: order ids are assigned
+/// sequentially, so the newer order is the one that arrived and crossed the spread.
+pub fn infer_aggressor_side(book: &OrderbookState, trade: &TradeData) -> &'static str {
+    let resting_at_trade_price = |order_id: u128| {
+        book.orders
+            .get(&(order_id as u64))
+            .map(|order| order.price == trade.price)
+            .unwrap_or(false)
+    };
+
+    match (
+        resting_at_trade_price(trade.sell_order_id),
+        resting_at_trade_price(trade.buy_order_id),
+    ) {
+        (true, false) => "Buy",
+        (false, true) => "Sell",
+        _ if trade.buy_order_id > trade.sell_order_id => "Buy",
+        _ => "Sell",
+    }
+}
+
+/// Insert a trade, returning `false` when it was skipped as a duplicate.
+///
+/// A trade is only a duplicate when the same event (block number + event index) with the
+/// same trade id is already stored, e.g. after re-indexing a block. Two distinct
+/// `TradeExecuted` events sharing a trade id in one block both persist.
+///
+/// Each stored trade takes the next sequence number of its market, one above the highest
+/// stored; skipped duplicates don't consume one. `created_at` is the block time, so trades
+/// indexed while catching up land in the candles of the minute they happened in.
+pub async fn insert_trade(
+    pool: &PgPool,
+    trade: &TradeData,
+    symbol: &str,
+    skip_duplicates: bool,
+) -> Result<bool> {
+    let insert_started = Instant::now();
+    let inserted = sqlx::query(
+        "INSERT INTO trades
+        (trade_id, block_number, buy_order_id, sell_order_id, buyer, seller, price, quantity, value, symbol, side, event_index, raw_price, raw_quantity, enrichment, block_hash, extrinsic_index, seq, created_at)
+        SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $14::numeric, $15::numeric, $16, $17, $18,
+            COALESCE((SELECT MAX(seq) FROM trades WHERE symbol = $10), 0) + 1,
+            COALESCE(to_timestamp($19::bigint / 1000.0::double precision), NOW())
+        WHERE NOT $13 OR NOT EXISTS (
+            SELECT 1 FROM trades
+            WHERE block_number = $2 AND event_index = $12 AND trade_id = $1
+        )",
+    )
+    .bind(trade.trade_id as i64)
+    .bind(trade.block_number as i64)
+    .bind(trade.buy_order_id as i64)
+    .bind(trade.sell_order_id as i64)
+    .bind(&trade.buyer)
+    .bind(&trade.seller)
+    .bind(trade.price)
+    .bind(trade.quantity)
+    .bind(trade.value())
+    .bind(symbol)
+    .bind(&trade.side)
+    .bind(trade.event_index as i32)
+    .bind(skip_duplicates)
+    .bind(trade.raw_price.to_string())
+    .bind(trade.raw_quantity.to_string())
+    .bind((!trade.enrichment.is_empty()).then_some(sqlx::types::Json(&trade.enrichment)))
+    .bind(&trade.block_hash)
+    .bind(trade.extrinsic_index.map(|index| index as i32))
+    .bind(trade.timestamp_ms)
+    .execute(pool)
+    .await;
+    telemetry::observe_db_write(insert_started.elapsed());
+
+    Ok(inserted?.rows_affected() > 0)
+}
+
+/// Record a trade that failed validation in the dead-letter table
+pub async fn insert_dead_letter(
+    pool: &PgPool,
+    trade: &TradeData,
+    symbol: &str,
+    reason: &str,
+    policy: InvalidTradePolicy,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO trade_dead_letters
+        (trade_id, block_number, event_index, symbol, reason, policy, raw_price, raw_quantity)
+        VALUES ($1, $2, $3, $4, $5, $6, $7::numeric, $8::numeric)",
+    )
+    .bind(trade.trade_id as i64)
+    .bind(trade.block_number as i64)
+    .bind(trade.event_index as i32)
+    .bind(symbol)
+    .bind(reason)
+    .bind(policy.as_str())
+    .bind(trade.raw_price.to_string())
+    .bind(trade.raw_quantity.to_string())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Everything that happens to a trade before it is persisted: decoding, aggressor
+/// side inference and custom enrichment
+pub async fn prepare_trade(
+    ctx: &mut TradeProcessingContext<'_>,
+    origin: &EventOrigin,
+    event: &TradeExecuted,
+) -> TradeData {
+    let mut trade = TradeData::from_typed_event(event, origin, ctx.scale);
+    if !ctx.capture_origin {
+        trade.block_hash = None;
+        trade.extrinsic_index = None;
+    }
+
+    if ctx.infer_side {
+        let book = ctx.orderbook.lock().await;
+        let side = infer_aggressor_side(&book, &trade);
+        debug!("Inferred {} aggressor for trade #{}", side, trade.trade_id);
+        trade.side = Some(side.to_string());
+    }
+
+    ctx.enricher.enrich(&mut trade);
+    trade
+}
+
+/// Fire the large trade webhook for a stored trade at or above the configured quantity
+fn report_large_trade(ctx: &TradeProcessingContext<'_>, trade: &TradeData) {
+    let (Some(events), Some(threshold)) = (ctx.events, ctx.large_trade_quantity) else {
+        return;
+    };
+    if trade.quantity < threshold {
+        return;
+    }
+    info!(
+        "🐋 Large trade #{}: {} at {}",
+        trade.trade_id, trade.quantity, trade.price
+    );
+    events.fire(MarketEvent::LargeTrade {
+        symbol: ctx.symbol.to_string(),
+        trade_id: trade.trade_id,
+        price: trade.price,
+        quantity: trade.quantity,
+        side: trade.side.clone(),
+        block_number: trade.block_number,
+    });
 }
 
 /// Parse TradeExecuted event and insert into database with candle updates
 pub async fn process_trade(
     ctx: &mut TradeProcessingContext<'_>,
-    block_number: u32,
+    origin: &EventOrigin,
     event: &TradeExecuted,
 ) -> Result<()> {
-    let trade = TradeData::from_typed_event(event, block_number);
+    let mut trade = prepare_trade(ctx, origin, event).await;
+
+    let invalid_reason = trade.invalid_reason();
+    if let Some(reason) = invalid_reason {
+        warn!(
+            "⚠️  Trade #{} (block {}, event {}) has {} (raw price={}, qty={}), policy: {}",
+            trade.trade_id,
+            trade.block_number,
+            trade.event_index,
+            reason,
+            trade.raw_price,
+            trade.raw_quantity,
+            ctx.invalid_policy.as_str()
+        );
+        insert_dead_letter(ctx.pool, &trade, ctx.symbol, reason, ctx.invalid_policy).await?;
+
+        match ctx.invalid_policy {
+            InvalidTradePolicy::Reject => return Ok(()),
+            InvalidTradePolicy::Flag => {
+                trade
+                    .enrichment
+                    .insert("invalid".to_string(), reason.into());
+            }
+        }
+    }
 
     info!(
         "🎯 TradeExecuted parsed: trade_id={}, buy={}, sell={}, price={}, qty={}, value={}",
@@ -71,33 +376,404 @@ pub async fn process_trade(
         trade.value()
     );
 
-    let value = trade.value();
-
     // Insert into trades table
-    sqlx::query(
-        "INSERT INTO trades
-        (trade_id, block_number, buy_order_id, sell_order_id, buyer, seller, price, quantity, value, symbol)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
-    )
-    .bind(trade.trade_id as i64)
-    .bind(trade.block_number as i64)
-    .bind(trade.buy_order_id as i64)
-    .bind(trade.sell_order_id as i64)
-    .bind(&trade.buyer)
-    .bind(&trade.seller)
-    .bind(trade.price)
-    .bind(trade.quantity)
-    .bind(value)
-    .bind(SYMBOL)
-    .execute(ctx.pool)
-    .await?;
+    if !insert_trade(ctx.pool, &trade, ctx.symbol, ctx.skip_duplicates).await? {
+        info!(
+            "⏭️  Trade #{} (block {}, event {}) already indexed, skipping",
+            trade.trade_id, trade.block_number, trade.event_index
+        );
+        return Ok(());
+    }
 
     info!("✅ Trade #{} inserted into database!", trade.trade_id);
 
+    if invalid_reason.is_some() {
+        // Flagged trades are kept for the record; every candle, tape and replay query
+        // filters them out with `price > 0 AND quantity > 0`
+        return Ok(());
+    }
+    report_large_trade(ctx, &trade);
+
     // Update candles and broadcast to websocket subscribers
-    let timestamp_ms = chrono::Utc::now().timestamp_millis();
+    let timestamp_ms = trade
+        .timestamp_ms
+        .unwrap_or_else(|| chrono::Utc::now().timestamp_millis());
     ctx.candle_agg
-        .process_trade(SYMBOL, trade.price, trade.quantity, timestamp_ms)?;
+        .process_trade(ctx.symbol, trade.price, trade.quantity, timestamp_ms)?;
+
+    if let Some(clusters) = ctx.clusters.as_deref_mut() {
+        clusters.observe(
+            ctx.symbol,
+            trade.side.as_deref(),
+            trade.quantity,
+            timestamp_ms,
+        );
+    }
+
+    if let Some(trades) = ctx.trades {
+        // No subscribers is not an error
+        let _ = trades.send(TradeUpdate {
+            symbol: ctx.symbol.to_string(),
+            trade_id: trade.trade_id as u64,
+            price: trade.price,
+            quantity: trade.quantity,
+            side: trade.side.clone(),
+            time: timestamp_ms,
+        });
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::orderbook_reducer::OrderInfo;
+
+    fn resting(order_id: u64, side: &str, price: i64) -> OrderInfo {
+        OrderInfo {
+            order_id,
+            side: side.to_string(),
+            price: Decimal::from(price),
+            quantity: Decimal::from(10),
+            filled_quantity: Decimal::ZERO,
+            status: "Open".to_string(),
+            raw_price: price as u128 * 1_000_000,
+            raw_quantity: 10_000_000,
+        }
+    }
+
+    fn trade(buy_order_id: u128, sell_order_id: u128, price: i64) -> TradeData {
+        TradeData {
+            trade_id: 1,
+            block_number: 1,
+            event_index: 0,
+            block_hash: None,
+            extrinsic_index: None,
+            buy_order_id,
+            sell_order_id,
+            buyer: "0xb".to_string(),
+            seller: "0xs".to_string(),
+            price: Decimal::from(price),
+            quantity: Decimal::from(1),
+            side: None,
+            raw_price: price as u128 * 1_000_000,
+            raw_quantity: 1_000_000,
+            enrichment: Map::new(),
+            timestamp_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_buy_aggressor_lifts_resting_ask() {
+        let mut book = OrderbookState::new();
+        book.add_order(resting(1, "Sell", 100));
+        book.add_order(resting(2, "Buy", 105));
+
+        assert_eq!(infer_aggressor_side(&book, &trade(2, 1, 100)), "Buy");
+    }
+
+    #[test]
+    fn test_sell_aggressor_hits_resting_bid() {
+        let mut book = OrderbookState::new();
+        book.add_order(resting(1, "Buy", 100));
+        book.add_order(resting(2, "Sell", 95));
+
+        assert_eq!(infer_aggressor_side(&book, &trade(1, 2, 100)), "Sell");
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_same_trade_id_in_one_block_both_persist(pool: PgPool) {
+        let mut first = trade(2, 1, 100);
+        first.event_index = 4;
+        let mut second = trade(3, 1, 100);
+        second.event_index = 7;
+
+        assert!(insert_trade(&pool, &first, "ETH/USDT", true).await.unwrap());
+        assert!(insert_trade(&pool, &second, "ETH/USDT", true)
+            .await
+            .unwrap());
+        // Re-indexing the same event is a true duplicate
+        assert!(!insert_trade(&pool, &first, "ETH/USDT", true).await.unwrap());
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM trades WHERE trade_id = 1")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_trade_sequences_are_contiguous_per_market(pool: PgPool) {
+        for (event_index, symbol) in ["ETH/USDT", "BTC/USD", "ETH/USDT", "ETH/USDT", "BTC/USD"]
+            .into_iter()
+            .enumerate()
+        {
+            let mut next = trade(2, 1, 100);
+            next.event_index = event_index as u32;
+            assert!(insert_trade(&pool, &next, symbol, true).await.unwrap());
+        }
+        // A skipped duplicate leaves no hole
+        let mut duplicate = trade(2, 1, 100);
+        duplicate.event_index = 0;
+        assert!(!insert_trade(&pool, &duplicate, "ETH/USDT", true)
+            .await
+            .unwrap());
+
+        let seqs = |symbol: &'static str| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query_scalar::<_, i64>(
+                    "SELECT seq FROM trades WHERE symbol = $1 ORDER BY event_index",
+                )
+                .bind(symbol)
+                .fetch_all(&pool)
+                .await
+                .unwrap()
+            }
+        };
+        assert_eq!(seqs("ETH/USDT").await, vec![1, 2, 3]);
+        assert_eq!(seqs("BTC/USD").await, vec![1, 2]);
+    }
+
+    /// Enricher that counts how often it ran and tags trades with that count
+    #[derive(Default)]
+    struct RecordingEnricher {
+        seen: Vec<u128>,
+    }
+
+    impl TradeEnricher for RecordingEnricher {
+        fn enrich(&mut self, trade: &mut TradeData) {
+            self.seen.push(trade.trade_id);
+            trade
+                .enrichment
+                .insert("seen".to_string(), self.seen.len().into());
+        }
+    }
+
+    fn trade_event(trade_id: u64) -> TradeExecuted {
+        TradeExecuted {
+            trade_id,
+            buy_order_id: 2,
+            sell_order_id: 1,
+            buyer: subxt::utils::AccountId32([1; 32]),
+            seller: subxt::utils::AccountId32([2; 32]),
+            price: 100_000_000,
+            quantity: 1_000_000,
+        }
+    }
+
+    fn origin(block_number: u32, event_index: u32) -> EventOrigin {
+        EventOrigin {
+            block_number,
+            block_hash: format!("0x{:064x}", block_number),
+            event_index,
+            extrinsic_index: Some(event_index / 2),
+            timestamp_ms: Some(1_700_000_000_000 + block_number as i64 * 6_000),
+        }
+    }
+
+    /// A context for `ETH/USDT` storing trades as-is; tests override what they exercise
+    fn ctx<'a>(
+        pool: &'a PgPool,
+        candle_agg: &'a mut CandleAggregator,
+        orderbook: &'a Mutex<OrderbookState>,
+        enricher: &'a mut dyn TradeEnricher,
+    ) -> TradeProcessingContext<'a> {
+        TradeProcessingContext {
+            pool,
+            candle_agg,
+            orderbook,
+            infer_side: false,
+            skip_duplicates: true,
+            symbol: "ETH/USDT",
+            scale: AmountScale::default(),
+            enricher,
+            invalid_policy: InvalidTradePolicy::Reject,
+            capture_origin: true,
+            clusters: None,
+            events: None,
+            large_trade_quantity: None,
+            trades: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_enricher_invoked_per_trade() {
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        let (candle_tx, _) = tokio::sync::broadcast::channel(8);
+        let mut candle_agg = CandleAggregator::new(candle_tx);
+        let orderbook = Mutex::new(OrderbookState::new());
+        let mut enricher = RecordingEnricher::default();
+
+        let mut ctx = TradeProcessingContext {
+            infer_side: true,
+            ..ctx(&pool, &mut candle_agg, &orderbook, &mut enricher)
+        };
+
+        let first = prepare_trade(&mut ctx, &origin(10, 0), &trade_event(1)).await;
+        let second = prepare_trade(&mut ctx, &origin(10, 1), &trade_event(2)).await;
+
+        assert_eq!(first.enrichment["seen"], 1);
+        assert_eq!(second.enrichment["seen"], 2);
+        assert_eq!(enricher.seen, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_invalid_reason_flags_zero_values() {
+        assert_eq!(trade(2, 1, 100).invalid_reason(), None);
+        assert_eq!(trade(2, 1, 0).invalid_reason(), Some("non-positive price"));
+
+        let mut zero_qty = trade(2, 1, 100);
+        zero_qty.quantity = Decimal::ZERO;
+        assert_eq!(zero_qty.invalid_reason(), Some("non-positive quantity"));
+
+        assert_eq!(
+            InvalidTradePolicy::parse("FLAG"),
+            Some(InvalidTradePolicy::Flag)
+        );
+        assert_eq!(InvalidTradePolicy::parse("drop"), None);
+    }
+
+    #[sqlx::test(migrations = "db/migrations")]
+    #[ignore = "requires a TimescaleDB instance via DATABASE_URL"]
+    async fn test_zero_quantity_trade_skips_candle_volume(pool: PgPool) {
+        let (candle_tx, _) = tokio::sync::broadcast::channel(8);
+        let mut candle_agg = CandleAggregator::new(candle_tx);
+        let reader = candle_agg.reader();
+        let (trade_tx, mut trade_rx) = broadcast::channel(8);
+        let orderbook = Mutex::new(OrderbookState::new());
+        let mut enricher = crate::indexer::trade_enricher::NoopEnricher;
+
+        for policy in [InvalidTradePolicy::Reject, InvalidTradePolicy::Flag] {
+            let mut ctx = TradeProcessingContext {
+                invalid_policy: policy,
+                trades: Some(&trade_tx),
+                ..ctx(&pool, &mut candle_agg, &orderbook, &mut enricher)
+            };
+            let mut zero_qty = trade_event(10);
+            zero_qty.quantity = 0;
+            process_trade(&mut ctx, &origin(10, 0), &zero_qty)
+                .await
+                .unwrap();
+        }
+        assert!(reader.current("ETH/USDT", "1m").is_none());
+
+        let mut ctx = TradeProcessingContext {
+            trades: Some(&trade_tx),
+            ..ctx(&pool, &mut candle_agg, &orderbook, &mut enricher)
+        };
+        process_trade(&mut ctx, &origin(11, 0), &trade_event(11))
+            .await
+            .unwrap();
+        let candle = reader.current("ETH/USDT", "1m").unwrap();
+        assert_eq!(candle.volume, Decimal::ONE);
+        // Only the valid trade reaches the live feed
+        let update = trade_rx.try_recv().unwrap();
+        assert_eq!((update.trade_id, update.quantity), (11, Decimal::ONE));
+        assert!(trade_rx.try_recv().is_err());
+
+        // Stored and streamed at its block's time, not the time it was indexed
+        let block_time = origin(11, 0).timestamp_ms.unwrap();
+        let created_at: i64 = sqlx::query_scalar(
+            "SELECT (EXTRACT(EPOCH FROM created_at) * 1000)::bigint FROM trades WHERE trade_id = 11",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!((created_at, update.time), (block_time, block_time));
+
+        let dead_letters: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM trade_dead_letters")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(dead_letters, 2);
+        // Only the flagged copy made it into the trades table
+        let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM trades WHERE trade_id = 10")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, 1);
+    }
+
+    #[tokio::test]
+    async fn test_trade_captures_block_origin() {
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        let (candle_tx, _) = tokio::sync::broadcast::channel(8);
+        let mut candle_agg = CandleAggregator::new(candle_tx);
+        let orderbook = Mutex::new(OrderbookState::new());
+        let mut enricher = crate::indexer::trade_enricher::NoopEnricher;
+        let source = EventOrigin {
+            block_number: 42,
+            block_hash: "0xabc123".to_string(),
+            event_index: 5,
+            extrinsic_index: Some(2),
+            timestamp_ms: Some(1_700_000_000_000),
+        };
+
+        let mut ctx = ctx(&pool, &mut candle_agg, &orderbook, &mut enricher);
+        let trade = prepare_trade(&mut ctx, &source, &trade_event(1)).await;
+        assert_eq!(trade.block_number, 42);
+        assert_eq!(trade.event_index, 5);
+        assert_eq!(trade.block_hash.as_deref(), Some("0xabc123"));
+        assert_eq!(trade.extrinsic_index, Some(2));
+        assert_eq!(trade.timestamp_ms, Some(1_700_000_000_000));
+
+        // With capture off the trade keeps its natural key but no extra metadata
+        ctx.capture_origin = false;
+        let trade = prepare_trade(&mut ctx, &source, &trade_event(2)).await;
+        assert_eq!((trade.block_number, trade.event_index), (42, 5));
+        assert_eq!(trade.block_hash, None);
+        assert_eq!(trade.extrinsic_index, None);
+    }
+
+    #[tokio::test]
+    async fn test_large_trade_fires_webhook() {
+        use crate::indexer::event_hooks::tests::{mock_receiver, next};
+        use crate::indexer::event_hooks::MarketEventKind;
+
+        let (url, mut rx) = mock_receiver(0).await;
+        let hook = EventHook::new(
+            url,
+            vec![MarketEventKind::LargeTrade],
+            std::time::Duration::from_secs(2),
+            0,
+        );
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        let (candle_tx, _) = tokio::sync::broadcast::channel(8);
+        let mut candle_agg = CandleAggregator::new(candle_tx);
+        let orderbook = Mutex::new(OrderbookState::new());
+        let mut enricher = crate::indexer::trade_enricher::NoopEnricher;
+        let ctx = TradeProcessingContext {
+            events: Some(&hook),
+            large_trade_quantity: Some(Decimal::from(10)),
+            ..ctx(&pool, &mut candle_agg, &orderbook, &mut enricher)
+        };
+
+        let mut small = trade(2, 1, 100);
+        small.quantity = Decimal::from(9);
+        report_large_trade(&ctx, &small);
+        let mut large = trade(2, 1, 100);
+        large.trade_id = 5;
+        large.quantity = Decimal::from(10);
+        report_large_trade(&ctx, &large);
+
+        // Only the trade at the threshold is reported
+        let body = next(&mut rx).await;
+        assert_eq!(body["event"], "large_trade");
+        assert_eq!(body["trade_id"], 5);
+        assert_eq!(body["quantity"], "10");
+        assert_eq!(body["symbol"], "ETH/USDT");
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_unknown_orders_fall_back_to_order_age() {
+        let book = OrderbookState::new();
+        assert_eq!(infer_aggressor_side(&book, &trade(7, 3, 100)), "Buy");
+        assert_eq!(infer_aggressor_side(&book, &trade(3, 7, 100)), "Sell");
+    }
+}
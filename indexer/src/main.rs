@@ -1,18 +1,33 @@
 use anyhow::Result;
 use dotenvy::dotenv;
 use std::env;
-use tokio::sync::broadcast;
-use tracing::info;
+use tokio::sync::{broadcast, watch};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
 
 mod api;
+mod config;
 mod db;
 mod indexer;
+mod telemetry;
 
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
-use indexer::candle_aggregator::{CandleAggregator, CandleUpdate};
-use indexer::orderbook_reducer::OrderbookState;
+use api::handlers::ticker_hand::TickerCache;
+use api::state::{AppState, Channels};
+use api::websocket::disconnect::DisconnectHook;
+use config::IndexerConfig;
+use indexer::backpressure::{Backpressure, BroadcastBacklog};
+use indexer::candle_aggregator::{CandleAggregator, CandleSource, CandleUpdate};
+use indexer::event_collector::{CollectorContext, CollectorProgress};
+use indexer::node_failover::{run_with_failover, NodeEndpoints};
+use indexer::orderbook_reducer::{L3Event, OrderbookState};
+use indexer::trade_clusters::MomentumSignal;
+use indexer::trade_mapper::TradeUpdate;
+
+/// How long open API connections get to finish on shutdown
+const SHUTDOWN_GRACE: std::time::Duration = std::time::Duration::from_secs(10);
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -31,7 +46,6 @@ async fn main() -> Result<()> {
         .with_line_number(true)
         .init();
 
-    let node_url = env::var("NODE_WS_URL").unwrap_or_else(|_| "ws://127.0.0.1:9944".to_string());
     let db_url = format!(
         "postgres://{}:{}@{}:{}/{}",
         env::var("POSTGRES_USER").unwrap_or_else(|_| "postgres".to_string()),
@@ -41,53 +55,293 @@ async fn main() -> Result<()> {
         env::var("POSTGRES_DB").unwrap_or_else(|_| "orbex".to_string()),
     );
 
+    let config = IndexerConfig::from_env();
+    config.validate()?;
+    let config = Arc::new(config);
+    let nodes = Arc::new(NodeEndpoints::new(config.node_urls.clone()));
+
     info!("🚀 Starting Orderbook Indexer");
-    info!("📡 Node URL: {}", node_url);
+    info!("📡 Node URLs: {}", config.node_urls.join(", "));
     info!("🗄️  Database: {}", db_url);
 
+    let metrics_handle = if config.metrics_enabled {
+        info!("📏 Installing Prometheus metrics recorder...");
+        Some(telemetry::install()?)
+    } else {
+        None
+    };
+
     // Initialize database
     info!("📊 Connecting to database...");
     let pool = db::init_pool(&db_url).await?;
+    if config.run_migrations {
+        db::run_migrations(&pool).await?;
+    }
 
     // Create broadcast channels for push-based updates
     info!("📊 Initializing broadcast channels...");
 
     // Orderbook update channel (broadcasts full snapshots)
-    let (ob_tx, _) = broadcast::channel::<indexer::orderbook_reducer::OrderbookSnapshot>(1000);
+    let (ob_tx, _) = broadcast::channel::<indexer::orderbook_reducer::OrderbookSnapshot>(
+        config.ob_broadcast_capacity,
+    );
 
     // OHLCV update channel
-    let (candle_tx, _) = broadcast::channel::<CandleUpdate>(1000);
+    let (candle_tx, _) = broadcast::channel::<CandleUpdate>(config.candle_broadcast_capacity);
+
+    // Momentum signals from the trade cluster detector
+    let (signal_tx, _) = broadcast::channel::<MomentumSignal>(100);
+
+    // Live trade feed
+    let (trade_tx, _) = broadcast::channel::<TradeUpdate>(1000);
+
+    // Per-order (L3) events
+    let (l3_tx, _) = broadcast::channel::<L3Event>(1000);
 
     info!("📈 Initializing orderbook state with broadcast...");
-    let orderbook_state = Arc::new(Mutex::new(OrderbookState::with_broadcast(ob_tx.clone())));
+    let orderbook_state = Arc::new(Mutex::new(
+        OrderbookState::with_broadcast(ob_tx.clone())
+            .with_level_cap(config.level_cap())
+            .with_broadcast_limit(config.broadcast_limit())
+            .with_checksum_levels(config.book_checksum_levels)
+            .with_l3_feed(l3_tx.clone()),
+    ));
+
+    if config.snapshot_keepalive_secs > 0 {
+        info!(
+            "💓 Sending keepalive snapshots after {}s without changes",
+            config.snapshot_keepalive_secs
+        );
+        tokio::spawn(indexer::orderbook_reducer::run_keepalive(
+            orderbook_state.clone(),
+            std::time::Duration::from_secs(config.snapshot_keepalive_secs),
+        ));
+    }
 
     // Initialize candle aggregator
-    let candle_aggregator = Arc::new(Mutex::new(CandleAggregator::new(candle_tx.clone())));
-
-    // Clone for API server
-    let orderbook_for_api = orderbook_state.clone();
-    let pool_for_api = pool.clone();
-    let ob_tx_for_api = ob_tx.clone();
-    let candle_tx_for_api = candle_tx.clone();
-
-    // Start API server in background
-    info!("🌐 Starting API server...");
-    tokio::spawn(async move {
-        if let Err(e) = api::server::run_server(
-            orderbook_for_api,
-            pool_for_api,
-            ob_tx_for_api,
-            candle_tx_for_api,
+    let mut candle_aggregator = CandleAggregator::with_config(candle_tx.clone(), &config);
+    match candle_aggregator.backfill_from_db(&pool).await {
+        Ok(restored) => info!(
+            "🕯️  Backfilled {} open candles from stored trades",
+            restored
+        ),
+        Err(e) => warn!(
+            "⚠️  Candle backfill failed, starting with empty candles: {}",
+            e
+        ),
+    }
+    let candle_reader = candle_aggregator.reader();
+    let candle_aggregator = Arc::new(Mutex::new(candle_aggregator));
+
+    if config.candle_source == CandleSource::External {
+        info!(
+            "🕯️  Rolling up 1m candles from {}",
+            config.minute_candle_table
+        );
+        tokio::spawn(indexer::candle_aggregator::run_minute_feed(
+            pool.clone(),
+            candle_aggregator.clone(),
+            config.minute_candle_table.clone(),
+            config
+                .markets
+                .iter()
+                .map(|market| market.symbol.clone())
+                .collect(),
+        ));
+    }
+
+    // The API follows the live channels through these, so a restarted component can
+    // publish replacement senders without disconnecting WebSocket clients
+    let ob_channel = watch::channel(ob_tx.clone()).0;
+    let candle_channel = watch::channel(candle_tx.clone()).0;
+    let signal_channel = watch::channel(signal_tx.clone()).0;
+    let trade_channel = watch::channel(trade_tx.clone()).0;
+    let l3_channel = watch::channel(l3_tx).0;
+
+    if config.trade_retention_secs > 0 {
+        info!(
+            "🧹 Pruning raw trades older than {}s every {}s",
+            config.trade_retention_secs, config.trade_retention_interval_secs
+        );
+        tokio::spawn(db::retention::run_retention(
+            pool.clone(),
+            std::time::Duration::from_secs(config.trade_retention_secs as u64),
+            std::time::Duration::from_secs(config.trade_retention_interval_secs as u64),
+        ));
+    }
+
+    if config.book_sample_secs > 0 {
+        tokio::spawn(indexer::book_sampler::run_sampler(
+            pool.clone(),
+            orderbook_state.clone(),
+            config.default_symbol.clone(),
+            std::time::Duration::from_secs(config.book_sample_secs),
+        ));
+    }
+
+    let index_pricer = indexer::index_price::from_config(&config);
+    let progress = Arc::new(CollectorProgress::default());
+
+    if config.persist_orderbook {
+        match db::orders::restore_orderbook(
+            &pool,
+            &config.default_symbol,
+            &orderbook_state,
+            config.seed_warmup,
         )
         .await
         {
+            Ok(Some(last_block)) => progress.resume_from(last_block),
+            Ok(None) => info!("📭 No persisted orderbook, starting from chain state"),
+            Err(e) => warn!("⚠️  Restoring the persisted orderbook failed: {}", e),
+        }
+    } else if !config.seed_from_chain {
+        // Without a seed the book is built from events alone, so missed blocks can be
+        // replayed; a book seeded at the head must not see older events again
+        match db::orders::load_last_block(&pool).await {
+            Ok(Some(last_block)) => progress.resume_from(last_block),
+            Ok(None) => {}
+            Err(e) => warn!("⚠️  Loading the last indexed block failed: {}", e),
+        }
+    }
+
+    let event_hook = indexer::event_hooks::EventHook::from_config(&config).map(Arc::new);
+    if let (Some(hook), true) = (&event_hook, config.stall_alert_secs > 0) {
+        tokio::spawn(indexer::event_hooks::run_stall_watch(
+            progress.clone(),
+            hook.clone(),
+            std::time::Duration::from_secs(config.stall_alert_secs),
+        ));
+    }
+
+    let app_state = AppState {
+        orderbook: orderbook_state.clone(),
+        pool: pool.clone(),
+        candles: candle_reader.clone(),
+        channels: Channels {
+            orderbook: ob_channel.subscribe(),
+            candles: candle_channel.subscribe(),
+            signals: signal_channel.subscribe(),
+            trades: trade_channel.subscribe(),
+            l3: l3_channel.subscribe(),
+        },
+        disconnect_hook: config.disconnect_webhook_url.clone().map(|url| {
+            Arc::new(api::websocket::disconnect::WebhookHook::new(url)) as Arc<dyn DisconnectHook>
+        }),
+        index_pricer: index_pricer.clone(),
+        nodes: nodes.clone(),
+        progress: progress.clone(),
+        tickers: Arc::new(TickerCache::new(std::time::Duration::from_millis(
+            config.ticker_cache_ms,
+        ))),
+        config: config.clone(),
+    };
+
+    // Ctrl+C stops the collector after its current block and drains the API
+    let shutdown = CancellationToken::new();
+    tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                info!("🛑 Shutdown requested, finishing the current block...");
+            }
+            shutdown.cancel();
+        }
+    });
+
+    // Start API server in background, optionally only once the collector is ready
+    let wait_for = config.wait_for_ready.then(|| progress.clone());
+    let server_shutdown = shutdown.clone();
+    let server = tokio::spawn(async move {
+        if let Some(progress) = wait_for {
+            info!("⏳ API server waits for the cold-start seed and first block...");
+            tokio::select! {
+                _ = progress.wait_ready() => {}
+                _ = server_shutdown.cancelled() => return,
+            }
+        }
+        info!("🌐 Starting API server...");
+        if let Err(e) = api::server::run_server(app_state, metrics_handle, server_shutdown).await {
             eprintln!("❌ API server error: {}", e);
         }
     });
 
-    // Start event collector
-    info!("🔌 Connecting to node at {}", node_url);
-    indexer::event_collector::start(&node_url, pool, orderbook_state, candle_aggregator).await?;
+    if config.grpc_enabled {
+        let service = api::grpc::MarketDataService::new(
+            orderbook_state.clone(),
+            ob_tx.clone(),
+            candle_tx.clone(),
+            candle_reader,
+            index_pricer,
+            config.clone(),
+        );
+        let grpc_port = config.grpc_port;
+        tokio::spawn(async move {
+            if let Err(e) = api::grpc::run_grpc_server(service, grpc_port).await {
+                eprintln!("❌ gRPC server error: {}", e);
+            }
+        });
+    }
+
+    let backpressure = Arc::new(
+        Backpressure::default()
+            .watch(
+                indexer::backpressure::DbBacklog(pool.clone()),
+                config.backpressure_db_busy,
+            )
+            .watch(
+                BroadcastBacklog("orderbook broadcast", ob_tx.clone()),
+                config.backpressure_broadcast_backlog,
+            )
+            .watch(
+                BroadcastBacklog("candle broadcast", candle_tx.clone()),
+                config.backpressure_broadcast_backlog,
+            ),
+    );
 
+    // Start event collector, failing over between nodes on connection loss
+    let collector = CollectorContext {
+        pool: pool.clone(),
+        orderbook: orderbook_state.clone(),
+        candles: candle_aggregator.clone(),
+        config: config.clone(),
+        progress: progress.clone(),
+        backpressure,
+        signals: signal_tx,
+        trades: trade_tx,
+        events: event_hook,
+        shutdown: shutdown.clone(),
+    };
+    run_with_failover(&nodes, config.node_retry_backoff(), &shutdown, |node_url| {
+        let collector = collector.clone();
+        async move {
+            if collector.shutdown.is_cancelled() {
+                return Ok(());
+            }
+            indexer::event_collector::start(&node_url, collector).await
+        }
+    })
+    .await?;
+
+    // Every write of the last block was awaited; let open API requests finish and
+    // release the database connections. Long-lived WebSockets don't hold up the exit.
+    shutdown.cancel();
+    match tokio::time::timeout(SHUTDOWN_GRACE, server).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => warn!("⚠️  API server task failed: {}", e),
+        Err(_) => warn!(
+            "⚠️  API connections still open after {:?}, exiting",
+            SHUTDOWN_GRACE
+        ),
+    }
+    pool.close().await;
+
+    let (blocks, events) = progress.processed();
+    info!(
+        "👋 Indexer stopped after {} blocks and {} events (last block {})",
+        blocks,
+        events,
+        progress.last_block()
+    );
     Ok(())
 }
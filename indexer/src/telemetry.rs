@@ -0,0 +1,168 @@
+use anyhow::Result;
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use std::time::{Duration, Instant};
+
+/// Time spent processing a whole finalized block
+pub const BLOCK_PROCESSING_SECONDS: &str = "indexer_block_processing_seconds";
+/// Time spent decoding a single pallet event into its typed form
+pub const EVENT_DECODE_SECONDS: &str = "indexer_event_decode_seconds";
+/// Time spent on a single database write
+pub const DB_WRITE_SECONDS: &str = "indexer_db_write_seconds";
+/// Partial fills whose filled + remaining quantity disagreed with the tracked order
+pub const FILL_MISMATCHES_TOTAL: &str = "indexer_fill_mismatches_total";
+/// OrderFilled events for orders the book never saw placed
+pub const UNTRACKED_FILLS_TOTAL: &str = "indexer_untracked_fills_total";
+/// Raw trades deleted by the retention task
+pub const TRADES_PRUNED_TOTAL: &str = "indexer_trades_pruned_total";
+/// 1 while block processing is paused for backpressure, 0 otherwise
+pub const COLLECTOR_PAUSED: &str = "indexer_collector_paused";
+/// Finalized blocks fully processed
+pub const BLOCKS_PROCESSED_TOTAL: &str = "indexer_blocks_processed_total";
+/// Chain events seen, labelled by `pallet` and `event` (e.g. TradeExecuted)
+pub const EVENTS_TOTAL: &str = "indexer_events_total";
+/// Orders resting on the in-memory book after the last processed block
+pub const OPEN_ORDERS: &str = "indexer_open_orders";
+/// Open websocket connections, labelled by `endpoint`
+pub const WS_CONNECTIONS: &str = "indexer_ws_connections";
+/// Broadcast messages skipped by lagging websocket clients, labelled by `channel`
+pub const BROADCAST_LAGGED_TOTAL: &str = "indexer_broadcast_lagged_total";
+
+/// Histogram buckets (seconds), spanning sub-millisecond decodes to multi-second blocks
+const LATENCY_BUCKETS: &[f64] = &[
+    0.0001, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Build a Prometheus recorder with our latency buckets, without installing it
+pub fn build_recorder() -> Result<metrics_exporter_prometheus::PrometheusRecorder> {
+    Ok(PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), LATENCY_BUCKETS)?
+        .build_recorder())
+}
+
+/// Install the global Prometheus recorder, returning a handle that renders `/metrics`
+pub fn install() -> Result<PrometheusHandle> {
+    let recorder = build_recorder()?;
+    let handle = recorder.handle();
+    metrics::set_global_recorder(recorder)?;
+    Ok(handle)
+}
+
+pub fn observe_block(elapsed: Duration) {
+    metrics::histogram!(BLOCK_PROCESSING_SECONDS).record(elapsed.as_secs_f64());
+}
+
+pub fn observe_db_write(elapsed: Duration) {
+    metrics::histogram!(DB_WRITE_SECONDS).record(elapsed.as_secs_f64());
+}
+
+pub fn record_fill_mismatch() {
+    metrics::counter!(FILL_MISMATCHES_TOTAL).increment(1);
+}
+
+pub fn record_untracked_fill() {
+    metrics::counter!(UNTRACKED_FILLS_TOTAL).increment(1);
+}
+
+pub fn record_trades_pruned(rows: u64) {
+    metrics::counter!(TRADES_PRUNED_TOTAL).increment(rows);
+}
+
+pub fn set_collector_paused(paused: bool) {
+    metrics::gauge!(COLLECTOR_PAUSED).set(if paused { 1.0 } else { 0.0 });
+}
+
+pub fn record_block_processed() {
+    metrics::counter!(BLOCKS_PROCESSED_TOTAL).increment(1);
+}
+
+pub fn record_event(pallet: &str, event: &str) {
+    metrics::counter!(EVENTS_TOTAL, "pallet" => pallet.to_string(), "event" => event.to_string())
+        .increment(1);
+}
+
+pub fn set_open_orders(count: usize) {
+    metrics::gauge!(OPEN_ORDERS).set(count as f64);
+}
+
+pub fn record_broadcast_lag(channel: &'static str, skipped: u64) {
+    metrics::counter!(BROADCAST_LAGGED_TOTAL, "channel" => channel).increment(skipped);
+}
+
+/// Counts a websocket connection in `WS_CONNECTIONS` until dropped
+pub struct ConnectionGauge {
+    endpoint: &'static str,
+}
+
+impl ConnectionGauge {
+    pub fn open(endpoint: &'static str) -> Self {
+        metrics::gauge!(WS_CONNECTIONS, "endpoint" => endpoint).increment(1.0);
+        Self { endpoint }
+    }
+}
+
+impl Drop for ConnectionGauge {
+    fn drop(&mut self) {
+        metrics::gauge!(WS_CONNECTIONS, "endpoint" => self.endpoint).decrement(1.0);
+    }
+}
+
+/// Run an event decode, recording how long it took
+pub fn timed_decode<T>(decode: impl FnOnce() -> T) -> T {
+    let started = Instant::now();
+    let decoded = decode();
+    metrics::histogram!(EVENT_DECODE_SECONDS).record(started.elapsed().as_secs_f64());
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histograms_populated_after_block() {
+        let recorder = build_recorder().unwrap();
+        let handle = recorder.handle();
+
+        metrics::with_local_recorder(&recorder, || {
+            let block_started = Instant::now();
+            let decoded = timed_decode(|| 42);
+            observe_db_write(Duration::from_millis(3));
+            observe_block(block_started.elapsed());
+            assert_eq!(decoded, 42);
+        });
+
+        let rendered = handle.render();
+        assert!(rendered.contains("indexer_block_processing_seconds_count 1"));
+        assert!(rendered.contains("indexer_event_decode_seconds_count 1"));
+        assert!(rendered.contains("indexer_db_write_seconds_bucket{le=\"0.005\"} 1"));
+    }
+
+    #[test]
+    fn test_operational_counters_rendered() {
+        let recorder = build_recorder().unwrap();
+        let handle = recorder.handle();
+
+        let rendered = metrics::with_local_recorder(&recorder, || {
+            record_block_processed();
+            record_event("Orderbook", "TradeExecuted");
+            record_event("Orderbook", "TradeExecuted");
+            record_event("Orderbook", "OrderPlaced");
+            set_open_orders(7);
+            record_broadcast_lag("ohlcv", 12);
+            // One connection still open while rendering, one already closed
+            let _open = ConnectionGauge::open("market");
+            drop(ConnectionGauge::open("market"));
+            handle.render()
+        });
+
+        assert!(rendered.contains("indexer_blocks_processed_total 1"));
+        assert!(rendered
+            .contains("indexer_events_total{pallet=\"Orderbook\",event=\"TradeExecuted\"} 2"));
+        assert!(
+            rendered.contains("indexer_events_total{pallet=\"Orderbook\",event=\"OrderPlaced\"} 1")
+        );
+        assert!(rendered.contains("indexer_open_orders 7"));
+        assert!(rendered.contains("indexer_broadcast_lagged_total{channel=\"ohlcv\"} 12"));
+        assert!(rendered.contains("indexer_ws_connections{endpoint=\"market\"} 1"));
+    }
+}